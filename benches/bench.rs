@@ -5,7 +5,7 @@ extern crate compressed_vec;
 use criterion::{Criterion, Benchmark, BenchmarkId, Throughput};
 use compressed_vec::*;
 use compressed_vec::sink::{Sink, U32_256Sink};
-use compressed_vec::section::{FixedSectReader, NibblePackMedFixedSect};
+use compressed_vec::raw::section::{FixedSectReader, NibblePackMedFixedSect};
 
 fn nibblepack8_varlen(c: &mut Criterion) {
     // This method from Criterion allows us to run benchmarks and vary some variable.
@@ -243,6 +243,119 @@ fn bench_filter_f32_vect(c: &mut Criterion) {
     group.finish();
 }
 
+fn shaped_vector<W: FixedSectionWriter<u32>>(shape: benchmark::DataShape) -> Vec<u8> {
+    let mut appender = vector::VectorAppender::<u32, W>::try_new(8192).unwrap();
+    appender.encode_all(shape.generate_u32(VECTOR_LENGTH)).unwrap()
+}
+
+fn bench_decode_by_shape(c: &mut Criterion) {
+    let mut group = c.benchmark_group("u32 vector decode by shape");
+    group.throughput(Throughput::Elements(VECTOR_LENGTH as u64));
+
+    for shape in benchmark::DataShape::ALL.iter() {
+        let vectors = vec![("auto", shaped_vector::<section::AutoEncoder>(*shape)),
+                           ("nibblepack", shaped_vector::<NibblePackMedFixedSect<u32>>(*shape)),
+                           ("delta", shaped_vector::<section::DeltaNPMedFixedSect<u32>>(*shape)),
+                           ("simple8b", shaped_vector::<section::Simple8bMedFixedSect<u32>>(*shape)),
+                           ("auto-simple8b", shaped_vector::<section::Simple8bAutoEncoder>(*shape))];
+        for (writer, vect) in vectors.iter() {
+            let reader = vector::VectorReader::<u32>::try_new(&vect[..]).unwrap();
+            group.bench_function(BenchmarkId::new(format!("{:?}", shape), writer), |b| b.iter(|| {
+                let mut sink = U32_256Sink::new();
+                for sect in reader.sect_iter() {
+                    sink.reset();
+                    sect.unwrap().decode(&mut sink).unwrap();
+                }
+            }));
+        }
+    }
+
+    group.finish();
+}
+
+// 90% of sections are null sections: one dense lowcard section for every 9 null sections
+fn mostly_null_vector() -> Vec<u8> {
+    let inputs = sinewave_varnonzeros_u32(1.0, VECTOR_LENGTH);
+    let mut appender = vector::VectorU32Appender::try_new(8192).unwrap();
+    for (i, chunk) in inputs.chunks(256).enumerate() {
+        if i % 10 == 0 {
+            chunk.iter().for_each(|&a| appender.append(a).unwrap());
+        } else {
+            appender.append_nulls(chunk.len()).unwrap();
+        }
+    }
+    appender.finish(VECTOR_LENGTH).unwrap()
+}
+
+// Null sections should take the metadata-only paths of each kernel, never decoding zero payloads
+fn bench_mostly_null_vect(c: &mut Criterion) {
+    let mut group = c.benchmark_group("90% null u32 vector");
+    group.throughput(Throughput::Elements(VECTOR_LENGTH as u64));
+
+    let null_vect = mostly_null_vector();
+    let reader = vector::VectorReader::<u32>::try_new(&null_vect[..]).unwrap();
+    let dense_vect = dense_lowcard_vector();
+    let dense_reader = vector::VectorReader::<u32>::try_new(&dense_vect[..]).unwrap();
+
+    group.bench_function("decode", |b| b.iter(|| {
+        let mut sink = U32_256Sink::new();
+        for sect in reader.sect_iter() {
+            sink.reset();
+            sect.unwrap().decode(&mut sink).unwrap();
+        }
+    }));
+    group.bench_function("iterate", |b| b.iter(|| reader.iterate().fold(0u32, |a, b| a.wrapping_add(b))));
+    group.bench_function("filter", |b| b.iter(|| {
+        filter::count_hits(reader.filter_iter(filter::EqualsSink::<u32>::new(&3)))
+    }));
+    group.bench_function("masked aggregate", |b| b.iter(|| {
+        let masks = dense_reader.filter_iter(filter::EqualsSink::<u32>::new(&3));
+        aggregate::masked_aggregate(&reader, masks).unwrap()
+    }));
+    group.bench_function("gather", |b| b.iter(|| {
+        let appender = vector::VectorU32Appender::try_new(8192).unwrap();
+        let mut builder = selection::SelectionBuilder::new(appender);
+        let masks = dense_reader.filter_iter(filter::EqualsSink::<u32>::new(&3));
+        builder.select_vector(&null_vect[..], masks).unwrap();
+    }));
+    group.bench_function("add", |b| {
+        let mut appender = vector::VectorU32Appender::try_new(8192).unwrap();
+        b.iter(|| arith::binary_op(&mut appender, &reader, &dense_reader,
+                                   arith::ArithOp::Add, arith::NullPolicy::Propagate).unwrap())
+    });
+
+    group.finish();
+}
+
+// Whole vector scans of a vector much larger than the CPU caches, with and without section lookahead
+fn bench_large_vector_scan(c: &mut Criterion) {
+    const LARGE_LENGTH: usize = 16 * 1024 * 1024;
+    let mut group = c.benchmark_group("large u64 vector scan");
+    group.throughput(Throughput::Elements(LARGE_LENGTH as u64));
+    group.sample_size(10);
+
+    let mut appender = vector::VectorU64Appender::try_new(LARGE_LENGTH).unwrap();
+    let vect = appender.encode_all((0..LARGE_LENGTH as u64).map(|i| (i * 7919) % 100_003)).unwrap();
+    let reader = vector::VectorReader::<u64>::try_new(&vect[..]).unwrap();
+
+    group.bench_function("section at a time", |b| b.iter(|| {
+        let mut sink = sink::U64_256Sink::new();
+        for sect in reader.sect_iter() {
+            sink.reset();
+            sect.unwrap().decode(&mut sink).unwrap();
+        }
+    }));
+    group.bench_function("lookahead", |b| b.iter(|| {
+        let mut sink = sink::U64_256Sink::new();
+        for sect in reader.lookahead_sect_iter() {
+            sink.reset();
+            sect.unwrap().decode(&mut sink).unwrap();
+        }
+    }));
+
+    group.finish();
+}
+
 const BATCH_SIZE: usize = 100;
 
 fn repack_2d_deltas(c: &mut Criterion) {
@@ -284,6 +397,9 @@ criterion_group!(benches, //nibblepack8_varlen,
                           bench_filter_vect,
                           bench_filter_u64_vect,
                           bench_filter_f32_vect,
+                          bench_decode_by_shape,
+                          bench_mostly_null_vect,
+                          bench_large_vector_scan,
                           // repack_2d_deltas,
                           );
 criterion_main!(benches);
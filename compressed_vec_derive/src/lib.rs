@@ -0,0 +1,54 @@
+//! Derive macro for `compressed_vec::columns::Columnar`.  Use it through the `derive` feature of
+//! compressed_vec, which re-exports it, rather than depending on this crate directly.
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DataStruct, DeriveInput, Fields};
+
+/// Derives `Columnar` for a struct with named fields, all of whose types implement `ColumnType`.
+/// Each field is encoded as its own vector, in a column named after the field.
+#[proc_macro_derive(Columnar)]
+pub fn derive_columnar(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let fields = match &input.data {
+        Data::Struct(DataStruct { fields: Fields::Named(fields), .. }) => &fields.named,
+        _ => return syn::Error::new_spanned(&input, "Columnar can only be derived for structs with named fields")
+                       .to_compile_error()
+                       .into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident.clone().unwrap()).collect();
+    let field_types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let column_names: Vec<_> = field_names.iter().map(|f| f.to_string()).collect();
+    let column_vars: Vec<_> = field_names.iter().map(|f| format_ident!("__column_{}", f)).collect();
+
+    let expanded = quote! {
+        impl #impl_generics ::compressed_vec::columns::Columnar for #name #ty_generics #where_clause {
+            fn to_columns(rows: &[Self])
+                -> ::std::result::Result<::compressed_vec::columns::ColumnSet, ::compressed_vec::error::CodingError> {
+                let mut columns = ::compressed_vec::columns::ColumnSet::new(rows.len());
+                #(
+                    columns.add(#column_names,
+                                <#field_types as ::compressed_vec::columns::ColumnType>::encode_column(
+                                    rows.iter().map(|row| row.#field_names))?);
+                )*
+                Ok(columns)
+            }
+
+            fn from_columns(columns: &::compressed_vec::columns::ColumnSet)
+                -> ::std::result::Result<::std::vec::Vec<Self>, ::compressed_vec::error::CodingError> {
+                let num_rows = columns.num_rows();
+                #(
+                    let mut #column_vars = <#field_types as ::compressed_vec::columns::ColumnType>::decode_column(
+                        columns.get(#column_names)?, num_rows)?.into_iter();
+                )*
+                // decode_column() checked that every column has num_rows values
+                Ok((0..num_rows).map(|_| Self { #( #field_names: #column_vars.next().unwrap() ),* }).collect())
+            }
+        }
+    };
+    expanded.into()
+}
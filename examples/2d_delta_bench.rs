@@ -1,7 +1,8 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::time::Instant;
-use compressed_vec::{histogram, nibblepacking};
+use compressed_vec::histogram;
+use compressed_vec::raw::nibblepacking;
 use compressed_vec::sink::Sink;
 
 ///
@@ -0,0 +1,35 @@
+//! Parses vector containers: headers, footers, Bloom filters, the typed readers of every vector type, and
+//! SectionWriter streams.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use compressed_vec::data::VectorData;
+use compressed_vec::sink::VecSink;
+use compressed_vec::stream::SectionHeaderIter;
+
+fuzz_target!(|data: &[u8]| {
+    match VectorData::from_bytes(data) {
+        Ok(VectorData::U64(reader)) => {
+            if let Some(footer) = reader.footer() {
+                footer.iter().count();
+            }
+            reader.might_contain(0);
+        }
+        Ok(VectorData::U32(reader)) => { reader.might_contain(7); }
+        Ok(VectorData::F32(reader)) => { reader.preview().count(); }
+        Ok(VectorData::F64(reader)) => { reader.preview().count(); }
+        Ok(VectorData::Bin16(reader)) => { reader.sect_iter().take_while(|s| s.is_ok()).count(); }
+        Ok(VectorData::U16(reader)) => { reader.sect_iter().take_while(|s| s.is_ok()).count(); }
+        Ok(VectorData::U8(reader)) => { reader.sect_iter().take_while(|s| s.is_ok()).count(); }
+        Ok(VectorData::KeyValue(reader)) => { reader.sect_iter().take_while(|s| s.is_ok()).count(); }
+        Ok(VectorData::Half(reader)) => { reader.decode_to_sink(&mut VecSink::<f32>::new()).ok(); }
+        Ok(VectorData::StrDict(reader)) => { reader.iterate().take_while(|s| s.is_ok()).count(); }
+        Ok(VectorData::Bool(reader)) => { reader.filter_true().count(); }
+        Ok(_) => {}
+        Err(_) => {}
+    }
+
+    for sect in SectionHeaderIter::new(data) {
+        if sect.is_err() { break }
+    }
+});
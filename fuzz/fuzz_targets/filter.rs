@@ -0,0 +1,23 @@
+//! Evaluates filters over vectors.  The first 8 input bytes are the value to filter for.
+#![no_main]
+use std::convert::TryInto;
+
+use libfuzzer_sys::fuzz_target;
+
+use compressed_vec::filter::{count_hits, match_positions, EqualsSink};
+use compressed_vec::shared::SharedVector;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 8 { return }
+    let (value, vect_bytes) = data.split_at(8);
+    let value = u64::from_le_bytes(value.try_into().unwrap());
+
+    if let Ok(shared) = SharedVector::<u64>::attach(vect_bytes) {
+        let reader = shared.reader();
+        let hits = count_hits(reader.filter_iter(EqualsSink::<u64>::new(&value)));
+        assert_eq!(hits, match_positions(reader.filter_iter(EqualsSink::<u64>::new(&value))).len());
+    }
+    if let Ok(shared) = SharedVector::<u32>::attach(vect_bytes) {
+        count_hits(shared.reader().filter_iter(EqualsSink::<u32>::new(&(value as u32))));
+    }
+});
@@ -0,0 +1,28 @@
+//! Parses and decodes a single FixedSection.  The first input byte picks the element type.
+#![no_main]
+use std::convert::TryFrom;
+
+use libfuzzer_sys::fuzz_target;
+
+use compressed_vec::VectBase;
+use compressed_vec::raw::section::{FixedSectEnum, FixedSection};
+use compressed_vec::sink::Section256Sink;
+
+fn decode<T: VectBase>(sect_bytes: &[u8]) {
+    if let Ok(sect) = FixedSectEnum::<T>::try_from(sect_bytes) {
+        assert!(sect.num_bytes() <= sect_bytes.len());
+        let mut sink = Section256Sink::<T>::new();
+        let _ = sect.decode(&mut sink);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Some((&elem_type, sect_bytes)) = data.split_first() {
+        match elem_type % 4 {
+            0 => decode::<u32>(sect_bytes),
+            1 => decode::<u64>(sect_bytes),
+            2 => decode::<f32>(sect_bytes),
+            _ => decode::<f64>(sect_bytes),
+        }
+    }
+});
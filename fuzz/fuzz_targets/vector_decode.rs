@@ -0,0 +1,36 @@
+//! Reads whole vectors: every section is decoded, and once a vector is validated by `SharedVector::attach()`,
+//! iterating over it must not panic.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use compressed_vec::VectBase;
+use compressed_vec::shared::SharedVector;
+use compressed_vec::sink::{Section256Sink, Sink};
+use compressed_vec::vector::{BaseSubtypeMapping, VectorReader};
+
+fn decode<T: VectBase + BaseSubtypeMapping>(vect_bytes: &[u8]) {
+    if let Ok(reader) = VectorReader::<T>::try_new(vect_bytes) {
+        let mut sink = Section256Sink::<T>::new();
+        for sect in reader.sect_iter() {
+            match sect {
+                Ok(sect) => { sink.reset(); let _ = sect.decode(&mut sink); }
+                Err(_)   => break,
+            }
+        }
+    }
+    if let Ok(mut shared) = SharedVector::<T>::attach(vect_bytes) {
+        assert!(shared.reader().iterate().count() <= shared.reader().num_elements());
+        let num_sections = shared.pin_directory().unwrap().num_sections();
+        for n in 0..num_sections {
+            shared.section(n).unwrap();
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    // The subtype in the header picks the reader; the others fail with WrongElementType
+    decode::<u32>(data);
+    decode::<u64>(data);
+    decode::<f32>(data);
+    decode::<f64>(data);
+});
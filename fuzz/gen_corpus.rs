@@ -0,0 +1,100 @@
+//! Writes seed corpora for the fuzz targets into corpus/<target>/, using vectors made with the appenders, so
+//! that fuzzing starts from valid vectors of every section type.  Run from the fuzz directory:
+//!     cargo run --bin gen_corpus
+use std::fs;
+use std::path::Path;
+
+use compressed_vec::fixedbin::FixedBin16Appender;
+use compressed_vec::kvpairs::KeyValueAppender;
+use compressed_vec::VectBase;
+use compressed_vec::raw::section::{FixedSection, SectionWriter};
+use compressed_vec::stream::vector_to_stream;
+use compressed_vec::vector::*;
+
+fn write_seeds(target: &str, seeds: &[Vec<u8>]) -> std::io::Result<()> {
+    let dir = Path::new("corpus").join(target);
+    fs::create_dir_all(&dir)?;
+    for (n, seed) in seeds.iter().enumerate() {
+        fs::write(dir.join(format!("seed_{:03}", n)), seed)?;
+    }
+    println!("Wrote {} seeds to {}", seeds.len(), dir.display());
+    Ok(())
+}
+
+fn u64_vectors() -> Vec<Vec<u8>> {
+    let mut appender = VectorU64Appender::try_new(4096).unwrap();
+    let mut vectors = vec![
+        appender.encode_all(0..1000u64).unwrap(),                          // Delta
+        appender.encode_all((0..600u64).map(|i| i * i * 7919)).unwrap(),   // NibblePacked
+        appender.encode_all(std::iter::repeat(42).take(512)).unwrap(),     // Constant
+        appender.encode_all((0..700u64).map(|i| if i % 31 == 0 { i } else { 0 })).unwrap(),  // Zero runs
+    ];
+    appender.append_nulls(300).unwrap();
+    vectors.push(appender.finish(400).unwrap());                           // Null sections
+    appender.dedup_sections(true);
+    appender.enable_bloom_filter(10);
+    vectors.push(appender.encode_all((0..768u64).map(|i| i % 256 * 3)).unwrap());   // RepeatPrevious, footer
+    vectors
+}
+
+fn other_vectors() -> Vec<Vec<u8>> {
+    let mut kv_appender = KeyValueAppender::try_new(4096).unwrap();
+    for i in 0..300u64 { kv_appender.append(&[(7, i), (100 + i % 3, 1)]).unwrap(); }
+    vec![
+        VectorU32Appender::try_new(4096).unwrap().encode_all((0..500u32).map(|i| i % 17)).unwrap(),
+        VectorF32XorAppender::try_new(4096).unwrap().encode_all((0..300).map(|i| i as f32 * 0.25)).unwrap(),
+        VectorF64XorAppender::try_new(4096).unwrap().encode_all((0..300).map(|i| 20.0 + i as f64)).unwrap(),
+        FixedBin16Appender::try_new(4096).unwrap().encode_all(&[[9u8; 16]; 20]).unwrap(),
+        kv_appender.finish(300).unwrap(),
+    ]
+}
+
+// Each section of a vector, prefixed by the element type byte of the fixed_sect target
+fn sections<T: VectBase + BaseSubtypeMapping>(vect_bytes: &[u8], elem_type: u8) -> Vec<Vec<u8>> {
+    let reader = VectorReader::<T>::try_new(vect_bytes).unwrap();
+    reader.sect_iter().map(|sect| {
+        let sect = sect.unwrap();
+        let mut seed = vec![elem_type];
+        match sect.sect_bytes() {
+            Some(bytes) => seed.extend_from_slice(&bytes[..sect.num_bytes()]),
+            None        => seed.push(0),    // Null section
+        }
+        seed
+    }).collect()
+}
+
+fn main() -> std::io::Result<()> {
+    let u64_vects = u64_vectors();
+    let others = other_vectors();
+    let all_vects: Vec<Vec<u8>> = u64_vects.iter().chain(others.iter()).cloned().collect();
+
+    let mut sects: Vec<Vec<u8>> = u64_vects.iter().flat_map(|v| sections::<u64>(v, 1)).collect();
+    sects.extend(sections::<u32>(&others[0], 0));
+    sects.extend(sections::<f32>(&others[1], 2));
+    sects.extend(sections::<f64>(&others[2], 3));
+    write_seeds("fixed_sect", &sects)?;
+
+    // Plus header lengths which do not cover the header, or overflow once the length field is added
+    let mut vect_seeds = all_vects.clone();
+    for &num_bytes in &[8u32, u32::max_value()] {
+        let mut corrupt = u64_vects[5].clone();    // With a footer
+        corrupt[..4].copy_from_slice(&num_bytes.to_le_bytes());
+        vect_seeds.push(corrupt);
+    }
+    write_seeds("vector_decode", &vect_seeds)?;
+
+    let filters: Vec<Vec<u8>> = u64_vects.iter().map(|v| {
+        let mut seed = 42u64.to_le_bytes().to_vec();
+        seed.extend_from_slice(v);
+        seed
+    }).collect();
+    write_seeds("filter", &filters)?;
+
+    let mut buf = vec![0u8; 65536];
+    let mut writer = SectionWriter::new(&mut buf[..], 100);
+    vector_to_stream(&u64_vects[0], &mut writer).unwrap();
+    let stream_len = writer.cur_pos();
+    let mut containers = all_vects;
+    containers.push(buf[..stream_len].to_vec());
+    write_seeds("container", &containers)
+}
@@ -0,0 +1,569 @@
+/// The `aggregate` module computes aggregates over only the elements of a vector selected by section masks,
+/// such as `SUM(x) WHERE y > k`, in one pass over the sections of x.  The masks usually come from a filter over
+/// another vector y with the same number of elements, one `SectionMask` per section:
+/// ```
+/// # use compressed_vec::aggregate::masked_aggregate;
+/// # use compressed_vec::filter::EqualsSink;
+/// # use compressed_vec::vector::*;
+///     let x = VectorU32Appender::try_new(1024).unwrap().encode_all((0..1000u32).map(|i| i * 2)).unwrap();
+///     let y = VectorU32Appender::try_new(1024).unwrap().encode_all((0..1000u32).map(|i| i % 10)).unwrap();
+///     let x = VectorReader::<u32>::try_new(&x[..]).unwrap();
+///     let y = VectorReader::<u32>::try_new(&y[..]).unwrap();
+///
+///     // SUM(x), MIN(x), MAX(x) WHERE y = 3
+///     let agg = masked_aggregate(&x, y.filter_iter(EqualsSink::<u32>::new(&3))).unwrap();
+///     assert_eq!(agg.count, 100);
+///     assert_eq!((agg.sum, agg.min, agg.max), (99_600, Some(6), Some(1986)));
+/// ```
+/// Null handling follows SQL: selected tracked nulls, the null elements of partial-null sections, are not
+/// aggregated, but counted in `nulls`, so the SUM, MIN and MAX of only nulls are `0`, `None` and `None`.  The
+/// elements of null sections are zeroes.  Null sections, and sections with an empty mask, are never decoded,
+/// and null and constant sections are aggregated from their header.  Integer sums wrap around on overflow,
+/// like the arithmetic kernels.
+///
+/// `sum()` adds up all the elements of a vector without masks, accumulating in SIMD registers while decoding.
+/// `bucket_counts()` counts the elements of a vector falling into each bucket of a histogram, such as a latency
+/// distribution, comparing 8 decoded values at a time against the bucket upper bounds.  `mean()` averages the
+/// non-null elements of a vector, summing them in a wider type so that the sum of a whole vector never overflows.
+use std::cmp::Ordering;
+use std::ops::{Add, Mul};
+
+use num::NumCast;
+use packed_simd::{f64x8, u32x8, u64x8, FromCast};
+
+use crate::arith::{ArithBase, ArithOp};
+use crate::error::CodingError;
+use crate::filter::{for_each_match, SectionMask};
+use crate::section::*;
+use crate::selection::first_n_mask;
+use crate::sink::{Section256Sink, Sink, SinkInput};
+use crate::vector::{BaseSubtypeMapping, VectorReader};
+
+
+/// Aggregates of the selected elements of a vector
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaskedAggregate<T: VectBase> {
+    pub count: usize,    // Number of selected non-null elements
+    pub nulls: usize,    // Number of selected null elements
+    pub sum: T,
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<T: VectBase + ArithBase> MaskedAggregate<T> {
+    pub fn new() -> Self {
+        Self { count: 0, nulls: 0, sum: T::zero(), min: None, max: None }
+    }
+
+    #[inline]
+    pub(crate) fn add_min_max(&mut self, value: T) {
+        match self.min {
+            Some(min) if min <= value => {}
+            _ => self.min = Some(value),
+        }
+        match self.max {
+            Some(max) if max >= value => {}
+            _ => self.max = Some(value),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn add(&mut self, value: T) {
+        self.count += 1;
+        self.sum = T::apply(ArithOp::Add, self.sum, value);
+        self.add_min_max(value);
+    }
+}
+
+/// Aggregates the elements of the vector whose bits are ON in the section masks, one mask per section.
+/// Missing masks select nothing, and mask bits beyond the number of elements are ignored.
+pub fn masked_aggregate<T, I>(vector: &VectorReader<T>, masks: I) -> Result<MaskedAggregate<T>, CodingError>
+where T: ArithBase + NumCast + BaseSubtypeMapping,
+      I: IntoIterator<Item = SectionMask> {
+    let mut agg = MaskedAggregate::new();
+    let mut sink = Section256Sink::<T>::new();
+    let mut elems_left = vector.num_elements();
+    for (sect, mask) in vector.sect_iter().zip(masks) {
+        let sect = sect?;
+        let sect_elems = elems_left.min(FIXED_LEN);
+        elems_left -= sect_elems;
+        let mask = mask & first_n_mask(sect_elems);
+        let selected = mask.count_ones().wrapping_sum() as usize;
+        if selected == 0 { continue }
+
+        match sect {
+            FixedSectEnum::NullFixedSect(_) => {
+                agg.count += selected;
+                agg.add_min_max(T::zero());
+            }
+            FixedSectEnum::ConstFixedSect(cs) => {
+                let count: T = NumCast::from(selected).ok_or(CodingError::Overflow)?;
+                agg.count += selected;
+                agg.sum = T::apply(ArithOp::Add, agg.sum, T::apply(ArithOp::Mul, cs.get_value(), count));
+                agg.add_min_max(cs.get_value());
+            }
+            _ => {
+                let valid = mask & sect.validity_mask();
+                agg.nulls += selected - valid.count_ones().wrapping_sum() as usize;
+                sink.reset();
+                sect.decode(&mut sink)?;
+                for_each_match(std::iter::once(valid), |i| agg.add(sink.values[i]));
+            }
+        }
+    }
+    Ok(agg)
+}
+
+/// Sink which adds up the decoded values 8 lanes at a time, without writing them out
+struct SumSink<T: VectBase> {
+    lanes: T::SI,
+}
+
+impl<T: VectBase> Sink<T::SI> for SumSink<T> {
+    #[inline]
+    fn process(&mut self, data: T::SI) {
+        self.lanes = self.lanes + data;
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {}
+
+    #[inline]
+    fn reset(&mut self) {
+        self.lanes = T::SI::ZERO;
+    }
+}
+
+/// Returns the sum of all the elements of a vector, nulls counting as zero.  Values are added up in SIMD lanes as
+/// sections are decoded, without writing them out; constant sections are summed from their header, and null
+/// sections are skipped.  Only the partial last section, if any, is decoded into a buffer.  Integer sums wrap
+/// around on overflow, and float sums may differ in the last bits from adding the values one by one.
+pub fn sum<T>(vector: &VectorReader<T>) -> Result<T, CodingError>
+where T: ArithBase + NumCast + BaseSubtypeMapping {
+    let mut total = T::zero();
+    let mut sum_sink = SumSink::<T> { lanes: T::SI::ZERO };
+    let mut elems_left = vector.num_elements();
+    for sect in vector.sect_iter() {
+        if elems_left == 0 { break }
+        let sect = sect?;
+        let sect_elems = elems_left.min(FIXED_LEN);
+        elems_left -= sect_elems;
+        match sect {
+            FixedSectEnum::NullFixedSect(_) => {}
+            FixedSectEnum::ConstFixedSect(cs) => {
+                let count: T = NumCast::from(sect_elems).ok_or(CodingError::Overflow)?;
+                total = T::apply(ArithOp::Add, total, T::apply(ArithOp::Mul, cs.get_value(), count));
+            }
+            _ if sect_elems < FIXED_LEN => {
+                let mut sink = Section256Sink::<T>::new();
+                sect.decode(&mut sink)?;
+                total = sink.values[..sect_elems].iter().fold(total, |acc, &v| T::apply(ArithOp::Add, acc, v));
+            }
+            _ => sect.decode(&mut sum_sink)?,
+        }
+    }
+
+    let mut lanes = [T::zero(); 8];
+    sum_sink.lanes.write_to_slice(&mut lanes);
+    Ok(lanes.iter().fold(total, |acc, &v| T::apply(ArithOp::Add, acc, v)))
+}
+
+/// Sink which counts the decoded values at or below each bucket upper bound, 8 lanes at a time
+struct BucketSink<T: VectBase> {
+    bounds: Vec<T::SI>,
+    le_counts: Vec<u64>,      // Values at or below each bound
+    zero_bucket: usize,       // First bound which zero is at or below
+    num_values: u64,
+}
+
+impl<T: VectBase> Sink<T::SI> for BucketSink<T> {
+    #[inline]
+    fn process(&mut self, data: T::SI) {
+        for (bound, count) in self.bounds.iter().zip(self.le_counts.iter_mut()) {
+            *count += data.le_mask(*bound).count_ones() as u64;
+        }
+        self.num_values += 8;
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.le_counts[self.zero_bucket..].iter_mut().for_each(|count| *count += 8);
+        self.num_values += 8;
+    }
+
+    fn reset(&mut self) {
+        self.le_counts.iter_mut().for_each(|count| *count = 0);
+        self.num_values = 0;
+    }
+}
+
+// The bucket of a value: the index of the first bound it is at or below, or bounds.len() if above all of them
+#[inline]
+fn bucket_of<T: PartialOrd>(bounds: &[T], value: T) -> usize {
+    bounds.iter().position(|bound| value <= *bound).unwrap_or(bounds.len())
+}
+
+/// Counts the elements of a vector in each bucket of a histogram with the given upper bounds, which must be
+/// increasing.  Returns bounds.len() + 1 counts: bucket i counts the values above bounds[i - 1] and at or below
+/// bounds[i], and the last bucket counts the values above every bound, as well as NaNs.  Tracked nulls are not
+/// counted, and the elements of Null sections are zeroes.  Whole sections are counted as they are decoded,
+/// comparing 8 values at a time against every bound, and null and constant sections are counted from their
+/// header.
+pub fn bucket_counts<T>(vector: &VectorReader<T>, bounds: &[T]) -> Result<Vec<u64>, CodingError>
+where T: VectBase + BaseSubtypeMapping {
+    if bounds.windows(2).any(|w| w[0].partial_cmp(&w[1]) != Some(Ordering::Less)) {
+        return Err(CodingError::InvalidFormat("Bucket bounds must be increasing".into()));
+    }
+    let mut counts = vec![0u64; bounds.len() + 1];
+    let mut bucket_sink = BucketSink::<T> {
+        bounds: bounds.iter().map(|&b| T::SI::splat(b)).collect(),
+        le_counts: vec![0; bounds.len()],
+        zero_bucket: bucket_of(bounds, T::zero()),
+        num_values: 0,
+    };
+    let mut sink = Section256Sink::<T>::new();
+    let mut elems_left = vector.num_elements();
+    for sect in vector.sect_iter() {
+        if elems_left == 0 { break }
+        let sect = sect?;
+        let sect_elems = elems_left.min(FIXED_LEN);
+        elems_left -= sect_elems;
+        match sect {
+            FixedSectEnum::NullFixedSect(_) => counts[bucket_sink.zero_bucket] += sect_elems as u64,
+            FixedSectEnum::ConstFixedSect(cs) => counts[bucket_of(bounds, cs.get_value())] += sect_elems as u64,
+            FixedSectEnum::PartialNullFixedSect(_) => {
+                let valid = sect.validity_mask() & first_n_mask(sect_elems);
+                sink.reset();
+                sect.decode(&mut sink)?;
+                for_each_match(std::iter::once(valid), |i| counts[bucket_of(bounds, sink.values[i])] += 1);
+            }
+            _ if sect_elems < FIXED_LEN => {
+                sink.reset();
+                sect.decode(&mut sink)?;
+                sink.values[..sect_elems].iter().for_each(|&v| counts[bucket_of(bounds, v)] += 1);
+            }
+            _ => sect.decode(&mut bucket_sink)?,
+        }
+    }
+
+    // Turn the counts at or below each bound into counts per bucket
+    let mut below = 0;
+    for (count, &le_count) in counts.iter_mut().zip(bucket_sink.le_counts.iter()) {
+        *count += le_count - below;
+        below = le_count;
+    }
+    counts[bounds.len()] += bucket_sink.num_values - below;
+    Ok(counts)
+}
+
+/// Element types which `mean()` sums in a wider type: u128 for integers, with SIMD lanes of 64 bits which cannot
+/// overflow within a vector of at most 2^32 elements, and f64 for floats.
+pub trait MeanInput: VectBase {
+    type Wide: Copy + Add<Output = Self::Wide> + Mul<Output = Self::Wide>;
+    type Lanes: Copy;
+
+    const WIDE_ZERO: Self::Wide;
+    const LANES_ZERO: Self::Lanes;
+
+    fn widen(value: Self) -> Self::Wide;
+    fn widen_count(count: usize) -> Self::Wide;
+    fn to_f64(sum: Self::Wide) -> f64;
+
+    /// Adds 8 decoded values to the lanes
+    fn add_lanes(lanes: &mut Self::Lanes, values: Self::SI);
+
+    /// The sum of all the lanes
+    fn sum_lanes(lanes: Self::Lanes) -> Self::Wide;
+}
+
+impl MeanInput for u32 {
+    type Wide = u128;
+    type Lanes = u64x8;
+    const WIDE_ZERO: u128 = 0;
+    const LANES_ZERO: u64x8 = u64x8::splat(0);
+
+    #[inline]
+    fn widen(value: u32) -> u128 { value as u128 }
+    #[inline]
+    fn widen_count(count: usize) -> u128 { count as u128 }
+    #[inline]
+    fn to_f64(sum: u128) -> f64 { sum as f64 }
+
+    #[inline]
+    fn add_lanes(lanes: &mut u64x8, values: u32x8) {
+        *lanes += u64x8::from_cast(values);
+    }
+
+    fn sum_lanes(lanes: u64x8) -> u128 {
+        (0..8).map(|i| lanes.extract(i) as u128).sum()
+    }
+}
+
+impl MeanInput for u64 {
+    type Wide = u128;
+    type Lanes = (u64x8, u64x8);    // Sums of the low and high 32 bits of the values
+    const WIDE_ZERO: u128 = 0;
+    const LANES_ZERO: (u64x8, u64x8) = (u64x8::splat(0), u64x8::splat(0));
+
+    #[inline]
+    fn widen(value: u64) -> u128 { value as u128 }
+    #[inline]
+    fn widen_count(count: usize) -> u128 { count as u128 }
+    #[inline]
+    fn to_f64(sum: u128) -> f64 { sum as f64 }
+
+    #[inline]
+    fn add_lanes(lanes: &mut (u64x8, u64x8), values: u64x8) {
+        lanes.0 += values & u64x8::splat(0xffff_ffff);
+        lanes.1 += values >> u64x8::splat(32);
+    }
+
+    fn sum_lanes(lanes: (u64x8, u64x8)) -> u128 {
+        (0..8).map(|i| lanes.0.extract(i) as u128 + ((lanes.1.extract(i) as u128) << 32)).sum()
+    }
+}
+
+impl MeanInput for f64 {
+    type Wide = f64;
+    type Lanes = f64x8;
+    const WIDE_ZERO: f64 = 0.0;
+    const LANES_ZERO: f64x8 = f64x8::splat(0.0);
+
+    #[inline]
+    fn widen(value: f64) -> f64 { value }
+    #[inline]
+    fn widen_count(count: usize) -> f64 { count as f64 }
+    #[inline]
+    fn to_f64(sum: f64) -> f64 { sum }
+
+    #[inline]
+    fn add_lanes(lanes: &mut f64x8, values: f64x8) {
+        *lanes += values;
+    }
+
+    fn sum_lanes(lanes: f64x8) -> f64 {
+        lanes.sum()
+    }
+}
+
+/// Sink which adds up the decoded values in widened lanes, without writing them out
+struct MeanSink<T: MeanInput> {
+    lanes: T::Lanes,
+}
+
+impl<T: MeanInput> Sink<T::SI> for MeanSink<T> {
+    #[inline]
+    fn process(&mut self, data: T::SI) {
+        T::add_lanes(&mut self.lanes, data);
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {}
+
+    #[inline]
+    fn reset(&mut self) {
+        self.lanes = T::LANES_ZERO;
+    }
+}
+
+/// Returns the mean of the non-null elements of a vector, or None if there are none.  Tracked nulls (see
+/// `VectorAppender::track_nulls()`) are not counted; the elements of Null sections are zeroes.  Values are
+/// summed in a wider type as they are decoded, see `MeanInput`, and constant sections are summed from their
+/// header.  Only partial sections are decoded into a buffer.
+pub fn mean<T>(vector: &VectorReader<T>) -> Result<Option<f64>, CodingError>
+where T: MeanInput + BaseSubtypeMapping {
+    let mut total = T::WIDE_ZERO;
+    let mut count = 0;
+    let mut mean_sink = MeanSink::<T> { lanes: T::LANES_ZERO };
+    let mut sink = Section256Sink::<T>::new();
+    let mut elems_left = vector.num_elements();
+    for sect in vector.sect_iter() {
+        if elems_left == 0 { break }
+        let sect = sect?;
+        let sect_elems = elems_left.min(FIXED_LEN);
+        elems_left -= sect_elems;
+        match sect {
+            FixedSectEnum::NullFixedSect(_) => count += sect_elems,
+            FixedSectEnum::ConstFixedSect(cs) => {
+                total = total + T::widen(cs.get_value()) * T::widen_count(sect_elems);
+                count += sect_elems;
+            }
+            _ if sect_elems < FIXED_LEN || matches!(sect, FixedSectEnum::PartialNullFixedSect(_)) => {
+                let valid = sect.validity_mask() & first_n_mask(sect_elems);
+                sink.reset();
+                sect.decode(&mut sink)?;
+                for_each_match(std::iter::once(valid), |i| {
+                    total = total + T::widen(sink.values[i]);
+                    count += 1;
+                });
+            }
+            _ => {
+                sect.decode(&mut mean_sink)?;
+                count += FIXED_LEN;
+            }
+        }
+    }
+
+    if count == 0 { return Ok(None) }
+    total = total + T::sum_lanes(mean_sink.lanes);
+    Ok(Some(T::to_f64(total) / count as f64))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{EqualsSink, OneOfSink};
+    use crate::vector::{VectorF32XorAppender, VectorF64XorAppender, VectorU32Appender, VectorU64Appender};
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_masked_aggregate_with_nulls_and_constants() {
+        // Constant, null, regular and partial sections
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..256u64).for_each(|_| appender.append(9).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..300u64).for_each(|i| appender.append(i * i).unwrap());
+        let x = appender.finish(812).unwrap();
+        let y = appender.encode_all((0..812u64).map(|i| i % 5)).unwrap();
+        let x = VectorReader::<u64>::try_new(&x[..]).unwrap();
+        let y = VectorReader::<u64>::try_new(&y[..]).unwrap();
+
+        let agg = masked_aggregate(&x, y.filter_iter(OneOfSink::<u64>::new(&smallvec![0, 2]))).unwrap();
+        let selected: Vec<u64> = x.iterate().enumerate().filter(|(i, _)| i % 5 == 0 || i % 5 == 2)
+                                  .map(|(_, v)| v).collect();
+        assert_eq!(agg.count, selected.len());
+        assert_eq!(agg.nulls, 0);
+        assert_eq!(agg.sum, selected.iter().sum::<u64>());
+        assert_eq!(agg.min, Some(0));       // From the null section
+        assert_eq!(agg.max, selected.iter().max().cloned());
+
+        // Padding past the last element is never selected, even when the predicate matches zero
+        let all = masked_aggregate(&x, y.filter_iter(OneOfSink::<u64>::new(&smallvec![0, 1, 2, 3, 4]))).unwrap();
+        assert_eq!(all.count + all.nulls, 812);
+
+        // Only tracked nulls selected, and no masks at all
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        appender.track_nulls(true);
+        appender.append_options((0..512u64).map(|i| if i % 32 == 0 { None } else { Some(i) })).unwrap();
+        let tracked = appender.finish(512).unwrap();
+        let tracked = VectorReader::<u64>::try_new(&tracked[..]).unwrap();
+        let nulls = masked_aggregate(&tracked, vec![SectionMask::splat(0), SectionMask::splat(1)]).unwrap();
+        assert_eq!(nulls, MaskedAggregate { count: 0, nulls: 8, sum: 0, min: None, max: None });
+        assert_eq!(masked_aggregate(&x, vec![]).unwrap(), MaskedAggregate::new());
+
+        // A Null section written for zero values is selected as zeroes, not nulls
+        let zeroes = appender.encode_all(vec![0u64; 256]).unwrap();
+        let zeroes = VectorReader::<u64>::try_new(&zeroes[..]).unwrap();
+        assert_eq!(zeroes.num_null_sections().unwrap(), 1);
+        assert_eq!(masked_aggregate(&zeroes, vec![SectionMask::splat(u32::MAX)]).unwrap(),
+                   MaskedAggregate { count: 256, nulls: 0, sum: 0, min: Some(0), max: Some(0) });
+
+        let floats = VectorF32XorAppender::try_new(1024).unwrap()
+                         .encode_all((0..100).map(|i| i as f32 - 50.5)).unwrap();
+        let floats = VectorReader::<f32>::try_new(&floats[..]).unwrap();
+        let agg = masked_aggregate(&floats, x.filter_iter(EqualsSink::<u64>::new(&9))).unwrap();
+        assert_eq!((agg.count, agg.min, agg.max), (100, Some(-50.5), Some(48.5)));
+    }
+
+    #[test]
+    fn test_sum() {
+        // Constant, null, NibblePacked, delta and partial sections
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..256u64).for_each(|_| appender.append(9).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..256u64).for_each(|i| appender.append((i * 7919) % 1000).unwrap());
+        (0..300u64).for_each(|i| appender.append(1_000_000 + i * 3).unwrap());
+        let x = appender.finish(1068).unwrap();
+        let x = VectorReader::<u64>::try_new(&x[..]).unwrap();
+        assert_eq!(sum(&x).unwrap(), x.iterate().sum::<u64>());
+
+        let empty = appender.finish(0).unwrap();
+        assert_eq!(sum(&VectorReader::<u64>::try_new(&empty[..]).unwrap()).unwrap(), 0);
+
+        // Null sections written for zero values, even with null tracking, are zeroes
+        appender.track_nulls(true);
+        let zeroes = appender.encode_all((0..512u64).map(|i| if i < 256 { 0 } else { 3 })).unwrap();
+        assert_eq!(sum(&VectorReader::<u64>::try_new(&zeroes[..]).unwrap()).unwrap(), 768);
+
+        let floats = VectorF32XorAppender::try_new(1024).unwrap()
+                         .encode_all((0..600).map(|i| i as f32 * 0.5 - 50.0)).unwrap();
+        let floats = VectorReader::<f32>::try_new(&floats[..]).unwrap();
+        assert_eq!(sum(&floats).unwrap(), 59_850.0);
+    }
+
+    #[test]
+    fn test_bucket_counts() {
+        // Constant, null, sparse, regular and partial sections
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..256u64).for_each(|_| appender.append(70).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..256u64).for_each(|i| appender.append(if i % 50 == 0 { i } else { 0 }).unwrap());
+        (0..556u64).for_each(|i| appender.append(i * 3).unwrap());
+        let x = appender.finish(1324).unwrap();
+        let x = VectorReader::<u64>::try_new(&x[..]).unwrap();
+
+        let bounds = [0u64, 10, 100, 1000];
+        let mut expected = vec![0u64; 5];
+        x.iterate().for_each(|v| expected[bounds.iter().position(|&b| v <= b).unwrap_or(4)] += 1);
+        assert_eq!(bucket_counts(&x, &bounds[..]).unwrap(), expected);
+        assert_eq!(bucket_counts(&x, &[]).unwrap(), vec![1324]);
+        assert!(bucket_counts(&x, &[5, 5]).is_err());
+
+        // NaNs are counted in the last bucket, and tracked nulls are not counted
+        let mut appender = VectorF64XorAppender::try_new(1024).unwrap();
+        appender.track_nulls(true);
+        appender.append_options((0..300).map(|i| match i % 3 {
+            0 => None,
+            1 => Some(f64::NAN),
+            _ => Some(i as f64 * 0.5),
+        })).unwrap();
+        let floats = appender.finish(300).unwrap();
+        let floats = VectorReader::<f64>::try_new(&floats[..]).unwrap();
+        assert_eq!(bucket_counts(&floats, &[10.0, 100.0]).unwrap(), vec![7, 60, 33 + 100]);
+
+        // Null sections written for zero values, even with null tracking, are counted as zeroes
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.track_nulls(true);
+        let zeroes = appender.encode_all(vec![0u64; 256]).unwrap();
+        let zeroes = VectorReader::<u64>::try_new(&zeroes[..]).unwrap();
+        assert_eq!(bucket_counts(&zeroes, &[0, 10]).unwrap(), vec![256, 0, 0]);
+    }
+
+    #[test]
+    fn test_mean_widens_and_skips_nulls() {
+        // Sums over u64::MAX, with constant, null and partial sections
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..256u64).for_each(|_| appender.append(u64::MAX - 1).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..300u64).for_each(|i| appender.append(u64::MAX - i).unwrap());
+        let x = appender.finish(812).unwrap();
+        let x = VectorReader::<u64>::try_new(&x[..]).unwrap();
+        let sum = 256 * (u64::MAX - 1) as u128 + (0..300u64).map(|i| (u64::MAX - i) as u128).sum::<u128>();
+        assert_eq!(mean(&x).unwrap(), Some(sum as f64 / 812.0));
+
+        // Null sections written for zero values are counted as zeroes
+        let zeroes = appender.encode_all((0..512u64).map(|i| if i < 256 { 0 } else { 10 })).unwrap();
+        assert_eq!(mean(&VectorReader::<u64>::try_new(&zeroes[..]).unwrap()).unwrap(), Some(5.0));
+        let zeroes = appender.encode_all(vec![0u64; 256]).unwrap();
+        assert_eq!(mean(&VectorReader::<u64>::try_new(&zeroes[..]).unwrap()).unwrap(), Some(0.0));
+
+        // Tracked nulls are not counted, in full and partial sections
+        appender.track_nulls(true);
+        appender.append_options((0..600u64).map(|i| if i % 2 == 0 { None } else { Some(i * 1_000_000_000) }))
+                .unwrap();
+        let x = appender.finish(600).unwrap();
+        let x = VectorReader::<u64>::try_new(&x[..]).unwrap();
+        assert_eq!(mean(&x).unwrap(), Some(300e9));
+
+        let ints = VectorU32Appender::try_new(1024).unwrap().encode_all(vec![u32::MAX; 1000]).unwrap();
+        let ints = VectorReader::<u32>::try_new(&ints[..]).unwrap();
+        assert_eq!(mean(&ints).unwrap(), Some(u32::MAX as f64));
+
+        let floats = VectorF64XorAppender::try_new(1024).unwrap().encode_all((0..512).map(|i| i as f64)).unwrap();
+        let floats = VectorReader::<f64>::try_new(&floats[..]).unwrap();
+        assert_eq!(mean(&floats).unwrap(), Some(255.5));
+        let empty = VectorF64XorAppender::try_new(1024).unwrap().finish(0).unwrap();
+        assert_eq!(mean(&VectorReader::<f64>::try_new(&empty[..]).unwrap()).unwrap(), None);
+    }
+}
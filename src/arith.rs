@@ -0,0 +1,295 @@
+/// The `arith` module contains binary arithmetic kernels which combine two vectors of the same type
+/// element by element, producing a new encoded vector.  The kernels work a section at a time, so that
+/// section metadata can be used to skip decoding where possible.
+///
+/// ## Null propagation
+///
+/// Query engines generally expect SQL semantics for computed columns, ie `null + x = null`.  Only nulls
+/// tracked by appenders with `track_nulls()`, the null elements of PartialNull sections, are nulls here: Null
+/// sections are 256 zeroes, and are values like any other.  The `NullPolicy` decides how tracked nulls are
+/// treated:
+/// - `NullPolicy::Propagate`: an element null in either input is null in the output.  The appender tracks
+///   nulls while writing the output if either input has any, and is set back afterwards.
+/// - `NullPolicy::TreatAsZero`: null elements are decoded as zeroes, so `null + x = x`.
+///
+/// Where both input sections are Null sections, zeroes are written without decoding either.
+///
+/// ```
+/// # use compressed_vec::vector::{VectorU32Appender, VectorReader};
+/// # use compressed_vec::arith::{self, ArithOp, NullPolicy};
+///     let mut appender = VectorU32Appender::try_new(1024).unwrap();
+///     let a = appender.encode_all(vec![1, 2, 3]).unwrap();
+///     let b = appender.encode_all(vec![10, 20, 30]).unwrap();
+///     let reader_a = VectorReader::<u32>::try_new(&a[..]).unwrap();
+///     let reader_b = VectorReader::<u32>::try_new(&b[..]).unwrap();
+///     let sum = arith::binary_op(&mut appender, &reader_a, &reader_b,
+///                                ArithOp::Add, NullPolicy::Propagate).unwrap();
+///     let reader = VectorReader::<u32>::try_new(&sum[..]).unwrap();
+///     assert_eq!(reader.iterate().collect::<Vec<u32>>(), vec![11, 22, 33]);
+/// ```
+use packed_simd::u32x8;
+
+use crate::error::CodingError;
+use crate::section::*;
+use crate::sink::*;
+use crate::vector::{BaseSubtypeMapping, VectorAppender, VectorReader};
+
+
+/// The binary operation to apply to each pair of elements
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+/// How tracked nulls in either input are treated.  See the module docs.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NullPolicy {
+    Propagate,
+    TreatAsZero,
+}
+
+/// Base types which the arithmetic kernels can operate on.
+/// Integer operations wrap around on overflow/underflow, as do the SIMD lanes.
+pub trait ArithBase: VectBase {
+    fn apply(op: ArithOp, a: Self, b: Self) -> Self;
+}
+
+macro_rules! impl_arith_int {
+    ($t:ty) => {
+        impl ArithBase for $t {
+            #[inline]
+            fn apply(op: ArithOp, a: $t, b: $t) -> $t {
+                match op {
+                    ArithOp::Add => a.wrapping_add(b),
+                    ArithOp::Sub => a.wrapping_sub(b),
+                    ArithOp::Mul => a.wrapping_mul(b),
+                }
+            }
+        }
+    };
+}
+
+impl_arith_int!(u32);
+impl_arith_int!(u64);
+
+impl ArithBase for f32 {
+    #[inline]
+    fn apply(op: ArithOp, a: f32, b: f32) -> f32 {
+        match op {
+            ArithOp::Add => a + b,
+            ArithOp::Sub => a - b,
+            ArithOp::Mul => a * b,
+        }
+    }
+}
+
+impl ArithBase for f64 {
+    #[inline]
+    fn apply(op: ArithOp, a: f64, b: f64) -> f64 {
+        match op {
+            ArithOp::Add => a + b,
+            ArithOp::Sub => a - b,
+            ArithOp::Mul => a * b,
+        }
+    }
+}
+
+/// Applies op to each pair of elements from left and right, writing the results using the appender and
+/// returning the finished vector.  Both vectors must have the same number of elements, otherwise
+/// `InvalidNumRows(right_elements, left_elements)` is returned.
+/// Tracked nulls are handled according to the NullPolicy.
+pub fn binary_op<T, W>(appender: &mut VectorAppender<T, W>,
+                       left: &VectorReader<T>,
+                       right: &VectorReader<T>,
+                       op: ArithOp,
+                       nulls: NullPolicy) -> Result<Vec<u8>, CodingError>
+where T: ArithBase + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    let num_elements = left.num_elements();
+    if right.num_elements() != num_elements {
+        return Err(CodingError::InvalidNumRows(right.num_elements(), num_elements));
+    }
+
+    let tracked = appender.tracks_nulls();
+    if nulls == NullPolicy::Propagate && (has_partial_nulls(left) || has_partial_nulls(right)) {
+        appender.track_nulls(true);
+    }
+    let result = apply_sections(appender, left, right, op, nulls);
+    appender.track_nulls(tracked);
+    result
+}
+
+fn apply_sections<T, W>(appender: &mut VectorAppender<T, W>,
+                        left: &VectorReader<T>,
+                        right: &VectorReader<T>,
+                        op: ArithOp,
+                        nulls: NullPolicy) -> Result<Vec<u8>, CodingError>
+where T: ArithBase + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    let num_elements = left.num_elements();
+    let mut left_sink = Section256Sink::<T>::new();
+    let mut right_sink = Section256Sink::<T>::new();
+    let mut elems_left = num_elements;
+    for (left_sect, right_sect) in left.sect_iter().zip(right.sect_iter()) {
+        let (left_sect, right_sect) = (left_sect?, right_sect?);
+        let sect_elems = elems_left.min(FIXED_LEN);
+        // Every op on two zeroes gives zero
+        if left_sect.is_null() && right_sect.is_null() {
+            appender.append_zeroes(sect_elems)?;
+        } else {
+            let valid = match nulls {
+                NullPolicy::Propagate   => left_sect.validity_mask() & right_sect.validity_mask(),
+                NullPolicy::TreatAsZero => u32x8::splat(u32::MAX),
+            };
+            left_sink.reset();
+            right_sink.reset();
+            left_sect.decode(&mut left_sink)?;
+            right_sect.decode(&mut right_sink)?;
+            for i in 0..sect_elems {
+                if valid.extract(i / 32) & (1 << (i % 32)) == 0 {
+                    appender.append_nulls(1)?;
+                } else {
+                    appender.append(T::apply(op, left_sink.values[i], right_sink.values[i]))?;
+                }
+            }
+        }
+        elems_left -= sect_elems;
+    }
+
+    appender.finish(num_elements)
+}
+
+fn has_partial_nulls<T: VectBase + BaseSubtypeMapping>(vector: &VectorReader<T>) -> bool {
+    vector.sect_iter().any(|sect| matches!(sect, Ok(FixedSectEnum::PartialNullFixedSect(_))))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{VectorU32Appender, VectorF32XorAppender};
+
+    // 656 elements, with a null section either first or second
+    fn vector_with_nulls_at(appender: &mut VectorU32Appender, null_first: bool) -> Vec<u8> {
+        if null_first {
+            appender.append_nulls(256).unwrap();
+            (0..400).for_each(|i| appender.append(i + 1).unwrap());
+        } else {
+            (0..256).for_each(|i| appender.append(i + 1).unwrap());
+            appender.append_nulls(256).unwrap();
+            (0..144).for_each(|i| appender.append(i + 1).unwrap());
+        }
+        appender.finish(656).unwrap()
+    }
+
+    #[test]
+    fn test_add_propagate_keeps_zero_sections() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        let left = vector_with_nulls_at(&mut appender, true);
+        let right = vector_with_nulls_at(&mut appender, false);
+        let left_reader = VectorReader::<u32>::try_new(&left[..]).unwrap();
+        let right_reader = VectorReader::<u32>::try_new(&right[..]).unwrap();
+
+        let out = binary_op(&mut appender, &left_reader, &right_reader,
+                            ArithOp::Add, NullPolicy::Propagate).unwrap();
+        let reader = VectorReader::<u32>::try_new(&out[..]).unwrap();
+        assert_eq!(reader.num_elements(), 656);
+        // Null sections are zeroes, not tracked nulls, so x + 0 = x
+        assert_eq!(reader.num_null_sections().unwrap(), 0);
+        assert_eq!(reader.validity_masks().filter(|&m| m != u32x8::splat(u32::MAX)).count(), 0);
+
+        let expected: Vec<u32> = left_reader.iterate().zip(right_reader.iterate()).map(|(l, r)| l + r).collect();
+        assert_eq!(reader.iterate().collect::<Vec<u32>>(), expected);
+
+        // Zero sections in both inputs stay Null sections
+        let both = binary_op(&mut appender, &left_reader, &left_reader, ArithOp::Mul, NullPolicy::Propagate).unwrap();
+        assert_eq!(VectorReader::<u32>::try_new(&both[..]).unwrap().num_null_sections().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_propagate_partial_nulls() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.track_nulls(true);
+        let values: Vec<Option<u32>> = (0..300).map(|i| if i % 5 == 0 { None } else { Some(i) }).collect();
+        let left = appender.encode_all_options(values.clone()).unwrap();
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        let right = appender.encode_all(0..300u32).unwrap();
+        let left_reader = VectorReader::<u32>::try_new(&left[..]).unwrap();
+        let right_reader = VectorReader::<u32>::try_new(&right[..]).unwrap();
+
+        let out = binary_op(&mut appender, &right_reader, &left_reader,
+                            ArithOp::Add, NullPolicy::Propagate).unwrap();
+        let reader = VectorReader::<u32>::try_new(&out[..]).unwrap();
+        let expected: Vec<Option<u32>> = values.iter().map(|v| v.map(|v| v * 2)).collect();
+        assert_eq!(reader.iterate_options().take(300).collect::<Vec<_>>(), expected);
+        // Null tracking was only turned on for the output
+        assert!(!appender.tracks_nulls());
+
+        // Null elements are zeroes when treated as zero
+        let out = binary_op(&mut appender, &right_reader, &left_reader,
+                            ArithOp::Add, NullPolicy::TreatAsZero).unwrap();
+        let reader = VectorReader::<u32>::try_new(&out[..]).unwrap();
+        let expected: Vec<u32> = (0..300).map(|i| if i % 5 == 0 { i } else { i * 2 }).collect();
+        assert_eq!(reader.iterate().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_zero_sections_are_values() {
+        // 256 zero values are written as a Null section, even with null tracking on
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.track_nulls(true);
+        let zeroes = appender.encode_all(vec![0u32; 256]).unwrap();
+        let ones = appender.encode_all(vec![1u32; 256]).unwrap();
+        let zeroes = VectorReader::<u32>::try_new(&zeroes[..]).unwrap();
+        let ones = VectorReader::<u32>::try_new(&ones[..]).unwrap();
+        assert_eq!(zeroes.num_null_sections().unwrap(), 1);
+
+        let out = binary_op(&mut appender, &zeroes, &zeroes, ArithOp::Add, NullPolicy::Propagate).unwrap();
+        let reader = VectorReader::<u32>::try_new(&out[..]).unwrap();
+        assert!(reader.iterate_options().take(256).all(|v| v == Some(0)));
+        let out = binary_op(&mut appender, &zeroes, &ones, ArithOp::Add, NullPolicy::Propagate).unwrap();
+        let reader = VectorReader::<u32>::try_new(&out[..]).unwrap();
+        assert!(reader.iterate_options().take(256).all(|v| v == Some(1)));
+    }
+
+    #[test]
+    fn test_sub_treat_nulls_as_zero() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        let left = vector_with_nulls_at(&mut appender, true);
+        let right = vector_with_nulls_at(&mut appender, false);
+        let left_reader = VectorReader::<u32>::try_new(&left[..]).unwrap();
+        let right_reader = VectorReader::<u32>::try_new(&right[..]).unwrap();
+
+        let out = binary_op(&mut appender, &left_reader, &right_reader,
+                            ArithOp::Sub, NullPolicy::TreatAsZero).unwrap();
+        let reader = VectorReader::<u32>::try_new(&out[..]).unwrap();
+        assert_eq!(reader.num_null_sections().unwrap(), 0);
+
+        let expected: Vec<u32> = left_reader.iterate().zip(right_reader.iterate())
+                                            .map(|(l, r)| l.wrapping_sub(r))
+                                            .collect();
+        assert_eq!(reader.iterate().collect::<Vec<u32>>(), expected);
+    }
+
+    #[test]
+    fn test_mul_f32_and_length_mismatch() {
+        let mut appender = VectorF32XorAppender::try_new(2048).unwrap();
+        let left = appender.encode_all((0..300).map(|x| x as f32 / 2.0)).unwrap();
+        let right = appender.encode_all((0..300).map(|x| x as f32 + 0.5)).unwrap();
+        let short = appender.encode_all(vec![1.0f32, 2.0]).unwrap();
+        let left_reader = VectorReader::<f32>::try_new(&left[..]).unwrap();
+        let right_reader = VectorReader::<f32>::try_new(&right[..]).unwrap();
+        let short_reader = VectorReader::<f32>::try_new(&short[..]).unwrap();
+
+        let out = binary_op(&mut appender, &left_reader, &right_reader,
+                            ArithOp::Mul, NullPolicy::Propagate).unwrap();
+        let reader = VectorReader::<f32>::try_new(&out[..]).unwrap();
+        let expected: Vec<f32> = (0..300).map(|x| (x as f32 / 2.0) * (x as f32 + 0.5)).collect();
+        assert_eq!(reader.iterate().collect::<Vec<f32>>(), expected);
+
+        let res = binary_op(&mut appender, &left_reader, &short_reader, ArithOp::Add, NullPolicy::Propagate);
+        assert_eq!(res, Err(CodingError::InvalidNumRows(2, 300)));
+    }
+}
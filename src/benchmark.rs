@@ -0,0 +1,220 @@
+/// The `benchmark` module contains generators for the data shapes used by the decode benchmarks, plus a small
+/// harness for comparing decode throughput across builds.  It is exposed in the crate so that anyone changing
+/// the SIMD kernels can verify that no data shape regresses by more than some threshold:
+/// ```
+/// # use compressed_vec::benchmark::*;
+///     let baseline = decode_throughputs(4096, 10);
+///     // ... build and run with the new kernels, maybe in a different process ...
+///     let current = decode_throughputs(4096, 10);
+///     if let Err(regressions) = check_regressions(&baseline, &current, 0.5) {
+///         println!("Regressions: {:?}", regressions);
+///     }
+/// ```
+/// Measurements are wall-clock based, so compare runs from the same machine only.
+use std::time::Instant;
+
+use crate::section::{AutoEncoder, DeltaNPMedFixedSect, FixedSectionWriter, NibblePackMedFixedSect, VectBase};
+use crate::sink::{Section256Sink, Sink};
+use crate::vector::{BaseSubtypeMapping, VectorAppender, VectorReader};
+
+
+/// The shape of the data being encoded, which largely determines the section types chosen and decode speed
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DataShape {
+    Constant,     // The same nonzero value repeated
+    SmallRange,   // Values within a small range of a large base value
+    Random,       // Uniformly random 32-bit values
+    Sparse,       // Mostly zeroes, with about 1 in 20 values nonzero
+    Monotonic,    // Increasing values with small random steps, eg timestamps
+}
+
+impl DataShape {
+    pub const ALL: [DataShape; 5] = [DataShape::Constant, DataShape::SmallRange, DataShape::Random,
+                                     DataShape::Sparse, DataShape::Monotonic];
+
+    /// Generates len values of this shape.  The output is deterministic for a given shape and length.
+    pub fn generate_u64(self, len: usize) -> Vec<u64> {
+        let mut rng = XorShift64(0x2545_F491_4F6C_DD1D);
+        match self {
+            DataShape::Constant   => vec![12_345; len],
+            DataShape::SmallRange => (0..len).map(|_| 100_000 + rng.next() % 16).collect(),
+            DataShape::Random     => (0..len).map(|_| rng.next() >> 32).collect(),
+            DataShape::Sparse     => (0..len).map(|_| {
+                                         let r = rng.next();
+                                         if r % 20 == 0 { r >> 48 } else { 0 }
+                                     }).collect(),
+            DataShape::Monotonic  => {
+                let mut value = 1_500_000_000u64;
+                (0..len).map(|_| { value += 1 + rng.next() % 100; value }).collect()
+            }
+        }
+    }
+
+    /// Same as generate_u64 but with values truncated to u32
+    pub fn generate_u32(self, len: usize) -> Vec<u32> {
+        self.generate_u64(len).into_iter().map(|x| x as u32).collect()
+    }
+}
+
+// Tiny deterministic PRNG so that we don't need a dependency on rand outside of tests
+struct XorShift64(u64);
+
+impl XorShift64 {
+    #[inline]
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// One throughput measurement of a named kernel over a data shape
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapeMeasurement {
+    pub kernel: String,
+    pub shape: DataShape,
+    pub elements_per_sec: f64,
+}
+
+/// A kernel/shape combination whose throughput dropped by more than the allowed threshold
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regression {
+    pub kernel: String,
+    pub shape: DataShape,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+impl Regression {
+    /// The fraction of baseline throughput lost, eg 0.25 means 25% slower
+    pub fn slowdown(&self) -> f64 {
+        1.0 - self.current / self.baseline
+    }
+}
+
+/// Times iterations runs of func, each of which processes num_elements, returning the throughput.
+pub fn measure<F: FnMut()>(kernel: &str,
+                           shape: DataShape,
+                           num_elements: usize,
+                           iterations: usize,
+                           mut func: F) -> ShapeMeasurement {
+    func();   // Warm up caches
+    let start = Instant::now();
+    for _ in 0..iterations {
+        func();
+    }
+    let secs = start.elapsed().as_secs_f64().max(1e-9);
+    ShapeMeasurement { kernel: kernel.to_string(),
+                       shape,
+                       elements_per_sec: (num_elements * iterations) as f64 / secs }
+}
+
+fn measure_vector_decode<T, W>(kernel: &str,
+                               shape: DataShape,
+                               values: Vec<T>,
+                               iterations: usize) -> ShapeMeasurement
+where T: VectBase + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    let num_elements = values.len();
+    let mut appender = VectorAppender::<T, W>::try_new(num_elements * 2).unwrap();
+    let encoded = appender.encode_all(values).unwrap();
+    let reader = VectorReader::<T>::try_new(&encoded[..]).unwrap();
+    let mut sink = Section256Sink::<T>::new();
+    measure(kernel, shape, num_elements, iterations, || {
+        for sect in reader.sect_iter() {
+            sink.reset();
+            sect.unwrap().decode(&mut sink).unwrap();
+        }
+    })
+}
+
+/// Measures whole-vector decode throughput of vectors of len elements, for every DataShape and for the
+/// u32 and u64 section writers.  The results can be saved and compared using `check_regressions`.
+pub fn decode_throughputs(len: usize, iterations: usize) -> Vec<ShapeMeasurement> {
+    let mut results = Vec::new();
+    for &shape in DataShape::ALL.iter() {
+        let u32s = shape.generate_u32(len);
+        let u64s = shape.generate_u64(len);
+        results.push(measure_vector_decode::<u32, AutoEncoder>(
+                         "u32 auto", shape, u32s.clone(), iterations));
+        results.push(measure_vector_decode::<u32, NibblePackMedFixedSect<u32>>(
+                         "u32 nibblepack", shape, u32s.clone(), iterations));
+        results.push(measure_vector_decode::<u32, DeltaNPMedFixedSect<u32>>(
+                         "u32 delta", shape, u32s, iterations));
+        results.push(measure_vector_decode::<u64, AutoEncoder>(
+                         "u64 auto", shape, u64s.clone(), iterations));
+        results.push(measure_vector_decode::<u64, NibblePackMedFixedSect<u64>>(
+                         "u64 nibblepack", shape, u64s, iterations));
+    }
+    results
+}
+
+/// Compares current measurements against baseline ones with the same kernel and shape.  Returns Err with
+/// every measurement which is slower than baseline by more than max_slowdown (eg 0.1 for 10%).
+/// Measurements without a baseline counterpart are ignored.
+pub fn check_regressions(baseline: &[ShapeMeasurement],
+                         current: &[ShapeMeasurement],
+                         max_slowdown: f64) -> Result<(), Vec<Regression>> {
+    let regressions: Vec<Regression> = current.iter().filter_map(|cur| {
+        baseline.iter()
+                .find(|base| base.kernel == cur.kernel && base.shape == cur.shape)
+                .map(|base| Regression { kernel: cur.kernel.clone(),
+                                         shape: cur.shape,
+                                         baseline: base.elements_per_sec,
+                                         current: cur.elements_per_sec })
+                .filter(|r| r.slowdown() > max_slowdown)
+    }).collect();
+
+    if regressions.is_empty() { Ok(()) } else { Err(regressions) }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_shapes() {
+        let constant = DataShape::Constant.generate_u32(300);
+        assert_eq!(constant.len(), 300);
+        assert!(constant.iter().all(|&x| x == 12_345));
+
+        let small = DataShape::SmallRange.generate_u64(300);
+        assert!(small.iter().all(|&x| x >= 100_000 && x < 100_016));
+
+        let sparse = DataShape::Sparse.generate_u64(1000);
+        let nonzeroes = sparse.iter().filter(|&&x| x != 0).count();
+        assert!(nonzeroes > 10 && nonzeroes < 150);
+
+        let monotonic = DataShape::Monotonic.generate_u64(300);
+        assert!(monotonic.windows(2).all(|w| w[1] > w[0]));
+
+        // Deterministic
+        assert_eq!(DataShape::Random.generate_u32(50), DataShape::Random.generate_u32(50));
+    }
+
+    #[test]
+    fn test_check_regressions() {
+        let m = |kernel: &str, shape, elements_per_sec| ShapeMeasurement {
+            kernel: kernel.to_string(), shape, elements_per_sec
+        };
+        let baseline = vec![m("u32 auto", DataShape::Random, 100.0), m("u32 auto", DataShape::Sparse, 100.0)];
+        let current = vec![m("u32 auto", DataShape::Random, 95.0),
+                           m("u32 auto", DataShape::Sparse, 70.0),
+                           m("u64 auto", DataShape::Sparse, 1.0)];
+        assert_eq!(check_regressions(&baseline, &current[..1], 0.1), Ok(()));
+
+        let regressions = check_regressions(&baseline, &current, 0.1).unwrap_err();
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].shape, DataShape::Sparse);
+        assert!((regressions[0].slowdown() - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_decode_throughputs_covers_all_shapes() {
+        let results = decode_throughputs(512, 1);
+        assert_eq!(results.len(), DataShape::ALL.len() * 5);
+        assert!(results.iter().all(|r| r.elements_per_sec > 0.0));
+    }
+}
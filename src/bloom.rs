@@ -0,0 +1,252 @@
+/// The `bloom` module contains the Bloom filters over the values of a vector, which an appender can build while
+/// encoding and store in the vector footer.  Point lookups over thousands of vectors can then skip most of them
+/// by checking `VectorReader::might_contain()`, which reads only the footer, without decoding any sections.
+/// ```
+/// # use compressed_vec::vector::{VectorReader, VectorU64Appender};
+///     let mut appender = VectorU64Appender::try_new(4096).unwrap();
+///     appender.enable_bloom_filter(10);
+///     let bytes = appender.encode_all((0..1000u64).map(|i| i * 1000)).unwrap();
+///
+///     let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+///     assert!(reader.might_contain(5000));
+///     assert_eq!(reader.iterate().nth(5), Some(5000));
+///     // False positives are possible, but rare with 10 bits per value
+///     assert!((0..1000u64).filter(|i| reader.might_contain(i * 1000 + 1)).count() < 30);
+/// ```
+/// Appenders can also build a small filter per section with `enable_section_bloom_filters()`, which equality
+/// filters use to skip sections without decoding them, see `VectorFilter::set_section_bloom_filters()`.
+///
+/// Values are hashed by their bits, so for floats -0.0 and 0.0 are different values, and NaNs match only NaNs
+/// with the same bits.  The hash is fixed, so Bloom filters are identical across platforms.
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::error::CodingError;
+
+
+const MAX_HASHES: u32 = 16;
+const MAX_SECTION_BITS: u32 = 64;
+
+// The splitmix64 finalizer: a fixed, fast 64-bit mix
+#[inline]
+pub(crate) fn mix(bits: u64) -> u64 {
+    let mut z = bits.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// Bit positions for a hash, using double hashing
+#[inline]
+fn bit_positions(hash: u64, num_hashes: u32, num_bits: u64) -> impl Iterator<Item = u64> {
+    let h1 = hash & 0xffff_ffff;
+    let h2 = (hash >> 32) | 1;
+    (0..num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+}
+
+/// Collects the hashes of the distinct values appended to a vector, and writes the Bloom filter when the vector
+/// is finished, sized for the number of distinct values.
+#[derive(Debug)]
+pub(crate) struct BloomBuilder<T> {
+    to_bits: fn(T) -> u64,
+    bits_per_value: u32,
+    hashes: HashSet<u64>,
+}
+
+impl<T> BloomBuilder<T> {
+    pub fn new(to_bits: fn(T) -> u64, bits_per_value: u32) -> Self {
+        Self { to_bits, bits_per_value: bits_per_value.max(1), hashes: HashSet::new() }
+    }
+
+    #[inline]
+    pub fn insert(&mut self, value: T) {
+        self.hashes.insert(mix((self.to_bits)(value)));
+    }
+
+    /// Returns the Bloom filter footer entry for the values inserted so far, and clears them.
+    /// Layout: u8 number of hashes, then the bits, LSB first.
+    pub fn finish(&mut self) -> Vec<u8> {
+        // k = bits per value * ln 2 minimizes the false positive rate
+        let num_hashes = ((self.bits_per_value * 69 + 50) / 100).max(1).min(MAX_HASHES);
+        let num_bytes = ((self.hashes.len() * self.bits_per_value as usize + 7) / 8).max(8);
+        let mut entry = vec![0u8; num_bytes + 1];
+        entry[0] = num_hashes as u8;
+        let num_bits = num_bytes as u64 * 8;
+        for &hash in &self.hashes {
+            for bit in bit_positions(hash, num_hashes, num_bits) {
+                entry[1 + (bit / 8) as usize] |= 1 << (bit % 8);
+            }
+        }
+        self.hashes.clear();
+        entry
+    }
+
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+    }
+}
+
+/// Builds the Bloom filter of each section, from the values encoded into it, for the footer entry read by
+/// `SectionBloomFilters`.  Layout: for each section, a u16 filter length, 0 for sections without a filter such
+/// as Null sections, then the filter as laid out by `BloomBuilder::finish()`.
+#[derive(Debug)]
+pub(crate) struct SectionBloomBuilder<T> {
+    section: BloomBuilder<T>,
+    entry: Vec<u8>,
+}
+
+impl<T: Copy> SectionBloomBuilder<T> {
+    pub fn new(to_bits: fn(T) -> u64, bits_per_value: u32) -> Self {
+        // 256 distinct values must fit in a u16 filter length
+        Self { section: BloomBuilder::new(to_bits, bits_per_value.min(MAX_SECTION_BITS)), entry: Vec::new() }
+    }
+
+    /// Adds the filter of a section with the given values
+    pub fn add_section(&mut self, values: &[T]) {
+        values.iter().for_each(|&v| self.section.insert(v));
+        let filter = self.section.finish();
+        self.entry.extend_from_slice(&(filter.len() as u16).to_le_bytes());
+        self.entry.extend_from_slice(&filter[..]);
+    }
+
+    /// Adds a section without a filter, which may contain any value
+    pub fn add_unfiltered(&mut self) {
+        self.entry.extend_from_slice(&0u16.to_le_bytes());
+    }
+
+    /// Drops the filters of the sections after the first num_sections
+    pub fn truncate(&mut self, num_sections: usize) {
+        let mut pos = 0;
+        for _ in 0..num_sections {
+            if pos >= self.entry.len() { return }
+            pos += 2 + u16::from_le_bytes([self.entry[pos], self.entry[pos + 1]]) as usize;
+        }
+        self.entry.truncate(pos);
+    }
+
+    /// Returns the footer entry for the sections added so far, and clears them
+    pub fn finish(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.entry)
+    }
+
+    pub fn clear(&mut self) {
+        self.entry.clear();
+    }
+}
+
+/// A Bloom filter read from a vector footer
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BloomFilter<'buf> {
+    num_hashes: u32,
+    bits: &'buf [u8],
+}
+
+impl<'buf> BloomFilter<'buf> {
+    /// Reads a Bloom filter from its footer entry
+    pub fn try_from_entry(entry: &'buf [u8]) -> Result<Self, CodingError> {
+        match entry.split_first() {
+            Some((&num_hashes, bits)) if num_hashes > 0 && !bits.is_empty() =>
+                Ok(Self { num_hashes: num_hashes as u32, bits }),
+            _ => Err(CodingError::InvalidFormat(format!("Invalid Bloom filter of {} bytes", entry.len()))),
+        }
+    }
+
+    /// Number of bits in the filter
+    pub fn num_bits(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// Returns false if the value, given as its bits, is definitely not in the vector
+    #[inline]
+    pub fn might_contain_bits(&self, value_bits: u64) -> bool {
+        bit_positions(mix(value_bits), self.num_hashes, self.num_bits() as u64)
+            .all(|bit| self.bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+    }
+}
+
+/// The per-section Bloom filters of a vector, read from its footer, as an iterator over the filter of each
+/// section in order.  Sections without a filter yield None.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionBloomFilters<'buf> {
+    rest: &'buf [u8],
+}
+
+impl<'buf> SectionBloomFilters<'buf> {
+    /// Reads the section filters from their footer entry, checking every filter
+    pub fn try_from_entry(entry: &'buf [u8]) -> Result<Self, CodingError> {
+        let filters = Self { rest: entry };
+        for filter in filters.clone().entries() {
+            if let Some(filter) = filter? { BloomFilter::try_from_entry(filter)?; }
+        }
+        Ok(filters)
+    }
+
+    /// Returns the part of a footer entry holding the filters of the given range of sections, itself a footer
+    /// entry for a vector of just those sections, eg a shard.  Sections past the last filter are left out.
+    pub(crate) fn entry_for_sections(entry: &'buf [u8], sections: Range<usize>) -> Result<&'buf [u8], CodingError> {
+        let mut offsets = vec![0];
+        for filter in (Self { rest: entry }).entries().take(sections.end) {
+            offsets.push(offsets[offsets.len() - 1] + 2 + filter?.map_or(0, |f| f.len()));
+        }
+        let end = offsets[sections.end.min(offsets.len() - 1)];
+        Ok(&entry[offsets[sections.start.min(offsets.len() - 1)]..end])
+    }
+
+    fn entries(mut self) -> impl Iterator<Item = Result<Option<&'buf [u8]>, CodingError>> {
+        std::iter::from_fn(move || {
+            if self.rest.is_empty() { return None }
+            let len = match self.rest.get(..2) {
+                Some(len) => u16::from_le_bytes([len[0], len[1]]) as usize,
+                None      => return Some(Err(CodingError::InputTooShort)),
+            };
+            let filter = match self.rest.get(2..2 + len) {
+                Some(filter) => filter,
+                None         => return Some(Err(CodingError::InputTooShort)),
+            };
+            self.rest = &self.rest[2 + len..];
+            Some(Ok(if len == 0 { None } else { Some(filter) }))
+        })
+    }
+}
+
+impl<'buf> Iterator for SectionBloomFilters<'buf> {
+    type Item = Option<BloomFilter<'buf>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() { return None }
+        // Lengths and filters were checked by try_from_entry()
+        let len = u16::from_le_bytes([self.rest[0], self.rest[1]]) as usize;
+        let filter = &self.rest[2..2 + len];
+        self.rest = &self.rest[2 + len..];
+        Some(BloomFilter::try_from_entry(filter).ok())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_false_positive_rate() {
+        let mut builder = BloomBuilder::<u64>::new(|v| v, 10);
+        (0..5000u64).for_each(|i| builder.insert(i * 7));
+        (0..5000u64).for_each(|i| builder.insert(i * 7));    // Duplicates don't grow the filter
+        let entry = builder.finish();
+        assert_eq!(entry.len(), 1 + 5000 * 10 / 8);
+        assert_eq!(entry[0], 7);
+
+        let bloom = BloomFilter::try_from_entry(&entry[..]).unwrap();
+        assert!((0..5000u64).all(|i| bloom.might_contain_bits(i * 7)));
+        let false_positives = (0..10000u64).filter(|i| bloom.might_contain_bits(i * 7 + 3)).count();
+        assert!(false_positives < 200, "{} false positives", false_positives);
+
+        // The builder is cleared by finish()
+        let empty = builder.finish();
+        assert_eq!(empty.len(), 9);
+        assert!(!BloomFilter::try_from_entry(&empty[..]).unwrap().might_contain_bits(7));
+
+        assert!(BloomFilter::try_from_entry(&[3]).is_err());
+        assert!(BloomFilter::try_from_entry(&[0, 255]).is_err());
+    }
+}
@@ -0,0 +1,362 @@
+/// The `boolvec` module supports vectors of booleans, such as flag columns.  The vectors use the same 16-byte
+/// header and 256-element sections as the integer vectors, but each section is a 32-byte bitmap, 1 bit per
+/// element, instead of 32 bits per element in a u32 vector.
+///
+/// Sections which are all false, including nulls, are written as null sections.  Filtering needs no decoding
+/// at all: the bitmap of a section is already the u32x8 mask used by the `filter` module.
+/// ```
+/// # use compressed_vec::boolvec::*;
+/// # use compressed_vec::filter::count_hits;
+///     let mut appender = BoolAppender::try_new(1024).unwrap();
+///     let bytes = appender.encode_all((0..300).map(|i| i % 3 == 0)).unwrap();
+///
+///     let reader = BoolReader::try_new(&bytes[..]).unwrap();
+///     assert_eq!(reader.iterate().take(4).collect::<Vec<_>>(), vec![true, false, false, true]);
+///     assert_eq!(count_hits(reader.filter_true()), 100);
+/// ```
+use packed_simd::u32x8;
+use scroll::{Pread, Pwrite, LE};
+
+use crate::error::CodingError;
+use crate::section::*;
+use crate::vector::{BinaryVector, FixedSectStats, VectorSubType, VectorType,
+                    BINARYVECT_HEADER_SIZE, NUM_HEADER_BYTES_TOTAL, format_version, wrong_subtype_error};
+
+
+const BITMAP_BYTES: usize = FIXED_LEN / 8;
+
+/// A section of 256 booleans, stored as a bitmap.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::Bitmap256
+///  +1   32 bytes of bitmap.  Element i is bit i % 8 of byte i / 8, least significant bit first
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Bitmap256Sect<'buf> {
+    bitmap: &'buf [u8],
+}
+
+impl<'buf> Bitmap256Sect<'buf> {
+    /// Tries to create a Bitmap256Sect from a byte slice starting from the section type byte.
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let sect_type: u8 = sect_bytes.pread_with(0, LE)?;
+        if sect_type != SectionType::Bitmap256.as_num() {
+            return Err(CodingError::InvalidSectionType(sect_type));
+        }
+        let bitmap = sect_bytes.get(1..1 + BITMAP_BYTES).ok_or(CodingError::InputTooShort)?;
+        Ok(Self { bitmap })
+    }
+
+    pub fn num_bytes(&self) -> usize { 1 + BITMAP_BYTES }
+
+    /// Writes out a section of 256 values from a bitmap starting at offset, or a null section if all values
+    /// are false.  Returns the offset after the section.
+    pub fn write(out_buf: &mut [u8], offset: usize, bitmap: &[u8; BITMAP_BYTES]) -> Result<usize, CodingError> {
+        if bitmap.iter().all(|&b| b == 0) { return NullFixedSect::write(out_buf, offset) }
+        out_buf.pwrite_with(SectionType::Bitmap256.as_num(), offset, LE)?;
+        out_buf.get_mut(offset + 1..offset + 1 + BITMAP_BYTES).ok_or(CodingError::NotEnoughSpace)?
+               .copy_from_slice(&bitmap[..]);
+        Ok(offset + 1 + BITMAP_BYTES)
+    }
+
+    /// Returns the bitmap as a mask of true elements
+    pub fn true_mask(&self) -> u32x8 {
+        bitmap_mask(self.bitmap)
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        self.bitmap[index / 8] & (1 << (index % 8)) != 0
+    }
+}
+
+/// A section in a FixedBool vector: either a null section (256 false values) or a bitmap
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BoolSect<'buf> {
+    Null,
+    Bitmap(Bitmap256Sect<'buf>),
+}
+
+impl<'buf> BoolSect<'buf> {
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        if sect_bytes.is_empty() { return Err(CodingError::InputTooShort) }
+        if sect_bytes[0] == SectionType::Null.as_num() {
+            Ok(BoolSect::Null)
+        } else {
+            Bitmap256Sect::try_from(sect_bytes).map(BoolSect::Bitmap)
+        }
+    }
+
+    pub fn num_bytes(&self) -> usize {
+        match self {
+            BoolSect::Null => 1,
+            BoolSect::Bitmap(s) => s.num_bytes(),
+        }
+    }
+
+    pub fn is_null(&self) -> bool { *self == BoolSect::Null }
+
+    /// Returns the mask of true elements in this section
+    pub fn true_mask(&self) -> u32x8 {
+        match self {
+            BoolSect::Null => u32x8::splat(0),
+            BoolSect::Bitmap(s) => s.true_mask(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        match self {
+            BoolSect::Null => false,
+            BoolSect::Bitmap(s) => s.get(index),
+        }
+    }
+}
+
+/// Iterates over the BoolSects in the bytes following the vector header
+pub struct BoolSectIterator<'buf> {
+    encoded_bytes: &'buf [u8],
+}
+
+impl<'buf> Iterator for BoolSectIterator<'buf> {
+    type Item = Result<BoolSect<'buf>, CodingError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.encoded_bytes.is_empty() {
+            None
+        } else {
+            let res = BoolSect::try_from(self.encoded_bytes);
+            if let Ok(sect) = &res {
+                self.encoded_bytes = &self.encoded_bytes[sect.num_bytes()..];
+            }
+            Some(res)
+        }
+    }
+}
+
+const GROW_BYTES: usize = 4096;
+
+/// An appender for FixedBool vectors.  Works just like `VectorAppender`: values are buffered and written
+/// 256 at a time, nulls are false, and `finish()` clones out the vector and resets the appender.
+pub struct BoolAppender {
+    vect_buf: Vec<u8>,
+    offset: usize,
+    header: BinaryVector,
+    bitmap: [u8; BITMAP_BYTES],
+    num_buffered: usize,
+    stats: FixedSectStats,
+}
+
+impl BoolAppender {
+    pub fn try_new(initial_capacity: usize) -> Result<Self, CodingError> {
+        let mut new_self = Self {
+            vect_buf: vec![0; initial_capacity],
+            offset: NUM_HEADER_BYTES_TOTAL,
+            header: BinaryVector::new(VectorType::FixedSection256, VectorSubType::FixedBool),
+            bitmap: [0u8; BITMAP_BYTES],
+            num_buffered: 0,
+            stats: FixedSectStats::new(),
+        };
+        new_self.header.write_header(new_self.vect_buf.as_mut_slice())?;
+        Ok(new_self)
+    }
+
+    /// Appends all values from a collection and finishes the vector, returning the encoded bytes.
+    pub fn encode_all<C>(&mut self, collection: C) -> Result<Vec<u8>, CodingError>
+    where C: IntoIterator<Item = bool> {
+        let mut count = 0;
+        for x in collection.into_iter() {
+            count += 1;
+            self.append(x)?;
+        }
+        self.finish(count)
+    }
+
+    /// Total number of elements including encoded sections and buffered values
+    pub fn num_elements(&self) -> usize {
+        self.stats.num_elements as usize + self.num_buffered
+    }
+
+    /// Resets the internal state for appending a new vector.
+    pub fn reset(&mut self) -> Result<(), CodingError> {
+        self.header.reset();
+        self.offset = NUM_HEADER_BYTES_TOTAL;
+        self.bitmap = [0u8; BITMAP_BYTES];
+        self.num_buffered = 0;
+        self.vect_buf.resize(self.vect_buf.capacity(), 0);
+        self.stats.reset();
+        self.stats.update_num_elems(&mut self.vect_buf, 0)?;
+        self.header.write_header(self.vect_buf.as_mut_slice())
+    }
+
+    fn write_section(&mut self) -> Result<(), CodingError> {
+        if self.vect_buf.len() < self.offset + 1 + BITMAP_BYTES {
+            self.vect_buf.reserve(GROW_BYTES);
+            self.vect_buf.resize(self.vect_buf.capacity(), 0);
+        }
+        self.offset = Bitmap256Sect::write(self.vect_buf.as_mut_slice(), self.offset, &self.bitmap)?;
+        self.bitmap = [0u8; BITMAP_BYTES];
+        self.num_buffered = 0;
+        self.stats.update_num_elems(&mut self.vect_buf, self.stats.num_elements + FIXED_LEN as u32)?;
+        self.header.update_num_bytes(self.vect_buf.as_mut_slice(),
+                                     (self.offset - NUM_HEADER_BYTES_TOTAL) as u32)
+    }
+
+    /// Appends a single value.  When a section fills up, it is encoded into the vector.
+    pub fn append(&mut self, value: bool) -> Result<(), CodingError> {
+        if value { self.bitmap[self.num_buffered / 8] |= 1 << (self.num_buffered % 8); }
+        self.num_buffered += 1;
+        if self.num_buffered >= FIXED_LEN { self.write_section() } else { Ok(()) }
+    }
+
+    /// Appends a number of nulls (false values).  Whole sections of nulls are counted as null sections.
+    pub fn append_nulls(&mut self, num_nulls: usize) -> Result<(), CodingError> {
+        let mut left = num_nulls;
+        while left > 0 {
+            if self.num_buffered == 0 && left >= FIXED_LEN { self.stats.num_null_sections += 1; }
+            let num_to_fill = left.min(FIXED_LEN - self.num_buffered);
+            self.num_buffered += num_to_fill;
+            left -= num_to_fill;
+            if self.num_buffered >= FIXED_LEN { self.write_section()?; }
+        }
+        Ok(())
+    }
+
+    /// Wraps up the vector, filling the last section with nulls, and clones out the vector bytes.
+    /// See `VectorAppender::finish()`.
+    pub fn finish(&mut self, total_num_rows: usize) -> Result<Vec<u8>, CodingError> {
+        let total_so_far = self.num_elements();
+        if total_so_far > total_num_rows { return Err(CodingError::InvalidNumRows(total_num_rows, total_so_far)); }
+        if total_num_rows > u32::max_value() as usize {
+            return Err(CodingError::InvalidNumRows(total_num_rows, u32::max_value() as usize));
+        }
+
+        if self.num_buffered > 0 {
+            self.append_nulls(FIXED_LEN - self.num_buffered)?;
+        }
+        while (self.stats.num_elements as usize) < total_num_rows {
+            self.append_nulls(FIXED_LEN)?;
+        }
+
+        self.stats.update_num_elems(self.vect_buf.as_mut_slice(), total_num_rows as u32)?;
+        self.vect_buf.resize(self.offset, 0);
+        let mut returned_vec = Vec::with_capacity(self.offset);
+        returned_vec.append(&mut self.vect_buf);
+        self.reset()?;
+        Ok(returned_vec)
+    }
+}
+
+/// A reader for FixedBool vectors written by `BoolAppender`
+pub struct BoolReader<'buf> {
+    vect_bytes: &'buf [u8],
+}
+
+impl<'buf> BoolReader<'buf> {
+    pub fn try_new(vect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let bytes_from_header: u32 = vect_bytes.pread_with(0, LE)?;
+        let subtype: u8 = vect_bytes.pread_with(offset_of!(BinaryVector, minor_type), LE)?;
+        format_version(vect_bytes)?;
+        let total_bytes = bytes_from_header as usize + 4;
+        // The header must at least cover itself, or reading the stats and sections would panic
+        if vect_bytes.len() < total_bytes || total_bytes < NUM_HEADER_BYTES_TOTAL {
+            Err(CodingError::InputTooShort)
+        } else if subtype != VectorSubType::FixedBool as u8 {
+            Err(wrong_subtype_error(subtype, VectorSubType::FixedBool))
+        } else {
+            Ok(Self { vect_bytes: &vect_bytes[..total_bytes] })
+        }
+    }
+
+    pub fn num_elements(&self) -> usize {
+        self.get_stats().num_elements as usize
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.vect_bytes.len()
+    }
+
+    /// Returns a FixedSectStats extracted from the vector header.
+    pub fn get_stats(&self) -> FixedSectStats {
+        self.vect_bytes.pread_with(BINARYVECT_HEADER_SIZE, LE).unwrap()
+    }
+
+    /// Returns an iterator over each section in this vector
+    pub fn sect_iter(&self) -> BoolSectIterator<'buf> {
+        BoolSectIterator { encoded_bytes: &self.vect_bytes[NUM_HEADER_BYTES_TOTAL..] }
+    }
+
+    /// Returns an iterator over all values in this vector
+    pub fn iterate(&self) -> impl Iterator<Item = bool> + 'buf {
+        self.sect_iter()
+            .flat_map(|sect| {
+                let sect = sect.expect("Unexpected end of section");
+                (0..FIXED_LEN).map(move |i| sect.get(i))
+            })
+            .take(self.num_elements())
+    }
+
+    /// Returns an iterator of u32x8 masks, one per section, for true elements.
+    /// Can be used with `filter::count_hits` etc.
+    pub fn filter_true(&self) -> impl Iterator<Item = u32x8> + 'buf {
+        self.sect_iter().map(|sect| sect.expect("This should not fail!").true_mask())
+    }
+
+    /// Returns an iterator of u32x8 masks, one per section, for false elements, including nulls and the
+    /// padding at the end of the last section.
+    pub fn filter_false(&self) -> impl Iterator<Item = u32x8> + 'buf {
+        self.filter_true().map(|mask| !mask)
+    }
+
+    /// Counts the true elements without decoding any sections
+    pub fn count_true(&self) -> Result<usize, CodingError> {
+        let mut count = 0;
+        for sect in self.sect_iter() {
+            if let BoolSect::Bitmap(s) = sect? {
+                count += s.bitmap.iter().map(|b| b.count_ones() as usize).sum::<usize>();
+            }
+        }
+        Ok(count)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::count_hits;
+
+    #[test]
+    fn test_bool_append_iterate_filter() {
+        let mut appender = BoolAppender::try_new(64).unwrap();
+        (0..300).for_each(|i| appender.append(i % 7 == 0).unwrap());
+        appender.append_nulls(600).unwrap();
+        (0..50).for_each(|_| appender.append(true).unwrap());
+        let bytes = appender.finish(960).unwrap();
+
+        let reader = BoolReader::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.num_elements(), 960);
+        let sects: Vec<BoolSect> = reader.sect_iter().map(|s| s.unwrap()).collect();
+        assert_eq!(sects.len(), 4);
+        assert_eq!(sects.iter().filter(|s| s.is_null()).count(), 1);
+        // Each bitmap section is 33 bytes, 32x smaller than 256 u32's
+        assert_eq!(bytes.len(), NUM_HEADER_BYTES_TOTAL + 3 * 33 + 1);
+
+        let values: Vec<bool> = reader.iterate().collect();
+        let expected: Vec<bool> = (0..960).map(|i| if i < 300 { i % 7 == 0 } else { (900..950).contains(&i) })
+                                          .collect();
+        assert_eq!(values, expected);
+
+        let num_true = expected.iter().filter(|&&b| b).count();
+        assert_eq!(count_hits(reader.filter_true()), num_true);
+        assert_eq!(reader.count_true().unwrap(), num_true);
+        assert_eq!(count_hits(reader.filter_false()), 1024 - num_true);
+        let mask = sects[0].true_mask();
+        assert_eq!(mask.extract(1), (0..32).filter(|b| (32 + b) % 7 == 0).map(|b| 1u32 << b).sum::<u32>());
+
+        let u32_bytes = crate::vector::VectorU32Appender::try_new(1024).unwrap().encode_all(vec![1, 2]).unwrap();
+        assert_eq!(BoolReader::try_new(&u32_bytes[..]).err(),
+                   Some(CodingError::WrongElementType { expected: VectorSubType::FixedBool,
+                                                        found: VectorSubType::FixedU32 }));
+
+        // A header whose length does not even cover the header itself
+        let mut corrupt = bytes.clone();
+        corrupt[..4].copy_from_slice(&8u32.to_le_bytes());
+        assert_eq!(BoolReader::try_new(&corrupt[..]).err(), Some(CodingError::InputTooShort));
+    }
+}
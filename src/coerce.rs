@@ -0,0 +1,169 @@
+/// The `coerce` module defines the implicit conversions between vector element types, for query engines which
+/// compare or aggregate columns of different types.  The allowed conversions never change a value, except that
+/// u64 values above 2^53 are rounded when coerced to f64:
+///
+/// - u32 -> u64 -> f64
+/// - u32 -> f64
+/// - f32 -> f64
+///
+/// `common_subtype()` picks the type two columns are coerced to, and `CoerceSink` converts decoded values
+/// 8 at a time with SIMD casts, as they are decoded, so whole sections are converted without a scalar loop.
+/// ```
+/// # use compressed_vec::coerce::*;
+/// # use compressed_vec::sink::VecSink;
+/// # use compressed_vec::vector::*;
+///     let ints = VectorU32Appender::try_new(1024).unwrap().encode_all(vec![1, 2, 3]).unwrap();
+///     let floats = VectorF64XorAppender::try_new(1024).unwrap().encode_all(vec![0.5, 0.25, 0.125]).unwrap();
+///     assert_eq!(common_subtype(VectorSubType::FixedU32, VectorSubType::FixedF64), Some(VectorSubType::FixedF64));
+///
+///     let mut sink = VecSink::<f64>::new();
+///     let reader = VectorReader::<u32>::try_new(&ints[..]).unwrap();
+///     reader.decode_to_sink(&mut CoerceSink::<u32, f64, _>::new(&mut sink)).unwrap();
+///     let floats = VectorReader::<f64>::try_new(&floats[..]).unwrap();
+///     let sums: Vec<f64> = sink.vec.iter().zip(floats.iterate()).map(|(a, b)| a + b).collect();
+///     assert_eq!(sums, vec![1.5, 2.25, 3.125]);
+/// ```
+/// Dictionary vectors of strings, from the `strdict` module, are not coerced: their u32 codes can be read like
+/// any u32 vector, and `StrDictReader` maps them to strings.
+use std::marker::PhantomData;
+
+use packed_simd::FromCast;
+
+use crate::section::VectBase;
+use crate::sink::Sink;
+use crate::vector::VectorSubType;
+
+
+/// An implicit conversion from Self to To, for single values and for 8 values at a time.
+/// Every type coerces to itself.
+pub trait Coerce<To: VectBase>: VectBase {
+    fn coerce(self) -> To;
+    fn coerce_simd(values: Self::SI) -> To::SI;
+}
+
+impl<T: VectBase> Coerce<T> for T {
+    #[inline]
+    fn coerce(self) -> T { self }
+    #[inline]
+    fn coerce_simd(values: T::SI) -> T::SI { values }
+}
+
+macro_rules! impl_coerce {
+    ($($from:ty => $to:ty),*) => { $(
+        impl Coerce<$to> for $from {
+            #[inline]
+            fn coerce(self) -> $to { self as $to }
+            #[inline]
+            fn coerce_simd(values: <$from as VectBase>::SI) -> <$to as VectBase>::SI {
+                <$to as VectBase>::SI::from_cast(values)
+            }
+        }
+    )* };
+}
+
+impl_coerce!(u32 => u64, u32 => f64, u64 => f64, f32 => f64);
+
+/// Returns true if elements of vector subtype from can be implicitly coerced to subtype to
+pub fn can_coerce(from: VectorSubType, to: VectorSubType) -> bool {
+    use VectorSubType::*;
+    match (from, to) {
+        _ if from == to => true,
+        (FixedU32, FixedU64) | (FixedU32, FixedF64) | (FixedU64, FixedF64) | (FixedF32, FixedF64) => true,
+        _ => false,
+    }
+}
+
+/// The subtype that elements of two vectors are coerced to for comparing or combining them, if any
+pub fn common_subtype(a: VectorSubType, b: VectorSubType) -> Option<VectorSubType> {
+    if can_coerce(a, b) {
+        Some(b)
+    } else if can_coerce(b, a) {
+        Some(a)
+    } else if can_coerce(a, VectorSubType::FixedF64) && can_coerce(b, VectorSubType::FixedF64) {
+        Some(VectorSubType::FixedF64)
+    } else {
+        None
+    }
+}
+
+/// A Sink which coerces values of type From to type To, and forwards them to another sink
+#[derive(Debug)]
+pub struct CoerceSink<'a, From, To, S>
+where From: Coerce<To>,
+      To: VectBase,
+      S: Sink<To::SI> {
+    inner_sink: &'a mut S,
+    _types: PhantomData<(From, To)>,
+}
+
+impl<'a, From, To, S> CoerceSink<'a, From, To, S>
+where From: Coerce<To>,
+      To: VectBase,
+      S: Sink<To::SI> {
+    pub fn new(inner_sink: &'a mut S) -> Self {
+        Self { inner_sink, _types: PhantomData }
+    }
+}
+
+impl<'a, From, To, S> Sink<From::SI> for CoerceSink<'a, From, To, S>
+where From: Coerce<To>,
+      To: VectBase,
+      S: Sink<To::SI> {
+    #[inline]
+    fn process(&mut self, data: From::SI) {
+        self.inner_sink.process(From::coerce_simd(data));
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.inner_sink.process_zeroes();
+    }
+
+    fn reset(&mut self) {
+        self.inner_sink.reset();
+    }
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.inner_sink.is_done()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::{Section256Sink, VecSink};
+    use crate::vector::{VectorF32XorAppender, VectorReader, VectorU64Appender};
+
+    #[test]
+    fn test_coerce_sink_and_subtypes() {
+        use VectorSubType::*;
+        assert_eq!(common_subtype(FixedU32, FixedU64), Some(FixedU64));
+        assert_eq!(common_subtype(FixedF64, FixedU64), Some(FixedF64));
+        assert_eq!(common_subtype(FixedU64, FixedF32), Some(FixedF64));
+        assert_eq!(common_subtype(FixedBin16, FixedBin16), Some(FixedBin16));
+        assert_eq!(common_subtype(FixedU32, FixedBin16), None);
+        assert!(!can_coerce(FixedU64, FixedU32) && !can_coerce(FixedF64, FixedF32));
+
+        // Sparse vectors exercise process_zeroes()
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.append(1 << 40).unwrap();
+        appender.append_nulls(300).unwrap();
+        appender.append(3).unwrap();
+        let bytes = appender.finish(302).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        let mut sink = VecSink::<f64>::new();
+        reader.decode_to_sink(&mut CoerceSink::<u64, f64, _>::new(&mut sink)).unwrap();
+        let expected: Vec<f64> = reader.iterate().map(|v| v.coerce()).collect();
+        assert_eq!(sink.vec[..302], expected[..]);
+        assert_eq!(sink.vec[0], (1u64 << 40) as f64);
+
+        let bytes = VectorF32XorAppender::try_new(1024).unwrap().encode_all(vec![1.5f32, -2.25]).unwrap();
+        let reader = VectorReader::<f32>::try_new(&bytes[..]).unwrap();
+        let mut sink = Section256Sink::<f64>::new();
+        reader.decode_to_sink(&mut CoerceSink::<f32, f64, _>::new(&mut sink)).unwrap();
+        assert_eq!(sink.values[..3], [1.5, -2.25, 0.0]);
+        assert_eq!(<u32 as Coerce<u32>>::coerce(7), 7);
+    }
+}
@@ -0,0 +1,170 @@
+/// The `columns` module encodes slices of row structs into one compressed vector per field, and decodes them
+/// back, as an easy entry point to the columnar format for applications which work with rows.  With the
+/// `derive` feature, `#[derive(Columnar)]` implements the conversion for structs whose fields are u32, u64,
+/// f32 or f64:
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use compressed_vec::columns::Columnar;
+///
+/// #[derive(Columnar, Debug, PartialEq)]
+/// struct Reading {
+///     sensor: u32,
+///     timestamp: u64,
+///     value: f64,
+/// }
+///
+///     let rows: Vec<Reading> = (0..1000).map(|i| Reading { sensor: i % 4, timestamp: 1000 + i as u64,
+///                                                         value: i as f64 * 0.5 }).collect();
+///     let columns = Reading::to_columns(&rows[..]).unwrap();
+///     assert_eq!(columns.names().collect::<Vec<_>>(), vec!["sensor", "timestamp", "value"]);
+///     assert_eq!(Reading::from_columns(&columns).unwrap(), rows);
+/// # }
+/// ```
+/// Only numeric fields are supported, as each column is a numeric vector.  String fields can be stored in a
+/// dictionary vector from the `strdict` module, and added with `ColumnSet::add()`.
+use crate::error::CodingError;
+
+use crate::section::VectBase;
+use crate::vector::*;
+
+
+/// A set of named columns with the same number of rows, each one an encoded vector
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSet {
+    num_rows: usize,
+    columns: Vec<(String, Vec<u8>)>,
+}
+
+impl ColumnSet {
+    pub fn new(num_rows: usize) -> Self {
+        Self { num_rows, columns: Vec::new() }
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// Adds a column, replacing any column with the same name
+    pub fn add(&mut self, name: &str, vect_bytes: Vec<u8>) {
+        match self.columns.iter_mut().find(|(n, _)| n == name) {
+            Some(column) => column.1 = vect_bytes,
+            None         => self.columns.push((name.to_string(), vect_bytes)),
+        }
+    }
+
+    /// Returns the encoded vector of the named column
+    pub fn get(&self, name: &str) -> Result<&[u8], CodingError> {
+        self.columns.iter().find(|(n, _)| n == name)
+            .map(|(_, bytes)| &bytes[..])
+            .ok_or_else(|| CodingError::InvalidFormat(format!("No column named {}", name)))
+    }
+
+    /// Column names, in the order the columns were added
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.columns.iter().map(|(n, _)| &n[..])
+    }
+}
+
+/// An element type which can be stored in a column
+pub trait ColumnType: VectBase + BaseSubtypeMapping {
+    fn encode_column<I: IntoIterator<Item = Self>>(values: I) -> Result<Vec<u8>, CodingError>;
+
+    /// Decodes a column, which must have exactly num_rows values
+    fn decode_column(vect_bytes: &[u8], num_rows: usize) -> Result<Vec<Self>, CodingError> {
+        let reader = VectorReader::<Self>::try_new(vect_bytes)?;
+        if reader.num_elements() != num_rows {
+            return Err(CodingError::InvalidNumRows(num_rows, reader.num_elements()));
+        }
+        Ok(reader.iterate().collect())
+    }
+}
+
+impl ColumnType for u32 {
+    fn encode_column<I: IntoIterator<Item = u32>>(values: I) -> Result<Vec<u8>, CodingError> {
+        VectorU32Appender::try_new(4096)?.encode_all(values)
+    }
+}
+
+impl ColumnType for u64 {
+    fn encode_column<I: IntoIterator<Item = u64>>(values: I) -> Result<Vec<u8>, CodingError> {
+        VectorU64Appender::try_new(4096)?.encode_all(values)
+    }
+}
+
+impl ColumnType for u16 {
+    fn encode_column<I: IntoIterator<Item = u16>>(values: I) -> Result<Vec<u8>, CodingError> {
+        VectorU16Appender::try_new(4096)?.encode_all(values)
+    }
+}
+
+impl ColumnType for u8 {
+    fn encode_column<I: IntoIterator<Item = u8>>(values: I) -> Result<Vec<u8>, CodingError> {
+        VectorU8Appender::try_new(4096)?.encode_all(values)
+    }
+}
+
+impl ColumnType for f32 {
+    fn encode_column<I: IntoIterator<Item = f32>>(values: I) -> Result<Vec<u8>, CodingError> {
+        VectorF32XorAppender::try_new(4096)?.encode_all(values)
+    }
+}
+
+impl ColumnType for f64 {
+    fn encode_column<I: IntoIterator<Item = f64>>(values: I) -> Result<Vec<u8>, CodingError> {
+        VectorF64XorAppender::try_new(4096)?.encode_all(values)
+    }
+}
+
+/// Row structs which can be converted to and from a ColumnSet, usually with `#[derive(Columnar)]`
+pub trait Columnar: Sized {
+    fn to_columns(rows: &[Self]) -> Result<ColumnSet, CodingError>;
+    fn from_columns(columns: &ColumnSet) -> Result<Vec<Self>, CodingError>;
+}
+
+#[cfg(feature = "derive")]
+pub use compressed_vec_derive::Columnar;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What #[derive(Columnar)] generates
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: u32,
+        y: f32,
+    }
+
+    impl Columnar for Point {
+        fn to_columns(rows: &[Self]) -> Result<ColumnSet, CodingError> {
+            let mut columns = ColumnSet::new(rows.len());
+            columns.add("x", u32::encode_column(rows.iter().map(|r| r.x))?);
+            columns.add("y", f32::encode_column(rows.iter().map(|r| r.y))?);
+            Ok(columns)
+        }
+
+        fn from_columns(columns: &ColumnSet) -> Result<Vec<Self>, CodingError> {
+            let mut xs = u32::decode_column(columns.get("x")?, columns.num_rows())?.into_iter();
+            let mut ys = f32::decode_column(columns.get("y")?, columns.num_rows())?.into_iter();
+            Ok((0..columns.num_rows()).map(|_| Point { x: xs.next().unwrap(), y: ys.next().unwrap() }).collect())
+        }
+    }
+
+    #[test]
+    fn test_column_set_round_trip() {
+        let points: Vec<Point> = (0..300).map(|i| Point { x: i * 2, y: i as f32 / 4.0 }).collect();
+        let mut columns = Point::to_columns(&points[..]).unwrap();
+        assert_eq!(columns.num_rows(), 300);
+        assert_eq!(Point::from_columns(&columns).unwrap(), points);
+
+        // Missing columns and columns of the wrong length or type are errors
+        let short = u32::encode_column(0..10u32).unwrap();
+        columns.add("x", short.clone());
+        assert_eq!(Point::from_columns(&columns), Err(CodingError::InvalidNumRows(300, 10)));
+        assert!(matches!(f32::decode_column(&short[..], 10), Err(CodingError::WrongElementType { .. })));
+        let mut missing = ColumnSet::new(300);
+        missing.add("x", u32::encode_column(0..300u32).unwrap());
+        assert!(matches!(Point::from_columns(&missing), Err(CodingError::InvalidFormat(_))));
+    }
+}
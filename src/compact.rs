@@ -0,0 +1,363 @@
+/// The `compact` module plans the re-encoding of existing vectors, for background compaction.  Vectors written
+/// by older code, or with a fixed section writer, can often be re-encoded much smaller by the current
+/// encoders.  `plan_recompression()` estimates the savings and byte costs of re-encoding each vector without
+/// decoding everything: null, constant and RepeatPrevious sections are already as small as they get, and only
+/// a sample of the other sections is decoded and re-encoded.  A scheduler can then pick the most worthwhile
+/// vectors from the plan, and `execute_plan()` re-encodes them.
+/// ```
+/// # use compressed_vec::compact::*;
+/// # use compressed_vec::raw::section::{AutoEncoder, NibblePackMedFixedSect};
+/// # use compressed_vec::vector::*;
+///     // Large timestamps, written without delta encoding
+///     let mut old_appender = VectorAppender::<u64, NibblePackMedFixedSect<u64>>::try_new(4096).unwrap();
+///     let old = old_appender.encode_all((0..5000u64).map(|i| 1_600_000_000_000 + i * 15)).unwrap();
+///     let readers = vec![VectorReader::<u64>::try_new(&old[..]).unwrap()];
+///
+///     let plan = plan_recompression::<u64, AutoEncoder>(&readers[..], 4).unwrap();
+///     assert!(plan.vectors[0].estimated_savings() > old.len() / 2);
+///
+///     let mut appender = VectorU64Appender::try_new(4096).unwrap();
+///     let compacted = execute_plan(plan, &readers[..], &mut appender).unwrap();
+///     assert!(compacted[0].1.len() < old.len() / 2);
+/// ```
+/// Footers, such as Bloom filters, do not depend on the section encoding, so they are copied as is.
+///
+/// Vectors are re-encoded in one pass by `transcode_into()`, which decodes each section through a
+/// `TranscodeSink` into an appender.  The appender can also have another element type, eg to narrow u64
+/// vectors whose values turned out to be small to u32 vectors.
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+
+use num::NumCast;
+use scroll::{Pread, Pwrite, LE};
+
+use crate::error::CodingError;
+use crate::footer::{FLAGS_OFFSET, FLAG_FOOTER};
+use crate::section::*;
+use crate::sink::{Section256Sink, Sink, SinkInput};
+use crate::split::section_offsets;
+use crate::vector::{BaseSubtypeMapping, VectorAppender, VectorReader, NUM_HEADER_BYTES_TOTAL};
+
+
+/// The estimated result of re-encoding one vector
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorPlan {
+    pub index: usize,             // Index of the vector in the readers given to plan_recompression()
+    pub current_bytes: usize,
+    pub estimated_bytes: usize,
+    pub sampled_sections: usize,  // Number of sections decoded and re-encoded for the estimate
+}
+
+impl VectorPlan {
+    pub fn estimated_savings(&self) -> usize {
+        self.current_bytes.saturating_sub(self.estimated_bytes)
+    }
+
+    /// Bytes read and written to re-encode the vector
+    pub fn cost_bytes(&self) -> usize {
+        self.current_bytes + self.estimated_bytes
+    }
+}
+
+/// A re-encoding plan for a set of vectors, ordered by estimated savings per byte of cost, best first
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionPlan {
+    pub vectors: Vec<VectorPlan>,
+}
+
+impl CompactionPlan {
+    pub fn total_savings(&self) -> usize {
+        self.vectors.iter().map(|v| v.estimated_savings()).sum()
+    }
+
+    /// Keeps only the vectors estimated to save at least min_savings bytes
+    pub fn retain_savings_over(&mut self, min_savings: usize) {
+        self.vectors.retain(|v| v.estimated_savings() >= min_savings);
+    }
+}
+
+// Offset just past the end of the vector, including any footer
+fn vector_end(vect_bytes: &[u8]) -> Result<usize, CodingError> {
+    (vect_bytes.pread_with::<u32>(0, LE)? as usize).checked_add(4).ok_or(CodingError::InputTooShort)
+}
+
+fn plan_vector<T, W>(index: usize, reader: &VectorReader<T>, sample_every: usize) -> Result<VectorPlan, CodingError>
+where T: VectBase + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    let vect_bytes = reader.vect_bytes();
+    let current_bytes = vector_end(vect_bytes)?;
+    let (offsets, sects_end) = section_offsets(reader)?;
+    // The header and footer are kept as is
+    let mut estimated_bytes = current_bytes - (sects_end - NUM_HEADER_BYTES_TOTAL);
+
+    let mut sink = Section256Sink::<T>::new();
+    let mut buf = vec![0u8; FIXED_LEN * std::mem::size_of::<T>() * 2 + 64];
+    let (mut sampled_old, mut sampled_new, mut unsampled) = (0, 0, 0);
+    let mut sampled_sections = 0;
+    for (i, &(offset, resolved)) in offsets.iter().enumerate() {
+        let sect_len = offsets.get(i + 1).map_or(sects_end, |&(next, _)| next) - offset;
+        let sect = FixedSectEnum::<T>::try_from(&vect_bytes[resolved..])?;
+        if offset != resolved || sect.is_null() || sect.sect_type() == SectionType::Constant {
+            estimated_bytes += sect_len;
+        } else if i % sample_every.max(1) == 0 {
+            sink.reset();
+            sect.decode(&mut sink)?;
+            sampled_old += sect_len;
+            sampled_new += W::gen_stats_and_write(&mut buf[..], 0, &sink.values[..])?;
+            sampled_sections += 1;
+        } else {
+            unsampled += sect_len;
+        }
+    }
+    // Unsampled sections are assumed to shrink like the sampled ones
+    estimated_bytes += sampled_new + (unsampled * sampled_new).checked_div(sampled_old).unwrap_or(unsampled);
+    Ok(VectorPlan { index, current_bytes, estimated_bytes, sampled_sections })
+}
+
+/// Estimates the savings and costs of re-encoding each vector with the section writer W, decoding and
+/// re-encoding every sample_every-th section for the estimate.  A sample_every of 1 gives exact estimates.
+pub fn plan_recompression<T, W>(readers: &[VectorReader<T>], sample_every: usize) -> Result<CompactionPlan, CodingError>
+where T: VectBase + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    let mut vectors = readers.iter().enumerate()
+                             .map(|(index, reader)| plan_vector::<T, W>(index, reader, sample_every))
+                             .collect::<Result<Vec<_>, _>>()?;
+    let score = |v: &VectorPlan| v.estimated_savings() as f64 / v.cost_bytes().max(1) as f64;
+    vectors.sort_by(|a, b| score(b).partial_cmp(&score(a)).unwrap().then(a.index.cmp(&b.index)));
+    Ok(CompactionPlan { vectors })
+}
+
+/// Re-encodes the vectors of the plan, in plan order, with the appender, which is reset first.
+/// Returns the index of each vector with its re-encoded bytes.
+pub fn execute_plan<T, W>(plan: CompactionPlan,
+                          readers: &[VectorReader<T>],
+                          appender: &mut VectorAppender<T, W>) -> Result<Vec<(usize, Vec<u8>)>, CodingError>
+where T: VectBase + NumCast + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    plan.vectors.into_iter().map(|vect_plan| {
+        let reader = readers.get(vect_plan.index).ok_or(CodingError::BadOffset(vect_plan.index))?;
+        appender.reset()?;
+        transcode_into(reader, appender)?;
+        let mut vect_bytes = appender.finish(reader.num_elements())?;
+
+        // Copy the footer, unless the appender wrote its own
+        let (_, sects_end) = section_offsets(reader)?;
+        let footer = &reader.vect_bytes()[sects_end..vector_end(reader.vect_bytes())?];
+        if !footer.is_empty() && vect_bytes[FLAGS_OFFSET] & FLAG_FOOTER == 0 {
+            vect_bytes.extend_from_slice(footer);
+            vect_bytes[FLAGS_OFFSET] |= FLAG_FOOTER;
+            let num_bytes = (vect_bytes.len() - 4) as u32;
+            vect_bytes.pwrite_with(num_bytes, 0, LE)?;
+        }
+        Ok((vect_plan.index, vect_bytes))
+    }).collect()
+}
+
+/// A Sink which appends decoded values to a VectorAppender, converting each to the appender's element type, so
+/// that a section is re-encoded by the appender's section writer as it is decoded.  Only the first num_values
+/// values are appended, so the padding of the last section is dropped.  A value which does not fit the element
+/// type is an Overflow error; sinks cannot fail, so the first error is kept and returned by `finish()`.
+pub struct TranscodeSink<'a, I, O, W>
+where I: VectBase + NumCast,
+      O: VectBase + NumCast + BaseSubtypeMapping,
+      W: FixedSectionWriter<O> {
+    appender: &'a mut VectorAppender<O, W>,
+    values_left: usize,
+    error: Option<CodingError>,
+    _input: PhantomData<I>,
+}
+
+impl<'a, I, O, W> TranscodeSink<'a, I, O, W>
+where I: VectBase + NumCast,
+      O: VectBase + NumCast + BaseSubtypeMapping,
+      W: FixedSectionWriter<O> {
+    pub fn new(appender: &'a mut VectorAppender<O, W>, num_values: usize) -> Self {
+        Self { appender, values_left: num_values, error: None, _input: PhantomData }
+    }
+
+    /// Returns the first error converting or appending a value, if any
+    pub fn finish(self) -> Result<(), CodingError> {
+        self.error.map_or(Ok(()), Err)
+    }
+
+    fn append_values(&mut self, values: &[I]) -> Result<(), CodingError> {
+        values.iter().try_for_each(|&v| {
+            let converted: O = NumCast::from(v).ok_or(CodingError::Overflow)?;
+            self.appender.append(converted)
+        })
+    }
+}
+
+impl<'a, I, O, W> Sink<I::SI> for TranscodeSink<'a, I, O, W>
+where I: VectBase + NumCast,
+      O: VectBase + NumCast + BaseSubtypeMapping,
+      W: FixedSectionWriter<O> {
+    #[inline]
+    fn process(&mut self, data: I::SI) {
+        if self.values_left == 0 || self.error.is_some() { return }
+        let mut values = [I::zero(); 8];
+        data.write_to_slice(&mut values);
+        let n = self.values_left.min(8);
+        self.values_left -= n;
+        if let Err(err) = self.append_values(&values[..n]) { self.error = Some(err); }
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.process(I::SI::ZERO);
+    }
+
+    fn reset(&mut self) {}
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.values_left == 0 || self.error.is_some()
+    }
+}
+
+/// Appends the elements of a vector to an appender in one pass, decoding each section through a TranscodeSink.
+/// Null sections are appended as zeroes without decoding.  Tracked nulls are appended with `append_nulls()`, so
+/// they stay nulls if the appender tracks nulls too.  Finish the appender to get the re-encoded vector:
+/// ```
+/// # use compressed_vec::compact::transcode_into;
+/// # use compressed_vec::raw::section::NibblePackMedFixedSect;
+/// # use compressed_vec::vector::*;
+///     let mut old_appender = VectorAppender::<u64, NibblePackMedFixedSect<u64>>::try_new(4096).unwrap();
+///     let old = old_appender.encode_all((0..1000u64).map(|i| i % 300)).unwrap();
+///     let old = VectorReader::<u64>::try_new(&old[..]).unwrap();
+///
+///     let mut appender = VectorU32Appender::try_new(4096).unwrap();
+///     transcode_into(&old, &mut appender).unwrap();
+///     let narrowed = appender.finish(old.num_elements()).unwrap();
+///     let reader = VectorReader::<u32>::try_new(&narrowed[..]).unwrap();
+///     assert!(reader.iterate().zip(old.iterate()).all(|(a, b)| a as u64 == b));
+/// ```
+pub fn transcode_into<I, O, W>(reader: &VectorReader<I>,
+                               appender: &mut VectorAppender<O, W>) -> Result<(), CodingError>
+where I: VectBase + NumCast + BaseSubtypeMapping,
+      O: VectBase + NumCast + BaseSubtypeMapping,
+      W: FixedSectionWriter<O> {
+    let mut elems_left = reader.num_elements();
+    for sect in reader.sect_iter() {
+        if elems_left == 0 { break }
+        let sect = sect?;
+        let sect_elems = elems_left.min(FIXED_LEN);
+        elems_left -= sect_elems;
+        if sect.is_null() {
+            appender.append_zeroes(sect_elems)?;
+        } else if sect.sect_type() == SectionType::PartialNull {
+            let mut sink = Section256Sink::<I>::new();
+            sect.decode(&mut sink)?;
+            let valid = sect.validity_mask();
+            for (i, &v) in sink.values[..sect_elems].iter().enumerate() {
+                if valid.extract(i / 32) & (1 << (i % 32)) == 0 {
+                    appender.append_nulls(1)?;
+                } else {
+                    appender.append(NumCast::from(v).ok_or(CodingError::Overflow)?)?;
+                }
+            }
+        } else {
+            let mut sink = TranscodeSink::<I, O, W>::new(appender, sect_elems);
+            sect.decode(&mut sink)?;
+            sink.finish()?;
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{VectorU32Appender, VectorU64Appender};
+
+    #[test]
+    fn test_plan_ranks_and_execute_keeps_values_and_footer() {
+        // An old vector without delta encoding, with a Bloom filter and a null section
+        let mut old_appender = VectorAppender::<u64, NibblePackMedFixedSect<u64>>::try_new(4096).unwrap();
+        old_appender.enable_bloom_filter(10);
+        (0..512u64).for_each(|i| old_appender.append(1 << 40 | i * 3).unwrap());
+        old_appender.append_nulls(256).unwrap();
+        (0..100u64).for_each(|i| old_appender.append(1 << 40 | i).unwrap());
+        let old = old_appender.finish(868).unwrap();
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        let current = appender.encode_all((0..868u64).map(|i| i % 7)).unwrap();
+        let readers = vec![VectorReader::<u64>::try_new(&current[..]).unwrap(),
+                           VectorReader::<u64>::try_new(&old[..]).unwrap()];
+
+        let plan = plan_recompression::<u64, AutoEncoder>(&readers[..], 1).unwrap();
+        assert_eq!(plan.vectors.iter().map(|v| v.index).collect::<Vec<_>>(), vec![1, 0]);
+        assert_eq!(plan.vectors[1].estimated_savings(), 0);
+        assert_eq!(plan.vectors[0].sampled_sections, 3);
+
+        let mut small_plan = plan.clone();
+        small_plan.retain_savings_over(1);
+        assert_eq!(small_plan.vectors.len(), 1);
+        let compacted = execute_plan(small_plan, &readers[..], &mut appender).unwrap();
+        assert_eq!(compacted.len(), 1);
+        let (index, bytes) = &compacted[0];
+        assert_eq!(*index, 1);
+        // Estimates with every section sampled are exact
+        assert_eq!(bytes.len(), plan.vectors[0].estimated_bytes);
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.iterate().collect::<Vec<_>>(), readers[1].iterate().collect::<Vec<_>>());
+        assert_eq!(reader.num_null_sections().unwrap(), 1);
+        assert!(reader.might_contain(1 << 40 | 99));
+        reader.cross_check().unwrap();
+
+        // Sampling fewer sections still finds the savings
+        let sampled = plan_recompression::<u64, AutoEncoder>(&readers[1..], 3).unwrap();
+        assert_eq!(sampled.vectors[0].sampled_sections, 2);
+        assert!(sampled.total_savings() > 0);
+    }
+
+    #[test]
+    fn test_transcode_into_delta_and_u32() {
+        let mut old_appender = VectorAppender::<u64, NibblePackMedFixedSect<u64>>::try_new(4096).unwrap();
+        (0..256u64).for_each(|i| old_appender.append(5000 + i * 2).unwrap());
+        old_appender.append_nulls(256).unwrap();
+        (0..100u64).for_each(|i| old_appender.append(i).unwrap());
+        let old = old_appender.finish(612).unwrap();
+        let old = VectorReader::<u64>::try_new(&old[..]).unwrap();
+
+        // Same element type with delta sections, keeping the null section
+        let mut appender = VectorAppender::<u64, DeltaNPMedFixedSect<u64>>::try_new(4096).unwrap();
+        transcode_into(&old, &mut appender).unwrap();
+        let bytes = appender.finish(612).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.iterate().collect::<Vec<_>>(), old.iterate().collect::<Vec<_>>());
+        assert_eq!(reader.sect_iter().next().unwrap().unwrap().sect_type(), SectionType::DeltaNPMedium);
+        assert_eq!(reader.num_null_sections().unwrap(), 1);
+
+        // Narrowed to u32, unless a value does not fit
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        transcode_into(&old, &mut appender).unwrap();
+        let bytes = appender.finish(612).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert!(reader.iterate().zip(old.iterate()).all(|(a, b)| a as u64 == b));
+        assert_eq!(reader.num_elements(), 612);
+
+        let big = VectorU64Appender::try_new(1024).unwrap().encode_all(vec![1, 1 << 40, 3]).unwrap();
+        let big = VectorReader::<u64>::try_new(&big[..]).unwrap();
+        appender.reset().unwrap();
+        assert_eq!(transcode_into(&big, &mut appender), Err(CodingError::Overflow));
+    }
+
+    #[test]
+    fn test_transcode_into_keeps_tracked_nulls() {
+        let values: Vec<Option<u64>> = (0..600u64).map(|i| if i % 5 == 0 { None } else { Some(i) }).collect();
+        let mut old_appender = VectorU64Appender::try_new(4096).unwrap();
+        old_appender.track_nulls(true);
+        let old = old_appender.encode_all_options(values.clone()).unwrap();
+        let old = VectorReader::<u64>::try_new(&old[..]).unwrap();
+        assert_eq!(old.null_count(), 120);
+
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        appender.track_nulls(true);
+        transcode_into(&old, &mut appender).unwrap();
+        let bytes = appender.finish(600).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.null_count(), 120);
+        assert_eq!(reader.iterate_options().map(|v| v.map(|v| v as u64)).collect::<Vec<_>>(), values);
+    }
+}
@@ -0,0 +1,281 @@
+/// The `data` module lets dynamically typed query engines open a vector without knowing its element type in
+/// advance.  `VectorData::from_bytes()` reads the subtype from the vector header and returns the matching
+/// reader, so engines can dispatch on the element type with a `match` instead of keeping their own registry
+/// of vector types.
+/// ```
+/// # use compressed_vec::data::VectorData;
+/// # use compressed_vec::vector::{VectorU32Appender, VectorF64XorAppender};
+///     let u32_bytes = VectorU32Appender::try_new(1024).unwrap().encode_all(vec![1, 2, 3]).unwrap();
+///     let f64_bytes = VectorF64XorAppender::try_new(1024).unwrap().encode_all(vec![0.5, 1.5]).unwrap();
+///
+///     let mut total = 0.0;
+///     for bytes in &[u32_bytes, f64_bytes] {
+///         total += match VectorData::from_bytes(&bytes[..]).unwrap() {
+///             VectorData::U32(reader) => reader.iterate().map(|v| v as f64).sum::<f64>(),
+///             VectorData::F64(reader) => reader.iterate().sum::<f64>(),
+///             _ => unimplemented!(),
+///         };
+///     }
+///     assert_eq!(total, 8.0);
+/// ```
+/// `peek_info()` reads the element type and size of a vector from its header alone, without opening a reader,
+/// and `migrate()` rewrites a vector of any type for another format version.
+use crate::error::CodingError;
+
+use std::convert::TryFrom;
+
+use scroll::{Pread, LE};
+
+use crate::boolvec::BoolReader;
+use crate::fixedbin::FixedBin16Reader;
+use crate::half::HalfReader;
+use crate::kvpairs::KeyValueReader;
+use crate::section::FIXED_LEN;
+use crate::strdict::StrDictReader;
+use crate::vector::{format_version, BinaryVector, FixedSectStats, VectorReader, VectorSubType,
+                    BINARYVECT_HEADER_SIZE, FORMAT_MAGIC, FORMAT_MAGIC_OFFSET, FORMAT_VERSION,
+                    NUM_HEADER_BYTES_TOTAL};
+
+
+/// A reader for a vector of any of the element types in this crate.  New vector types add variants, so
+/// matches outside this crate need a wildcard arm.
+#[non_exhaustive]
+pub enum VectorData<'buf> {
+    U32(VectorReader<'buf, u32>),
+    U64(VectorReader<'buf, u64>),
+    U16(VectorReader<'buf, u16>),
+    U8(VectorReader<'buf, u8>),
+    F32(VectorReader<'buf, f32>),
+    F64(VectorReader<'buf, f64>),
+    Bin16(FixedBin16Reader<'buf>),
+    KeyValue(KeyValueReader<'buf>),
+    Half(HalfReader<'buf>),      // f16 or bfloat16
+    StrDict(StrDictReader<'buf>),
+    Bool(BoolReader<'buf>),
+}
+
+impl<'buf> VectorData<'buf> {
+    /// Creates the reader for the vector type recorded in the header.  Returns `WrongVectorType` for
+    /// subtypes which are not FixedSection256 vectors.
+    pub fn from_bytes(vect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let subtype: u8 = vect_bytes.pread_with(offset_of!(BinaryVector, minor_type), LE)?;
+        match subtype {
+            s if s == VectorSubType::FixedU32 as u8      => Ok(VectorData::U32(VectorReader::try_new(vect_bytes)?)),
+            s if s == VectorSubType::FixedU64 as u8      => Ok(VectorData::U64(VectorReader::try_new(vect_bytes)?)),
+            s if s == VectorSubType::FixedU16 as u8      => Ok(VectorData::U16(VectorReader::try_new(vect_bytes)?)),
+            s if s == VectorSubType::FixedU8 as u8       => Ok(VectorData::U8(VectorReader::try_new(vect_bytes)?)),
+            s if s == VectorSubType::FixedF32 as u8      => Ok(VectorData::F32(VectorReader::try_new(vect_bytes)?)),
+            s if s == VectorSubType::FixedF64 as u8      => Ok(VectorData::F64(VectorReader::try_new(vect_bytes)?)),
+            s if s == VectorSubType::FixedBin16 as u8    => Ok(VectorData::Bin16(FixedBin16Reader::try_new(vect_bytes)?)),
+            s if s == VectorSubType::FixedKeyValue as u8 => Ok(VectorData::KeyValue(KeyValueReader::try_new(vect_bytes)?)),
+            s if s == VectorSubType::FixedF16 as u8 || s == VectorSubType::FixedBF16 as u8
+                                                         => Ok(VectorData::Half(HalfReader::try_new(vect_bytes)?)),
+            s if s == VectorSubType::FixedStrDict as u8  => Ok(VectorData::StrDict(StrDictReader::try_new(vect_bytes)?)),
+            s if s == VectorSubType::FixedBool as u8     => Ok(VectorData::Bool(BoolReader::try_new(vect_bytes)?)),
+            s => Err(CodingError::WrongVectorType(s)),
+        }
+    }
+
+    /// The vector subtype of the elements
+    pub fn subtype(&self) -> VectorSubType {
+        match self {
+            VectorData::U32(_)      => VectorSubType::FixedU32,
+            VectorData::U64(_)      => VectorSubType::FixedU64,
+            VectorData::U16(_)      => VectorSubType::FixedU16,
+            VectorData::U8(_)       => VectorSubType::FixedU8,
+            VectorData::F32(_)      => VectorSubType::FixedF32,
+            VectorData::F64(_)      => VectorSubType::FixedF64,
+            VectorData::Bin16(_)    => VectorSubType::FixedBin16,
+            VectorData::KeyValue(_) => VectorSubType::FixedKeyValue,
+            VectorData::Half(r)     => r.half_type().subtype(),
+            VectorData::StrDict(_)  => VectorSubType::FixedStrDict,
+            VectorData::Bool(_)     => VectorSubType::FixedBool,
+        }
+    }
+
+    pub fn num_elements(&self) -> usize {
+        match self {
+            VectorData::U32(r)      => r.num_elements(),
+            VectorData::U64(r)      => r.num_elements(),
+            VectorData::U16(r)      => r.num_elements(),
+            VectorData::U8(r)       => r.num_elements(),
+            VectorData::F32(r)      => r.num_elements(),
+            VectorData::F64(r)      => r.num_elements(),
+            VectorData::Bin16(r)    => r.num_elements(),
+            VectorData::KeyValue(r) => r.num_elements(),
+            VectorData::Half(r)     => r.num_elements(),
+            VectorData::StrDict(r)  => r.num_elements(),
+            VectorData::Bool(r)     => r.num_elements(),
+        }
+    }
+}
+
+/// Opens a vector whose element type is not known in advance, returning the reader for the element type in its
+/// header.  Use this instead of guessing with `VectorReader::<T>::try_new()`, which returns `WrongElementType`
+/// for any other T.
+pub fn open_dynamic(vect_bytes: &[u8]) -> Result<VectorData<'_>, CodingError> {
+    VectorData::from_bytes(vect_bytes)
+}
+
+/// The size and element type of a vector, read from its header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VectorInfo {
+    pub elements: usize,
+    pub decoded_bytes: Option<usize>,    // Bytes of the decoded elements; None for variable size rows and strings
+    pub sections: usize,
+    pub element_type: VectorSubType,
+    pub format_version: u8,              // 0 for vectors written before headers had a version
+}
+
+// Bytes of one decoded element, for fixed size elements.  Half floats decode to f32, and booleans to bool.
+fn decoded_element_bytes(subtype: VectorSubType) -> Option<usize> {
+    match subtype {
+        VectorSubType::FixedU32 | VectorSubType::FixedF32 => Some(4),
+        VectorSubType::FixedF16 | VectorSubType::FixedBF16 => Some(4),
+        VectorSubType::FixedU64 | VectorSubType::FixedF64 => Some(8),
+        VectorSubType::FixedBin16 => Some(16),
+        VectorSubType::FixedU16 => Some(2),
+        VectorSubType::FixedU8 | VectorSubType::FixedBool => Some(1),
+        _ => None,
+    }
+}
+
+/// Reads the number of elements, decoded size, number of sections, element type and format version of a vector
+/// from its header only, without reading any sections, so that output buffers can be allocated, or a decode
+/// refused, before decoding anything.  Returns the same errors as `from_bytes()` for unknown subtypes, short
+/// input, bad magic bytes and unsupported format versions.
+pub fn peek_info(vect_bytes: &[u8]) -> Result<VectorInfo, CodingError> {
+    let bytes_from_header: u32 = vect_bytes.pread_with(0, LE)?;
+    let subtype: u8 = vect_bytes.pread_with(offset_of!(BinaryVector, minor_type), LE)?;
+    let stats: FixedSectStats = vect_bytes.pread_with(BINARYVECT_HEADER_SIZE, LE)?;
+    let version = format_version(vect_bytes)?;
+    if vect_bytes.len() < bytes_from_header as usize + 4 {
+        return Err(CodingError::InputTooShort);
+    }
+    let element_type = VectorSubType::try_from(subtype)?;
+    let elements = stats.num_elements as usize;
+    Ok(VectorInfo {
+        elements,
+        decoded_bytes: decoded_element_bytes(element_type).map(|bytes| elements * bytes),
+        sections: (elements + FIXED_LEN - 1) / FIXED_LEN,
+        element_type,
+        format_version: version,
+    })
+}
+
+/// Rewrites a vector of any type for the given format version, eg to upgrade vectors written before headers had
+/// a version, or to hand vectors to readers which predate the version check.  Versions 0 and 1 differ only in
+/// the magic and version bytes of the header, so the sections and footer are copied byte for byte, and no
+/// migration between them is lossy.  Bytes past the end of the vector are dropped.  Returns UnsupportedVersion
+/// for versions newer than FORMAT_VERSION, and the errors of `peek_info()` for the vector.
+pub fn migrate(vect_bytes: &[u8], target_version: u8) -> Result<Vec<u8>, CodingError> {
+    if target_version > FORMAT_VERSION { return Err(CodingError::UnsupportedVersion(target_version)) }
+    peek_info(vect_bytes)?;
+    let total_bytes = vect_bytes.pread_with::<u32>(0, LE)? as usize + 4;
+    if total_bytes < NUM_HEADER_BYTES_TOTAL { return Err(CodingError::InputTooShort) }
+    let mut migrated = vect_bytes[..total_bytes].to_vec();
+    let magic = if target_version == 0 { 0 } else { FORMAT_MAGIC };
+    migrated[FORMAT_MAGIC_OFFSET..NUM_HEADER_BYTES_TOTAL].copy_from_slice(&[magic, target_version]);
+    Ok(migrated)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolvec::BoolAppender;
+    use crate::fixedbin::FixedBin16Appender;
+    use crate::half::{HalfAppender, HalfType};
+    use crate::kvpairs::KeyValueAppender;
+    use crate::strdict::StrDictAppender;
+    use crate::vector::{VectorF32XorAppender, VectorU64Appender};
+
+    #[test]
+    fn test_from_bytes_dispatches_on_subtype() {
+        let u64_bytes = VectorU64Appender::try_new(1024).unwrap().encode_all(0..300u64).unwrap();
+        let data = VectorData::from_bytes(&u64_bytes[..]).unwrap();
+        assert_eq!(data.subtype(), VectorSubType::FixedU64);
+        assert_eq!(data.num_elements(), 300);
+
+        let f32_bytes = VectorF32XorAppender::try_new(1024).unwrap().encode_all(vec![1.5f32; 10]).unwrap();
+        assert_eq!(VectorData::from_bytes(&f32_bytes[..]).unwrap().subtype(), VectorSubType::FixedF32);
+
+        let bin16_bytes = FixedBin16Appender::try_new(1024).unwrap().encode_all(&[[3u8; 16]; 5]).unwrap();
+        match VectorData::from_bytes(&bin16_bytes[..]).unwrap() {
+            VectorData::Bin16(reader) => assert_eq!(reader.iterate().next(), Some([3u8; 16])),
+            _ => panic!("Expected a Bin16 vector"),
+        }
+
+        let mut kv_appender = KeyValueAppender::try_new(1024).unwrap();
+        kv_appender.append(&[(1, 2)]).unwrap();
+        let kv_bytes = kv_appender.finish(1).unwrap();
+        assert_eq!(VectorData::from_bytes(&kv_bytes[..]).unwrap().subtype(), VectorSubType::FixedKeyValue);
+
+        let bf16_bytes = HalfAppender::try_new(1024, HalfType::BF16, true).unwrap().encode_all(vec![0.5; 3]).unwrap();
+        assert_eq!(VectorData::from_bytes(&bf16_bytes[..]).unwrap().subtype(), VectorSubType::FixedBF16);
+
+        let str_bytes = StrDictAppender::try_new(1024).unwrap().encode_all(vec!["a", "b", "a"]).unwrap();
+        assert_eq!(VectorData::from_bytes(&str_bytes[..]).unwrap().subtype(), VectorSubType::FixedStrDict);
+        assert_eq!(peek_info(&str_bytes[..]).unwrap().decoded_bytes, None);
+        let bool_bytes = BoolAppender::try_new(1024).unwrap().encode_all(vec![true, false]).unwrap();
+        assert!(matches!(VectorData::from_bytes(&bool_bytes[..]), Ok(VectorData::Bool(_))));
+
+        let mut bad = u64_bytes.clone();
+        bad[offset_of!(BinaryVector, minor_type)] = VectorSubType::INT as u8;
+        assert!(matches!(VectorData::from_bytes(&bad[..]), Err(CodingError::WrongVectorType(0x07))));
+        assert_eq!(VectorData::from_bytes(&[1, 2]).err(), Some(CodingError::BadOffset(5)));
+
+        // A typed reader of the wrong type fails, but open_dynamic() finds the type
+        assert_eq!(VectorReader::<f64>::try_new(&u64_bytes[..]).err(),
+                   Some(CodingError::WrongElementType { expected: VectorSubType::FixedF64,
+                                                        found: VectorSubType::FixedU64 }));
+        assert!(matches!(open_dynamic(&u64_bytes[..]), Ok(VectorData::U64(_))));
+    }
+
+    #[test]
+    fn test_peek_info_reads_header_only() {
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.append_nulls(600).unwrap();
+        let bytes = appender.finish(600).unwrap();
+        assert_eq!(peek_info(&bytes[..]).unwrap(),
+                   VectorInfo { elements: 600, decoded_bytes: Some(4800), sections: 3,
+                                element_type: VectorSubType::FixedU64, format_version: FORMAT_VERSION });
+
+        // Sections are never read, even if they are garbage
+        let mut garbage = bytes.clone();
+        garbage[NUM_HEADER_BYTES_TOTAL..].iter_mut().for_each(|b| *b = 0xff);
+        assert_eq!(peek_info(&garbage[..]), peek_info(&bytes[..]));
+
+        let f16_bytes = HalfAppender::try_new(1024, HalfType::F16, false).unwrap().encode_all(vec![1.0; 3]).unwrap();
+        assert_eq!(peek_info(&f16_bytes[..]).unwrap().decoded_bytes, Some(12));
+        let mut kv_appender = KeyValueAppender::try_new(1024).unwrap();
+        kv_appender.append(&[(1, 2)]).unwrap();
+        let kv_bytes = kv_appender.finish(257).unwrap();
+        let kv_info = peek_info(&kv_bytes[..]).unwrap();
+        assert_eq!((kv_info.elements, kv_info.decoded_bytes, kv_info.sections), (257, None, 2));
+
+        assert_eq!(peek_info(&bytes[..bytes.len() - 1]), Err(CodingError::InputTooShort));
+    }
+
+    #[test]
+    fn test_migrate_between_versions() {
+        let mut appender = StrDictAppender::try_new(1024).unwrap();
+        let bytes = appender.encode_all((0..300).map(|i| if i % 3 == 0 { "a" } else { "bc" })).unwrap();
+
+        // Downgrading only clears the magic byte and version, and upgrading restores the original bytes
+        let mut padded = bytes.clone();
+        padded.extend_from_slice(&[7, 7]);
+        let old = migrate(&padded[..], 0).unwrap();
+        assert_eq!(format_version(&old[..]), Ok(0));
+        assert_eq!(old[NUM_HEADER_BYTES_TOTAL..], bytes[NUM_HEADER_BYTES_TOTAL..]);
+        let reader = StrDictReader::try_new(&old[..]).unwrap();
+        assert_eq!(reader.to_vec().unwrap(), StrDictReader::try_new(&bytes[..]).unwrap().to_vec().unwrap());
+        assert_eq!(migrate(&old[..], FORMAT_VERSION).unwrap(), bytes);
+        assert_eq!(migrate(&bytes[..], FORMAT_VERSION).unwrap(), bytes);
+
+        assert_eq!(migrate(&bytes[..], FORMAT_VERSION + 1), Err(CodingError::UnsupportedVersion(FORMAT_VERSION + 1)));
+        let mut short = bytes.clone();
+        short[..4].copy_from_slice(&8u32.to_le_bytes());
+        assert_eq!(migrate(&short[..], 0), Err(CodingError::InputTooShort));
+    }
+}
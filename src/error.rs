@@ -1,4 +1,7 @@
+use crate::vector::VectorSubType;
+
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum CodingError {
     NotEnoughSpace,
     InputTooShort,
@@ -6,8 +9,16 @@ pub enum CodingError {
     InvalidSectionType(u8),
     InvalidFormat(String),
     InvalidNumRows(usize, usize),    // Number passed into finish(), number of actual rows written so far
-    WrongVectorType(u8),             // Eg Used a VectorReader::<u64> on a u32 vector
+    WrongVectorType(u8),             // Unknown or unsupported vector subtype in the header
+    WrongElementType { expected: VectorSubType, found: VectorSubType },  // Eg Used a VectorReader::<u64> on a u32 vector
     ScrollErr(String),
+    MemoryLimitExceeded(usize),      // Number of bytes refused by a MemoryTracker
+    SectionFull,                     // Item does not fit even in a new, empty section
+    Overflow,                        // Byte or element counts would overflow, eg a filler wrote more than it was given
+    TooManySections(usize),          // Number of sections of a vector over the appender's max_sections limit
+    VectorTooLarge(usize),           // Number of bytes of a vector over the appender's max_bytes limit
+    IoErr(String),                   // Writing to an io::Write failed
+    UnsupportedVersion(u8),          // Vector format version newer than this library can read
 }
 
 impl From<scroll::Error> for CodingError {
@@ -19,3 +30,9 @@ impl From<scroll::Error> for CodingError {
         }
     }
 }
+
+impl From<std::io::Error> for CodingError {
+    fn from(err: std::io::Error) -> CodingError {
+        CodingError::IoErr(err.to_string())
+    }
+}
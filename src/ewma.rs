@@ -0,0 +1,226 @@
+/// The `ewma` module computes a time-decayed exponentially weighted moving average of a value vector, using
+/// the timestamps in a paired u64 vector, for smoothing queries.  Both vectors are read in one pass, a section
+/// of each at a time: each timestamp section is decoded to a buffer, and the matching value section is
+/// decoded straight into an `EwmaSink`, which writes the smoothed values, as f64, to another sink.
+/// ```
+/// # use compressed_vec::ewma::ewma;
+/// # use compressed_vec::sink::VecSink;
+/// # use compressed_vec::vector::*;
+///     let values = VectorU32Appender::try_new(1024).unwrap().encode_all(vec![10, 20, 20, 0]).unwrap();
+///     let times = VectorU64Appender::try_new(1024).unwrap().encode_all(vec![100, 101, 103, 104]).unwrap();
+///     let values = VectorReader::<u32>::try_new(&values[..]).unwrap();
+///     let times = VectorReader::<u64>::try_new(&times[..]).unwrap();
+///     let mut sink = VecSink::<f64>::new();
+///     ewma(&values, &times, 0.5, &mut sink).unwrap();
+///     assert_eq!(sink.vec[..4], [10.0, 15.0, 18.75, 9.375]);
+/// ```
+/// Each value moves the average by a weight of `1 - (1 - alpha)^dt`, where dt is the time since the previous
+/// value and at least 1, so evenly spaced values give the usual EWMA with smoothing factor alpha.  The first
+/// value starts the average.  Tracked nulls of the value vector are skipped: the average is held, and is the
+/// output for each of them.  The elements of Null sections are zeroes, like any other values.
+use std::marker::PhantomData;
+
+use num::NumCast;
+use packed_simd::{f64x8, u32x8};
+
+use crate::error::CodingError;
+use crate::section::*;
+use crate::sink::{Section256Sink, Sink, SinkInput};
+use crate::vector::{BaseSubtypeMapping, VectorReader};
+
+
+/// A Sink which turns the values of one section into their time-decayed moving average, given the timestamps
+/// of the section with `set_timestamps()`, and passes the averages 8 at a time to an inner Sink.
+#[derive(Debug)]
+pub struct EwmaSink<'a, T: VectBase, S: Sink<f64x8>> {
+    inner_sink: &'a mut S,
+    decay: f64,                  // 1 - alpha
+    average: Option<(u64, f64)>, // Timestamp of the last value, and the average so far
+    timestamps: [u64; FIXED_LEN],
+    valid: u32x8,                // Non-null elements of the current section
+    num_values: usize,           // Number of values in the current section; the rest are padding
+    pos: usize,
+    _type: PhantomData<T>,
+}
+
+impl<'a, T: VectBase + NumCast, S: Sink<f64x8>> EwmaSink<'a, T, S> {
+    /// Creates a sink with the smoothing factor alpha, which must be in (0, 1]
+    pub fn new(alpha: f64, inner_sink: &'a mut S) -> Result<Self, CodingError> {
+        if !(alpha > 0.0 && alpha <= 1.0) {
+            return Err(CodingError::InvalidFormat(format!("EWMA alpha {} is not in (0, 1]", alpha)));
+        }
+        Ok(Self { inner_sink, decay: 1.0 - alpha, average: None, timestamps: [0; FIXED_LEN],
+                  valid: u32x8::splat(u32::MAX), num_values: 0, pos: 0, _type: PhantomData })
+    }
+
+    /// Sets the timestamps of the next section, of which only the first num_values are real values.
+    /// All of them are non-null until `set_validity()` is called.
+    pub fn set_timestamps(&mut self, timestamps: &[u64; FIXED_LEN], num_values: usize) {
+        self.timestamps.copy_from_slice(timestamps);
+        self.valid = u32x8::splat(u32::MAX);
+        self.num_values = num_values.min(FIXED_LEN);
+        self.pos = 0;
+    }
+
+    /// Sets the mask of non-null elements of the next section, after `set_timestamps()`.  The average is held
+    /// over null elements.
+    pub fn set_validity(&mut self, valid: u32x8) {
+        self.valid = valid;
+    }
+
+    /// The average so far, or None before the first value
+    pub fn average(&self) -> Option<f64> {
+        self.average.map(|(_, avg)| avg)
+    }
+
+    /// Passes the held average, without updating it, for all FIXED_LEN elements of the current section,
+    /// so a held section is as long as a decoded one
+    pub fn hold(&mut self) {
+        let held = f64x8::splat(self.average().unwrap_or(0.0));
+        for _ in (0..FIXED_LEN).step_by(8) {
+            self.inner_sink.process(held);
+        }
+    }
+
+    #[inline]
+    fn update(&mut self, timestamp: u64, value: f64) -> f64 {
+        let average = match self.average {
+            None => value,
+            Some((last, avg)) => {
+                let dt = timestamp.saturating_sub(last).max(1);
+                let weight = 1.0 - self.decay.powf(dt as f64);
+                avg + weight * (value - avg)
+            }
+        };
+        self.average = Some((timestamp, average));
+        average
+    }
+}
+
+impl<'a, T, S> Sink<T::SI> for EwmaSink<'a, T, S>
+where T: VectBase + NumCast,
+      S: Sink<f64x8> {
+    #[inline]
+    fn process(&mut self, data: T::SI) {
+        let mut values = [T::zero(); 8];
+        data.write_to_slice(&mut values);
+        let mut averages = [0.0f64; 8];
+        for (i, &v) in values.iter().enumerate() {
+            let pos = self.pos + i;
+            if pos < self.num_values {
+                averages[i] = if self.valid.extract(pos / 32) & (1 << (pos % 32)) != 0 {
+                    self.update(self.timestamps[pos], v.to_f64().unwrap_or(0.0))
+                } else {
+                    self.average().unwrap_or(0.0)
+                };
+            }
+        }
+        self.pos += 8;
+        self.inner_sink.process(f64x8::from_slice_unaligned(&averages));
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.process(T::SI::ZERO);
+    }
+
+    fn reset(&mut self) {
+        self.pos = 0;
+    }
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.inner_sink.is_done()
+    }
+}
+
+/// Writes the time-decayed moving average of each value, using the timestamp at the same position, to the
+/// sink, in one pass over both vectors.  Like any sink, the sink gets whole sections, so up to 255 extra
+/// padding values at the end.  Both vectors must have the same number of elements, otherwise
+/// `InvalidNumRows(timestamps_elements, values_elements)` is returned.
+pub fn ewma<T, S>(values: &VectorReader<T>,
+                  timestamps: &VectorReader<u64>,
+                  alpha: f64,
+                  sink: &mut S) -> Result<(), CodingError>
+where T: VectBase + NumCast + BaseSubtypeMapping,
+      S: Sink<f64x8> {
+    let num_elements = values.num_elements();
+    if timestamps.num_elements() != num_elements {
+        return Err(CodingError::InvalidNumRows(timestamps.num_elements(), num_elements));
+    }
+
+    let mut ewma_sink = EwmaSink::<T, _>::new(alpha, sink)?;
+    let mut ts_sink = Section256Sink::<u64>::new();
+    let mut elems_left = num_elements;
+    for (value_sect, ts_sect) in values.sect_iter().zip(timestamps.sect_iter()) {
+        let (value_sect, ts_sect) = (value_sect?, ts_sect?);
+        let sect_elems = elems_left.min(FIXED_LEN);
+        ts_sink.reset();
+        ts_sect.decode(&mut ts_sink)?;
+        ewma_sink.set_timestamps(&ts_sink.values, sect_elems);
+        ewma_sink.set_validity(value_sect.validity_mask());
+        value_sect.decode(&mut ewma_sink)?;
+        elems_left -= sect_elems;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::VecSink;
+    use crate::vector::{VectorF64XorAppender, VectorU32Appender, VectorU64Appender};
+
+    #[test]
+    fn test_ewma_matches_scalar_with_null_sections() {
+        let raw: Vec<f64> = (0..300).map(|i| ((i * 37) % 101) as f64 * 0.5).collect();
+        let mut appender = VectorF64XorAppender::try_new(4096).unwrap();
+        raw[..256].iter().for_each(|&v| appender.append(v).unwrap());
+        appender.append_nulls(256).unwrap();
+        raw[256..].iter().for_each(|&v| appender.append(v).unwrap());
+        let values = appender.finish(556).unwrap();
+        // Irregular gaps, including repeated timestamps
+        let times = VectorU64Appender::try_new(4096).unwrap()
+                        .encode_all((0..556u64).map(|i| 1000 + i + i / 3 - i % 2)).unwrap();
+        let values = VectorReader::<f64>::try_new(&values[..]).unwrap();
+        let times = VectorReader::<u64>::try_new(&times[..]).unwrap();
+
+        let mut sink = VecSink::<f64>::new();
+        ewma(&values, &times, 0.2, &mut sink).unwrap();
+        assert_eq!(sink.vec.len(), 768);
+
+        let mut expected = Vec::new();
+        let mut state: Option<(u64, f64)> = None;
+        for (v, t) in values.iterate().zip(times.iterate()) {
+            let avg = match state {
+                None            => v,
+                Some((last, a)) => a + (1.0 - 0.8f64.powf(t.saturating_sub(last).max(1) as f64)) * (v - a),
+            };
+            state = Some((t, avg));
+            expected.push(avg);
+        }
+        assert_eq!(sink.vec[..556], expected[..]);
+        assert!(sink.vec[511] < expected[255]);     // Decays towards the zeroes of the Null section
+
+        let short = VectorU64Appender::try_new(1024).unwrap().encode_all(vec![1u64, 2]).unwrap();
+        let short = VectorReader::<u64>::try_new(&short[..]).unwrap();
+        assert_eq!(ewma(&values, &short, 0.2, &mut sink), Err(CodingError::InvalidNumRows(2, 556)));
+        assert!(matches!(ewma(&values, &times, 1.5, &mut sink), Err(CodingError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_ewma_holds_over_null_elements() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.track_nulls(true);
+        let values = appender.encode_all_options(vec![Some(10), None, Some(20)]).unwrap();
+        let times = VectorU64Appender::try_new(1024).unwrap().encode_all(vec![100, 101, 102]).unwrap();
+        let values = VectorReader::<u32>::try_new(&values[..]).unwrap();
+        let times = VectorReader::<u64>::try_new(&times[..]).unwrap();
+
+        // The null is skipped, so the last value is 2 time units after the first
+        let mut sink = VecSink::<f64>::new();
+        ewma(&values, &times, 0.5, &mut sink).unwrap();
+        assert_eq!(sink.vec[..3], [10.0, 10.0, 17.5]);
+    }
+}
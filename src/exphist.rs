@@ -0,0 +1,368 @@
+/// The `exphist` module encodes columns of OpenTelemetry exponential histograms, one histogram per row,
+/// for example successive cumulative snapshots of one histogram series over time.
+///
+/// Each row records the scale, the zero count, and the positive and negative bucket runs (an offset plus
+/// consecutive bucket counts).  Counts are NibblePacked in ZeroRuns mode.  When a row has the same scale as
+/// the previous row and no count decreased, the counts are written as deltas from the previous row's count
+/// for the same bucket index, which are usually small or zero for cumulative histograms.  Otherwise (counter
+/// reset, or scale change) the row is written in full.
+///
+/// Readers can re-bucket rows to a coarser target scale while decoding, so rows with differing scales can be
+/// merged or compared:
+/// ```
+/// # use compressed_vec::exphist::*;
+///     let mut appender = ExpHistogramAppender::new();
+///     let h1 = ExpHistogram::new(2, 1, ExpBuckets::new(-1, vec![1, 2, 3]), ExpBuckets::default());
+///     let h2 = ExpHistogram::new(2, 3, ExpBuckets::new(-1, vec![2, 2, 5, 1]), ExpBuckets::default());
+///     appender.append(&h1).unwrap();
+///     appender.append(&h2).unwrap();
+///     let bytes = appender.finish();
+///
+///     let reader = ExpHistogramReader::try_new(&bytes[..]).unwrap();
+///     assert_eq!(reader.decode_all().unwrap(), vec![h1, h2.clone()]);
+///     // Scale 1 buckets each cover two scale 2 buckets
+///     let coarse = reader.decode_at_scale(1).unwrap();
+///     assert_eq!(coarse[1], ExpHistogram::new(1, 3, ExpBuckets::new(-1, vec![2, 7, 1]), ExpBuckets::default()));
+/// ```
+use crate::error::CodingError;
+use crate::nibblepacking::{pack_u64_zero_runs, unpack_with_mode, NibblePackMode};
+use crate::sink::VecSink;
+
+use scroll::{Pread, Pwrite, LE};
+
+
+/// Consecutive buckets starting at bucket index `offset`.  Bucket index i covers values in
+/// (base^i, base^(i+1)], where base = 2^(2^-scale).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExpBuckets {
+    pub offset: i32,
+    pub counts: Vec<u64>,
+}
+
+impl ExpBuckets {
+    pub fn new(offset: i32, counts: Vec<u64>) -> Self {
+        Self { offset, counts }
+    }
+
+    /// Returns the count for the absolute bucket index, or 0 if outside of this run
+    #[inline]
+    pub fn count_at(&self, index: i32) -> u64 {
+        let rel = index as i64 - self.offset as i64;
+        if rel < 0 || rel >= self.counts.len() as i64 { 0 } else { self.counts[rel as usize] }
+    }
+
+    /// Re-buckets to a scale `shift` lower, merging each 2^shift adjacent buckets into one
+    pub fn downscale(&self, shift: u32) -> ExpBuckets {
+        if self.counts.is_empty() || shift == 0 { return self.clone() }
+        let new_offset = self.offset >> shift;
+        let last = (self.offset + self.counts.len() as i32 - 1) >> shift;
+        let mut counts = vec![0u64; (last - new_offset + 1) as usize];
+        for (i, &count) in self.counts.iter().enumerate() {
+            counts[(((self.offset + i as i32) >> shift) - new_offset) as usize] += count;
+        }
+        ExpBuckets { offset: new_offset, counts }
+    }
+}
+
+/// A single OpenTelemetry exponential histogram
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExpHistogram {
+    pub scale: i8,
+    pub zero_count: u64,
+    pub positive: ExpBuckets,
+    pub negative: ExpBuckets,
+}
+
+impl ExpHistogram {
+    pub fn new(scale: i8, zero_count: u64, positive: ExpBuckets, negative: ExpBuckets) -> Self {
+        Self { scale, zero_count, positive, negative }
+    }
+
+    /// Re-buckets this histogram to a target scale, which must not be greater than the current scale.
+    pub fn downscale(&self, target_scale: i8) -> Result<ExpHistogram, CodingError> {
+        if target_scale > self.scale {
+            return Err(CodingError::InvalidFormat(format!("Cannot rescale histogram from scale {} to finer scale {}",
+                                                          self.scale, target_scale)));
+        }
+        let shift = (self.scale as i32 - target_scale as i32) as u32;
+        Ok(ExpHistogram { scale: target_scale,
+                          zero_count: self.zero_count,
+                          positive: self.positive.downscale(shift),
+                          negative: self.negative.downscale(shift) })
+    }
+
+    fn num_values(&self) -> usize {
+        1 + self.positive.counts.len() + self.negative.counts.len()
+    }
+
+    // Can this histogram be delta encoded against prev?  Same scale and no count went down.
+    fn is_increasing_from(&self, prev: &ExpHistogram) -> bool {
+        fn buckets_increasing(cur: &ExpBuckets, prev: &ExpBuckets) -> bool {
+            cur.counts.iter().enumerate().all(|(i, &c)| c >= prev.count_at(cur.offset + i as i32))
+        }
+        self.scale == prev.scale && self.zero_count >= prev.zero_count &&
+        buckets_increasing(&self.positive, &prev.positive) &&
+        buckets_increasing(&self.negative, &prev.negative)
+    }
+}
+
+const FLAG_DELTA: u8 = 0x01;
+
+// flags, scale, positive offset + len, negative offset + len
+const ROW_HEADER_BYTES: usize = 14;
+// Worst case for a NibblePacked group of 8 u64's: bitmask, nibble word and 64 bytes
+const MAX_GROUP_BYTES: usize = 66;
+
+/// Appends exponential histograms to an encoded column.
+#[derive(Debug, Default)]
+pub struct ExpHistogramAppender {
+    buf: Vec<u8>,
+    num_rows: u32,
+    last: Option<ExpHistogram>,
+}
+
+impl ExpHistogramAppender {
+    pub fn new() -> Self {
+        Self { buf: vec![0u8; 4], num_rows: 0, last: None }
+    }
+
+    /// Number of rows appended so far
+    pub fn num_rows(&self) -> usize {
+        self.num_rows as usize
+    }
+
+    /// Appends one histogram row.  Bucket runs may have at most 65535 buckets.
+    pub fn append(&mut self, hist: &ExpHistogram) -> Result<(), CodingError> {
+        let pos_len = hist.positive.counts.len();
+        let neg_len = hist.negative.counts.len();
+        if pos_len > u16::MAX as usize || neg_len > u16::MAX as usize {
+            return Err(CodingError::InvalidFormat(format!("Too many buckets: {} positive, {} negative",
+                                                          pos_len, neg_len)));
+        }
+
+        let prev = self.last.as_ref().filter(|prev| hist.is_increasing_from(prev));
+        let flags = if prev.is_some() { FLAG_DELTA } else { 0 };
+
+        let start = self.buf.len();
+        let max_bytes = ROW_HEADER_BYTES + (hist.num_values() + 7) / 8 * MAX_GROUP_BYTES;
+        self.buf.resize(start + max_bytes, 0);
+        let row = &mut self.buf[start..];
+        row.pwrite_with(flags, 0, LE)?;
+        row.pwrite_with(hist.scale, 1, LE)?;
+        row.pwrite_with(hist.positive.offset, 2, LE)?;
+        row.pwrite_with(pos_len as u16, 6, LE)?;
+        row.pwrite_with(hist.negative.offset, 8, LE)?;
+        row.pwrite_with(neg_len as u16, 12, LE)?;
+
+        let values = Self::row_values(hist);
+        let row_bytes = match prev {
+            Some(prev) => {
+                let prev_values = Self::aligned_values(prev, hist);
+                pack_u64_zero_runs(values.zip(prev_values).map(|(cur, prev)| cur - prev), row, ROW_HEADER_BYTES)?
+            },
+            None => pack_u64_zero_runs(values, row, ROW_HEADER_BYTES)?,
+        };
+        self.buf.truncate(start + row_bytes);
+
+        self.num_rows += 1;
+        self.last = Some(hist.clone());
+        Ok(())
+    }
+
+    /// Writes the number of rows and returns the encoded column bytes.  The appender is reset for reuse.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let num_rows = self.num_rows;
+        let mut out = std::mem::replace(&mut self.buf, vec![0u8; 4]);
+        out[..4].copy_from_slice(&num_rows.to_le_bytes());
+        self.num_rows = 0;
+        self.last = None;
+        out
+    }
+
+    // Zero count, then positive counts, then negative counts
+    fn row_values(hist: &ExpHistogram) -> impl Iterator<Item = u64> + '_ {
+        std::iter::once(hist.zero_count)
+            .chain(hist.positive.counts.iter().cloned())
+            .chain(hist.negative.counts.iter().cloned())
+    }
+
+    // The values of prev for the same bucket indices as hist
+    fn aligned_values<'a>(prev: &'a ExpHistogram, hist: &'a ExpHistogram) -> impl Iterator<Item = u64> + 'a {
+        let pos = &hist.positive;
+        let neg = &hist.negative;
+        std::iter::once(prev.zero_count)
+            .chain((0..pos.counts.len()).map(move |i| prev.positive.count_at(pos.offset + i as i32)))
+            .chain((0..neg.counts.len()).map(move |i| prev.negative.count_at(neg.offset + i as i32)))
+    }
+}
+
+/// Reads a column of exponential histograms written by `ExpHistogramAppender`.
+#[derive(Debug)]
+pub struct ExpHistogramReader<'buf> {
+    num_rows: usize,
+    rows_bytes: &'buf [u8],
+}
+
+impl<'buf> ExpHistogramReader<'buf> {
+    pub fn try_new(col_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let num_rows: u32 = col_bytes.pread_with(0, LE)?;
+        Ok(Self { num_rows: num_rows as usize, rows_bytes: &col_bytes[4..] })
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// Returns an iterator over decoded histograms.  Rows must be decoded in order because of delta encoding.
+    pub fn iterate(&self) -> ExpHistogramIter<'buf> {
+        ExpHistogramIter { rows_left: self.num_rows, encoded: self.rows_bytes, last: None, target_scale: None }
+    }
+
+    /// Returns an iterator which re-buckets each histogram to target_scale.  Returns an error for rows
+    /// whose scale is coarser than the target scale.
+    pub fn iterate_at_scale(&self, target_scale: i8) -> ExpHistogramIter<'buf> {
+        ExpHistogramIter { target_scale: Some(target_scale), ..self.iterate() }
+    }
+
+    pub fn decode_all(&self) -> Result<Vec<ExpHistogram>, CodingError> {
+        self.iterate().collect()
+    }
+
+    pub fn decode_at_scale(&self, target_scale: i8) -> Result<Vec<ExpHistogram>, CodingError> {
+        self.iterate_at_scale(target_scale).collect()
+    }
+}
+
+/// Iterator over the histograms in a column
+pub struct ExpHistogramIter<'buf> {
+    rows_left: usize,
+    encoded: &'buf [u8],
+    last: Option<ExpHistogram>,
+    target_scale: Option<i8>,
+}
+
+impl<'buf> ExpHistogramIter<'buf> {
+    fn decode_row(&mut self) -> Result<ExpHistogram, CodingError> {
+        let row = self.encoded;
+        let flags: u8 = row.pread_with(0, LE)?;
+        let scale: i8 = row.pread_with(1, LE)?;
+        let pos_offset: i32 = row.pread_with(2, LE)?;
+        let pos_len: u16 = row.pread_with(6, LE)?;
+        let neg_offset: i32 = row.pread_with(8, LE)?;
+        let neg_len: u16 = row.pread_with(12, LE)?;
+
+        let num_values = 1 + pos_len as usize + neg_len as usize;
+        let mut sink = VecSink::<u64>::new();
+        let packed = row.get(ROW_HEADER_BYTES..).ok_or(CodingError::InputTooShort)?;
+        self.encoded = unpack_with_mode(packed, &mut sink, num_values, NibblePackMode::ZeroRuns)?;
+
+        let values = &sink.vec[..num_values];
+        let mut hist = ExpHistogram::new(scale, values[0],
+                                         ExpBuckets::new(pos_offset, values[1..1 + pos_len as usize].to_vec()),
+                                         ExpBuckets::new(neg_offset, values[1 + pos_len as usize..].to_vec()));
+        if flags & FLAG_DELTA != 0 {
+            let prev = self.last.as_ref()
+                           .ok_or_else(|| CodingError::InvalidFormat("Delta row without previous row".into()))?;
+            let mut prev_values = ExpHistogramAppender::aligned_values(prev, &hist).collect::<Vec<_>>().into_iter();
+            for v in std::iter::once(&mut hist.zero_count)
+                         .chain(hist.positive.counts.iter_mut())
+                         .chain(hist.negative.counts.iter_mut()) {
+                *v = v.wrapping_add(prev_values.next().unwrap_or(0));
+            }
+        }
+        self.last = Some(hist.clone());
+        Ok(hist)
+    }
+}
+
+impl<'buf> Iterator for ExpHistogramIter<'buf> {
+    type Item = Result<ExpHistogram, CodingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rows_left == 0 { return None }
+        self.rows_left -= 1;
+        let res = self.decode_row();
+        if res.is_err() { self.rows_left = 0; }
+        Some(match self.target_scale {
+            Some(scale) => res.and_then(|hist| hist.downscale(scale)),
+            None        => res,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cumulative_series() -> Vec<ExpHistogram> {
+        let mut hists = Vec::new();
+        let mut hist = ExpHistogram::new(3, 0, ExpBuckets::new(-4, vec![0; 20]), ExpBuckets::new(2, vec![0; 5]));
+        for t in 0..50u64 {
+            hist.zero_count += t % 2;
+            hist.positive.counts[(t % 20) as usize] += t;
+            if t % 7 == 0 { hist.negative.counts[2] += 1; }
+            // Bucket range grows on the low end
+            if t == 30 {
+                hist.positive.offset -= 2;
+                hist.positive.counts.insert(0, 5);
+                hist.positive.counts.insert(0, 1);
+            }
+            hists.push(hist.clone());
+        }
+        hists
+    }
+
+    #[test]
+    fn test_exphist_delta_roundtrip() {
+        let hists = cumulative_series();
+        let mut appender = ExpHistogramAppender::new();
+        hists.iter().for_each(|h| appender.append(h).unwrap());
+        assert_eq!(appender.num_rows(), 50);
+        let bytes = appender.finish();
+
+        // Deltas should take far less space than 26 buckets * 50 rows as full counts
+        let mut full_appender = ExpHistogramAppender::new();
+        for h in hists.iter() {
+            full_appender.append(h).unwrap();
+            full_appender.last = None;
+        }
+        assert!(bytes.len() < full_appender.finish().len());
+
+        let reader = ExpHistogramReader::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.num_rows(), 50);
+        assert_eq!(reader.decode_all().unwrap(), hists);
+
+        // Appender is reusable after finish()
+        appender.append(&hists[10]).unwrap();
+        let bytes = appender.finish();
+        assert_eq!(ExpHistogramReader::try_new(&bytes[..]).unwrap().decode_all().unwrap(), vec![hists[10].clone()]);
+    }
+
+    #[test]
+    fn test_exphist_reset_and_scale_change() {
+        let h1 = ExpHistogram::new(4, 10, ExpBuckets::new(0, vec![5, 6, 7]), ExpBuckets::default());
+        // Counter reset
+        let h2 = ExpHistogram::new(4, 1, ExpBuckets::new(0, vec![1, 0, 2]), ExpBuckets::default());
+        // Scale change, and a bucket which disappears
+        let h3 = ExpHistogram::new(2, 3, ExpBuckets::new(1, vec![4]), ExpBuckets::new(-3, vec![1, 1]));
+        let h4 = ExpHistogram::new(2, 3, ExpBuckets::new(1, vec![9]), ExpBuckets::new(-3, vec![1, 2]));
+
+        let mut appender = ExpHistogramAppender::new();
+        for h in [&h1, &h2, &h3, &h4].iter() { appender.append(h).unwrap(); }
+        let bytes = appender.finish();
+        let reader = ExpHistogramReader::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.decode_all().unwrap(), vec![h1.clone(), h2, h3, h4.clone()]);
+
+        // Rescaling to 2: h1 buckets 0..=2 all fall into scale 2 bucket 0
+        let rescaled = reader.decode_at_scale(2).unwrap();
+        assert_eq!(rescaled[0], ExpHistogram::new(2, 10, ExpBuckets::new(0, vec![18]), ExpBuckets::default()));
+        assert_eq!(rescaled[3], h4);
+
+        // Negative indices round towards negative infinity
+        assert_eq!(h4.negative.downscale(1), ExpBuckets::new(-2, vec![1, 2]));
+
+        // Cannot rescale to a finer scale than the coarsest row
+        assert!(reader.iterate_at_scale(3).any(|res| res.is_err()));
+    }
+}
@@ -5,12 +5,17 @@
 /// TODO: add examples for EqualsSink, OneOfSink, etc.
 ///
 use core::marker::PhantomData;
+use std::convert::TryFrom;
+use std::ops::{Bound, RangeBounds};
+use std::time::{Duration, Instant};
 
 use packed_simd::u32x8;
 use smallvec::SmallVec;
 
+use crate::bloom::SectionBloomFilters;
+use crate::reference::RefBits;
 use crate::section::*;
-use crate::sink::{Sink, SinkInput};
+use crate::sink::{Sink, SinkInput, Section256Sink};
 
 
 /// A Sink designed to filter 256-section vectors.  The workflow:
@@ -24,6 +29,14 @@ pub trait SectFilterSink<T: VectBase>: Sink<T::SI> {
 
     /// Returns a mask when its a null section
     fn null_mask(&self) -> u32x8;
+
+    /// Returns false if no value between min and max inclusive can match, so that sections with those bounds in
+    /// a zone map can be skipped without decoding.  The default never rules out a section.
+    fn may_match(&self, _min: T, _max: T) -> bool { true }
+
+    /// Returns the values the filter can match, if there are only a few, so that sections whose Bloom filter
+    /// contains none of them can be skipped without decoding.  The default is None, which never skips sections.
+    fn probe_values(&self) -> Option<SmallVec<[T; 4]>> { None }
 }
 
 
@@ -44,6 +57,12 @@ pub trait InnerFilter<T: VectBase> {
     /// This method is called with the SinkInput from the decoder, and has to do filtering using
     /// the predicate type and return a bitmask; LSB=first item processed
     fn filter_bitmask(pred: &Self::P, decoded: T::SI) -> u8;
+
+    /// Returns false if no value between min and max inclusive can match the predicate
+    fn may_match(_pred: &Self::P, _min: T, _max: T) -> bool { true }
+
+    /// Returns the only values the predicate can match, if there are only a few
+    fn probe_values(_pred: &Self::P) -> Option<SmallVec<[T; 4]>> { None }
 }
 
 /// Sink designed to filter 8 items at a time from the decoder, building up a bitmask for each section.
@@ -104,6 +123,16 @@ impl<T: VectBase, IF: InnerFilter<T>> SectFilterSink<T> for GenericFilterSink<T,
     fn null_mask(&self) -> u32x8 {
         if self.match_zero { ALL_MATCHES } else { NO_MATCHES }
     }
+
+    #[inline]
+    fn may_match(&self, min: T, max: T) -> bool {
+        IF::may_match(&self.predicate, min, max)
+    }
+
+    #[inline]
+    fn probe_values(&self) -> Option<SmallVec<[T; 4]>> {
+        IF::probe_values(&self.predicate)
+    }
 }
 
 
@@ -111,6 +140,7 @@ impl<T: VectBase, IF: InnerFilter<T>> SectFilterSink<T> for GenericFilterSink<T,
 // type SingleValuePredicate<T> = <T as VectBase>::SI;
 pub struct SingleValuePredicate<T: VectBase> {
     pred: T::SI,
+    value: T,
 }
 
 impl<T: VectBase> Predicate<T> for SingleValuePredicate<T> {
@@ -122,7 +152,7 @@ impl<T: VectBase> Predicate<T> for SingleValuePredicate<T> {
 
     #[inline]
     fn from_input(input: &T) -> Self {
-        Self { pred: T::SI::splat(*input) }
+        Self { pred: T::SI::splat(*input), value: *input }
     }
 }
 
@@ -134,6 +164,15 @@ impl<T: VectBase> InnerFilter<T> for EqualsIF {
     fn filter_bitmask(p: &Self::P, decoded: T::SI) -> u8 {
         T::SI::eq_mask(p.pred, decoded)
     }
+
+    #[inline]
+    fn may_match(p: &Self::P, min: T, max: T) -> bool {
+        min <= p.value && p.value <= max
+    }
+
+    fn probe_values(p: &Self::P) -> Option<SmallVec<[T; 4]>> {
+        Some(smallvec::smallvec![p.value])
+    }
 }
 
 pub type EqualsSink<T> = GenericFilterSink<T, EqualsIF>;
@@ -142,6 +181,7 @@ pub type EqualsSink<T> = GenericFilterSink<T, EqualsIF>;
 ///  A predicate for low cardinality SET membership (one of/IN matches), consisting of a Vec of 8 values each
 pub struct MembershipPredicate<T: VectBase> {
     set: Vec<T::SI>,
+    items: SmallVec<[T; 4]>,
 }
 
 impl<T: VectBase> Predicate<T> for MembershipPredicate<T> {
@@ -155,7 +195,7 @@ impl<T: VectBase> Predicate<T> for MembershipPredicate<T> {
 
     #[inline]
     fn from_input(input: &Self::Input) -> Self {
-        Self { set: input.iter().map(|&item| T::SI::splat(item)).collect() }
+        Self { set: input.iter().map(|&item| T::SI::splat(item)).collect(), items: input.clone() }
     }
 }
 
@@ -172,11 +212,255 @@ impl<T: VectBase> InnerFilter<T> for OneOfIF {
         }
         mask
     }
+
+    #[inline]
+    fn may_match(p: &Self::P, min: T, max: T) -> bool {
+        p.items.iter().any(|&item| min <= item && item <= max)
+    }
+
+    fn probe_values(p: &Self::P) -> Option<SmallVec<[T; 4]>> {
+        Some(p.items.clone())
+    }
 }
 
 pub type OneOfSink<T> = GenericFilterSink<T, OneOfIF>;
 
 
+///  A predicate for a range of values, with a lower and an upper bound each inclusive, exclusive or unbounded
+pub struct RangePredicate<T: VectBase> {
+    lower: Bound<T::SI>,
+    upper: Bound<T::SI>,
+    bounds: (Bound<T>, Bound<T>),
+}
+
+#[inline]
+fn splat_bound<T: VectBase>(bound: Bound<T>) -> Bound<T::SI> {
+    match bound {
+        Bound::Included(item) => Bound::Included(T::SI::splat(item)),
+        Bound::Excluded(item) => Bound::Excluded(T::SI::splat(item)),
+        Bound::Unbounded      => Bound::Unbounded,
+    }
+}
+
+impl<T: VectBase> Predicate<T> for RangePredicate<T> {
+    type Input = (Bound<T>, Bound<T>);
+    #[inline]
+    fn pred_matches_zero(input: &Self::Input) -> bool {
+        input.contains(&T::zero())
+    }
+
+    #[inline]
+    fn from_input(input: &Self::Input) -> Self {
+        Self { lower: splat_bound(input.0), upper: splat_bound(input.1), bounds: *input }
+    }
+}
+
+pub struct RangeIF {}
+
+impl<T: VectBase> InnerFilter<T> for RangeIF {
+    type P = RangePredicate<T>;
+    #[inline]
+    fn filter_bitmask(p: &Self::P, decoded: T::SI) -> u8 {
+        let above_lower = match p.lower {
+            Bound::Included(lower) => lower.le_mask(decoded),
+            Bound::Excluded(lower) => lower.lt_mask(decoded),
+            Bound::Unbounded       => 0xff,
+        };
+        let below_upper = match p.upper {
+            Bound::Included(upper) => decoded.le_mask(upper),
+            Bound::Excluded(upper) => decoded.lt_mask(upper),
+            Bound::Unbounded       => 0xff,
+        };
+        above_lower & below_upper
+    }
+
+    #[inline]
+    fn may_match(p: &Self::P, min: T, max: T) -> bool {
+        let above_lower = match p.bounds.0 {
+            Bound::Included(lower) => lower <= max,
+            Bound::Excluded(lower) => lower < max,
+            Bound::Unbounded       => true,
+        };
+        let below_upper = match p.bounds.1 {
+            Bound::Included(upper) => min <= upper,
+            Bound::Excluded(upper) => min < upper,
+            Bound::Unbounded       => true,
+        };
+        above_lower && below_upper
+    }
+}
+
+/// Filters values within a range.  Its masks are the same as those of EqualsSink and OneOfSink, so they can be
+/// ANDed or ORed together to combine predicates.
+/// ```
+/// # use compressed_vec::filter::*;
+/// # use compressed_vec::vector::{VectorReader, VectorU32Appender};
+///     let bytes = VectorU32Appender::try_new(1024).unwrap().encode_all(0..1000u32).unwrap();
+///     let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+///     assert_eq!(count_hits(reader.filter_iter(RangeSink::between(100, 199))), 100);
+///     assert_eq!(count_hits(reader.filter_iter(RangeSink::greater_than(989))), 10);
+/// ```
+pub type RangeSink<T> = GenericFilterSink<T, RangeIF>;
+
+impl<T: VectBase> GenericFilterSink<T, RangeIF> {
+    pub fn less_than(value: T) -> Self {
+        Self::new(&(Bound::Unbounded, Bound::Excluded(value)))
+    }
+
+    pub fn less_or_equal(value: T) -> Self {
+        Self::new(&(Bound::Unbounded, Bound::Included(value)))
+    }
+
+    pub fn greater_than(value: T) -> Self {
+        Self::new(&(Bound::Excluded(value), Bound::Unbounded))
+    }
+
+    pub fn greater_or_equal(value: T) -> Self {
+        Self::new(&(Bound::Included(value), Bound::Unbounded))
+    }
+
+    /// Matches values from low to high, both inclusive
+    pub fn between(low: T, high: T) -> Self {
+        Self::new(&(Bound::Included(low), Bound::Included(high)))
+    }
+}
+
+
+// Converts 32 bytes of mask, first byte for first 8 elements, into a u32x8 mask
+#[inline]
+fn mask_from_bytes(bytes: &[u8; 32]) -> u32x8 {
+    let mut words = [0u32; 8];
+    for (w, chunk) in words.iter_mut().zip(bytes.chunks(4)) {
+        *w = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    u32x8::from(words)
+}
+
+/// A filter sink which calls a user-supplied closure with each decoded SIMD octet.  The closure returns a
+/// bitmask, LSB=first item in the octet.  This allows for exotic predicates (modulo, bit tests, etc.)
+/// to reuse all of the section-skipping machinery of VectorFilter.
+/// The closure is called once at creation with all zeroes to compute the mask for null sections.
+/// ```
+/// # use compressed_vec::filter::*;
+/// # use compressed_vec::vector::VectorU32Appender;
+/// # use compressed_vec::sink::SinkInput;
+/// # use packed_simd::u32x8;
+///     let mut appender = VectorU32Appender::try_new(1024).unwrap();
+///     let bytes = appender.encode_all(0..300u32).unwrap();
+///     let reader = compressed_vec::VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+///     // Odd numbers only
+///     let odd = FilterFnSink::<u32, _>::new(|octet: u32x8| (octet & u32x8::splat(1)).eq_mask(u32x8::splat(1)));
+///     assert_eq!(count_hits(reader.filter_iter(odd)), 150);
+/// ```
+pub struct FilterFnSink<T, F>
+where T: VectBase,
+      F: Fn(T::SI) -> u8 {
+    mask: [u8; 32],
+    func: F,
+    i: usize,
+    zero_mask: u8,
+    _t: PhantomData<T>,
+}
+
+impl<T, F> FilterFnSink<T, F>
+where T: VectBase,
+      F: Fn(T::SI) -> u8 {
+    pub fn new(func: F) -> Self {
+        let zero_mask = func(T::SI::ZERO);
+        Self { mask: [0u8; 32], func, i: 0, zero_mask, _t: PhantomData }
+    }
+}
+
+impl<T, F> Sink<T::SI> for FilterFnSink<T, F>
+where T: VectBase,
+      F: Fn(T::SI) -> u8 {
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.mask[self.i] = self.zero_mask;
+        self.i += 1;
+    }
+
+    #[inline]
+    fn process(&mut self, unpacked: T::SI) {
+        self.mask[self.i] = (self.func)(unpacked);
+        self.i += 1;
+    }
+
+    #[inline]
+    fn reset(&mut self) {
+        self.i = 0;
+    }
+}
+
+impl<T, F> SectFilterSink<T> for FilterFnSink<T, F>
+where T: VectBase,
+      F: Fn(T::SI) -> u8 {
+    #[inline]
+    fn get_mask(&self) -> u32x8 {
+        mask_from_bytes(&self.mask)
+    }
+
+    #[inline]
+    fn null_mask(&self) -> u32x8 {
+        mask_from_bytes(&[self.zero_mask; 32])
+    }
+}
+
+/// A filter sink which decodes an entire section, then calls a user-supplied closure with the slice of all
+/// FIXED_LEN values.  The closure returns the u32x8 mask for the section, bit 0 of the first word being the first
+/// element.  Slower than FilterFnSink but useful for predicates which need to see more than 8 values at once.
+/// The closure is called once at creation with all zeroes to compute the mask for null sections.
+pub struct SectionFilterFnSink<T, F>
+where T: VectBase,
+      F: Fn(&[T]) -> u32x8 {
+    values: Section256Sink<T>,
+    func: F,
+    null_mask: u32x8,
+}
+
+impl<T, F> SectionFilterFnSink<T, F>
+where T: VectBase,
+      F: Fn(&[T]) -> u32x8 {
+    pub fn new(func: F) -> Self {
+        let null_mask = func(&[T::zero(); FIXED_LEN]);
+        Self { values: Section256Sink::new(), func, null_mask }
+    }
+}
+
+impl<T, F> Sink<T::SI> for SectionFilterFnSink<T, F>
+where T: VectBase,
+      F: Fn(&[T]) -> u32x8 {
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.values.process_zeroes();
+    }
+
+    #[inline]
+    fn process(&mut self, unpacked: T::SI) {
+        self.values.process(unpacked);
+    }
+
+    #[inline]
+    fn reset(&mut self) {
+        self.values.reset();
+    }
+}
+
+impl<T, F> SectFilterSink<T> for SectionFilterFnSink<T, F>
+where T: VectBase,
+      F: Fn(&[T]) -> u32x8 {
+    #[inline]
+    fn get_mask(&self) -> u32x8 {
+        (self.func)(&self.values.values[..])
+    }
+
+    #[inline]
+    fn null_mask(&self) -> u32x8 {
+        self.null_mask
+    }
+}
+
+
 /// A Unary filter takes one mask input, does some kind of filtering and creates a new mask.
 /// Filters that process and filter vectors are a subset of the above.
 pub trait UnaryFilter {
@@ -184,6 +468,71 @@ pub trait UnaryFilter {
     fn filter(input: u32x8) -> u32x8;
 }
 
+/// Counters for the sections of one section type seen by a filter
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct SectTypeStats {
+    pub skipped: u64,             // Sections skipped by short-circuiting, neither decoded nor masked
+    pub pruned: u64,              // Sections ruled out by zone map bounds or Bloom filters, neither decoded nor masked
+    pub mask_only: u64,           // Null sections, whose mask is evaluated without decoding
+    pub decoded: u64,             // Sections decoded through the filter sink
+    pub decode_time: Duration,    // Cumulative time spent decoding and masking
+}
+
+impl SectTypeStats {
+    fn add(&mut self, other: &SectTypeStats) {
+        self.skipped += other.skipped;
+        self.pruned += other.pruned;
+        self.mask_only += other.mask_only;
+        self.decoded += other.decoded;
+        self.decode_time += other.decode_time;
+    }
+}
+
+// One entry per SectionType number; Lz4Medium must stay the last variant
+const NUM_STATS_TYPES: usize = SectionType::Lz4Medium as usize + 1;
+
+/// Per section type breakdown of the work done by a filter, for diagnosing slow queries.
+/// Collected only when enabled with `VectorFilter::enable_stats()`, as timing every section has a cost.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FilterStats {
+    by_type: [SectTypeStats; NUM_STATS_TYPES],
+}
+
+impl FilterStats {
+    /// Counters for the given section type
+    pub fn for_type(&self, sect_type: SectionType) -> &SectTypeStats {
+        &self.by_type[sect_type.as_num() as usize]
+    }
+
+    /// Iterates over the counters of the section types which were seen
+    pub fn iter(&self) -> impl Iterator<Item = (SectionType, &SectTypeStats)> {
+        self.by_type.iter().enumerate()
+            .filter(|(_, stats)| **stats != SectTypeStats::default())
+            .filter_map(|(n, stats)| SectionType::try_from(n as u8).ok().map(|typ| (typ, stats)))
+    }
+
+    /// Counters summed over all section types
+    pub fn total(&self) -> SectTypeStats {
+        let mut total = SectTypeStats::default();
+        self.by_type.iter().for_each(|stats| total.add(stats));
+        total
+    }
+
+    /// Adds the counters of other to this one, eg to combine the stats of several vectors
+    pub fn merge(&mut self, other: &FilterStats) {
+        self.by_type.iter_mut().zip(other.by_type.iter()).for_each(|(mine, theirs)| mine.add(theirs));
+    }
+
+    #[inline]
+    fn entry(&mut self, sect_type: SectionType) -> &mut SectTypeStats {
+        &mut self.by_type[sect_type.as_num() as usize]
+    }
+}
+
+/// A bitmask over the 256 elements of one section, as yielded by VectorFilter: bit b of word w is ON if element
+/// w * 32 + b matches.
+pub type SectionMask = u32x8;
+
 /// Allows for filtering over each section of a vector.
 /// Yields an Iterator of u32x8 mask for each section in the vector.
 pub struct VectorFilter<'buf, SF, T>
@@ -191,6 +540,12 @@ where T: VectBase,
       SF: SectFilterSink<T> {
     sect_iter: FixedSectIterator<'buf, T>,
     sf: SF,
+    stats: Option<FilterStats>,
+    zone_map: &'buf [(T, T)],
+    sect_blooms: Option<SectionBloomFilters<'buf>>,
+    probe_bits: SmallVec<[u64; 4]>,    // Bits of the values looked up in sect_blooms
+    sect_num: usize,
+    sql_nulls: bool,
     _t: PhantomData<T>,
 }
 
@@ -198,14 +553,78 @@ impl<'buf, SF, T> VectorFilter<'buf, SF, T>
 where T: VectBase,
       SF: SectFilterSink<T> {
     pub fn new(vector_bytes: &'buf [u8], sf: SF) -> Self {
-        Self { sect_iter: FixedSectIterator::new(vector_bytes), sf, _t: PhantomData }
+        Self { sect_iter: FixedSectIterator::new(vector_bytes), sf, stats: None, zone_map: &[], sect_blooms: None,
+               probe_bits: SmallVec::new(), sect_num: 0, sql_nulls: false, _t: PhantomData }
+    }
+
+    /// Enables or disables SQL null semantics: predicates never match nulls, that is the nulls of PartialNull
+    /// sections (see `VectorAppender::track_nulls()`).  By default nulls are zeroes, and match predicates which
+    /// match zero.  The elements of Null sections are zeroes either way.
+    pub fn sql_nulls(&mut self, enabled: bool) {
+        self.sql_nulls = enabled;
+    }
+
+    /// Sets the inclusive (min, max) bounds of the values of each section, in section order.  Zone maps are not
+    /// stored in vectors: the caller keeps them in its own side index, eg computed once with
+    /// `minmax::per_section_minmax()`, and must keep them in step with the vector.  Sections whose bounds the
+    /// filter sink rules out with `may_match()` yield an empty mask without being decoded.  Sections past the
+    /// end of the zone map are always filtered.  Bounds cover only the elements of the vector, so padding after
+    /// the last element is not matched in a pruned section.  Bounds also skip tracked nulls, which are zeroes
+    /// unless `sql_nulls()` is enabled, so PartialNull sections are only pruned with SQL null semantics.
+    pub fn set_zone_map(&mut self, zone_map: &'buf [(T, T)]) {
+        self.zone_map = zone_map;
+    }
+
+    /// Starts collecting FilterStats for the sections filtered from now on
+    pub fn enable_stats(&mut self) {
+        if self.stats.is_none() { self.stats = Some(FilterStats::default()); }
+    }
+
+    /// The stats collected so far, if enabled
+    pub fn stats(&self) -> Option<&FilterStats> {
+        self.stats.as_ref()
     }
 
     /// Advances the iterator without calling the filter.  This is used to skip processing the filter
     /// for short circuiting.
     #[inline]
     pub fn advance(&mut self) {
-        self.sect_iter.next();
+        self.sect_num += 1;
+        if let Some(blooms) = self.sect_blooms.as_mut() { blooms.next(); }
+        let next = self.sect_iter.next();
+        if let (Some(stats), Some(Ok(sect))) = (self.stats.as_mut(), next) {
+            stats.entry(sect.sect_type()).skipped += 1;
+        }
+    }
+
+    /// Counts the matches in the remaining sections by popcounting their masks, like count_hits()
+    pub fn count_matches(&mut self) -> usize {
+        count_hits(self)
+    }
+
+    /// Counts matches like count_matches(), but stops filtering sections as soon as limit matches are found, eg
+    /// for EXISTS or LIMIT queries.  The count returned may be over limit, by up to the matches of one section.
+    pub fn count_matches_up_to(&mut self, limit: usize) -> usize {
+        let mut count = 0;
+        while count < limit {
+            match self.next() {
+                Some(mask) => count += mask.count_ones().wrapping_sum() as usize,
+                None       => break,
+            }
+        }
+        count
+    }
+
+    #[inline]
+    fn filter_sect(&mut self, sect: FixedSectEnum<'buf, T>) -> Option<u32x8> {
+        if sect.is_null() {
+            Some(self.sf.null_mask())
+        } else {
+            self.sf.reset();
+            sect.decode(&mut self.sf).ok()?;
+            let mask = self.sf.get_mask();
+            Some(if self.sql_nulls { mask & sect.validity_mask() } else { mask })
+        }
     }
 }
 
@@ -216,17 +635,47 @@ where T: VectBase,
 
     #[inline]
     fn next(&mut self) -> Option<u32x8> {
-        self.sect_iter.next()
-            .and_then(|res| {
-                let sect = res.expect("This should not fail!");
-                if sect.is_null() {
-                    Some(self.sf.null_mask())
-                } else {
-                    self.sf.reset();
-                    sect.decode(&mut self.sf).ok()?;
-                    Some(self.sf.get_mask())
-                }
-            })
+        let sect = self.sect_iter.next()?.expect("This should not fail!");
+        let bounds = self.zone_map.get(self.sect_num);
+        self.sect_num += 1;
+        let bloom = self.sect_blooms.as_mut().and_then(|blooms| blooms.next()).flatten();
+        let zone_pruned = (self.sql_nulls || sect.sect_type() != SectionType::PartialNull) &&
+                          matches!(bounds, Some(&(min, max)) if !self.sf.may_match(min, max));
+        let bloom_pruned = matches!(bloom, Some(bloom)
+                                    if !self.probe_bits.iter().any(|&b| bloom.might_contain_bits(b)));
+        if zone_pruned || bloom_pruned {
+            if let Some(stats) = self.stats.as_mut() { stats.entry(sect.sect_type()).pruned += 1; }
+            return Some(NO_MATCHES)
+        }
+        if self.stats.is_none() { return self.filter_sect(sect) }
+
+        let start = Instant::now();
+        let mask = self.filter_sect(sect);
+        let elapsed = start.elapsed();
+        let entry = self.stats.as_mut().unwrap().entry(sect.sect_type());
+        if sect.is_null() { entry.mask_only += 1 } else { entry.decoded += 1 }
+        entry.decode_time += elapsed;
+        mask
+    }
+}
+
+impl<'buf, SF, T> VectorFilter<'buf, SF, T>
+where T: RefBits,
+      SF: SectFilterSink<T> {
+    /// Sets the per-section Bloom filters of the vector, from `VectorReader::section_bloom_filters()`.  Sections
+    /// whose filter contains none of the sink's `probe_values()` yield an empty mask without being decoded, so
+    /// only sinks with probe values, such as EqualsSink and OneOfSink, use the filters.
+    pub fn set_section_bloom_filters(&mut self, mut blooms: SectionBloomFilters<'buf>) {
+        if let Some(values) = self.sf.probe_values() {
+            self.probe_bits = values.iter().map(|v| v.to_bits()).collect();
+            // Floats are hashed by their bits, but 0.0 and -0.0 match each other
+            if T::IS_FLOAT && values.iter().any(|v| v.is_zero()) {
+                self.probe_bits.push(0);
+                self.probe_bits.push(1 << (T::BITS - 1));
+            }
+            if self.sect_num > 0 { blooms.nth(self.sect_num - 1); }
+            self.sect_blooms = Some(blooms);
+        }
     }
 }
 
@@ -249,6 +698,23 @@ where SF: SectFilterSink<T>,
         if vect_filters.is_empty() { panic!("Cannot pass in empty filters to MultiVectorFilter"); }
         Self { vect_filters }
     }
+
+    /// Starts collecting FilterStats for every vector filter
+    pub fn enable_stats(&mut self) {
+        self.vect_filters.iter_mut().for_each(|f| f.enable_stats());
+    }
+
+    /// The stats of the vector filter at index n, in the order passed to new()
+    pub fn vector_stats(&self, n: usize) -> Option<&FilterStats> {
+        self.vect_filters.get(n).and_then(|f| f.stats())
+    }
+
+    /// The stats of all the vector filters merged together, if enabled
+    pub fn stats(&self) -> Option<FilterStats> {
+        let mut merged = self.vect_filters[0].stats()?.clone();
+        self.vect_filters[1..].iter().filter_map(|f| f.stats()).for_each(|stats| merged.merge(stats));
+        Some(merged)
+    }
 }
 
 impl<'buf, SF, T> Iterator for MultiVectorFilter<'buf, SF, T>
@@ -285,6 +751,149 @@ where SF: SectFilterSink<T>,
     }
 }
 
+/// An iterator of section masks which can skip a section without computing its mask, so that combined filters
+/// can short-circuit.  Implemented by all the vector filters in this module.
+pub trait SectionMasks: Iterator<Item = u32x8> {
+    /// Moves past the next section without computing its mask
+    fn advance(&mut self);
+}
+
+impl<'buf, SF, T> SectionMasks for VectorFilter<'buf, SF, T>
+where T: VectBase,
+      SF: SectFilterSink<T> {
+    #[inline]
+    fn advance(&mut self) {
+        VectorFilter::advance(self)
+    }
+}
+
+impl<'buf, SF, T> SectionMasks for MultiVectorFilter<'buf, SF, T>
+where SF: SectFilterSink<T>,
+       T: VectBase {
+    fn advance(&mut self) {
+        self.vect_filters.iter_mut().for_each(|f| f.advance());
+    }
+}
+
+/// Masks of the null elements of a vector, or of its non-null elements, from the section types and validity
+/// bitmaps alone without decoding values.  Only the nulls of PartialNull sections, that is tracked nulls, are
+/// null, including the padding after the last element of a vector with tracked nulls; the elements of Null
+/// sections are zeroes.  See `VectorReader::is_null()`.
+pub struct NullFilter<'buf, T: VectBase> {
+    sect_iter: FixedSectIterator<'buf, T>,
+    match_nulls: bool,
+}
+
+impl<'buf, T: VectBase> NullFilter<'buf, T> {
+    /// Creates a filter over the sections in vector_bytes matching nulls, or non-null elements if match_nulls
+    /// is false
+    pub fn new(vector_bytes: &'buf [u8], match_nulls: bool) -> Self {
+        Self { sect_iter: FixedSectIterator::new(vector_bytes), match_nulls }
+    }
+}
+
+impl<'buf, T: VectBase> Iterator for NullFilter<'buf, T> {
+    type Item = u32x8;
+
+    #[inline]
+    fn next(&mut self) -> Option<u32x8> {
+        let sect = self.sect_iter.next()?.expect("This should not fail!");
+        let valid = sect.validity_mask();
+        Some(if self.match_nulls { !valid } else { valid })
+    }
+}
+
+impl<'buf, T: VectBase> SectionMasks for NullFilter<'buf, T> {
+    #[inline]
+    fn advance(&mut self) {
+        self.sect_iter.next();
+    }
+}
+
+/// How the masks of a filter are combined into the running mask of a CombinedFilter
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Combine {
+    And,
+    Or,
+    AndNot,
+    OrNot,
+}
+
+/// Combines the section masks of filters over several vectors, possibly of different element types and filter
+/// sinks, with AND, OR and NOT, one section at a time.  The masks are combined in the order the filters were
+/// added; the first filter's mask starts the running mask, negated for AndNot and OrNot.  A filter is not
+/// evaluated for a section when it cannot change the running mask: an And filter when no elements match so
+/// far, or an Or filter when all do.  Put the filters most likely to decide the result first.
+/// ```
+/// # use compressed_vec::filter::*;
+/// # use compressed_vec::vector::{VectorReader, VectorU32Appender, VectorF64XorAppender};
+///     let keys = VectorU32Appender::try_new(1024).unwrap().encode_all((0..1000u32).map(|i| i % 10)).unwrap();
+///     let prices = VectorF64XorAppender::try_new(1024).unwrap()
+///                      .encode_all((0..1000).map(|i| i as f64 * 0.25)).unwrap();
+///     let keys = VectorReader::<u32>::try_new(&keys[..]).unwrap();
+///     let prices = VectorReader::<f64>::try_new(&prices[..]).unwrap();
+///
+///     // key == 3 AND NOT price < 100.0
+///     let mut filter = CombinedFilter::new();
+///     filter.add(Combine::And, keys.filter_iter(EqualsSink::<u32>::new(&3)));
+///     filter.add(Combine::AndNot, prices.filter_iter(RangeSink::<f64>::less_than(100.0)));
+///     assert_eq!(count_hits(filter), 60);
+/// ```
+/// NOT also sets the bits of the padding after the last element, so limit the number of sections or mask the
+/// last one where that matters.
+#[derive(Default)]
+pub struct CombinedFilter<'a> {
+    filters: Vec<(Combine, Box<dyn SectionMasks + 'a>)>,
+}
+
+impl<'a> CombinedFilter<'a> {
+    pub fn new() -> Self {
+        Self { filters: Vec::new() }
+    }
+
+    /// Adds a filter whose masks are combined with the running mask using op
+    pub fn add<F: SectionMasks + 'a>(&mut self, op: Combine, filter: F) {
+        self.filters.push((op, Box::new(filter)));
+    }
+}
+
+impl<'a> Iterator for CombinedFilter<'a> {
+    type Item = u32x8;
+
+    fn next(&mut self) -> Option<u32x8> {
+        let (first_op, first) = self.filters.first_mut()?;
+        let first_mask = first.next()?;
+        let mut mask = match first_op {
+            Combine::And | Combine::Or       => first_mask,
+            Combine::AndNot | Combine::OrNot => !first_mask,
+        };
+        for (op, filter) in self.filters[1..].iter_mut() {
+            let decided = match op {
+                Combine::And | Combine::AndNot => mask == NO_MATCHES,
+                Combine::Or | Combine::OrNot   => mask == ALL_MATCHES,
+            };
+            if decided {
+                filter.advance();
+                continue;
+            }
+            let next_mask = filter.next()?;
+            mask = match op {
+                Combine::And    => mask & next_mask,
+                Combine::Or     => mask | next_mask,
+                Combine::AndNot => mask & !next_mask,
+                Combine::OrNot  => mask | !next_mask,
+            };
+        }
+        Some(mask)
+    }
+}
+
+impl<'a> SectionMasks for CombinedFilter<'a> {
+    fn advance(&mut self) {
+        self.filters.iter_mut().for_each(|(_, f)| f.advance());
+    }
+}
+
 pub type EmptyFilter = std::iter::Empty<u32x8>;
 
 pub const EMPTY_FILTER: EmptyFilter = std::iter::empty::<u32x8>();
@@ -298,35 +907,58 @@ pub fn count_hits<I>(filter_iter: I) -> usize
     (filter_iter.map(|mask| mask.count_ones().wrapping_sum()).sum::<u32>()) as usize
 }
 
+// Calls f with the element position of every match, in order, visiting only the bits which are ON.
+// For a single section mask, pass std::iter::once(mask) to get positions within the section.
+#[inline]
+pub(crate) fn for_each_match<I, F>(filter_iter: I, mut f: F)
+where I: Iterator<Item = u32x8>,
+      F: FnMut(usize) {
+    for (sect_num, mask) in filter_iter.enumerate() {
+        for word in 0..8 {
+            let mut u32mask = mask.extract(word);
+            while u32mask != 0 {
+                f(sect_num * FIXED_LEN + word * 32 + u32mask.trailing_zeros() as usize);
+                u32mask &= u32mask - 1;
+            }
+        }
+    }
+}
+
 /// Creates a Vec of the element positions where matches occur
 pub fn match_positions<I>(filter_iter: I) -> Vec<usize>
 where I: Iterator<Item = u32x8> {
-    let mut pos = 0;
     let mut matches = Vec::<usize>::new();
-    filter_iter.for_each(|mask| {
-        for word in 0..8 {
-            let u32mask = mask.extract(word);
-            if u32mask != 0 {
-                // TODO: find highest bit (intrinsic) for O(on bits) speed
-                for bit in 0..32 {
-                    if (u32mask & (1 << bit)) != 0 {
-                        matches.push(pos);
-                    }
-                    pos += 1;
-                }
-            }
-        }
-    });
+    for_each_match(filter_iter, |pos| matches.push(pos));
     matches
 }
 
+/// Appends the row indexes of the matches to out, a selection vector for operators such as joins and gathers
+/// which consume row indexes rather than masks.  Reusing out across calls avoids reallocating it.
+/// ```
+/// # use compressed_vec::filter::*;
+/// # use compressed_vec::vector::{VectorReader, VectorU32Appender};
+///     let bytes = VectorU32Appender::try_new(1024).unwrap().encode_all((0..1000u32).map(|i| i % 300)).unwrap();
+///     let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+///     let mut rows = Vec::new();
+///     selection_vector(reader.filter_iter(EqualsSink::<u32>::new(&7)), &mut rows);
+///     assert_eq!(rows, vec![7, 307, 607, 907]);
+/// ```
+pub fn selection_vector<I>(filter_iter: I, out: &mut Vec<u32>)
+where I: Iterator<Item = u32x8> {
+    for_each_match(filter_iter, |pos| out.push(pos as u32));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::cell::Cell;
+
+    use packed_simd::u64x8;
     use smallvec::smallvec;
     use crate::filter::match_positions;
-    use crate::vector::{VectorU32Appender, VectorU64Appender, VectorReader};
+    use crate::minmax::per_section_minmax;
+    use crate::vector::{VectorF64XorAppender, VectorU32Appender, VectorU64Appender, VectorReader};
 
     #[test]
     fn test_filter_u64_equals() {
@@ -369,4 +1001,265 @@ mod tests {
         expected_pos.resize(67, 0);
         assert_eq!(matches, expected_pos);
     }
+
+    #[test]
+    fn test_filter_fn_closures() {
+        let vector_size: usize = 600;
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        (0..300).for_each(|i| appender.append(i).unwrap());
+        appender.append_nulls(300).unwrap();
+        let finished_vec = appender.finish(vector_size).unwrap();
+        let reader = VectorReader::<u32>::try_new(&finished_vec[..]).unwrap();
+
+        // Modulo predicate using SIMD lanes.  Zeroes match, so the null section matches entirely:
+        // 100 values divisible by 3, 212 zeroes at end of second section, 256 in the null section
+        let div3 = FilterFnSink::<u32, _>::new(|octet: u32x8| (octet % u32x8::splat(3)).eq_mask(u32x8::splat(0)));
+        assert_eq!(count_hits(reader.filter_iter(div3)), 100 + 212 + 256);
+
+        // Bit test over the whole section slice: bit 7 set.  Nulls never match
+        let bit7 = SectionFilterFnSink::<u32, _>::new(|values: &[u32]| {
+            let mut words = [0u32; 8];
+            for (i, v) in values.iter().enumerate() {
+                if v & 0x80 != 0 { words[i / 32] |= 1 << (i % 32); }
+            }
+            u32x8::from(words)
+        });
+        assert_eq!(count_hits(reader.filter_iter(bit7)), 128);
+    }
+
+    #[test]
+    fn test_filter_stats() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        (0..256).for_each(|i| appender.append(i % 4).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..256).for_each(|_| appender.append(7).unwrap());
+        let keys = appender.finish(768).unwrap();
+        let values = appender.encode_all((0..768).map(|i| i % 3)).unwrap();
+
+        let r1 = VectorReader::<u32>::try_new(&keys[..]).unwrap();
+        let r2 = VectorReader::<u32>::try_new(&values[..]).unwrap();
+        let mut filter = r1.filter_iter(EqualsSink::<u32>::new(&1));
+        assert!(filter.stats().is_none());
+        filter.enable_stats();
+        assert_eq!(count_hits(&mut filter), 64);
+        let stats = filter.stats().unwrap();
+        assert_eq!(stats.for_type(SectionType::NibblePackedMedium).decoded, 1);
+        assert_eq!(stats.for_type(SectionType::Null).mask_only, 1);
+        assert_eq!(stats.for_type(SectionType::Constant).decoded, 1);
+        assert_eq!(stats.iter().count(), 3);
+        assert_eq!(stats.total().decoded, 2);
+
+        // Sections of the second vector are skipped where the first vector has no matches
+        let mut multi = MultiVectorFilter::new(vec![r1.filter_iter(EqualsSink::<u32>::new(&1)),
+                                                    r2.filter_iter(EqualsSink::<u32>::new(&1))]);
+        multi.enable_stats();
+        assert_eq!(count_hits(&mut multi), 22);
+        let second = multi.vector_stats(1).unwrap();
+        assert_eq!(second.total().decoded, 1);
+        assert_eq!(second.total().skipped, 2);
+        let merged = multi.stats().unwrap();
+        assert_eq!(merged.total().decoded, 3);
+        assert_eq!(merged.total().skipped, 2);
+        assert_eq!(merged.total().mask_only, 1);
+
+        // Every section type has an entry
+        let types: Vec<SectionType> = (0..=255u8).filter_map(|n| SectionType::try_from(n).ok()).collect();
+        assert!(types.iter().all(|t| (t.as_num() as usize) < NUM_STATS_TYPES));
+        assert_eq!(FilterStats::default().for_type(*types.last().unwrap()).decoded, 0);
+    }
+
+    #[test]
+    fn test_range_filters() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        (0..300u32).for_each(|i| appender.append(i % 100 + 1).unwrap());
+        appender.append_nulls(212).unwrap();
+        let bytes = appender.finish(512).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+
+        assert_eq!(count_hits(reader.filter_iter(RangeSink::less_than(11))), 30 + 212);
+        assert_eq!(count_hits(reader.filter_iter(RangeSink::less_or_equal(0))), 212);
+        assert_eq!(count_hits(reader.filter_iter(RangeSink::greater_than(90))), 30);
+        assert_eq!(count_hits(reader.filter_iter(RangeSink::greater_or_equal(90))), 33);
+        assert_eq!(count_hits(reader.filter_iter(RangeSink::between(10, 19))), 30);
+        assert_eq!(count_hits(reader.filter_iter(RangeSink::new(&(Bound::Excluded(10), Bound::Excluded(20))))), 27);
+
+        // Masks combine with equality masks
+        let combined = reader.filter_iter(RangeSink::between(10, 19))
+                             .zip(reader.filter_iter(OneOfSink::new(&smallvec![15, 50])))
+                             .map(|(range, one_of)| range | one_of);
+        assert_eq!(count_hits(combined), 33);
+
+        let floats = VectorF64XorAppender::try_new(1024).unwrap()
+                         .encode_all((0..256).map(|i| i as f64 * 0.5 - 10.0)).unwrap();
+        let reader = VectorReader::<f64>::try_new(&floats[..]).unwrap();
+        assert_eq!(count_hits(reader.filter_iter(RangeSink::between(-1.0, 1.0))), 5);
+
+        // Zone maps rule out sections using the bounds
+        let zone_map = [(-10.0, 117.5)];
+        let mut filter = reader.filter_iter(RangeSink::greater_than(117.5));
+        filter.set_zone_map(&zone_map[..]);
+        filter.enable_stats();
+        assert_eq!(count_hits(&mut filter), 0);
+        assert_eq!(filter.stats().unwrap().total().pruned, 1);
+    }
+
+    #[test]
+    fn test_combined_filter() {
+        // Keys 0..9 repeating, then a null section, and values counting up
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        (0..512u32).for_each(|i| appender.append(i % 10).unwrap());
+        appender.append_nulls(256).unwrap();
+        let keys = appender.finish(768).unwrap();
+        let values = VectorU64Appender::try_new(1024).unwrap().encode_all(0..768u64).unwrap();
+        let keys = VectorReader::<u32>::try_new(&keys[..]).unwrap();
+        let values = VectorReader::<u64>::try_new(&values[..]).unwrap();
+
+        // key == 1 OR value >= 700
+        let mut filter = CombinedFilter::new();
+        filter.add(Combine::And, keys.filter_iter(EqualsSink::<u32>::new(&1)));
+        filter.add(Combine::Or, values.filter_iter(RangeSink::<u64>::greater_or_equal(700)));
+        assert_eq!(count_hits(filter), 52 + 68);
+
+        // NOT key < 5 AND NOT value < 100
+        let mut filter = CombinedFilter::new();
+        filter.add(Combine::AndNot, keys.filter_iter(RangeSink::<u32>::less_than(5)));
+        filter.add(Combine::AndNot, values.filter_iter(RangeSink::<u64>::less_than(100)));
+        assert_eq!(count_hits(filter), 205);
+
+        // The values filter is never evaluated for the null section, where no key is 1
+        let evaluated = Cell::new(0);
+        let counting = FilterFnSink::<u64, _>::new(|octet: u64x8| {
+            evaluated.set(evaluated.get() + 1);
+            octet.eq_mask(octet)
+        });
+        let mut filter = CombinedFilter::new();
+        filter.add(Combine::And, keys.filter_iter(EqualsSink::<u32>::new(&1)));
+        filter.add(Combine::And, values.filter_iter(counting));
+        assert_eq!(count_hits(filter), 52);
+        assert_eq!(evaluated.get(), 1 + 2 * 32);
+    }
+
+    #[test]
+    fn test_count_matches() {
+        let bytes = VectorU32Appender::try_new(1024).unwrap().encode_all((0..2560u32).map(|i| i % 4)).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.filter_iter(EqualsSink::<u32>::new(&3)).count_matches(), 640);
+
+        // Stops after the section reaching the limit
+        let mut filter = reader.filter_iter(EqualsSink::<u32>::new(&3));
+        filter.enable_stats();
+        assert_eq!(filter.count_matches_up_to(100), 128);
+        assert_eq!(filter.stats().unwrap().total().decoded, 2);
+        assert_eq!(filter.count_matches_up_to(10_000), 512);
+        assert_eq!(filter.count_matches_up_to(0), 0);
+    }
+
+    #[test]
+    fn test_match_positions_skip_empty_words() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        (0..700u32).for_each(|i| appender.append(if i % 100 == 99 { 1 } else { 2 }).unwrap());
+        let bytes = appender.finish(700).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        let expected = vec![99, 199, 299, 399, 499, 599, 699];
+        assert_eq!(match_positions(reader.filter_iter(EqualsSink::<u32>::new(&1))), expected);
+
+        let mut rows = vec![5];
+        selection_vector(reader.filter_iter(EqualsSink::<u32>::new(&1)), &mut rows);
+        assert_eq!(rows[1..], expected.iter().map(|&i| i as u32).collect::<Vec<_>>()[..]);
+        assert_eq!(rows[0], 5);
+    }
+
+    #[test]
+    fn test_sql_nulls() {
+        // 100 zeroes, 156 tracked nulls, then values
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.track_nulls(true);
+        (0..100u32).for_each(|_| appender.append(0).unwrap());
+        appender.append_nulls(156).unwrap();
+        (0..256u32).for_each(|i| appender.append(i % 2).unwrap());
+        let tracked = appender.finish(512).unwrap();
+        let reader = VectorReader::<u32>::try_new(&tracked[..]).unwrap();
+
+        assert_eq!(count_hits(reader.filter_iter(EqualsSink::<u32>::new(&0))), 100 + 156 + 128);
+        let mut filter = reader.filter_iter(EqualsSink::<u32>::new(&0));
+        filter.sql_nulls(true);
+        assert_eq!(count_hits(filter), 100 + 128);
+        assert_eq!(count_hits(reader.is_null()), 156);
+        assert_eq!(count_hits(reader.is_not_null()), 356);
+
+        // Null sections are zeroes, not nulls
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.append_nulls(256).unwrap();
+        (0..256u32).for_each(|i| appender.append(i).unwrap());
+        let untracked = appender.finish(512).unwrap();
+        let reader = VectorReader::<u32>::try_new(&untracked[..]).unwrap();
+        let mut filter = reader.filter_iter(RangeSink::<u32>::less_than(10));
+        filter.sql_nulls(true);
+        assert_eq!(count_hits(filter), 256 + 10);
+        assert_eq!(count_hits(reader.is_null()), 0);
+        assert_eq!(count_hits(reader.is_not_null()), 512);
+
+        // Including Null sections written for zero values with null tracking on
+        appender.track_nulls(true);
+        let zeroes = appender.encode_all(vec![0u32; 256]).unwrap();
+        let reader = VectorReader::<u32>::try_new(&zeroes[..]).unwrap();
+        assert_eq!(reader.num_null_sections().unwrap(), 1);
+        let mut filter = reader.filter_iter(EqualsSink::<u32>::new(&0));
+        filter.sql_nulls(true);
+        assert_eq!(count_hits(filter), 256);
+    }
+
+    #[test]
+    fn test_zone_map_prunes_sections() {
+        // Sorted timestamps, so each value is in one section only
+        let bytes = VectorU64Appender::try_new(4096).unwrap().encode_all((0..2000u64).map(|i| 5000 + i * 10)).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        let zone_map = per_section_minmax::<u64>(&bytes[..]).unwrap();
+
+        let mut filter = reader.filter_iter(EqualsSink::<u64>::new(&13_000));
+        filter.set_zone_map(&zone_map[..]);
+        filter.enable_stats();
+        assert_eq!(match_positions(&mut filter), vec![800]);
+        assert_eq!(filter.stats().unwrap().total().pruned, 7);
+        assert_eq!(filter.stats().unwrap().total().decoded, 1);
+
+        let mut filter = reader.filter_iter(OneOfSink::<u64>::new(&smallvec![5000, 24_990, 99]));
+        filter.set_zone_map(&zone_map[..]);
+        filter.enable_stats();
+        assert_eq!(count_hits(&mut filter), 2);
+        assert_eq!(filter.stats().unwrap().total().pruned, 6);
+
+        // Zero only matches the padding of the last section without a zone map
+        let mut filter = reader.filter_iter(EqualsSink::<u64>::new(&0));
+        assert_eq!(count_hits(&mut filter), 48);
+        let mut filter = reader.filter_iter(EqualsSink::<u64>::new(&0));
+        filter.set_zone_map(&zone_map[..]);
+        assert_eq!(count_hits(&mut filter), 0);
+    }
+
+    #[test]
+    fn test_zone_map_keeps_tracked_nulls() {
+        // The bounds of the PartialNull section skip its nulls, which still match zero without SQL nulls
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.track_nulls(true);
+        appender.append_options((0..512u64).map(|i| if i % 3 == 0 { None } else { Some(100 + i) })).unwrap();
+        let bytes = appender.finish(512).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        let zone_map = per_section_minmax::<u64>(&bytes[..]).unwrap();
+        assert_eq!(zone_map[0], (101, 354));
+
+        for &sql_nulls in &[false, true] {
+            let mut filter = reader.filter_iter(EqualsSink::<u64>::new(&0));
+            filter.sql_nulls(sql_nulls);
+            let expected = count_hits(&mut filter);
+            assert_eq!(expected, if sql_nulls { 0 } else { 171 });
+
+            let mut filter = reader.filter_iter(EqualsSink::<u64>::new(&0));
+            filter.sql_nulls(sql_nulls);
+            filter.set_zone_map(&zone_map[..]);
+            filter.enable_stats();
+            assert_eq!(count_hits(&mut filter), expected);
+            assert_eq!(filter.stats().unwrap().total().pruned, if sql_nulls { 2 } else { 0 });
+        }
+    }
 }
@@ -0,0 +1,474 @@
+/// The `fixedbin` module supports vectors of 16-byte fixed size binary values, such as UUIDs or trace IDs.
+/// The vectors use the same 16-byte header and 256-element sections as the integer vectors.
+///
+/// Each section is stored as 16 "byte planes": plane 0 contains byte 0 of all 256 values, plane 1 byte 1, etc.
+/// Each plane is then encoded as a regular u32 FixedSection using the `AutoEncoder`, so that planes with
+/// little variation (eg UUID version bits, or trace IDs with common prefixes) compress to Constant or
+/// small NibblePacked sections.
+///
+/// Equality filtering works one plane at a time, ANDing the masks together and stopping as soon as no
+/// elements in the section can match.
+/// ```
+/// # use compressed_vec::fixedbin::*;
+/// # use compressed_vec::filter::count_hits;
+///     let mut appender = FixedBin16Appender::try_new(4096).unwrap();
+///     let ids: Vec<Bin16> = (0..300u32).map(|i| { let mut id = [7u8; 16]; id[15] = (i % 10) as u8; id }).collect();
+///     let bytes = appender.encode_all(ids.iter()).unwrap();
+///
+///     let reader = FixedBin16Reader::try_new(&bytes[..]).unwrap();
+///     assert_eq!(reader.iterate().nth(13), Some(ids[13]));
+///     assert_eq!(count_hits(reader.filter_equals(&ids[3])), 30);
+/// ```
+use packed_simd::u32x8;
+use scroll::{Pread, Pwrite, LE};
+
+use crate::error::CodingError;
+use crate::filter::{EqualsSink, SectFilterSink};
+use crate::section::*;
+use crate::sink::{Sink, U32_256Sink};
+use crate::vector::{BinaryVector, FixedSectStats, VectorSubType, VectorType,
+                    BINARYVECT_HEADER_SIZE, NUM_HEADER_BYTES_TOTAL, format_version, wrong_subtype_error};
+
+
+/// A single 16-byte fixed binary value
+pub type Bin16 = [u8; 16];
+
+pub const BIN16_WIDTH: usize = 16;
+
+const ALL_MATCHES: u32x8 = u32x8::splat(0xffff_ffff);
+const NO_MATCHES: u32x8 = u32x8::splat(0);
+
+/// A section of 256 Bin16 values, stored as 16 byte planes.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::BytePlanes16
+///  +1   2-byte LE size of plane bytes to follow
+///  +3   16 u32 FixedSections, one for each byte plane, back to back
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct BytePlanes16Sect<'buf> {
+    sect_bytes: &'buf [u8],
+    encoded_bytes: u16,
+}
+
+impl<'buf> BytePlanes16Sect<'buf> {
+    /// Tries to create a BytePlanes16Sect from a byte slice starting from the section type byte.
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let sect_type: u8 = sect_bytes.pread_with(0, LE)?;
+        if sect_type != SectionType::BytePlanes16.as_num() {
+            return Err(CodingError::InvalidSectionType(sect_type));
+        }
+        let encoded_bytes = sect_bytes.pread_with(1, LE)
+                                .and_then(|n| {
+                                    if (n as usize + 3) <= sect_bytes.len() { Ok(n) }
+                                    else { Err(scroll::Error::Custom("Slice not large enough".to_string())) }
+                                })?;
+        Ok(Self { sect_bytes, encoded_bytes })
+    }
+
+    pub fn num_bytes(&self) -> usize { self.encoded_bytes as usize + 3 }
+
+    /// Returns an iterator over the 16 byte planes, each of which is a u32 FixedSection
+    pub fn planes(&self) -> impl Iterator<Item = Result<FixedSectEnum<'buf, u32>, CodingError>> {
+        // NOTE: the decoders may read past the end of a section, so don't trim to num_bytes
+        FixedSectIterator::<u32>::new(&self.sect_bytes[3..]).take(BIN16_WIDTH)
+    }
+
+    /// Writes out a section of FIXED_LEN values starting at offset, returning the offset after the section.
+    pub fn write(out_buf: &mut [u8], offset: usize, values: &[Bin16]) -> Result<usize, CodingError> {
+        assert_eq!(values.len(), FIXED_LEN);
+        out_buf.pwrite_with(SectionType::BytePlanes16.as_num(), offset, LE)?;
+        let mut plane = [0u32; FIXED_LEN];
+        let mut off = offset + 3;
+        for b in 0..BIN16_WIDTH {
+            for (p, value) in plane.iter_mut().zip(values) {
+                *p = value[b] as u32;
+            }
+            off = AutoEncoder::gen_stats_and_write(out_buf, off, &plane[..])?;
+        }
+        let num_bytes = off - offset - 3;
+        if num_bytes <= 65535 {
+            out_buf.pwrite_with(num_bytes as u16, offset + 1, LE)?;
+            Ok(off)
+        } else {
+            Err(CodingError::NotEnoughSpace)
+        }
+    }
+
+    /// Decodes all FIXED_LEN values of this section into output
+    pub fn decode(&self, output: &mut [Bin16; FIXED_LEN]) -> Result<(), CodingError> {
+        let mut sink = U32_256Sink::new();
+        let mut num_planes = 0;
+        for (b, plane) in self.planes().enumerate() {
+            sink.reset();
+            plane?.decode(&mut sink)?;
+            for (value, &byte) in output.iter_mut().zip(sink.values.iter()) {
+                value[b] = byte as u8;
+            }
+            num_planes += 1;
+        }
+        if num_planes < BIN16_WIDTH {
+            return Err(CodingError::InvalidFormat(format!("Only {} byte planes in section", num_planes)));
+        }
+        Ok(())
+    }
+
+    /// Returns the mask of elements in this section equal to value.  Planes are compared one at a time,
+    /// and the remaining planes are skipped as soon as no elements can match.
+    pub fn equals_mask(&self, value: &Bin16) -> Result<u32x8, CodingError> {
+        let mut mask = ALL_MATCHES;
+        let mut planes = self.planes();
+        for &byte in value.iter() {
+            let plane = planes.next().ok_or(CodingError::InputTooShort)??;
+            let mut sink = EqualsSink::<u32>::new(&(byte as u32));
+            mask &= if plane.is_null() {
+                sink.null_mask()
+            } else {
+                plane.decode(&mut sink)?;
+                sink.get_mask()
+            };
+            if mask == NO_MATCHES { break; }
+        }
+        Ok(mask)
+    }
+}
+
+/// A section in a FixedBin16 vector: either a null section (256 all-zero values) or byte planes
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Bin16Sect<'buf> {
+    Null,
+    BytePlanes(BytePlanes16Sect<'buf>),
+}
+
+impl<'buf> Bin16Sect<'buf> {
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        if sect_bytes.is_empty() { return Err(CodingError::InputTooShort) }
+        if sect_bytes[0] == SectionType::Null.as_num() {
+            Ok(Bin16Sect::Null)
+        } else {
+            BytePlanes16Sect::try_from(sect_bytes).map(Bin16Sect::BytePlanes)
+        }
+    }
+
+    pub fn num_bytes(&self) -> usize {
+        match self {
+            Bin16Sect::Null => 1,
+            Bin16Sect::BytePlanes(s) => s.num_bytes(),
+        }
+    }
+
+    pub fn is_null(&self) -> bool { *self == Bin16Sect::Null }
+
+    pub fn decode(&self, output: &mut [Bin16; FIXED_LEN]) -> Result<(), CodingError> {
+        match self {
+            Bin16Sect::Null => {
+                output.iter_mut().for_each(|v| *v = [0u8; BIN16_WIDTH]);
+                Ok(())
+            }
+            Bin16Sect::BytePlanes(s) => s.decode(output),
+        }
+    }
+
+    pub fn equals_mask(&self, value: &Bin16) -> Result<u32x8, CodingError> {
+        match self {
+            Bin16Sect::Null => Ok(if value.iter().all(|&b| b == 0) { ALL_MATCHES } else { NO_MATCHES }),
+            Bin16Sect::BytePlanes(s) => s.equals_mask(value),
+        }
+    }
+}
+
+/// Iterates over the Bin16Sects in the bytes following the vector header
+pub struct Bin16SectIterator<'buf> {
+    encoded_bytes: &'buf [u8],
+}
+
+impl<'buf> Iterator for Bin16SectIterator<'buf> {
+    type Item = Result<Bin16Sect<'buf>, CodingError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.encoded_bytes.is_empty() {
+            None
+        } else {
+            let res = Bin16Sect::try_from(self.encoded_bytes);
+            if let Ok(sect) = &res {
+                self.encoded_bytes = &self.encoded_bytes[sect.num_bytes()..];
+            }
+            Some(res)
+        }
+    }
+}
+
+const GROW_BYTES: usize = 4096;
+
+/// An appender for FixedBin16 vectors.  Works just like `VectorAppender`: values are buffered and written
+/// 256 at a time, nulls are all-zero values, and `finish()` clones out the vector and resets the appender.
+pub struct FixedBin16Appender {
+    vect_buf: Vec<u8>,
+    offset: usize,
+    header: BinaryVector,
+    write_buf: Vec<Bin16>,
+    stats: FixedSectStats,
+}
+
+impl FixedBin16Appender {
+    pub fn try_new(initial_capacity: usize) -> Result<Self, CodingError> {
+        let mut new_self = Self {
+            vect_buf: vec![0; initial_capacity],
+            offset: NUM_HEADER_BYTES_TOTAL,
+            header: BinaryVector::new(VectorType::FixedSection256, VectorSubType::FixedBin16),
+            write_buf: Vec::with_capacity(FIXED_LEN),
+            stats: FixedSectStats::new(),
+        };
+        new_self.header.write_header(new_self.vect_buf.as_mut_slice())?;
+        Ok(new_self)
+    }
+
+    /// Appends all values from a collection and finishes the vector, returning the encoded bytes.
+    pub fn encode_all<'a, C>(&mut self, collection: C) -> Result<Vec<u8>, CodingError>
+    where C: IntoIterator<Item = &'a Bin16> {
+        let mut count = 0;
+        for x in collection.into_iter() {
+            count += 1;
+            self.append(x)?;
+        }
+        self.finish(count)
+    }
+
+    /// Total number of elements including encoded sections and write buffer
+    pub fn num_elements(&self) -> usize {
+        self.stats.num_elements as usize + self.write_buf.len()
+    }
+
+    /// Resets the internal state for appending a new vector.
+    pub fn reset(&mut self) -> Result<(), CodingError> {
+        self.header.reset();
+        self.offset = NUM_HEADER_BYTES_TOTAL;
+        self.write_buf.clear();
+        self.vect_buf.resize(self.vect_buf.capacity(), 0);
+        self.stats.reset();
+        self.stats.update_num_elems(&mut self.vect_buf, 0)?;
+        self.header.write_header(self.vect_buf.as_mut_slice())
+    }
+
+    fn write_section<F>(&mut self, func: F) -> Result<(), CodingError>
+    where F: Fn(&mut [u8], usize, &[Bin16]) -> Result<usize, CodingError> {
+        self.offset = match func(self.vect_buf.as_mut_slice(), self.offset, &self.write_buf[..]) {
+            Err(CodingError::NotEnoughSpace) | Err(CodingError::BadOffset(_)) => {
+                self.vect_buf.reserve(GROW_BYTES);
+                self.vect_buf.resize(self.vect_buf.capacity(), 0);
+                func(self.vect_buf.as_mut_slice(), self.offset, &self.write_buf[..])?
+            }
+            res => res?,
+        };
+        self.write_buf.clear();
+        self.stats.update_num_elems(&mut self.vect_buf, self.stats.num_elements + FIXED_LEN as u32)?;
+        self.header.update_num_bytes(self.vect_buf.as_mut_slice(),
+                                     (self.offset - NUM_HEADER_BYTES_TOTAL) as u32)
+    }
+
+    /// Appends a single value.  When a section fills up, it is encoded into the vector.
+    pub fn append(&mut self, value: &Bin16) -> Result<(), CodingError> {
+        self.write_buf.push(*value);
+        if self.write_buf.len() >= FIXED_LEN {
+            self.write_section(BytePlanes16Sect::write)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Appends a number of nulls (all-zero values).  Whole sections of nulls are written as null sections.
+    pub fn append_nulls(&mut self, num_nulls: usize) -> Result<(), CodingError> {
+        let mut left = num_nulls;
+        while left > 0 {
+            if self.write_buf.is_empty() && left >= FIXED_LEN {
+                self.write_section(|buf, off, _| NullFixedSect::write(buf, off))?;
+                self.stats.num_null_sections += 1;
+                left -= FIXED_LEN;
+            } else {
+                let num_to_fill = left.min(FIXED_LEN - self.write_buf.len());
+                self.write_buf.resize(self.write_buf.len() + num_to_fill, [0u8; BIN16_WIDTH]);
+                left -= num_to_fill;
+                if self.write_buf.len() >= FIXED_LEN { self.write_section(BytePlanes16Sect::write)?; }
+            }
+        }
+        Ok(())
+    }
+
+    /// Wraps up the vector, filling the last section with nulls, and clones out the vector bytes.
+    /// See `VectorAppender::finish()`.
+    pub fn finish(&mut self, total_num_rows: usize) -> Result<Vec<u8>, CodingError> {
+        let total_so_far = self.num_elements();
+        if total_so_far > total_num_rows { return Err(CodingError::InvalidNumRows(total_num_rows, total_so_far)); }
+        if total_num_rows > u32::max_value() as usize {
+            return Err(CodingError::InvalidNumRows(total_num_rows, u32::max_value() as usize));
+        }
+
+        if !self.write_buf.is_empty() {
+            self.append_nulls(FIXED_LEN - self.write_buf.len())?;
+        }
+        while (self.stats.num_elements as usize) < total_num_rows {
+            self.append_nulls(FIXED_LEN)?;
+        }
+
+        self.stats.update_num_elems(self.vect_buf.as_mut_slice(), total_num_rows as u32)?;
+        self.vect_buf.resize(self.offset, 0);
+        let mut returned_vec = Vec::with_capacity(self.offset);
+        returned_vec.append(&mut self.vect_buf);
+        self.reset()?;
+        Ok(returned_vec)
+    }
+}
+
+/// A reader for FixedBin16 vectors written by `FixedBin16Appender`
+pub struct FixedBin16Reader<'buf> {
+    vect_bytes: &'buf [u8],
+}
+
+impl<'buf> FixedBin16Reader<'buf> {
+    pub fn try_new(vect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let bytes_from_header: u32 = vect_bytes.pread_with(0, LE)?;
+        let subtype: u8 = vect_bytes.pread_with(offset_of!(BinaryVector, minor_type), LE)?;
+        format_version(vect_bytes)?;
+        let total_bytes = (bytes_from_header as usize).checked_add(4).ok_or(CodingError::InputTooShort)?;
+        if vect_bytes.len() < total_bytes || total_bytes < NUM_HEADER_BYTES_TOTAL {
+            Err(CodingError::InputTooShort)
+        } else if subtype != VectorSubType::FixedBin16 as u8 {
+            Err(wrong_subtype_error(subtype, VectorSubType::FixedBin16))
+        } else {
+            Ok(Self { vect_bytes })
+        }
+    }
+
+    pub fn num_elements(&self) -> usize {
+        self.get_stats().num_elements as usize
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.vect_bytes.len()
+    }
+
+    /// Returns a FixedSectStats extracted from the vector header.
+    pub fn get_stats(&self) -> FixedSectStats {
+        self.vect_bytes.pread_with(BINARYVECT_HEADER_SIZE, LE).unwrap()
+    }
+
+    /// Returns an iterator over each section in this vector
+    pub fn sect_iter(&self) -> Bin16SectIterator<'buf> {
+        Bin16SectIterator { encoded_bytes: &self.vect_bytes[NUM_HEADER_BYTES_TOTAL..] }
+    }
+
+    /// Returns an iterator over all values in this vector.  Decodes one section at a time.
+    pub fn iterate(&self) -> Bin16ItemIter<'buf> {
+        Bin16ItemIter { sect_iter: self.sect_iter(),
+                        values: Box::new([[0u8; BIN16_WIDTH]; FIXED_LEN]),
+                        i: 0,
+                        num_elems: self.num_elements() }
+    }
+
+    /// Returns an iterator of u32x8 masks, one per section, for elements equal to value.
+    /// Can be used with `filter::count_hits` etc.
+    pub fn filter_equals<'a>(&self, value: &'a Bin16) -> impl Iterator<Item = u32x8> + 'a
+    where 'buf: 'a {
+        self.sect_iter().map(move |sect| {
+            sect.and_then(|s| s.equals_mask(value)).expect("This should not fail!")
+        })
+    }
+}
+
+/// Iterator over the values of a FixedBin16 vector
+pub struct Bin16ItemIter<'buf> {
+    sect_iter: Bin16SectIterator<'buf>,
+    values: Box<[Bin16; FIXED_LEN]>,
+    i: usize,
+    num_elems: usize,
+}
+
+impl<'buf> Iterator for Bin16ItemIter<'buf> {
+    type Item = Bin16;
+    fn next(&mut self) -> Option<Bin16> {
+        if self.i >= self.num_elems { return None }
+        if self.i % FIXED_LEN == 0 {
+            let sect = self.sect_iter.next()?.expect("Unexpected end of section");
+            sect.decode(&mut self.values).expect("Could not decode section");
+        }
+        let value = self.values[self.i % FIXED_LEN];
+        self.i += 1;
+        Some(value)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::count_hits;
+
+    // Version-4-like UUIDs: a fixed prefix, a version nibble, and varying bytes at the end
+    fn uuid(i: u32) -> Bin16 {
+        let mut id = [0u8; BIN16_WIDTH];
+        id[..4].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        id[6] = 0x40;
+        id[12..].copy_from_slice(&i.wrapping_mul(2_654_435_761).to_le_bytes());
+        id
+    }
+
+    #[test]
+    fn test_section_write_decode_and_filter() {
+        let values: Vec<Bin16> = (0..256).map(uuid).collect();
+        let mut buf = [0u8; 8192];
+        let off = BytePlanes16Sect::write(&mut buf, 0, &values[..]).unwrap();
+        // Twelve planes are null or constant, and four are random: much smaller than 4096 raw bytes
+        assert!(off < 1500);
+
+        let sect = BytePlanes16Sect::try_from(&buf[..]).unwrap();
+        assert_eq!(sect.num_bytes(), off);
+        assert_eq!(sect.planes().count(), BIN16_WIDTH);
+
+        let mut output = [[0u8; BIN16_WIDTH]; FIXED_LEN];
+        sect.decode(&mut output).unwrap();
+        assert_eq!(output[..], values[..]);
+
+        let mask = sect.equals_mask(&values[77]).unwrap();
+        assert_eq!(count_hits(std::iter::once(mask)), 1);
+        assert_eq!(mask.extract(2), 1 << (77 - 64));
+        assert_eq!(sect.equals_mask(&[1u8; BIN16_WIDTH]).unwrap(), NO_MATCHES);
+    }
+
+    #[test]
+    fn test_vector_append_iterate_filter() {
+        let mut appender = FixedBin16Appender::try_new(1024).unwrap();
+        (0..300).for_each(|i| appender.append(&uuid(i % 100)).unwrap());
+        appender.append_nulls(400).unwrap();
+        (0..50).for_each(|i| appender.append(&uuid(i)).unwrap());
+        let bytes = appender.finish(750).unwrap();
+
+        let reader = FixedBin16Reader::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.num_elements(), 750);
+        assert_eq!(reader.sect_iter().count(), 3);
+        assert_eq!(reader.sect_iter().filter(|s| s.as_ref().unwrap().is_null()).count(), 0);
+
+        let values: Vec<Bin16> = reader.iterate().collect();
+        assert_eq!(values.len(), 750);
+        assert_eq!(values[299], uuid(99));
+        assert_eq!(values[300], [0u8; BIN16_WIDTH]);
+        assert_eq!(values[749], uuid(49));
+
+        assert_eq!(count_hits(reader.filter_equals(&uuid(42))), 4);
+        assert_eq!(count_hits(reader.filter_equals(&[0u8; BIN16_WIDTH])), 400 + 18);
+
+        // Whole null sections
+        let mut appender = FixedBin16Appender::try_new(1024).unwrap();
+        appender.append_nulls(600).unwrap();
+        let bytes = appender.finish(600).unwrap();
+        let reader = FixedBin16Reader::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.get_stats().num_elements, 600);
+        assert_eq!(reader.sect_iter().filter(|s| s.as_ref().unwrap().is_null()).count(), 2);
+        assert_eq!(count_hits(reader.filter_equals(&[0u8; BIN16_WIDTH])), 768);
+    }
+
+    #[test]
+    fn test_wrong_vector_type() {
+        let mut appender = crate::vector::VectorU32Appender::try_new(1024).unwrap();
+        let bytes = appender.encode_all(vec![1, 2, 3]).unwrap();
+        let res = FixedBin16Reader::try_new(&bytes[..]);
+        assert_eq!(res.err().unwrap(), CodingError::WrongElementType { expected: VectorSubType::FixedBin16,
+                                                                       found: VectorSubType::FixedU32 });
+    }
+}
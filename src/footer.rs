@@ -0,0 +1,168 @@
+/// The `footer` module defines the optional footer of a vector, which holds metadata too large for the 16-byte
+/// header, such as Bloom filters.  A vector has a footer when the `FLAG_FOOTER` bit of header byte 7 is set.
+/// The footer follows the last section, and is a list of tagged entries followed by the footer length, so that
+/// readers can find where the sections end starting from the end of the vector:
+///
+/// | offset | description |
+/// | ------ | ----------- |
+/// | +0     | u8: entry tag, see `FooterTag` |
+/// | +1     | u32: number of entry bytes following |
+/// | +5     | entry bytes, then more entries |
+/// | end-4  | u32: total number of bytes in the footer, including this length |
+///
+/// Readers skip entries with tags they don't know about.
+use std::convert::TryFrom;
+
+use num_enum::TryFromPrimitive;
+use scroll::{Pread, LE};
+
+use crate::error::CodingError;
+use crate::vector::NUM_HEADER_BYTES_TOTAL;
+
+
+/// Offset of the header flags byte
+pub(crate) const FLAGS_OFFSET: usize = 7;
+/// Header flag set when the vector has a footer
+pub(crate) const FLAG_FOOTER: u8 = 0x01;
+/// Header flag set when the values of each section are XORed with the previous value, see the half module
+pub(crate) const FLAG_XOR_DELTA: u8 = 0x02;
+
+const ENTRY_HEADER_BYTES: usize = 5;
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[non_exhaustive]
+pub enum FooterTag {
+    BloomFilter = 1,    // See the bloom module
+    StrDictionary = 2,  // See the strdict module
+    SectionBloomFilters = 3,  // See the bloom module
+    StrDictFingerprint = 4,   // See the strdict module
+}
+
+/// Builds the bytes of a footer, one entry at a time
+#[derive(Debug, Default)]
+pub(crate) struct FooterWriter {
+    bytes: Vec<u8>,
+}
+
+impl FooterWriter {
+    pub fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    pub fn add(&mut self, tag: FooterTag, entry: &[u8]) {
+        self.add_raw(tag as u8, entry);
+    }
+
+    /// Adds an entry with any tag, eg one copied from another footer which this library does not know about
+    pub fn add_raw(&mut self, tag: u8, entry: &[u8]) {
+        self.bytes.push(tag);
+        self.bytes.extend_from_slice(&(entry.len() as u32).to_le_bytes());
+        self.bytes.extend_from_slice(entry);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns the footer bytes, including the trailing footer length
+    pub fn finish(mut self) -> Vec<u8> {
+        let total_len = (self.bytes.len() + 4) as u32;
+        self.bytes.extend_from_slice(&total_len.to_le_bytes());
+        self.bytes
+    }
+}
+
+/// The entries of a vector footer
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Footer<'buf> {
+    entries: &'buf [u8],
+}
+
+impl<'buf> Footer<'buf> {
+    /// Splits the bytes of a vector, trimmed to the length in its header, into the section bytes following the
+    /// header and the footer, if the header flags say there is one.  The footer entries are validated.
+    pub(crate) fn split(vect_bytes: &'buf [u8]) -> Result<(&'buf [u8], Option<Footer<'buf>>), CodingError> {
+        let flags: u8 = vect_bytes.pread_with(FLAGS_OFFSET, LE)?;
+        if vect_bytes.len() < NUM_HEADER_BYTES_TOTAL { return Err(CodingError::InputTooShort) }
+        if flags & FLAG_FOOTER == 0 {
+            return Ok((&vect_bytes[NUM_HEADER_BYTES_TOTAL..], None));
+        }
+        let footer_len = vect_bytes.len().checked_sub(4)
+                                   .and_then(|off| vect_bytes.pread_with::<u32>(off, LE).ok())
+                                   .ok_or(CodingError::InputTooShort)? as usize;
+        if footer_len < 4 || footer_len > vect_bytes.len() - NUM_HEADER_BYTES_TOTAL {
+            return Err(CodingError::InvalidFormat(format!("Invalid footer length {}", footer_len)));
+        }
+        let sect_end = vect_bytes.len() - footer_len;
+        let footer = Footer { entries: &vect_bytes[sect_end..vect_bytes.len() - 4] };
+        footer.validate()?;
+        Ok((&vect_bytes[NUM_HEADER_BYTES_TOTAL..sect_end], Some(footer)))
+    }
+
+    fn validate(&self) -> Result<(), CodingError> {
+        let mut rest = self.entries;
+        while !rest.is_empty() {
+            let len: u32 = rest.pread_with(1, LE)?;
+            rest = rest.get(ENTRY_HEADER_BYTES + len as usize..).ok_or(CodingError::InputTooShort)?;
+        }
+        Ok(())
+    }
+
+    /// Iterates over the (tag, entry bytes) of every entry, including the ones with unknown tags
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &'buf [u8])> {
+        let mut rest = self.entries;
+        std::iter::from_fn(move || {
+            if rest.is_empty() { return None }
+            // Lengths were checked by validate()
+            let len = rest.pread_with::<u32>(1, LE).ok()? as usize;
+            let entry = (rest[0], &rest[ENTRY_HEADER_BYTES..ENTRY_HEADER_BYTES + len]);
+            rest = &rest[ENTRY_HEADER_BYTES + len..];
+            Some(entry)
+        })
+    }
+
+    /// Returns the bytes of the first entry with the given tag
+    pub fn get(&self, tag: FooterTag) -> Option<&'buf [u8]> {
+        self.iter().find(|&(t, _)| FooterTag::try_from(t) == Ok(tag)).map(|(_, entry)| entry)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_footer_split_and_entries() {
+        let mut vect = vec![0u8; NUM_HEADER_BYTES_TOTAL];
+        vect.extend_from_slice(&[1, 2, 3]);    // Section bytes
+        vect.extend_from_slice(&[99, 1, 0, 0, 0, 42]);   // Entry with an unknown tag
+        let mut writer = FooterWriter::new();
+        writer.add(FooterTag::BloomFilter, &[9, 9]);
+        vect.extend_from_slice(&writer.finish());
+        let footer_len = vect.len() - NUM_HEADER_BYTES_TOTAL - 3;
+        let last = vect.len() - 4;
+        vect[last] = footer_len as u8;
+
+        // Without the flag, the footer bytes are sections
+        assert_eq!(Footer::split(&vect[..]).unwrap(), (&vect[NUM_HEADER_BYTES_TOTAL..], None));
+
+        vect[FLAGS_OFFSET] = FLAG_FOOTER;
+        let (sects, footer) = Footer::split(&vect[..]).unwrap();
+        assert_eq!(sects, &[1, 2, 3]);
+        let footer = footer.unwrap();
+        assert_eq!(footer.iter().map(|(tag, _)| tag).collect::<Vec<_>>(), vec![99, 1]);
+        assert_eq!(footer.get(FooterTag::BloomFilter), Some(&[9u8, 9][..]));
+
+        // Bad footer and entry lengths are errors
+        vect[last] = 200;
+        assert!(Footer::split(&vect[..]).is_err());
+        vect[last] = footer_len as u8;
+        vect[NUM_HEADER_BYTES_TOTAL + 4] = 50;
+        assert_eq!(Footer::split(&vect[..]).unwrap_err(), CodingError::InputTooShort);
+
+        // So are vectors shorter than their header
+        assert_eq!(Footer::split(&vect[..NUM_HEADER_BYTES_TOTAL - 1]).unwrap_err(), CodingError::InputTooShort);
+    }
+}
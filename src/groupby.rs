@@ -0,0 +1,172 @@
+/// The `groupby` module computes per-group aggregates, such as `SELECT k, SUM(x), MIN(x), MAX(x) GROUP BY k`,
+/// where the group keys are a u32 vector, usually of dictionary codes, with the same number of elements as the
+/// value vector.  Both vectors are walked section by section in one fused pass, without decoding either of them
+/// into a whole Vec:
+/// ```
+/// # use compressed_vec::groupby::group_aggregate;
+/// # use compressed_vec::vector::*;
+///     let keys = VectorU32Appender::try_new(1024).unwrap().encode_all((0..1000u32).map(|i| i % 3)).unwrap();
+///     let x = VectorU64Appender::try_new(1024).unwrap().encode_all((0..1000u64).map(|i| i * 2)).unwrap();
+///     let keys = VectorReader::<u32>::try_new(&keys[..]).unwrap();
+///     let x = VectorReader::<u64>::try_new(&x[..]).unwrap();
+///
+///     let groups = group_aggregate(&keys, &x).unwrap();
+///     assert_eq!(groups.len(), 3);
+///     assert_eq!(groups[&1].count, 333);
+///     assert_eq!((groups[&1].min, groups[&1].max), (Some(2), Some(1994)));
+/// ```
+/// Aggregates are `MaskedAggregate`s, with the same SQL null handling as the aggregate module: tracked null values
+/// are counted in `nulls` of their group but not aggregated, and the elements of Null sections are zeroes.  Null
+/// keys decode as key 0.  Null and constant value sections are aggregated from their header without decoding.
+use std::collections::HashMap;
+
+use num::NumCast;
+
+use crate::aggregate::MaskedAggregate;
+use crate::arith::{ArithBase, ArithOp};
+use crate::error::CodingError;
+use crate::section::*;
+use crate::sink::{Section256Sink, Sink};
+use crate::vector::{BaseSubtypeMapping, VectorReader};
+
+
+/// Computes the count, nulls, sum, min and max of the values of each distinct key, in one pass over the sections
+/// of both vectors.  The two vectors must have the same number of elements.
+pub fn group_aggregate<T>(keys: &VectorReader<u32>,
+                          values: &VectorReader<T>) -> Result<HashMap<u32, MaskedAggregate<T>>, CodingError>
+where T: ArithBase + NumCast + BaseSubtypeMapping {
+    if keys.num_elements() != values.num_elements() {
+        return Err(CodingError::InvalidFormat(
+            format!("Key vector has {} elements but value vector has {}", keys.num_elements(),
+                    values.num_elements())));
+    }
+
+    let mut groups = HashMap::<u32, MaskedAggregate<T>>::new();
+    let mut key_sink = Section256Sink::<u32>::new();
+    let mut value_sink = Section256Sink::<T>::new();
+    let mut elems_left = values.num_elements();
+    for (key_sect, value_sect) in keys.sect_iter().zip(values.sect_iter()) {
+        if elems_left == 0 { break }
+        let (key_sect, value_sect) = (key_sect?, value_sect?);
+        let sect_elems = elems_left.min(FIXED_LEN);
+        elems_left -= sect_elems;
+
+        // A constant key section is one group for the whole section
+        if let FixedSectEnum::ConstFixedSect(cs) = key_sect {
+            add_section(groups.entry(cs.get_value()).or_insert_with(MaskedAggregate::new),
+                        value_sect, sect_elems, &mut value_sink)?;
+            continue;
+        }
+        key_sink.reset();
+        key_sect.decode(&mut key_sink)?;
+        let keys = &key_sink.values[..sect_elems];
+
+        match value_sect {
+            FixedSectEnum::NullFixedSect(_) => for &k in keys {
+                groups.entry(k).or_insert_with(MaskedAggregate::new).add(T::zero());
+            },
+            FixedSectEnum::ConstFixedSect(cs) => for &k in keys {
+                groups.entry(k).or_insert_with(MaskedAggregate::new).add(cs.get_value());
+            },
+            _ => {
+                let valid = value_sect.validity_mask();
+                value_sink.reset();
+                value_sect.decode(&mut value_sink)?;
+                for (i, &k) in keys.iter().enumerate() {
+                    let agg = groups.entry(k).or_insert_with(MaskedAggregate::new);
+                    if valid.extract(i / 32) & (1 << (i % 32)) != 0 {
+                        agg.add(value_sink.values[i]);
+                    } else {
+                        agg.nulls += 1;
+                    }
+                }
+            }
+        }
+    }
+    Ok(groups)
+}
+
+// Aggregates the first sect_elems values of a section into a single group
+fn add_section<T>(agg: &mut MaskedAggregate<T>,
+                  sect: FixedSectEnum<T>,
+                  sect_elems: usize,
+                  sink: &mut Section256Sink<T>) -> Result<(), CodingError>
+where T: ArithBase + NumCast + BaseSubtypeMapping {
+    match sect {
+        FixedSectEnum::NullFixedSect(_) => add_repeated(agg, T::zero(), sect_elems)?,
+        FixedSectEnum::ConstFixedSect(cs) => add_repeated(agg, cs.get_value(), sect_elems)?,
+        _ => {
+            let valid = sect.validity_mask();
+            sink.reset();
+            sect.decode(sink)?;
+            for (i, &v) in sink.values[..sect_elems].iter().enumerate() {
+                if valid.extract(i / 32) & (1 << (i % 32)) != 0 {
+                    agg.add(v);
+                } else {
+                    agg.nulls += 1;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Aggregates a value repeated count times into a single group
+fn add_repeated<T>(agg: &mut MaskedAggregate<T>, value: T, count: usize) -> Result<(), CodingError>
+where T: ArithBase + NumCast {
+    let times: T = NumCast::from(count).ok_or(CodingError::Overflow)?;
+    agg.count += count;
+    agg.sum = T::apply(ArithOp::Add, agg.sum, T::apply(ArithOp::Mul, value, times));
+    agg.add_min_max(value);
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{VectorU32Appender, VectorU64Appender};
+
+    #[test]
+    fn test_group_aggregate_matches_scalar() {
+        // Constant, null, regular and partial value sections, and a constant key section
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..256u64).for_each(|_| appender.append(9).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..300u64).for_each(|i| appender.append(i * i).unwrap());
+        let x = appender.finish(812).unwrap();
+        let key_values: Vec<u32> = (0..812u32).map(|i| if i >= 512 && i < 768 { 7 } else { i % 4 }).collect();
+        let keys = VectorU32Appender::try_new(4096).unwrap().encode_all(key_values.clone()).unwrap();
+        let x = VectorReader::<u64>::try_new(&x[..]).unwrap();
+        let keys = VectorReader::<u32>::try_new(&keys[..]).unwrap();
+
+        let groups = group_aggregate(&keys, &x).unwrap();
+        let mut expected = HashMap::<u32, MaskedAggregate<u64>>::new();
+        for (&k, v) in key_values.iter().zip(x.iterate()) {
+            expected.entry(k).or_insert_with(MaskedAggregate::new).add(v);
+        }
+        assert_eq!(groups, expected);
+        assert_eq!(groups[&7].count, 256);
+        assert_eq!((groups[&0].count, groups[&0].nulls, groups[&0].min), (139, 0, Some(0)));
+
+        // Zero values in Null sections, with a constant key section, and tracked nulls
+        let zeroes = VectorU64Appender::try_new(1024).unwrap().encode_all(vec![0u64; 256]).unwrap();
+        let zeroes = VectorReader::<u64>::try_new(&zeroes[..]).unwrap();
+        let one_key = VectorU32Appender::try_new(1024).unwrap().encode_all(vec![3u32; 256]).unwrap();
+        let one_key = VectorReader::<u32>::try_new(&one_key[..]).unwrap();
+        let groups = group_aggregate(&one_key, &zeroes).unwrap();
+        assert_eq!(groups[&3], MaskedAggregate { count: 256, nulls: 0, sum: 0, min: Some(0), max: Some(0) });
+
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.track_nulls(true);
+        appender.append_options((0..256u64).map(|i| if i < 100 { None } else { Some(0) })).unwrap();
+        let tracked = appender.finish(256).unwrap();
+        let tracked = VectorReader::<u64>::try_new(&tracked[..]).unwrap();
+        let groups = group_aggregate(&one_key, &tracked).unwrap();
+        assert_eq!((groups[&3].count, groups[&3].nulls), (156, 100));
+
+        let short = VectorU64Appender::try_new(1024).unwrap().encode_all(vec![1, 2, 3]).unwrap();
+        let short = VectorReader::<u64>::try_new(&short[..]).unwrap();
+        assert!(group_aggregate(&keys, &short).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+/// The `half` module stores vectors of 16-bit floats, IEEE 754 half precision (f16) or bfloat16, such as
+/// ML feature columns.  The 16-bit patterns are stored as the elements of a u32 vector, so they get all the
+/// regular section encodings, and are converted back to f32 lanes on decode by a `HalfToF32Sink`, so that
+/// inference code gets SIMD-friendly f32 batches:
+/// ```
+/// # use compressed_vec::half::*;
+/// # use compressed_vec::sink::VecSink;
+///     let mut appender = HalfAppender::try_new(1024, HalfType::BF16, true).unwrap();
+///     let bytes = appender.encode_all(vec![0.5, 0.75, -2.0, 1.0e10]).unwrap();
+///
+///     let reader = HalfReader::try_new(&bytes[..]).unwrap();
+///     let mut sink = VecSink::<f32>::new();
+///     reader.decode_to_sink(&mut sink).unwrap();
+///     assert_eq!(sink.vec[..4], [0.5, 0.75, -2.0, 9.999221e9]);
+/// ```
+/// Values are rounded to the nearest 16-bit float, ties to even.  With XOR-delta enabled, each value is stored
+/// XORed with the previous value of its section, which compresses slowly changing values much better since
+/// their sign, exponent and high mantissa bits cancel out.  The first value of each section is XORed with
+/// zero, so sections still decode independently.  Nulls are 0.0.
+use crate::error::CodingError;
+
+use packed_simd::{f32x8, u32x8, IntoBits};
+use scroll::{Pread, LE};
+
+use crate::footer::{FLAGS_OFFSET, FLAG_XOR_DELTA};
+use crate::section::FIXED_LEN;
+use crate::sink::{Sink, SinkInput, VecSink};
+use crate::vector::{wrong_subtype_error, BinaryVector, VectorReader, VectorSubType, VectorU32Appender};
+
+
+/// The 16-bit float formats
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HalfType {
+    F16,    // IEEE 754 binary16: 5 exponent bits, 10 mantissa bits
+    BF16,   // bfloat16: the upper half of an f32, 8 exponent bits, 7 mantissa bits
+}
+
+impl HalfType {
+    pub fn subtype(&self) -> VectorSubType {
+        match self {
+            HalfType::F16  => VectorSubType::FixedF16,
+            HalfType::BF16 => VectorSubType::FixedBF16,
+        }
+    }
+
+    /// Rounds an f32 to the nearest 16-bit float of this type, returning its bits
+    #[inline]
+    pub fn to_bits(&self, value: f32) -> u16 {
+        match self {
+            HalfType::F16  => f32_to_f16_bits(value),
+            HalfType::BF16 => f32_to_bf16_bits(value),
+        }
+    }
+
+    #[inline]
+    pub fn to_f32(&self, bits: u16) -> f32 {
+        match self {
+            HalfType::F16  => f16_bits_to_f32(bits),
+            HalfType::BF16 => bf16_bits_to_f32(bits),
+        }
+    }
+}
+
+/// Converts an f32 to IEEE half precision bits, rounding to nearest even.  Values too large become infinity.
+pub fn f32_to_f16_bits(value: f32) -> u16 {
+    let x = value.to_bits();
+    let sign = ((x >> 16) & 0x8000) as u16;
+    let exp = ((x >> 23) & 0xff) as i32;
+    let mant = x & 0x7f_ffff;
+    if exp == 0xff {
+        // Infinity, or NaN with a quiet bit so it stays a NaN
+        return sign | 0x7c00 | if mant != 0 { 0x200 } else { 0 };
+    }
+    let half_exp = exp - 127 + 15;
+    if half_exp >= 0x1f { return sign | 0x7c00 }
+    // Mantissa with the implicit bit, and how far to shift it to get the half mantissa
+    let (mant, shift) = if half_exp <= 0 {
+        if half_exp < -10 { return sign }
+        (mant | 0x80_0000, (14 - half_exp) as u32)
+    } else {
+        (mant, 13)
+    };
+    let mut half = mant >> shift;
+    let rem = mant & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    if half_exp > 0 { half |= (half_exp as u32) << 10 }
+    // Rounding up may carry into the exponent, which is still correct, up to infinity
+    if rem > halfway || (rem == halfway && half & 1 == 1) { half += 1 }
+    sign | half as u16
+}
+
+/// Converts IEEE half precision bits to an f32, exactly
+pub fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exp = ((bits >> 10) & 0x1f) as u32;
+    let mant = (bits & 0x3ff) as u32;
+    match exp {
+        0 => {
+            // Zero or subnormal: mant * 2^-24
+            let magnitude = mant as f32 / (1u32 << 24) as f32;
+            f32::from_bits(sign | magnitude.to_bits())
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mant << 13)),
+        _    => f32::from_bits(sign | ((exp + 127 - 15) << 23) | (mant << 13)),
+    }
+}
+
+/// Converts an f32 to bfloat16 bits, rounding to nearest even
+pub fn f32_to_bf16_bits(value: f32) -> u16 {
+    let x = value.to_bits();
+    if value.is_nan() {
+        // Keep a quiet NaN, rounding could turn it into infinity
+        return ((x >> 16) | 0x40) as u16;
+    }
+    ((x + 0x7fff + ((x >> 16) & 1)) >> 16) as u16
+}
+
+/// Converts bfloat16 bits to an f32, exactly
+#[inline]
+pub fn bf16_bits_to_f32(bits: u16) -> f32 {
+    f32::from_bits((bits as u32) << 16)
+}
+
+/// A Sink which converts the 16-bit float bit patterns decoded from the u32 sections of a half vector into
+/// f32 values, undoing the XOR-delta if the vector has it, and passes them 8 at a time to an inner Sink.
+#[derive(Debug)]
+pub struct HalfToF32Sink<'a, S: Sink<f32x8>> {
+    inner_sink: &'a mut S,
+    half_type: HalfType,
+    xor_delta: bool,
+    prev: u16,
+    pos: usize,    // Position within the current section
+}
+
+impl<'a, S: Sink<f32x8>> HalfToF32Sink<'a, S> {
+    pub fn new(half_type: HalfType, xor_delta: bool, inner_sink: &'a mut S) -> Self {
+        Self { inner_sink, half_type, xor_delta, prev: 0, pos: 0 }
+    }
+}
+
+impl<'a, S: Sink<f32x8>> Sink<u32x8> for HalfToF32Sink<'a, S> {
+    #[inline]
+    fn process(&mut self, data: u32x8) {
+        if !self.xor_delta && self.half_type == HalfType::BF16 {
+            // bfloat16 is the upper half of an f32
+            self.inner_sink.process((data << 16).into_bits());
+            return;
+        }
+        let mut bits = [0u32; 8];
+        data.write_to_slice(&mut bits);
+        let mut values = [0f32; 8];
+        for (value, &b) in values.iter_mut().zip(bits.iter()) {
+            let mut b = b as u16;
+            if self.xor_delta {
+                b ^= self.prev;
+                self.prev = b;
+            }
+            *value = self.half_type.to_f32(b);
+        }
+        self.pos += 8;
+        if self.pos >= FIXED_LEN {
+            self.pos = 0;
+            self.prev = 0;
+        }
+        self.inner_sink.process(f32x8::from_slice_unaligned(&values));
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.process(u32x8::ZERO);
+    }
+
+    fn reset(&mut self) {
+        self.prev = 0;
+        self.pos = 0;
+    }
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.inner_sink.is_done()
+    }
+}
+
+/// An appender for half vectors.  Values are rounded to the 16-bit float type, optionally XOR-delta encoded,
+/// and appended to a u32 vector; `finish()` clones out the vector and resets the appender.
+pub struct HalfAppender {
+    inner: VectorU32Appender,
+    half_type: HalfType,
+    xor_delta: bool,
+    prev: u16,
+}
+
+impl HalfAppender {
+    pub fn try_new(initial_capacity: usize, half_type: HalfType, xor_delta: bool) -> Result<Self, CodingError> {
+        Ok(Self { inner: VectorU32Appender::try_new(initial_capacity)?, half_type, xor_delta, prev: 0 })
+    }
+
+    /// Appends all values from a collection and finishes the vector, returning the encoded bytes.
+    pub fn encode_all<C>(&mut self, collection: C) -> Result<Vec<u8>, CodingError>
+    where C: IntoIterator<Item = f32> {
+        let mut count = 0;
+        for x in collection.into_iter() {
+            count += 1;
+            self.append(x)?;
+        }
+        self.finish(count)
+    }
+
+    pub fn num_elements(&self) -> usize {
+        self.inner.num_elements()
+    }
+
+    /// Appends an f32, rounded to the nearest 16-bit float
+    pub fn append(&mut self, value: f32) -> Result<(), CodingError> {
+        self.append_bits(self.half_type.to_bits(value))
+    }
+
+    /// Appends the bits of a 16-bit float of the appender type
+    pub fn append_bits(&mut self, bits: u16) -> Result<(), CodingError> {
+        if self.num_elements() % FIXED_LEN == 0 { self.prev = 0 }
+        let stored = if self.xor_delta { bits ^ self.prev } else { bits };
+        self.prev = bits;
+        self.inner.append(stored as u32)
+    }
+
+    /// Appends a number of nulls, which are 0.0
+    pub fn append_nulls(&mut self, num_nulls: usize) -> Result<(), CodingError> {
+        if num_nulls == 0 { return Ok(()) }
+        // After the first null, the previous value is zero, so the rest are stored as zeroes
+        self.append_bits(0)?;
+        self.inner.append_nulls(num_nulls - 1)
+    }
+
+    /// Finishes the vector with total_num_rows elements, padding with nulls, and resets the appender.
+    pub fn finish(&mut self, total_num_rows: usize) -> Result<Vec<u8>, CodingError> {
+        if total_num_rows > self.num_elements() {
+            self.append_nulls(total_num_rows - self.num_elements())?;
+        }
+        let mut vect_bytes = self.inner.finish(total_num_rows)?;
+        vect_bytes[offset_of!(BinaryVector, minor_type)] = self.half_type.subtype() as u8;
+        if self.xor_delta { vect_bytes[FLAGS_OFFSET] |= FLAG_XOR_DELTA }
+        self.prev = 0;
+        Ok(vect_bytes)
+    }
+
+    pub fn reset(&mut self) -> Result<(), CodingError> {
+        self.prev = 0;
+        self.inner.reset()
+    }
+}
+
+/// A reader for half vectors
+pub struct HalfReader<'buf> {
+    inner: VectorReader<'buf, u32>,
+    half_type: HalfType,
+    xor_delta: bool,
+}
+
+impl<'buf> HalfReader<'buf> {
+    pub fn try_new(vect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let subtype: u8 = vect_bytes.pread_with(offset_of!(BinaryVector, minor_type), LE)?;
+        let half_type = match subtype {
+            s if s == VectorSubType::FixedF16 as u8  => HalfType::F16,
+            s if s == VectorSubType::FixedBF16 as u8 => HalfType::BF16,
+            s => return Err(wrong_subtype_error(s, VectorSubType::FixedF16)),
+        };
+        let inner = VectorReader::try_new_with_subtype(vect_bytes, half_type.subtype())?;
+        let flags: u8 = vect_bytes.pread_with(FLAGS_OFFSET, LE)?;
+        Ok(Self { inner, half_type, xor_delta: flags & FLAG_XOR_DELTA != 0 })
+    }
+
+    pub fn half_type(&self) -> HalfType {
+        self.half_type
+    }
+
+    pub fn num_elements(&self) -> usize {
+        self.inner.num_elements()
+    }
+
+    /// Decodes the vector to f32 values, 8 at a time, into the sink.  Like any sink, it gets whole sections.
+    pub fn decode_to_sink<S: Sink<f32x8>>(&self, sink: &mut S) -> Result<(), CodingError> {
+        let mut half_sink = HalfToF32Sink::new(self.half_type, self.xor_delta, sink);
+        self.inner.decode_to_sink(&mut half_sink)
+    }
+
+    /// Decodes all elements to a Vec of f32
+    pub fn to_f32_vec(&self) -> Result<Vec<f32>, CodingError> {
+        let mut sink = VecSink::<f32>::new();
+        self.decode_to_sink(&mut sink)?;
+        sink.vec.truncate(self.num_elements());
+        Ok(sink.vec)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_half_conversions_and_roundtrip() {
+        assert_eq!(f32_to_f16_bits(1.0), 0x3c00);
+        assert_eq!(f32_to_f16_bits(-2.5), 0xc100);
+        assert_eq!(f32_to_f16_bits(65504.0), 0x7bff);
+        assert_eq!(f32_to_f16_bits(1.0e6), 0x7c00);
+        assert_eq!(f32_to_f16_bits(5.9604645e-8), 0x0001);   // Smallest subnormal
+        assert_eq!(f32_to_f16_bits(1.0 + 1.0 / 2048.0), 0x3c00);   // Tie rounds to even
+        assert!(f16_bits_to_f32(f32_to_f16_bits(f32::NAN)).is_nan());
+        assert_eq!(f32_to_bf16_bits(1.0), 0x3f80);
+        assert!(bf16_bits_to_f32(f32_to_bf16_bits(f32::NAN)).is_nan());
+        for bits in (0..=0xffffu32).filter(|b| b & 0x7c00 != 0x7c00) {
+            assert_eq!(f32_to_f16_bits(f16_bits_to_f32(bits as u16)), bits as u16);
+            assert_eq!(f32_to_bf16_bits(bf16_bits_to_f32(bits as u16)), bits as u16);
+        }
+
+        // Slowly changing values, nulls and a partial last section, with and without XOR-delta
+        let values: Vec<f32> = (0..600).map(|i| 20.0 + (i as f32 * 0.01).sin()).collect();
+        for &half_type in &[HalfType::F16, HalfType::BF16] {
+            let mut sizes = vec![];
+            for &xor_delta in &[false, true] {
+                let mut appender = HalfAppender::try_new(1024, half_type, xor_delta).unwrap();
+                values[..300].iter().for_each(|&v| appender.append(v).unwrap());
+                appender.append_nulls(300).unwrap();
+                values[..200].iter().for_each(|&v| appender.append(v).unwrap());
+                let bytes = appender.finish(810).unwrap();
+                sizes.push(bytes.len());
+
+                let reader = HalfReader::try_new(&bytes[..]).unwrap();
+                assert_eq!(reader.half_type(), half_type);
+                let decoded = reader.to_f32_vec().unwrap();
+                let expected: Vec<f32> = values[..300].iter().cloned().chain(vec![0.0; 300])
+                                                      .chain(values[..200].iter().cloned())
+                                                      .chain(vec![0.0; 10])
+                                                      .map(|v| half_type.to_f32(half_type.to_bits(v)))
+                                                      .collect();
+                assert_eq!(decoded, expected);
+            }
+            assert!(sizes[1] < sizes[0]);
+        }
+
+        let u32_bytes = VectorU32Appender::try_new(1024).unwrap().encode_all(vec![1, 2]).unwrap();
+        assert!(matches!(HalfReader::try_new(&u32_bytes[..]), Err(CodingError::WrongElementType { .. })));
+        let f16_bytes = HalfAppender::try_new(1024, HalfType::F16, false).unwrap().encode_all(vec![1.0]).unwrap();
+        assert!(matches!(VectorReader::<u32>::try_new(&f16_bytes[..]), Err(CodingError::WrongElementType { .. })));
+    }
+}
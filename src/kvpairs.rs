@@ -0,0 +1,490 @@
+/// The `kvpairs` module supports vectors whose rows are small sparse maps of u64 keys to u64 values, such as
+/// per-label counters.  The vectors use the same 16-byte header and 256-row sections as the other vectors.
+///
+/// Each section stores the number of pairs in each row, then the keys of all rows, then the values of all
+/// rows, each NibblePacked in ZeroRuns mode.  The keys of each row are sorted, and all but the first key of
+/// each row are stored as deltas from the previous key, so that rows with similar key sets compress well.
+/// Since keys come before values, key presence can be tested without decoding any values.
+/// ```
+/// # use compressed_vec::kvpairs::*;
+/// # use compressed_vec::filter::count_hits;
+///     let mut appender = KeyValueAppender::try_new(4096).unwrap();
+///     for i in 0..300u64 {
+///         appender.append(&[(7, i), (100 + i % 3, 1)]).unwrap();
+///     }
+///     let bytes = appender.finish(300).unwrap();
+///
+///     let reader = KeyValueReader::try_new(&bytes[..]).unwrap();
+///     assert_eq!(reader.iterate().nth(5), Some(vec![(7, 5), (102, 1)]));
+///     assert_eq!(count_hits(reader.filter_has_key(101)), 100);
+/// ```
+use packed_simd::u32x8;
+use scroll::{Pread, Pwrite, LE};
+
+use crate::error::CodingError;
+use crate::nibblepacking::{pack_u64_zero_runs, unpack_with_mode, NibblePackMode};
+use crate::section::*;
+use crate::sink::VecSink;
+use crate::vector::{BinaryVector, FixedSectStats, VectorSubType, VectorType,
+                    BINARYVECT_HEADER_SIZE, NUM_HEADER_BYTES_TOTAL, format_version, wrong_subtype_error};
+
+
+/// A single (key, value) pair
+pub type KeyValue = (u64, u64);
+
+const SECT_HEADER_BYTES: usize = 9;
+
+/// A section of 256 rows of key/value pairs.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::KeyValuePairs
+///  +1   4-byte LE size of bytes to follow the size
+///  +5   4-byte LE total number of pairs in the section
+///  +9   NibblePacked (ZeroRuns) number of pairs in each of the 256 rows
+///       NibblePacked (ZeroRuns) keys of all rows; each row's first key, then deltas from the previous key
+///       NibblePacked (ZeroRuns) values of all rows
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct KeyValuePairsSect<'buf> {
+    sect_bytes: &'buf [u8],
+    encoded_bytes: u32,
+    num_pairs: u32,
+}
+
+impl<'buf> KeyValuePairsSect<'buf> {
+    /// Tries to create a KeyValuePairsSect from a byte slice starting from the section type byte.
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let sect_type: u8 = sect_bytes.pread_with(0, LE)?;
+        if sect_type != SectionType::KeyValuePairs.as_num() {
+            return Err(CodingError::InvalidSectionType(sect_type));
+        }
+        let encoded_bytes: u32 = sect_bytes.pread_with(1, LE)?;
+        if encoded_bytes as usize + 5 > sect_bytes.len() { return Err(CodingError::InputTooShort) }
+        let num_pairs: u32 = sect_bytes.pread_with(5, LE)?;
+        Ok(Self { sect_bytes, encoded_bytes, num_pairs })
+    }
+
+    pub fn num_bytes(&self) -> usize { self.encoded_bytes as usize + 5 }
+
+    /// Total number of pairs in all rows of this section
+    pub fn num_pairs(&self) -> usize { self.num_pairs as usize }
+
+    /// Writes out a section of FIXED_LEN rows starting at offset, returning the offset after the section.
+    /// counts has the number of pairs of each row, and pairs the pairs of all rows.  The keys of each row
+    /// must be sorted and unique.
+    pub fn write(out_buf: &mut [u8],
+                 offset: usize,
+                 counts: &[u32],
+                 pairs: &[KeyValue]) -> Result<usize, CodingError> {
+        assert_eq!(counts.len(), FIXED_LEN);
+        out_buf.pwrite_with(SectionType::KeyValuePairs.as_num(), offset, LE)?;
+        out_buf.pwrite_with(pairs.len() as u32, offset + 5, LE)?;
+        let mut off = pack_u64_zero_runs(counts.iter().map(|&c| c as u64), out_buf, offset + SECT_HEADER_BYTES)?;
+
+        let mut key_deltas = Vec::with_capacity(pairs.len());
+        let mut row_start = 0;
+        for &count in counts {
+            let row = &pairs[row_start..row_start + count as usize];
+            key_deltas.extend(row.iter().enumerate()
+                                 .map(|(i, &(key, _))| if i == 0 { key } else { key - row[i - 1].0 }));
+            row_start += count as usize;
+        }
+        off = pack_u64_zero_runs(key_deltas.into_iter(), out_buf, off)?;
+        off = pack_u64_zero_runs(pairs.iter().map(|&(_, value)| value), out_buf, off)?;
+        out_buf.pwrite_with((off - offset - 5) as u32, offset + 1, LE)?;
+        Ok(off)
+    }
+
+    // Decodes the pair counts, and the keys of each row, returning the rest of the encoded bytes
+    fn decode_counts_keys(&self,
+                          counts: &mut VecSink<u64>,
+                          keys: &mut VecSink<u64>) -> Result<&'buf [u8], CodingError> {
+        // NOTE: the decoders may read past the end of a section, so don't trim to num_bytes
+        let encoded = &self.sect_bytes[SECT_HEADER_BYTES..];
+        let encoded = unpack_with_mode(encoded, counts, FIXED_LEN, NibblePackMode::ZeroRuns)?;
+        let total = counts.vec[..FIXED_LEN].iter().try_fold(0u64, |acc, &count| acc.checked_add(count))
+                          .ok_or_else(|| CodingError::InvalidFormat("Row counts overflow".to_string()))?;
+        if total != self.num_pairs as u64 {
+            return Err(CodingError::InvalidFormat(format!("Row counts add up to {}, not {}",
+                                                          total, self.num_pairs)));
+        }
+        let encoded = unpack_with_mode(encoded, keys, self.num_pairs(), NibblePackMode::ZeroRuns)?;
+        // Undo the key deltas within each row
+        let mut i = 0;
+        for &count in counts.vec[..FIXED_LEN].iter() {
+            for j in 1..count as usize {
+                keys.vec[i + j] = keys.vec[i + j].checked_add(keys.vec[i + j - 1])
+                    .ok_or_else(|| CodingError::InvalidFormat("Key deltas overflow".to_string()))?;
+            }
+            i += count as usize;
+        }
+        Ok(encoded)
+    }
+
+    /// Decodes the pairs of all FIXED_LEN rows of this section
+    pub fn decode(&self) -> Result<Vec<Vec<KeyValue>>, CodingError> {
+        let mut counts = VecSink::<u64>::new();
+        let mut keys = VecSink::<u64>::new();
+        let encoded = self.decode_counts_keys(&mut counts, &mut keys)?;
+        let mut values = VecSink::<u64>::new();
+        unpack_with_mode(encoded, &mut values, self.num_pairs(), NibblePackMode::ZeroRuns)?;
+
+        let mut pairs = keys.vec.into_iter().zip(values.vec.into_iter());
+        Ok(counts.vec[..FIXED_LEN].iter().map(|&count| pairs.by_ref().take(count as usize).collect()).collect())
+    }
+
+    /// Returns the mask of rows in this section containing key.  Values are not decoded.
+    pub fn has_key_mask(&self, key: u64) -> Result<u32x8, CodingError> {
+        let mut counts = VecSink::<u64>::new();
+        let mut keys = VecSink::<u64>::new();
+        self.decode_counts_keys(&mut counts, &mut keys)?;
+        let mut words = [0u32; 8];
+        let mut i = 0;
+        for (row, &count) in counts.vec[..FIXED_LEN].iter().enumerate() {
+            if keys.vec[i..i + count as usize].binary_search(&key).is_ok() {
+                words[row / 32] |= 1 << (row % 32);
+            }
+            i += count as usize;
+        }
+        Ok(u32x8::from(words))
+    }
+}
+
+/// A section in a FixedKeyValue vector: either a null section (256 empty rows) or key/value pairs
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum KeyValueSect<'buf> {
+    Null,
+    Pairs(KeyValuePairsSect<'buf>),
+}
+
+impl<'buf> KeyValueSect<'buf> {
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        if sect_bytes.is_empty() { return Err(CodingError::InputTooShort) }
+        if sect_bytes[0] == SectionType::Null.as_num() {
+            Ok(KeyValueSect::Null)
+        } else {
+            KeyValuePairsSect::try_from(sect_bytes).map(KeyValueSect::Pairs)
+        }
+    }
+
+    pub fn num_bytes(&self) -> usize {
+        match self {
+            KeyValueSect::Null => 1,
+            KeyValueSect::Pairs(s) => s.num_bytes(),
+        }
+    }
+
+    pub fn is_null(&self) -> bool { *self == KeyValueSect::Null }
+
+    pub fn decode(&self) -> Result<Vec<Vec<KeyValue>>, CodingError> {
+        match self {
+            KeyValueSect::Null => Ok(vec![Vec::new(); FIXED_LEN]),
+            KeyValueSect::Pairs(s) => s.decode(),
+        }
+    }
+
+    pub fn has_key_mask(&self, key: u64) -> Result<u32x8, CodingError> {
+        match self {
+            KeyValueSect::Null => Ok(u32x8::splat(0)),
+            KeyValueSect::Pairs(s) => s.has_key_mask(key),
+        }
+    }
+}
+
+/// Iterates over the KeyValueSects in the bytes following the vector header
+pub struct KeyValueSectIterator<'buf> {
+    encoded_bytes: &'buf [u8],
+}
+
+impl<'buf> Iterator for KeyValueSectIterator<'buf> {
+    type Item = Result<KeyValueSect<'buf>, CodingError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.encoded_bytes.is_empty() {
+            None
+        } else {
+            let res = KeyValueSect::try_from(self.encoded_bytes);
+            if let Ok(sect) = &res {
+                self.encoded_bytes = &self.encoded_bytes[sect.num_bytes()..];
+            }
+            Some(res)
+        }
+    }
+}
+
+const GROW_BYTES: usize = 4096;
+
+/// An appender for FixedKeyValue vectors.  Works just like `VectorAppender`: rows are buffered and written
+/// 256 at a time, nulls are empty rows, and `finish()` clones out the vector and resets the appender.
+pub struct KeyValueAppender {
+    vect_buf: Vec<u8>,
+    offset: usize,
+    header: BinaryVector,
+    counts: Vec<u32>,
+    pairs: Vec<KeyValue>,
+    stats: FixedSectStats,
+}
+
+impl KeyValueAppender {
+    pub fn try_new(initial_capacity: usize) -> Result<Self, CodingError> {
+        let mut new_self = Self {
+            vect_buf: vec![0; initial_capacity],
+            offset: NUM_HEADER_BYTES_TOTAL,
+            header: BinaryVector::new(VectorType::FixedSection256, VectorSubType::FixedKeyValue),
+            counts: Vec::with_capacity(FIXED_LEN),
+            pairs: Vec::new(),
+            stats: FixedSectStats::new(),
+        };
+        new_self.header.write_header(new_self.vect_buf.as_mut_slice())?;
+        Ok(new_self)
+    }
+
+    /// Total number of rows including encoded sections and write buffer
+    pub fn num_elements(&self) -> usize {
+        self.stats.num_elements as usize + self.counts.len()
+    }
+
+    /// Resets the internal state for appending a new vector.
+    pub fn reset(&mut self) -> Result<(), CodingError> {
+        self.header.reset();
+        self.offset = NUM_HEADER_BYTES_TOTAL;
+        self.counts.clear();
+        self.pairs.clear();
+        self.vect_buf.resize(self.vect_buf.capacity(), 0);
+        self.stats.reset();
+        self.stats.update_num_elems(&mut self.vect_buf, 0)?;
+        self.header.write_header(self.vect_buf.as_mut_slice())
+    }
+
+    fn write_section(&mut self) -> Result<(), CodingError> {
+        let empty = self.pairs.is_empty();
+        loop {
+            let res = if empty {
+                NullFixedSect::write(self.vect_buf.as_mut_slice(), self.offset)
+            } else {
+                KeyValuePairsSect::write(self.vect_buf.as_mut_slice(), self.offset,
+                                         &self.counts[..], &self.pairs[..])
+            };
+            match res {
+                // Sections can be large, so keep growing until the section fits
+                Err(CodingError::NotEnoughSpace) | Err(CodingError::BadOffset(_)) => {
+                    self.vect_buf.reserve(GROW_BYTES.max(self.vect_buf.capacity()));
+                    self.vect_buf.resize(self.vect_buf.capacity(), 0);
+                }
+                res => {
+                    self.offset = res?;
+                    break;
+                }
+            }
+        }
+        if empty { self.stats.num_null_sections += 1; }
+        self.counts.clear();
+        self.pairs.clear();
+        self.stats.update_num_elems(&mut self.vect_buf, self.stats.num_elements + FIXED_LEN as u32)?;
+        self.header.update_num_bytes(self.vect_buf.as_mut_slice(),
+                                     (self.offset - NUM_HEADER_BYTES_TOTAL) as u32)
+    }
+
+    /// Appends one row of pairs, in any key order.  Returns InvalidFormat if a key occurs more than once.
+    pub fn append(&mut self, row: &[KeyValue]) -> Result<(), CodingError> {
+        let row_start = self.pairs.len();
+        self.pairs.extend_from_slice(row);
+        self.pairs[row_start..].sort_unstable_by_key(|&(key, _)| key);
+        if let Some(dup) = self.pairs[row_start..].windows(2).find(|w| w[0].0 == w[1].0) {
+            let key = dup[0].0;
+            self.pairs.truncate(row_start);
+            return Err(CodingError::InvalidFormat(format!("Duplicate key {} in row", key)));
+        }
+        self.counts.push(row.len() as u32);
+        if self.counts.len() >= FIXED_LEN {
+            self.write_section()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Appends a number of null (empty) rows.
+    pub fn append_nulls(&mut self, num_nulls: usize) -> Result<(), CodingError> {
+        for _ in 0..num_nulls {
+            self.append(&[])?;
+        }
+        Ok(())
+    }
+
+    /// Wraps up the vector, filling the last section with empty rows, and clones out the vector bytes.
+    /// See `VectorAppender::finish()`.
+    pub fn finish(&mut self, total_num_rows: usize) -> Result<Vec<u8>, CodingError> {
+        let total_so_far = self.num_elements();
+        if total_so_far > total_num_rows { return Err(CodingError::InvalidNumRows(total_num_rows, total_so_far)); }
+        if total_num_rows > u32::max_value() as usize {
+            return Err(CodingError::InvalidNumRows(total_num_rows, u32::max_value() as usize));
+        }
+
+        if !self.counts.is_empty() {
+            self.append_nulls(FIXED_LEN - self.counts.len())?;
+        }
+        while (self.stats.num_elements as usize) < total_num_rows {
+            self.append_nulls(FIXED_LEN)?;
+        }
+
+        self.stats.update_num_elems(self.vect_buf.as_mut_slice(), total_num_rows as u32)?;
+        self.vect_buf.resize(self.offset, 0);
+        let mut returned_vec = Vec::with_capacity(self.offset);
+        returned_vec.append(&mut self.vect_buf);
+        self.reset()?;
+        Ok(returned_vec)
+    }
+}
+
+/// A reader for FixedKeyValue vectors written by `KeyValueAppender`
+pub struct KeyValueReader<'buf> {
+    vect_bytes: &'buf [u8],
+}
+
+impl<'buf> KeyValueReader<'buf> {
+    pub fn try_new(vect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let bytes_from_header: u32 = vect_bytes.pread_with(0, LE)?;
+        let subtype: u8 = vect_bytes.pread_with(offset_of!(BinaryVector, minor_type), LE)?;
+        format_version(vect_bytes)?;
+        let total_bytes = (bytes_from_header as usize).checked_add(4).ok_or(CodingError::InputTooShort)?;
+        if vect_bytes.len() < total_bytes || total_bytes < NUM_HEADER_BYTES_TOTAL {
+            Err(CodingError::InputTooShort)
+        } else if subtype != VectorSubType::FixedKeyValue as u8 {
+            Err(wrong_subtype_error(subtype, VectorSubType::FixedKeyValue))
+        } else {
+            Ok(Self { vect_bytes })
+        }
+    }
+
+    pub fn num_elements(&self) -> usize {
+        self.get_stats().num_elements as usize
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.vect_bytes.len()
+    }
+
+    /// Returns a FixedSectStats extracted from the vector header.
+    pub fn get_stats(&self) -> FixedSectStats {
+        self.vect_bytes.pread_with(BINARYVECT_HEADER_SIZE, LE).unwrap()
+    }
+
+    /// Returns an iterator over each section in this vector
+    pub fn sect_iter(&self) -> KeyValueSectIterator<'buf> {
+        KeyValueSectIterator { encoded_bytes: &self.vect_bytes[NUM_HEADER_BYTES_TOTAL..] }
+    }
+
+    /// Returns an iterator over the rows of this vector, each a Vec of pairs sorted by key.
+    /// Decodes one section at a time.
+    pub fn iterate(&self) -> KeyValueRowIter<'buf> {
+        KeyValueRowIter { sect_iter: self.sect_iter(), rows: Vec::new(), i: 0, num_elems: self.num_elements() }
+    }
+
+    /// Returns an iterator of u32x8 masks, one per section, for rows containing key.
+    /// Can be used with `filter::count_hits` etc.
+    pub fn filter_has_key(&self, key: u64) -> impl Iterator<Item = u32x8> + 'buf {
+        self.sect_iter().map(move |sect| {
+            sect.and_then(|s| s.has_key_mask(key)).expect("This should not fail!")
+        })
+    }
+}
+
+/// Iterator over the rows of a FixedKeyValue vector
+pub struct KeyValueRowIter<'buf> {
+    sect_iter: KeyValueSectIterator<'buf>,
+    rows: Vec<Vec<KeyValue>>,
+    i: usize,
+    num_elems: usize,
+}
+
+impl<'buf> Iterator for KeyValueRowIter<'buf> {
+    type Item = Vec<KeyValue>;
+    fn next(&mut self) -> Option<Vec<KeyValue>> {
+        if self.i >= self.num_elems { return None }
+        if self.i % FIXED_LEN == 0 {
+            let sect = self.sect_iter.next()?.expect("Unexpected end of section");
+            self.rows = sect.decode().expect("Could not decode section");
+        }
+        let row = std::mem::replace(&mut self.rows[self.i % FIXED_LEN], Vec::new());
+        self.i += 1;
+        Some(row)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::count_hits;
+
+    fn row(i: u64) -> Vec<KeyValue> {
+        // Sparse rows: every 5th row is empty, others have up to 4 keys out of order
+        (0..(i % 5)).map(|k| (1000 - k * 10 - (i % 2), i * 3 + k)).collect()
+    }
+
+    #[test]
+    fn test_section_write_decode_and_filter() {
+        let rows: Vec<Vec<KeyValue>> = (0..256).map(row).collect();
+        let counts: Vec<u32> = rows.iter().map(|r| r.len() as u32).collect();
+        let mut pairs: Vec<KeyValue> = Vec::new();
+        for r in rows.iter() {
+            let mut sorted = r.clone();
+            sorted.sort();
+            pairs.extend(sorted);
+        }
+
+        let mut buf = [0u8; 8192];
+        let off = KeyValuePairsSect::write(&mut buf, 0, &counts[..], &pairs[..]).unwrap();
+        assert!(off < pairs.len() * 16 / 2);
+
+        let sect = KeyValuePairsSect::try_from(&buf[..]).unwrap();
+        assert_eq!(sect.num_bytes(), off);
+        assert_eq!(sect.num_pairs(), pairs.len());
+        let decoded = sect.decode().unwrap();
+        for (i, r) in rows.iter().enumerate() {
+            let mut sorted = r.clone();
+            sorted.sort();
+            assert_eq!(decoded[i], sorted);
+        }
+
+        // Key 990 is in rows with i % 5 >= 2 and even i; key 1000 in rows with i % 5 >= 1 and even i
+        let mask = sect.has_key_mask(990).unwrap();
+        let expected = (0..256).filter(|i| i % 5 >= 2 && i % 2 == 0).count();
+        assert_eq!(mask.count_ones().wrapping_sum() as usize, expected);
+        assert_eq!(mask.extract(0) & 0b111, 0b100);
+        assert_eq!(sect.has_key_mask(5).unwrap(), u32x8::splat(0));
+    }
+
+    #[test]
+    fn test_append_read_and_filter_vector() {
+        let mut appender = KeyValueAppender::try_new(1024).unwrap();
+        for i in 0..300u64 {
+            appender.append(&row(i)[..]).unwrap();
+        }
+        appender.append_nulls(600).unwrap();
+        appender.append(&[(42, 1), (41, 2)]).unwrap();
+        assert_eq!(appender.append(&[(5, 1), (5, 2)]),
+                   Err(CodingError::InvalidFormat("Duplicate key 5 in row".into())));
+        let bytes = appender.finish(901).unwrap();
+
+        let reader = KeyValueReader::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.num_elements(), 901);
+        // Section 2 (rows 512-767) is all empty rows, so is a null section
+        assert_eq!(reader.get_stats().num_null_sections, 1);
+        assert!(reader.sect_iter().nth(2).unwrap().unwrap().is_null());
+
+        let rows: Vec<_> = reader.iterate().collect();
+        assert_eq!(rows.len(), 901);
+        let mut expected = row(299);
+        expected.sort();
+        assert_eq!(rows[299], expected);
+        assert!(rows[300..900].iter().all(|r| r.is_empty()));
+        assert_eq!(rows[900], vec![(41, 2), (42, 1)]);
+
+        let expected = (0..300).filter(|i| i % 5 >= 1 && i % 2 == 0).count();
+        assert_eq!(count_hits(reader.filter_has_key(1000)), expected);
+        assert_eq!(count_hits(reader.filter_has_key(41)), 1);
+
+        // Reading with the wrong vector type fails
+        let mut u64_appender = crate::vector::VectorU64Appender::try_new(1024).unwrap();
+        let u64_bytes = u64_appender.encode_all(0..10u64).unwrap();
+        assert!(KeyValueReader::try_new(&u64_bytes[..]).is_err());
+    }
+}
@@ -66,6 +66,14 @@
 //!     println!("And the transformed vector is: {:?}", vecsink.vec);
 //! ```
 //!
+//! ### API Stability
+//!
+//! The types exported at the crate root — appenders, readers, filters, sinks and `CodingError` — are the
+//! stable API.  The section encodings and NibblePacking kernels are exported from the `raw` module, which is
+//! exempt from semver: it changes whenever the vector format gains new section types.  Enums which grow with
+//! the format, such as `SectionType`, `FixedSectEnum` and `CodingError`, are `#[non_exhaustive]`, so matches
+//! on them need a wildcard arm.
+//!
 //! ### Vector Format
 //!
 //! Details of the vector format can be found [here](https://github.com/velvia/compressed-vec/blob/main/vector_format.md).
@@ -87,17 +95,71 @@
 #[macro_use]
 extern crate memoffset;
 
+// Section internals, exported through the raw module
+#[doc(hidden)]
 pub mod nibblepacking;
+#[doc(hidden)]
 pub mod nibblepack_simd;
+#[doc(hidden)]
 pub mod byteutils;
+#[doc(hidden)]
+pub mod section;
+#[doc(hidden)]
+pub mod lz4;
+pub mod raw;
+
 pub mod vector;
 pub mod histogram;
-pub mod section;
 pub mod error;
 pub mod filter;
 pub mod sink;
+pub mod arith;
+pub mod benchmark;
+pub mod fixedbin;
+pub mod reference;
+pub mod transform;
+pub mod prom;
+pub mod exphist;
+pub mod selection;
+pub mod memory;
+pub mod kvpairs;
+pub mod stream;
+pub mod footer;
+pub mod bloom;
+pub mod shared;
+pub mod data;
+pub mod coerce;
+pub mod scratch;
+pub mod columns;
+pub mod split;
+pub mod minmax;
+pub mod ewma;
+pub mod compact;
+pub mod aggregate;
+pub mod half;
+pub mod strdict;
+pub mod boolvec;
+pub mod groupby;
+pub mod pipeline;
+pub mod hll;
+pub mod scanner;
 
-// Public crate-level exports for convenience
-pub use vector::{VectorU64Appender, VectorU32Appender, VectorF32XorAppender,
-                 VectorReader};
-pub use sink::{VecSink, Section256Sink, AddConstSink};
+// Public crate-level exports for convenience.  These are the stable API: builders, readers, filters and errors.
+pub use vector::{VectorAppender, VectorU64Appender, VectorU32Appender, VectorU16Appender, VectorU8Appender,
+                 VectorF32XorAppender, VectorF64XorAppender, VectorF64GorillaAppender, VectorU64DeltaDeltaAppender,
+                 VectorU64Simple8bAppender, VectorU32Simple8bAppender, VectorF64DecimalAppender,
+                 VectorReader, PointReader, AppenderLimits, ResumeToken, CompressedVec};
+#[cfg(feature = "lz4")]
+pub use vector::{VectorU64Lz4Appender, VectorU32Lz4Appender};
+pub use fixedbin::{FixedBin16Appender, FixedBin16Reader};
+pub use kvpairs::{KeyValueAppender, KeyValueReader};
+pub use strdict::{StrDictAppender, StrDictReader};
+pub use boolvec::{BoolAppender, BoolReader};
+pub use data::{VectorData, VectorInfo, open_dynamic, peek_info};
+pub use shared::SharedVector;
+pub use filter::{VectorFilter, MultiVectorFilter, CombinedFilter, Combine, EqualsSink, OneOfSink, RangeSink,
+                 count_hits, match_positions, selection_vector};
+pub use pipeline::Pipeline;
+pub use sink::{Sink, VecSink, Section256Sink, AddConstSink};
+pub use section::VectBase;
+pub use error::CodingError;
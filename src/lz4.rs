@@ -0,0 +1,185 @@
+/// The `lz4` module is a small implementation of the LZ4 block format, see
+/// <https://github.com/lz4/lz4/blob/dev/doc/lz4_Block_format.md>.  It is used by Lz4Medium sections to compress
+/// the raw bytes of values which NibblePacking would expand.
+/// Blocks are at most a few KB, so the compressor is a plain greedy matcher over a single hash table.
+///
+/// Decompression is always compiled in, so that any build can read vectors containing Lz4Medium sections.
+/// Compression is only compiled with the `lz4` cargo feature.
+use scroll::{Pread, LE};
+
+use crate::error::CodingError;
+
+const MIN_MATCH: usize = 4;
+#[cfg(feature = "lz4")]
+const LAST_LITERALS: usize = 5;   // The last 5 bytes of a block are always literals
+#[cfg(feature = "lz4")]
+const MF_LIMIT: usize = 12;       // The last match must start at least 12 bytes before the end of a block
+#[cfg(feature = "lz4")]
+const HASH_LOG: u32 = 12;
+
+/// The largest LZ4 block which compressing input_len bytes can produce
+pub const fn max_compressed_len(input_len: usize) -> usize {
+    input_len + input_len / 255 + 16
+}
+
+#[cfg(feature = "lz4")]
+#[inline]
+fn hash(sequence: u32) -> usize {
+    (sequence.wrapping_mul(2_654_435_761) >> (32 - HASH_LOG)) as usize
+}
+
+#[cfg(feature = "lz4")]
+fn put(out: &mut [u8], pos: usize, bytes: &[u8]) -> Result<usize, CodingError> {
+    let end = pos + bytes.len();
+    out.get_mut(pos..end).ok_or(CodingError::NotEnoughSpace)?.copy_from_slice(bytes);
+    Ok(end)
+}
+
+// Writes the 255-byte continuation of a literal or match length of 15 or more
+#[cfg(feature = "lz4")]
+fn put_length(out: &mut [u8], mut pos: usize, mut len: usize) -> Result<usize, CodingError> {
+    while len >= 255 {
+        pos = put(out, pos, &[255])?;
+        len -= 255;
+    }
+    put(out, pos, &[len as u8])
+}
+
+// Writes one sequence: literals followed by an optional (offset, length) match
+#[cfg(feature = "lz4")]
+fn put_sequence(out: &mut [u8],
+                mut pos: usize,
+                literals: &[u8],
+                matched: Option<(usize, usize)>) -> Result<usize, CodingError> {
+    let match_len = matched.map_or(0, |(_, len)| len - MIN_MATCH);
+    let token = (literals.len().min(15) << 4 | match_len.min(15)) as u8;
+    pos = put(out, pos, &[token])?;
+    if literals.len() >= 15 { pos = put_length(out, pos, literals.len() - 15)?; }
+    pos = put(out, pos, literals)?;
+    if let Some((offset, _)) = matched {
+        pos = put(out, pos, &(offset as u16).to_le_bytes())?;
+        if match_len >= 15 { pos = put_length(out, pos, match_len - 15)?; }
+    }
+    Ok(pos)
+}
+
+/// Compresses input as a single LZ4 block into out, returning the number of bytes written.
+/// out should have at least max_compressed_len(input.len()) bytes, otherwise NotEnoughSpace may be returned.
+#[cfg(feature = "lz4")]
+pub fn compress(input: &[u8], out: &mut [u8]) -> Result<usize, CodingError> {
+    let read_u32 = |pos: usize| u32::from_le_bytes([input[pos], input[pos + 1], input[pos + 2], input[pos + 3]]);
+    let mut table = [0usize; 1 << HASH_LOG];    // Position + 1 of the last sequence with each hash, 0 if none
+    let mut out_pos = 0;
+    let mut anchor = 0;     // Start of the literals not yet written
+    let mut pos = 0;
+    while pos + MF_LIMIT < input.len() {
+        let sequence = read_u32(pos);
+        let h = hash(sequence);
+        let candidate = table[h];
+        table[h] = pos + 1;
+        if candidate > 0 && pos - (candidate - 1) <= 65535 && read_u32(candidate - 1) == sequence {
+            let start = candidate - 1;
+            let mut len = MIN_MATCH;
+            while pos + len < input.len() - LAST_LITERALS && input[start + len] == input[pos + len] {
+                len += 1;
+            }
+            out_pos = put_sequence(out, out_pos, &input[anchor..pos], Some((pos - start, len)))?;
+            pos += len;
+            anchor = pos;
+        } else {
+            pos += 1;
+        }
+    }
+    put_sequence(out, out_pos, &input[anchor..], None)
+}
+
+// Reads the 255-byte continuation of a literal or match length of 15 or more
+fn read_length(input: &[u8], pos: &mut usize) -> Result<usize, CodingError> {
+    let mut len = 0;
+    loop {
+        let byte = *input.get(*pos).ok_or(CodingError::InputTooShort)?;
+        *pos += 1;
+        len += byte as usize;
+        if byte != 255 { return Ok(len) }
+    }
+}
+
+/// Decompresses a single LZ4 block into out, returning the number of bytes written.
+/// Blocks which would decompress to more than out.len() bytes, or which refer to bytes before the start of out,
+/// are InvalidFormat errors.
+pub fn decompress(input: &[u8], out: &mut [u8]) -> Result<usize, CodingError> {
+    let mut in_pos = 0;
+    let mut out_pos = 0;
+    loop {
+        let token = *input.get(in_pos).ok_or(CodingError::InputTooShort)?;
+        in_pos += 1;
+
+        let mut literal_len = (token >> 4) as usize;
+        if literal_len == 15 { literal_len += read_length(input, &mut in_pos)?; }
+        let literals = input.get(in_pos..in_pos + literal_len).ok_or(CodingError::InputTooShort)?;
+        out.get_mut(out_pos..out_pos + literal_len)
+           .ok_or_else(|| CodingError::InvalidFormat("LZ4 block decompresses past the end of output".into()))?
+           .copy_from_slice(literals);
+        in_pos += literal_len;
+        out_pos += literal_len;
+        // The last sequence has literals only
+        if in_pos == input.len() { return Ok(out_pos) }
+
+        let offset: u16 = input.pread_with(in_pos, LE)?;
+        in_pos += 2;
+        let offset = offset as usize;
+        if offset == 0 || offset > out_pos {
+            return Err(CodingError::InvalidFormat(format!("LZ4 match offset {} out of range", offset)))
+        }
+        let mut match_len = (token & 0x0f) as usize + MIN_MATCH;
+        if token & 0x0f == 15 { match_len += read_length(input, &mut in_pos)?; }
+        if out_pos + match_len > out.len() {
+            return Err(CodingError::InvalidFormat("LZ4 block decompresses past the end of output".into()))
+        }
+        // Matches may overlap the bytes they produce, so copy one byte at a time
+        for i in out_pos..out_pos + match_len {
+            out[i] = out[i - offset];
+        }
+        out_pos += match_len;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decompress_handwritten_blocks() {
+        // "abcabcabcabcX": 3 literals, a 9 byte match at offset 3, then 1 literal
+        let block = [0x35, b'a', b'b', b'c', 3, 0, 0x10, b'X'];
+        let mut out = [0u8; 16];
+        assert_eq!(decompress(&block, &mut out).unwrap(), 13);
+        assert_eq!(&out[..13], b"abcabcabcabcX");
+
+        assert_eq!(decompress(&block, &mut out[..12]),
+                   Err(CodingError::InvalidFormat("LZ4 block decompresses past the end of output".into())));
+        assert_eq!(decompress(&[0x10, b'a', 2, 0, 0x00], &mut out),
+                   Err(CodingError::InvalidFormat("LZ4 match offset 2 out of range".into())));
+        assert_eq!(decompress(&block[..2], &mut out), Err(CodingError::InputTooShort));
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_compress_round_trip() {
+        let repetitive: Vec<u8> = (0..2048u32).map(|i| (i % 37) as u8).collect();
+        let noisy: Vec<u8> = (0..2048u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let long_run = vec![7u8; 1000];
+        for input in [&repetitive[..], &noisy[..], &long_run[..], &b"short"[..], &[][..]].iter() {
+            let mut block = vec![0u8; max_compressed_len(input.len())];
+            let block_len = compress(input, &mut block).unwrap();
+            let mut out = vec![0u8; input.len()];
+            assert_eq!(decompress(&block[..block_len], &mut out).unwrap(), input.len());
+            assert_eq!(&out[..], *input);
+        }
+
+        let mut block = [0u8; 4096];
+        assert!(compress(&repetitive[..], &mut block).unwrap() < 100);
+        assert!(compress(&noisy[..], &mut block).unwrap() <= max_compressed_len(noisy.len()));
+    }
+}
@@ -0,0 +1,152 @@
+/// The `memory` module contains the `MemoryTracker` hooks, which appenders and section caches call when they
+/// allocate or release scratch buffers.  Multi-tenant processes can give each tenant's appenders and readers
+/// their own tracker, to attribute memory use per tenant and to cap it: an allocation refused by the tracker
+/// fails with `CodingError::MemoryLimitExceeded`, and any memory already reserved for it is given back.
+///
+/// Only scratch memory owned by this crate is tracked, not the encoded vectors handed back to the caller.
+/// ```
+/// # use std::sync::Arc;
+/// # use compressed_vec::memory::*;
+/// # use compressed_vec::vector::VectorU64Appender;
+/// # use compressed_vec::error::CodingError;
+///     let tenant = Arc::new(LimitTracker::new(8192));
+///     let mut appender = VectorU64Appender::try_new(4096).unwrap();
+///     appender.set_memory_tracker(tenant.clone()).unwrap();
+///     assert!(tenant.used() >= 4096);
+///
+///     // Random values need more than the 8KB limit
+///     let res = appender.encode_all((0..10_000u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15)));
+///     assert!(matches!(res, Err(CodingError::MemoryLimitExceeded(_))));
+///
+///     drop(appender);
+///     assert_eq!(tenant.used(), 0);
+/// ```
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::CodingError;
+
+
+/// Hooks called on allocation and release of scratch buffers.  Implementations must be thread safe, as
+/// one tracker is usually shared by many appenders and readers.
+pub trait MemoryTracker: Send + Sync {
+    /// Called before num_bytes are allocated.  Returns an error, usually MemoryLimitExceeded, to refuse.
+    fn allocate(&self, num_bytes: usize) -> Result<(), CodingError>;
+
+    /// Called when num_bytes previously allocated are released.
+    fn release(&self, num_bytes: usize);
+}
+
+/// A MemoryTracker which counts bytes in use and refuses allocations beyond a limit.
+#[derive(Debug)]
+pub struct LimitTracker {
+    limit: usize,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl LimitTracker {
+    pub fn new(limit: usize) -> Self {
+        Self { limit, used: AtomicUsize::new(0), peak: AtomicUsize::new(0) }
+    }
+
+    /// Number of bytes currently allocated
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Highest number of bytes allocated at any one time
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+impl MemoryTracker for LimitTracker {
+    fn allocate(&self, num_bytes: usize) -> Result<(), CodingError> {
+        let mut used = self.used.load(Ordering::Relaxed);
+        loop {
+            let new_used = used.checked_add(num_bytes)
+                               .filter(|&new_used| new_used <= self.limit)
+                               .ok_or(CodingError::MemoryLimitExceeded(num_bytes))?;
+            match self.used.compare_exchange_weak(used, new_used, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => {
+                    let mut peak = self.peak.load(Ordering::Relaxed);
+                    while new_used > peak {
+                        match self.peak.compare_exchange_weak(peak, new_used, Ordering::Relaxed, Ordering::Relaxed) {
+                            Ok(_) => break,
+                            Err(current) => peak = current,
+                        }
+                    }
+                    return Ok(());
+                }
+                Err(current) => used = current,
+            }
+        }
+    }
+
+    fn release(&self, num_bytes: usize) {
+        self.used.fetch_sub(num_bytes, Ordering::Relaxed);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::section::LazySection;
+    use crate::sink::Section256Sink;
+    use crate::vector::{VectorReader, VectorU32Appender};
+
+    #[test]
+    fn test_limit_tracker() {
+        let tracker = LimitTracker::new(100);
+        tracker.allocate(60).unwrap();
+        assert_eq!(tracker.allocate(50), Err(CodingError::MemoryLimitExceeded(50)));
+        assert_eq!(tracker.used(), 60);
+        tracker.allocate(40).unwrap();
+        tracker.release(70);
+        assert_eq!(tracker.used(), 30);
+        assert_eq!(tracker.peak(), 100);
+    }
+
+    #[test]
+    fn test_appender_and_lazy_section_tracking() {
+        let tracker = Arc::new(LimitTracker::new(1 << 20));
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.set_memory_tracker(tracker.clone()).unwrap();
+        let initial = tracker.used();
+        assert!(initial >= 1024);
+
+        // Growing the vector buffer is tracked, and finish() keeps the scratch buffer for reuse
+        let bytes = appender.encode_all((0..5000u32).map(|i| i.wrapping_mul(2654435761))).unwrap();
+        assert!(tracker.used() > initial);
+        assert_eq!(tracker.peak(), tracker.used());
+
+        // Decoded section caches are tracked until dropped
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        let before = tracker.used();
+        {
+            let dyn_tracker: Arc<dyn MemoryTracker> = tracker.clone();
+            let mut lazies: Vec<LazySection<u32>> = reader.lazy_sections_tracked(dyn_tracker)
+                                                          .map(|s| s.unwrap()).take(3).collect();
+            lazies[0].values().unwrap();
+            lazies[2].values().unwrap();
+            assert_eq!(tracker.used(), before + 2 * std::mem::size_of::<Section256Sink<u32>>());
+        }
+        assert_eq!(tracker.used(), before);
+
+        drop(appender);
+        assert_eq!(tracker.used(), 0);
+
+        // A decode refused by the tracker fails without caching
+        let tiny = Arc::new(LimitTracker::new(16));
+        let mut lazy = reader.lazy_sections_tracked(tiny).next().unwrap().unwrap();
+        let sink_bytes = std::mem::size_of::<Section256Sink<u32>>();
+        assert_eq!(lazy.values().err(), Some(CodingError::MemoryLimitExceeded(sink_bytes)));
+        assert!(!lazy.is_decoded());
+    }
+}
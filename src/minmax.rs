@@ -0,0 +1,189 @@
+/// The `minmax` module computes the exact minimum and maximum of each section of a vector, for example for the
+/// zone maps which callers keep in their own side index, since vectors do not store them.
+/// Null and constant sections are read from their headers only; other sections are decoded through a
+/// `MinMaxSink`, which keeps 8 running minimums and maximums in SIMD registers.
+/// ```
+/// # use compressed_vec::minmax::per_section_minmax;
+/// # use compressed_vec::vector::VectorU32Appender;
+///     let mut appender = VectorU32Appender::try_new(1024).unwrap();
+///     (0..256u32).for_each(|i| appender.append(1000 - i).unwrap());
+///     appender.append_nulls(256).unwrap();
+///     (0..10u32).for_each(|i| appender.append(i + 5).unwrap());
+///     let vector = appender.finish(522).unwrap();
+///     assert_eq!(per_section_minmax::<u32>(&vector[..]).unwrap(), vec![(745, 1000), (0, 0), (5, 14)]);
+/// ```
+/// Null sections are zeroes, as when decoding.  Null elements of PartialNull sections are skipped, and a
+/// PartialNull section without any values is treated like a null section.  Only the first num_elements
+/// values of a vector count, so the padding at the end of the last section never changes its minimum or maximum.
+use num::NumCast;
+use packed_simd::{f32x8, f64x8, u32x8, u64x8};
+
+use crate::error::CodingError;
+use crate::section::*;
+use crate::selection::first_n_mask;
+use crate::sink::{Section256Sink, Sink, SinkInput};
+use crate::vector::{BaseSubtypeMapping, VectorReader};
+
+
+/// Lanewise and horizontal minimum and maximum of 8 values
+pub trait MinMaxInput: SinkInput {
+    fn lanes_min(self, other: Self) -> Self;
+    fn lanes_max(self, other: Self) -> Self;
+    fn min_item(self) -> Self::Item;
+    fn max_item(self) -> Self::Item;
+}
+
+macro_rules! impl_minmax_input {
+    ($($simd:ty),*) => { $(
+        impl MinMaxInput for $simd {
+            #[inline]
+            fn lanes_min(self, other: Self) -> Self { self.min(other) }
+            #[inline]
+            fn lanes_max(self, other: Self) -> Self { self.max(other) }
+            #[inline]
+            fn min_item(self) -> Self::Item { self.min_element() }
+            #[inline]
+            fn max_item(self) -> Self::Item { self.max_element() }
+        }
+    )* };
+}
+
+impl_minmax_input!(u32x8, u64x8, f32x8, f64x8);
+
+/// A Sink which keeps the minimum and maximum of the first num_values values it is given
+#[derive(Debug)]
+pub struct MinMaxSink<T: VectBase>
+where T::SI: MinMaxInput {
+    min: T::SI,
+    max: T::SI,
+    seen_any: bool,
+    tail: Option<(T, T)>,   // Of the last partial group of 8 values
+    values_left: usize,
+}
+
+impl<T: VectBase> MinMaxSink<T>
+where T::SI: MinMaxInput {
+    pub fn new(num_values: usize) -> Self {
+        Self { min: T::SI::ZERO, max: T::SI::ZERO, seen_any: false, tail: None, values_left: num_values }
+    }
+
+    /// The (min, max) of the values seen, or None if no values were seen
+    pub fn min_max(&self) -> Option<(T, T)> {
+        let simd = if self.seen_any { Some((self.min.min_item(), self.max.max_item())) } else { None };
+        match (simd, self.tail) {
+            (Some((min, max)), Some((tmin, tmax))) => Some((if tmin < min { tmin } else { min },
+                                                            if tmax > max { tmax } else { max })),
+            (simd, tail) => simd.or(tail),
+        }
+    }
+}
+
+impl<T: VectBase> Sink<T::SI> for MinMaxSink<T>
+where T::SI: MinMaxInput {
+    #[inline]
+    fn process(&mut self, data: T::SI) {
+        if self.values_left == 0 { return }
+        if self.values_left < 8 {
+            // Only the first values_left lanes are values; the rest is padding
+            let mut values = [T::zero(); 8];
+            data.write_to_slice(&mut values);
+            let first = values[0];
+            self.tail = Some(values[..self.values_left].iter().fold((first, first), |(min, max), &v| {
+                (if v < min { v } else { min }, if v > max { v } else { max })
+            }));
+            self.values_left = 0;
+            return;
+        }
+        if self.seen_any {
+            self.min = self.min.lanes_min(data);
+            self.max = self.max.lanes_max(data);
+        } else {
+            self.min = data;
+            self.max = data;
+            self.seen_any = true;
+        }
+        self.values_left -= 8;
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.process(T::SI::ZERO);
+    }
+
+    fn reset(&mut self) {
+        self.seen_any = false;
+        self.tail = None;
+    }
+}
+
+/// Returns the exact inclusive (min, max) of the values of each section of a vector, in section order.
+pub fn per_section_minmax<T>(vect_bytes: &[u8]) -> Result<Vec<(T, T)>, CodingError>
+where T: VectBase + BaseSubtypeMapping + NumCast,
+      T::SI: MinMaxInput {
+    let reader = VectorReader::<T>::try_new(vect_bytes)?;
+    let mut values_left = reader.num_elements();
+    reader.lazy_sections().map(|sect_res| {
+        let sect = sect_res?;
+        let num_values = values_left.min(FIXED_LEN);
+        values_left -= num_values;
+        match sect.section() {
+            FixedSectEnum::NullFixedSect(_) | FixedSectEnum::ConstFixedSect(_) => Ok(sect.bounds().unwrap()),
+            FixedSectEnum::PartialNullFixedSect(ps) => {
+                let valid = ps.validity_mask() & first_n_mask(num_values);
+                let mut sink = Section256Sink::<T>::new();
+                sect.decode(&mut sink)?;
+                let valid_values = (0..FIXED_LEN).filter(|&i| valid.extract(i / 32) & (1 << (i % 32)) != 0)
+                                                 .map(|i| sink.values[i]);
+                Ok(valid_values.fold(None, |acc, v| match acc {
+                    None             => Some((v, v)),
+                    Some((min, max)) => Some((if v < min { v } else { min }, if v > max { v } else { max })),
+                }).unwrap_or((T::zero(), T::zero())))
+            }
+            _ => {
+                let mut sink = MinMaxSink::<T>::new(num_values.max(1));
+                sect.decode(&mut sink)?;
+                sink.min_max().ok_or_else(|| CodingError::InvalidFormat("Section with no values".into()))
+            }
+        }
+    }).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{VectorF64XorAppender, VectorU64Appender};
+
+    #[test]
+    fn test_per_section_minmax_matches_decoded_values() {
+        // Constant, NibblePacked, delta and null sections, and a partial last section
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..256u64).for_each(|_| appender.append(77).unwrap());
+        (0..256u64).for_each(|i| appender.append((i * 7919) % 1000 + 10).unwrap());
+        (0..256u64).for_each(|i| appender.append(1_000_000 + i * 3).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..20u64).for_each(|i| appender.append(500 - i).unwrap());
+        let vector = appender.finish(1044).unwrap();
+
+        let reader = VectorReader::<u64>::try_new(&vector[..]).unwrap();
+        let values: Vec<u64> = reader.iterate().collect();
+        let expected: Vec<(u64, u64)> = values.chunks(FIXED_LEN).map(|chunk| {
+            (*chunk.iter().min().unwrap(), *chunk.iter().max().unwrap())
+        }).collect();
+        let minmax = per_section_minmax::<u64>(&vector[..]).unwrap();
+        assert_eq!(minmax, expected);
+        assert_eq!(minmax[4], (481, 500));
+
+        let floats = VectorF64XorAppender::try_new(4096).unwrap()
+                         .encode_all((0..300).map(|i| (i as f64 - 100.0) * 0.5)).unwrap();
+        assert_eq!(per_section_minmax::<f64>(&floats[..]).unwrap(), vec![(-50.0, 77.5), (78.0, 99.5)]);
+        assert!(matches!(per_section_minmax::<u32>(&vector[..]), Err(CodingError::WrongElementType { .. })));
+
+        // Null elements are skipped, and a section of only nulls is like a null section
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        appender.track_nulls(true);
+        let values = (0..300u64).map(|i| if i % 3 == 0 || i >= 256 { None } else { Some(i + 100) });
+        let vector = appender.encode_all_options(values).unwrap();
+        assert_eq!(per_section_minmax::<u64>(&vector[..]).unwrap(), vec![(101, 354), (0, 0)]);
+    }
+}
@@ -9,7 +9,7 @@ use crate::error::CodingError;
 use crate::nibblepacking::*;
 use crate::sink::*;
 
-use packed_simd::{shuffle, u64x8, u32x8, m32x8, isizex8, cptrx8};
+use packed_simd::{shuffle, u64x8, u32x8, m32x8, isizex8, cptrx8, FromCast};
 
 
 const ZEROES_U64X8: u64x8 = u64x8::splat(0);
@@ -436,13 +436,13 @@ fn preload_u32x8_nibbles(buf: &[u8],
     let total_bytes = (num_nibbles * nonzeroes as usize + 1) / 2;
     let mut i = 0;
     let mut off = 2;
-    let simd_word = u32x8::splat(0);
+    let mut simd_word = u32x8::splat(0);
     while i < 8 && off < (total_bytes + 2) {
         let inword = direct_read_uint_le(buf, off)?;
         // Safe because we are checking boundaries in while loop conditions
-        unsafe { simd_word.replace_unchecked(i, inword as u32) };
+        simd_word = unsafe { simd_word.replace_unchecked(i, inword as u32) };
         let shift2 = (num_nibbles * 4) / 8 * 8;  // round off shift to lower byte boundary
-        unsafe { simd_word.replace_unchecked(i + 1, (inword >> shift2) as u32) };
+        simd_word = unsafe { simd_word.replace_unchecked(i + 1, (inword >> shift2) as u32) };
         i += 2;
         off += num_nibbles;
     }
@@ -585,6 +585,85 @@ fn simd_unpack_inner(simd_inputs: u32x8, num_nibbles: u8, trailing_zeros: u8,
 }
 
 
+// Lane numbers, for computing where each of the 8 values of the u64 decoder starts in the packed nibbles
+const U64_LANE_NUMS: u64x8 = u64x8::new(0, 1, 2, 3, 4, 5, 6, 7);
+
+// Max number of bytes that a U64 nibblepacked 8 inputs could take up: 2 + 8*8;
+pub const MAX_U64_NIBBLEPACKED_LEN: usize = 66;
+
+/// SIMD GATHER/cptr based loading of SIMD u64x8 register.  Each lane reads the 8 bytes starting at its byte offset
+/// into the packed nibbles.  Caller must ensure buf is at least MAX_U64_NIBBLEPACKED_LEN long.
+#[inline(always)]
+unsafe fn preload_u64x8_simd(buf: &[u8],
+                             byte_offsets: isizex8,
+                             nonzeroes: u32) -> u64x8 {
+    let first_byte = buf.as_ptr().offset(2);
+    let u8_offset = cptrx8::splat(first_byte).offset(byte_offsets);
+    // Change type from *u8 to *u64 and force unaligned reads
+    let u64_offsets: cptrx8<u64> = std::mem::transmute(u8_offset);
+
+    // Only read lanes for which values exist
+    let read_mask = U64_LANE_NUMS.lt(u64x8::splat(nonzeroes as u64));
+    let loaded: u64x8 = u64_offsets.read(read_mask, ZEROES_U64X8);
+    loaded.to_le()
+}
+
+/// SIMD-based decoding of NibblePacked data to u64x8, the u64 counterpart of `unpack8_u32_simd`.
+/// Groups whose values fit in 32 bits are decoded by the u32 SIMD decoder.  Wider groups are loaded with one
+/// SIMD gather (or one read per value near the end of the input), then shifted, masked and shuffled in SIMD.
+/// Checks that the input buffer has enough room to decode.
+#[inline]
+pub fn unpack8_u64_simd<'a, Output: SinkU64>(
+    inbuf: &'a [u8],
+    output: &mut Output,
+) -> Result<&'a [u8], CodingError> {
+    if inbuf.is_empty() { return Err(CodingError::NotEnoughSpace) }
+    let nonzero_mask = inbuf[0];
+    if nonzero_mask == 0 {
+        output.process_zeroes();
+        return Ok(&inbuf[1..]);
+    }
+    if inbuf.len() < 3 { return Err(CodingError::NotEnoughSpace) }
+    let num_nibbles = (inbuf[1] >> 4) as u64 + 1;
+    let trailing_zeros = (inbuf[1] & 0x0f) as u32 * 4;
+    let nonzero_count = nonzero_mask.count_ones();
+    let num_bytes = (num_nibbles as usize * nonzero_count as usize + 1) / 2;
+    if inbuf.len() < 2 + num_bytes { return Err(CodingError::NotEnoughSpace) }
+    if num_nibbles * 4 + trailing_zeros as u64 <= 32 {
+        return unpack8_u32_simd(inbuf, &mut U32ToU64Sink::new(output));
+    }
+
+    // Step 1. Load the word starting at the first byte of each value.  Values start on nibble boundaries, so a
+    // value starts at most 4 bits into its word, and at most 60 + 4 or 64 + 0 bits are needed from the word.
+    let lane_bits = U64_LANE_NUMS * (num_nibbles * 4);
+    let byte_offsets = isizex8::from_cast(lane_bits >> 3);
+    let simd_inputs = if inbuf.len() >= MAX_U64_NIBBLEPACKED_LEN {
+        // Safe since we have checked length above
+        unsafe { preload_u64x8_simd(inbuf, byte_offsets, nonzero_count) }
+    } else {
+        let mut words = [0u64; 8];
+        for (i, word) in words.iter_mut().enumerate().take(nonzero_count as usize) {
+            *word = direct_read_uint_le(inbuf, 2 + byte_offsets.extract(i) as usize)?;
+        }
+        u64x8::from_slice_unaligned(&words)
+    };
+
+    // Steps 2-4. Shift each value into place, strip upper bits, and shift left for trailing zeroes
+    let mask = if num_nibbles == 16 { u64::MAX } else { (1u64 << (num_nibbles * 4)) - 1 };
+    let anded = simd_inputs.shr(lane_bits & 7).bitand(u64x8::splat(mask));
+    let leftshifted = if trailing_zeros == 0 { anded } else { anded.shl(trailing_zeros) };
+
+    // Step 5. Shuffle inputs based on nonzero mask to proper places, then send to sink
+    let shuffled = if nonzero_count == 8 {
+        leftshifted
+    } else {
+        leftshifted.replace(7, 0).shuffle1_dyn(u64x8::from_cast(SHUFFLE_UNPACK_IDX_U32[nonzero_mask as usize]))
+    };
+    output.process(shuffled);
+    Ok(&inbuf[(2 + num_bytes)..])
+}
+
+
 #[test]
 fn test_unpack_u32simd_1_2nibbles() {
     let mut buf = [55u8; 512];
@@ -664,6 +743,21 @@ fn test_unpack_u32simd_3_4nibbles() {
     assert_eq!(sink.values[..inputs.len()], inputs);
 }
 
+#[test]
+fn test_unpack_u32simd_5_8nibbles_short_input() {
+    // With less than MAX_U32_NIBBLEPACKED_LEN bytes of input, such as the last group of a vector,
+    // 5-8 nibbles are loaded one word at a time
+    let inputs = [984064u64, 986049, 988036, 990025, 992016, 994009, 996004, 998001];
+    let mut buf = [0u8; 64];
+    let written = nibble_pack8(&inputs, &mut buf, 0).unwrap();
+    assert!(written < MAX_U32_NIBBLEPACKED_LEN);
+
+    let mut sink = U32_256Sink::new();
+    let rest = unpack8_u32_simd(&buf[..written], &mut sink).unwrap();
+    assert_eq!(rest.len(), 0);
+    assert_eq!(sink.values[..8], [984064u32, 986049, 988036, 990025, 992016, 994009, 996004, 998001]);
+}
+
 // NOTE: cfg(test) is needed so that proptest can just be a "dev-dependency" and not linked for final library
 // NOTE2: somehow cargo is happier when we put props tests in its own module
 #[cfg(test)]
@@ -715,6 +809,18 @@ mod props {
          -> Vec<u64> { v }
     }
 
+    // random u64 source arrays of 8 with up to 64 bits and some trailing zero bits
+    prop_compose! {
+        fn arb_wide_u64_octets()
+                          (nbits in 20u32..=64, tz_bits in 0u32..12, chance in 0.1f64..0.6)
+                          (v in proptest::collection::vec((any::<u64>(), prop::bool::weighted(chance)), 8),
+                           nbits in Just(nbits), tz_bits in Just(tz_bits))
+         -> Vec<u64> {
+            v.iter().map(|&(n, is_zero)| if is_zero { 0 } else { (n >> (64 - nbits)) >> tz_bits << tz_bits })
+                    .collect()
+        }
+    }
+
     proptest! {
         #[test]
         fn prop_u32simd_pack_unpack(input in arb_u32_vectors()) {
@@ -739,5 +845,20 @@ mod props {
             let res = nibble_unpack8(&buf, &mut sink).unwrap();
             assert_eq!(sink.values[..numelems], input[..numelems]);
         }
+
+        #[test]
+        fn prop_u64simd_pack_unpack(input in arb_wide_u64_octets()) {
+            let mut buf = [0u8; 1024];
+            let off = pack8_u64_simd(u64x8::from_slice_unaligned(&input[..]), &mut buf, 0).unwrap();
+
+            // Gather path with a long input buffer, and reads near the end of a short one
+            for inbuf in [&buf[..], &buf[..off]].iter() {
+                let mut sink = U64_256Sink::new();
+                let rest = unpack8_u64_simd(inbuf, &mut sink).unwrap();
+                assert_eq!(sink.values[..8], input[..]);
+                assert_eq!(rest.len(), inbuf.len() - off);
+            }
+            assert_eq!(unpack8_u64_simd(&buf[..off - 1], &mut U64_256Sink::new()), Err(CodingError::NotEnoughSpace));
+        }
     }
 }
@@ -55,7 +55,7 @@ pub fn pack_f64_xor<I: Iterator<Item = f64>>(mut stream: I,
 /// This method does no transformations to the input data.  You might want one of the other pack_* methods.
 ///
 /// ```
-/// # use compressed_vec::nibblepacking;
+/// # use compressed_vec::raw::nibblepacking;
 ///     let inputs = [0u64, 1000, 1001, 1002, 1003, 2005, 2010, 3034, 4045, 5056, 6067, 7078];
 ///     let mut buf = [0u8; 1024];
 ///     nibblepacking::pack_u64(inputs.into_iter().cloned(), &mut buf, 0);
@@ -92,6 +92,98 @@ pub fn pack_u64<I: Iterator<Item = u64>>(stream: I,
     Ok(off)
 }
 
+/// The NibblePack encoding mode.  Standard mode encodes each all-zero group of 8 as a single zero bitmask byte.
+/// ZeroRuns mode additionally encodes runs of 2 to 256 all-zero groups as a 2-byte marker: a byte containing
+/// (number of groups - 1), followed by the `ZERO_RUN_FLAG` nibble word, which can never occur in Standard mode
+/// since it implies 16 nibbles plus 15 trailing zero nibbles.
+/// ZeroRuns is never larger than Standard, and much smaller for sparse data such as counters.
+/// The two modes are not compatible: the decoder must know which mode was used to encode, so the mode must be
+/// recorded elsewhere, for example in the section type.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NibblePackMode {
+    Standard,
+    ZeroRuns,
+}
+
+/// Nibble word byte marking a run of zero groups in ZeroRuns mode.
+pub const ZERO_RUN_FLAG: u8 = 0xff;
+
+const MAX_ZERO_RUN: usize = 256;
+
+/// Packs a stream of u64's like `pack_u64`, but using the given NibblePackMode.
+pub fn pack_u64_with_mode<I: Iterator<Item = u64>>(stream: I,
+                                                   out_buffer: &mut [u8],
+                                                   offset: usize,
+                                                   mode: NibblePackMode) -> Result<usize, CodingError> {
+    match mode {
+        NibblePackMode::Standard => pack_u64(stream, out_buffer, offset),
+        NibblePackMode::ZeroRuns => pack_u64_zero_runs(stream, out_buffer, offset),
+    }
+}
+
+/// Packs a stream of u64's using NibblePackMode::ZeroRuns.  Like pack_u64, pads with zeroes to a multiple of 8.
+pub fn pack_u64_zero_runs<I: Iterator<Item = u64>>(stream: I,
+                                                   out_buffer: &mut [u8],
+                                                   offset: usize) -> Result<usize, CodingError> {
+    let mut in_buffer = [0u64; 8];
+    let mut bufindex = 0;
+    let mut zero_groups = 0;
+    let mut off = offset;
+    for num in stream {
+        in_buffer[bufindex] = num;
+        bufindex += 1;
+        if bufindex >= 8 {
+            if in_buffer.iter().all(|&x| x == 0) {
+                zero_groups += 1;
+                if zero_groups == MAX_ZERO_RUN {
+                    off = write_zero_run(zero_groups, out_buffer, off)?;
+                    zero_groups = 0;
+                }
+            } else {
+                off = write_zero_run(zero_groups, out_buffer, off)?;
+                zero_groups = 0;
+                off = nibble_pack8(&in_buffer, out_buffer, off)?;
+            }
+            bufindex = 0;
+        }
+    }
+    off = write_zero_run(zero_groups, out_buffer, off)?;
+    if bufindex > 0 {
+        for i in bufindex..8 { in_buffer[i] = 0; }
+        off = nibble_pack8(&in_buffer, out_buffer, off)?;
+    }
+    Ok(off)
+}
+
+#[inline]
+fn write_zero_run(num_groups: usize, out_buffer: &mut [u8], offset: usize) -> Result<usize, CodingError> {
+    match num_groups {
+        0 => Ok(offset),
+        1 => {
+            if offset >= out_buffer.len() { return Err(CodingError::NotEnoughSpace) }
+            out_buffer[offset] = 0;
+            Ok(offset + 1)
+        }
+        n => {
+            if (offset + 1) >= out_buffer.len() { return Err(CodingError::NotEnoughSpace) }
+            out_buffer[offset] = (n - 1) as u8;
+            out_buffer[offset + 1] = ZERO_RUN_FLAG;
+            Ok(offset + 2)
+        }
+    }
+}
+
+/// If the encoded ZeroRuns-mode input starts with a zero run marker, returns the number of all-zero groups
+/// of 8 in the run.  Always returns None for a single zero group, which is encoded as in Standard mode.
+#[inline]
+pub fn zero_run_groups(inbuf: &[u8]) -> Option<usize> {
+    if inbuf.len() >= 2 && inbuf[0] != 0 && inbuf[1] == ZERO_RUN_FLAG {
+        Some(inbuf[0] as usize + 1)
+    } else {
+        None
+    }
+}
+
 ///
 /// NibblePacking is an encoding technique for packing 8 u64's tightly into the same number of nibbles.
 /// It can be combined with a prediction algorithm to efficiency encode floats and long values.
@@ -327,7 +419,7 @@ impl Sink<u64x8> for DoubleXorSink {
 /// A sink that converts u32x8 output from SIMD 32-bit unpacker to 64-bit
 // TODO: figure out right place for this?
 #[derive(Debug)]
-struct U32ToU64Sink<'a, S: Sink<u64x8>> {
+pub(crate) struct U32ToU64Sink<'a, S: Sink<u64x8>> {
     u64sink: &'a mut S
 }
 
@@ -350,6 +442,11 @@ impl<'a, S: Sink<u64x8>> Sink<u32x8> for U32ToU64Sink<'a, S> {
     }
 
     fn reset(&mut self) {}
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.u64sink.is_done()
+    }
 }
 
 /// Unpacks num_values values from an encoded buffer, by calling nibble_unpack8 enough times.
@@ -376,6 +473,70 @@ where Output: Sink<u64x8> {
     Ok(inbuf)
 }
 
+/// Unpacks num_values values encoded in the given NibblePackMode.  For ZeroRuns, runs of zero groups result
+/// in calls to output.process_zeroes(), once for every 8 zeroes.
+/// InvalidFormat is returned if a zero run extends beyond num_values.
+pub fn unpack_with_mode<'a, Output>(
+    encoded: &'a [u8],
+    output: &mut Output,
+    num_values: usize,
+    mode: NibblePackMode,
+) -> Result<&'a [u8], CodingError>
+where Output: Sink<u64x8> {
+    if mode == NibblePackMode::Standard { return unpack(encoded, output, num_values) }
+
+    let mut groups_left = (num_values + 7) / 8;
+    let mut inbuf = encoded;
+    while groups_left > 0 {
+        if let Some(run) = zero_run_groups(inbuf) {
+            if run > groups_left {
+                return Err(CodingError::InvalidFormat(format!("Zero run of {} groups exceeds {} left",
+                                                              run, groups_left)));
+            }
+            for _ in 0..run { output.process_zeroes(); }
+            groups_left -= run;
+            inbuf = &inbuf[2..];
+        } else {
+            inbuf = nibble_unpack8(inbuf, output)?;
+            groups_left -= 1;
+        }
+    }
+    Ok(inbuf)
+}
+
+/// Returns the number of significant bits of the largest of num_values NibblePacked values, reading only the
+/// two header bytes of each group and skipping the packed nibbles.  Every value is less than 2^bits, which
+/// makes this a cheap upper bound for previews.
+pub fn max_bits_with_mode(encoded: &[u8], num_values: usize, mode: NibblePackMode) -> Result<u32, CodingError> {
+    let mut groups_left = (num_values + 7) / 8;
+    let mut inbuf = encoded;
+    let mut max_bits = 0;
+    while groups_left > 0 {
+        if let (NibblePackMode::ZeroRuns, Some(run)) = (mode, zero_run_groups(inbuf)) {
+            if run > groups_left {
+                return Err(CodingError::InvalidFormat(format!("Zero run of {} groups exceeds {} left",
+                                                              run, groups_left)));
+            }
+            groups_left -= run;
+            inbuf = &inbuf[2..];
+            continue;
+        }
+        let nonzero_mask = *inbuf.first().ok_or(CodingError::InputTooShort)?;
+        if nonzero_mask == 0 {
+            inbuf = &inbuf[1..];
+        } else {
+            let bits_byte = *inbuf.get(1).ok_or(CodingError::InputTooShort)?;
+            let num_bits = ((bits_byte >> 4) as u32 + 1) * 4;
+            let trailing_zeros = (bits_byte & 0x0f) as u32 * 4;
+            max_bits = max_bits.max(num_bits + trailing_zeros);
+            let total_bytes = 2 + (num_bits * nonzero_mask.count_ones() + 7) / 8;
+            inbuf = inbuf.get(total_bytes as usize..).ok_or(CodingError::InputTooShort)?;
+        }
+        groups_left -= 1;
+    }
+    Ok(max_bits)
+}
+
 /// Unpacks a buffer encoded with [`pack_f64_xor`]: #method.pack_f64_xor
 ///
 /// This wraps unpack() method with a read of the initial f64 value. InputTooShort error is returned
@@ -383,7 +544,7 @@ where Output: Sink<u64x8> {
 /// NOTE: the sink is automatically cleared at the beginning.
 ///
 /// ```
-/// # use compressed_vec::nibblepacking;
+/// # use compressed_vec::raw::nibblepacking;
 /// # let encoded = [0xffu8; 16];
 ///     let mut out = Vec::<f64>::with_capacity(64);
 ///     let mut sink = nibblepacking::DoubleXorSink::new(out);
@@ -719,6 +880,43 @@ fn pack_unpack_f64_xor() {
     assert_eq!(sink.vec[..inputs.len()], inputs);
 }
 
+#[test]
+fn pack_unpack_u64_zero_runs() {
+    // 1 nonzero group, 1 zero group, 40 zero groups, 300 zero groups (256 + 44), 1 nonzero partial group
+    let mut inputs = vec![1u64, 2, 3, 4, 5, 6, 7, 8];
+    inputs.resize(8 + 8 + 40 * 8, 0);
+    inputs.push(5);
+    inputs.resize(inputs.len() + 7 + 300 * 8, 0);
+    inputs.push(9);
+    let mut buf = [0u8; 1024];
+    let std_written = pack_u64_with_mode(inputs.iter().cloned(), &mut buf, 0, NibblePackMode::Standard).unwrap();
+    let written = pack_u64_with_mode(inputs.iter().cloned(), &mut buf, 0, NibblePackMode::ZeroRuns).unwrap();
+    assert!(written < std_written / 10);
+
+    let mut sink = VecSink::<u64>::new();
+    let res = unpack_with_mode(&buf, &mut sink, inputs.len(), NibblePackMode::ZeroRuns);
+    assert_eq!(res.unwrap().len(), buf.len() - written);
+    assert_eq!(sink.vec[..inputs.len()], inputs[..]);
+
+    // A zero run longer than the number of values is an error
+    let mut sink = VecSink::<u64>::new();
+    let res = unpack_with_mode(&buf, &mut sink, 24, NibblePackMode::ZeroRuns);
+    assert!(res.is_err());
+}
+
+#[test]
+fn max_bits_from_group_headers() {
+    // Trailing zeroes count towards the bits: 0x3000 has 14 significant bits, rounded up to 16
+    let inputs = [1u64, 0x3000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 7];
+    let mut buf = [0u8; 256];
+    for &mode in &[NibblePackMode::Standard, NibblePackMode::ZeroRuns] {
+        let written = pack_u64_with_mode(inputs.iter().cloned(), &mut buf, 0, mode).unwrap();
+        assert_eq!(max_bits_with_mode(&buf[..written], inputs.len(), mode).unwrap(), 16);
+        assert_eq!(max_bits_with_mode(&buf[..written], 8, mode).unwrap(), 16);
+        assert_eq!(max_bits_with_mode(&buf[..3], inputs.len(), mode), Err(CodingError::InputTooShort));
+    }
+}
+
 // NOTE: cfg(test) is needed so that proptest can just be a "dev-dependency" and not linked for final library
 // NOTE2: somehow cargo is happier when we put props tests in its own module
 #[cfg(test)]
@@ -783,5 +981,18 @@ mod props {
             let _res = unpack(&buf, &mut sink, input.len());
             assert_eq!(sink.sink.vec[..input.len()], input[..]);
         }
+
+        #[test]
+        fn prop_zero_runs_packing(input in proptest::collection::vec(arb_maybezero_nbits_u64(20, 0.97), 1..2048)) {
+            let mut buf = [0u8; 8192];
+            let std_written = pack_u64(input.iter().cloned(), &mut buf, 0).unwrap();
+            let written = pack_u64_zero_runs(input.iter().cloned(), &mut buf, 0).unwrap();
+            assert!(written <= std_written);
+
+            let mut sink = VecSink::<u64>::new();
+            let res = unpack_with_mode(&buf, &mut sink, input.len(), NibblePackMode::ZeroRuns);
+            assert_eq!(res.unwrap().len(), buf.len() - written);
+            assert_eq!(sink.vec[..input.len()], input[..]);
+        }
     }
 }
@@ -0,0 +1,177 @@
+/// The `pipeline` module is a small query API over one vector, which fuses filtering with decoding so that
+/// applications don't have to write their own section loops.  A pipeline scans a vector, keeps the elements
+/// matching all of its filters, and collects at most `take()` of them:
+/// ```
+/// # use compressed_vec::filter::{EqualsSink, RangeSink};
+/// # use compressed_vec::pipeline::Pipeline;
+/// # use compressed_vec::vector::*;
+///     let x = VectorU32Appender::try_new(1024).unwrap().encode_all((0..1000u32).map(|i| i % 10)).unwrap();
+///     let y = VectorU64Appender::try_new(1024).unwrap().encode_all((0..1000u64).map(|i| i * 3)).unwrap();
+///     let x = VectorReader::<u32>::try_new(&x[..]).unwrap();
+///     let y = VectorReader::<u64>::try_new(&y[..]).unwrap();
+///
+///     let found = Pipeline::scan(&x).filter(RangeSink::greater_than(6)).take(4).collect().unwrap();
+///     assert_eq!(found, vec![7, 8, 9, 7]);
+///
+///     // SELECT y WHERE x = 3 AND y > 1500, projecting y using a filter on x
+///     let ys = Pipeline::scan(&y).filter_by(x.filter_iter(EqualsSink::<u32>::new(&3)))
+///                                .filter(RangeSink::greater_than(1500)).collect_with_positions().unwrap();
+///     assert_eq!(ys[0], (503, 1509));
+///     assert_eq!(ys.len(), 50);
+/// ```
+/// Filters are ANDed together, and short-circuit like a `CombinedFilter`, so put the most selective first.
+/// Only sections with matching elements are decoded, and decoding stops once `take()` elements are found.
+/// Nulls are zeroes, as for `VectorReader::iterate()`.
+use packed_simd::u32x8;
+
+use crate::error::CodingError;
+use crate::filter::{for_each_match, CombinedFilter, Combine, SectFilterSink, SectionMask, SectionMasks};
+use crate::section::*;
+use crate::selection::first_n_mask;
+use crate::sink::{Section256Sink, Sink};
+use crate::vector::{BaseSubtypeMapping, VectorReader};
+
+
+/// A lazy scan of one vector with filters and a limit, evaluated by `collect()`, `collect_with_positions()`
+/// or `count()`
+pub struct Pipeline<'a, T: VectBase> {
+    reader: &'a VectorReader<'a, T>,
+    filters: CombinedFilter<'a>,
+    num_filters: usize,
+    limit: usize,
+}
+
+impl<'a, T> Pipeline<'a, T>
+where T: VectBase + BaseSubtypeMapping {
+    /// Starts a pipeline over all the elements of a vector
+    pub fn scan(reader: &'a VectorReader<'a, T>) -> Self {
+        Self { reader, filters: CombinedFilter::new(), num_filters: 0, limit: usize::MAX }
+    }
+
+    /// Keeps only the elements of the scanned vector matching the filter sink
+    pub fn filter<SF: SectFilterSink<T> + 'a>(self, sf: SF) -> Self {
+        let filter = self.reader.filter_iter(sf);
+        self.filter_by(filter)
+    }
+
+    /// Keeps only the elements whose bits are ON in the section masks, eg from a filter over another vector with
+    /// the same number of elements
+    pub fn filter_by<F: SectionMasks + 'a>(mut self, masks: F) -> Self {
+        self.filters.add(Combine::And, masks);
+        self.num_filters += 1;
+        self
+    }
+
+    /// Keeps at most the first n matching elements
+    pub fn take(mut self, n: usize) -> Self {
+        self.limit = self.limit.min(n);
+        self
+    }
+
+    /// Decodes the matching elements
+    pub fn collect(self) -> Result<Vec<T>, CodingError> {
+        let mut out = Vec::new();
+        self.for_each_match(|_, value| out.push(value))?;
+        Ok(out)
+    }
+
+    /// Decodes the matching elements, with the position of each in the vector
+    pub fn collect_with_positions(self) -> Result<Vec<(usize, T)>, CodingError> {
+        let mut out = Vec::new();
+        self.for_each_match(|pos, value| out.push((pos, value)))?;
+        Ok(out)
+    }
+
+    /// Counts the matching elements, up to the limit, without decoding the scanned vector
+    pub fn count(mut self) -> usize {
+        let mut count = 0;
+        let mut elems_left = self.reader.num_elements();
+        while elems_left > 0 && count < self.limit {
+            let sect_elems = elems_left.min(FIXED_LEN);
+            elems_left -= sect_elems;
+            match self.next_mask(sect_elems) {
+                Some(mask) => count += mask.count_ones().wrapping_sum() as usize,
+                None       => break,
+            }
+        }
+        count.min(self.limit)
+    }
+
+    // The mask of the next section, limited to its sect_elems elements, or None past the end of the filters
+    #[inline]
+    fn next_mask(&mut self, sect_elems: usize) -> Option<SectionMask> {
+        let mask = first_n_mask(sect_elems);
+        if self.num_filters == 0 { Some(mask) } else { self.filters.next().map(|m| m & mask) }
+    }
+
+    // Calls f with the position and value of each matching element in order, until the limit
+    fn for_each_match<F: FnMut(usize, T)>(mut self, mut f: F) -> Result<(), CodingError> {
+        let mut sink = Section256Sink::<T>::new();
+        let mut taken = 0;
+        let mut elems_left = self.reader.num_elements();
+        for (sect_num, sect) in self.reader.sect_iter().enumerate() {
+            if elems_left == 0 || taken >= self.limit { break }
+            let sect_elems = elems_left.min(FIXED_LEN);
+            elems_left -= sect_elems;
+            let mask = match self.next_mask(sect_elems) {
+                Some(mask) => mask,
+                None       => break,
+            };
+            if mask == u32x8::splat(0) { continue }
+
+            sink.reset();
+            sect?.decode(&mut sink)?;
+            let limit = self.limit;
+            for_each_match(std::iter::once(mask), |i| {
+                if taken < limit {
+                    f(sect_num * FIXED_LEN + i, sink.values[i]);
+                    taken += 1;
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{EqualsSink, OneOfSink};
+    use crate::vector::{VectorU32Appender, VectorU64Appender};
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_pipeline_filter_take_collect() {
+        // Constant, null and partial sections
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..256u64).for_each(|_| appender.append(4).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..300u64).for_each(|i| appender.append(i % 7).unwrap());
+        let x = appender.finish(812).unwrap();
+        let x = VectorReader::<u64>::try_new(&x[..]).unwrap();
+        let all: Vec<u64> = x.iterate().collect();
+
+        assert_eq!(Pipeline::scan(&x).collect().unwrap(), all);
+        assert_eq!(Pipeline::scan(&x).take(3).collect().unwrap(), vec![4, 4, 4]);
+        assert_eq!(Pipeline::scan(&x).count(), 812);
+
+        // Nulls are zeroes, and padding after the last element is never matched
+        let zeroes = Pipeline::scan(&x).filter(EqualsSink::<u64>::new(&0)).collect_with_positions().unwrap();
+        let expected: Vec<(usize, u64)> = all.iter().cloned().enumerate().filter(|&(_, v)| v == 0).collect();
+        assert_eq!(zeroes, expected);
+        assert_eq!(Pipeline::scan(&x).filter(EqualsSink::<u64>::new(&0)).count(), expected.len());
+        assert_eq!(Pipeline::scan(&x).filter(EqualsSink::<u64>::new(&0)).take(100).count(), 100);
+
+        // Filters over the scanned vector and another one are ANDed
+        let keys = VectorU32Appender::try_new(4096).unwrap().encode_all((0..812u32).map(|i| i % 2)).unwrap();
+        let keys = VectorReader::<u32>::try_new(&keys[..]).unwrap();
+        let found = Pipeline::scan(&x).filter(OneOfSink::<u64>::new(&smallvec![4, 5]))
+                                      .filter_by(keys.filter_iter(EqualsSink::<u32>::new(&1)))
+                                      .take(150).collect_with_positions().unwrap();
+        let expected: Vec<(usize, u64)> = all.iter().cloned().enumerate()
+                                             .filter(|&(i, v)| (v == 4 || v == 5) && i % 2 == 1).take(150).collect();
+        assert_eq!(found, expected);
+        assert_eq!((found.len(), found[0]), (150, (1, 4)));
+    }
+}
@@ -0,0 +1,115 @@
+/// The `prom` module converts batches of Prometheus remote-write samples for one series into a pair of
+/// encoded vectors, and back.  Timestamps (milliseconds since the epoch) are stored in a u64 vector, where the
+/// `AutoEncoder` picks delta encoding relative to each section's minimum timestamp, and values are stored in an
+/// XOR-encoded f64 vector.  Stale markers and other NaN payloads are preserved bit for bit.
+/// ```
+/// # use compressed_vec::prom::*;
+///     let samples: Vec<Sample> = (0..300).map(|i| Sample::new(1_600_000_000_000 + i * 15_000, i as f64 * 0.5))
+///                                        .collect();
+///     let mut encoder = SampleEncoder::try_new(4096).unwrap();
+///     let series = encoder.encode(&samples[..]).unwrap();
+///     assert_eq!(decode_samples(&series.timestamps[..], &series.values[..]).unwrap(), samples);
+/// ```
+use crate::error::CodingError;
+use crate::vector::{VectorF64XorAppender, VectorReader, VectorU64Appender};
+
+
+/// A single sample of a series, as found in a remote-write TimeSeries
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sample {
+    pub timestamp_ms: i64,
+    pub value: f64,
+}
+
+impl Sample {
+    pub fn new(timestamp_ms: i64, value: f64) -> Self {
+        Self { timestamp_ms, value }
+    }
+}
+
+/// The encoded timestamp and value vectors of a batch of samples
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedSeries {
+    pub timestamps: Vec<u8>,
+    pub values: Vec<u8>,
+}
+
+/// Encodes batches of samples.  The appenders are reused between batches, so keep one encoder around
+/// when encoding many series.
+pub struct SampleEncoder {
+    ts_appender: VectorU64Appender,
+    value_appender: VectorF64XorAppender,
+}
+
+impl SampleEncoder {
+    /// Creates a new encoder.  initial_capacity is the initial number of bytes allocated for each vector.
+    pub fn try_new(initial_capacity: usize) -> Result<Self, CodingError> {
+        Ok(Self {
+            ts_appender: VectorU64Appender::try_new(initial_capacity)?,
+            value_appender: VectorF64XorAppender::try_new(initial_capacity)?,
+        })
+    }
+
+    /// Encodes a batch of samples into timestamp and value vectors.
+    /// Timestamps are stored as their u64 bit patterns, so negative timestamps also round trip.
+    pub fn encode(&mut self, samples: &[Sample]) -> Result<EncodedSeries, CodingError> {
+        let timestamps = self.ts_appender.encode_all(samples.iter().map(|s| s.timestamp_ms as u64))?;
+        let values = self.value_appender.encode_all(samples.iter().map(|s| s.value))?;
+        Ok(EncodedSeries { timestamps, values })
+    }
+}
+
+/// Decodes timestamp and value vectors written by `SampleEncoder` back into samples.
+/// Returns InvalidNumRows if the two vectors have a different number of elements.
+pub fn decode_samples(ts_bytes: &[u8], value_bytes: &[u8]) -> Result<Vec<Sample>, CodingError> {
+    let ts_reader = VectorReader::<u64>::try_new(ts_bytes)?;
+    let value_reader = VectorReader::<f64>::try_new(value_bytes)?;
+    if ts_reader.num_elements() != value_reader.num_elements() {
+        return Err(CodingError::InvalidNumRows(value_reader.num_elements(), ts_reader.num_elements()));
+    }
+    Ok(ts_reader.iterate().zip(value_reader.iterate())
+                .map(|(ts, value)| Sample::new(ts as i64, value))
+                .collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_samples() {
+        let mut samples: Vec<Sample> = (0..600).map(|i| Sample::new(1_600_000_000_000 + i * 15_000 + i % 3,
+                                                                    100.0 + (i / 7) as f64))
+                                               .collect();
+        // Stale marker NaN must survive with its exact bits
+        samples[300].value = f64::from_bits(0x7ff0_0000_0000_0002);
+
+        let mut encoder = SampleEncoder::try_new(8192).unwrap();
+        let series = encoder.encode(&samples[..]).unwrap();
+        // Regular timestamps and slowly changing values should compress well
+        assert!(series.timestamps.len() < 600 * 4);
+        assert!(series.values.len() < 600 * 4);
+
+        let decoded = decode_samples(&series.timestamps[..], &series.values[..]).unwrap();
+        assert_eq!(decoded.len(), samples.len());
+        assert_eq!(decoded[300].value.to_bits(), 0x7ff0_0000_0000_0002);
+        assert_eq!(decoded[299], samples[299]);
+        assert_eq!(decoded[..300], samples[..300]);
+        assert_eq!(decoded[301..], samples[301..]);
+
+        // Encoder can be reused, and an empty batch round trips
+        let empty = encoder.encode(&[]).unwrap();
+        assert_eq!(decode_samples(&empty.timestamps[..], &empty.values[..]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_decode_mismatched_lengths() {
+        let mut encoder = SampleEncoder::try_new(4096).unwrap();
+        let samples: Vec<Sample> = (0..10).map(|i| Sample::new(i * 1000, 1.0)).collect();
+        let long = encoder.encode(&samples[..]).unwrap();
+        let short = encoder.encode(&samples[..5]).unwrap();
+        assert_eq!(decode_samples(&long.timestamps[..], &short.values[..]),
+                   Err(CodingError::InvalidNumRows(5, 10)));
+    }
+}
@@ -0,0 +1,11 @@
+//! The `raw` module exports the internals of the vector format: section types and encoders, and the
+//! NibblePacking kernels.  They are for tools which work on individual sections, such as format inspectors,
+//! fuzzers and custom encoders.
+//!
+//! **This module is exempt from semver.**  It changes whenever the format gains new section types or
+//! encodings, including in minor releases.  Use the types exported at the crate root where possible.
+pub use crate::section;
+pub use crate::nibblepacking;
+pub use crate::nibblepack_simd;
+pub use crate::byteutils;
+pub use crate::lz4;
@@ -0,0 +1,524 @@
+/// The `reference` module contains a pure safe, scalar reference decoder for all FixedSection types.
+/// It is much slower than the SIMD decoders, but it is simple, bounds-checked everywhere, and written
+/// directly from the format description in `vector_format.md`, so it is useful as an oracle:
+/// - `VectorReader::decode_checked()` decodes a whole vector using only the reference decoder
+/// - `VectorReader::cross_check()` (debug and test builds only) decodes every section using both the SIMD
+///   decoders and the reference decoder, and panics on the first divergence.  Run it over a corpus of
+///   vectors when porting the SIMD kernels to a new ISA.
+/// ```
+/// # use compressed_vec::vector::{VectorU32Appender, VectorReader};
+///     let mut appender = VectorU32Appender::try_new(1024).unwrap();
+///     let bytes = appender.encode_all((0..300u32).map(|x| x * 3)).unwrap();
+///     let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+///     assert_eq!(reader.decode_checked().unwrap(), reader.iterate().collect::<Vec<u32>>());
+///     reader.cross_check().unwrap();
+/// ```
+use std::convert::TryFrom;
+
+use scroll::{Pread, LE};
+
+use crate::error::CodingError;
+use crate::nibblepacking::zero_run_groups;
+use crate::section::{SectionType, VectBase, FIXED_LEN};
+
+/// Conversions between base types and the raw bits used by the reference decoder
+pub trait RefBits: VectBase {
+    /// Width of the type in bits
+    const BITS: u32;
+
+    /// True if sections are XOR encoded floating point bits
+    const IS_FLOAT: bool;
+
+    fn from_bits(bits: u64) -> Self;
+
+    fn to_bits(self) -> u64;
+}
+
+impl RefBits for u32 {
+    const BITS: u32 = 32;
+    const IS_FLOAT: bool = false;
+    fn from_bits(bits: u64) -> u32 { bits as u32 }
+    fn to_bits(self) -> u64 { self as u64 }
+}
+
+impl RefBits for u64 {
+    const BITS: u32 = 64;
+    const IS_FLOAT: bool = false;
+    fn from_bits(bits: u64) -> u64 { bits }
+    fn to_bits(self) -> u64 { self }
+}
+
+impl RefBits for u16 {
+    const BITS: u32 = 16;
+    const IS_FLOAT: bool = false;
+    fn from_bits(bits: u64) -> u16 { bits as u16 }
+    fn to_bits(self) -> u64 { self as u64 }
+}
+
+impl RefBits for u8 {
+    const BITS: u32 = 8;
+    const IS_FLOAT: bool = false;
+    fn from_bits(bits: u64) -> u8 { bits as u8 }
+    fn to_bits(self) -> u64 { self as u64 }
+}
+
+impl RefBits for f32 {
+    const BITS: u32 = 32;
+    const IS_FLOAT: bool = true;
+    fn from_bits(bits: u64) -> f32 { f32::from_bits(bits as u32) }
+    fn to_bits(self) -> u64 { f32::to_bits(self) as u64 }
+}
+
+impl RefBits for f64 {
+    const BITS: u32 = 64;
+    const IS_FLOAT: bool = true;
+    fn from_bits(bits: u64) -> f64 { f64::from_bits(bits) }
+    fn to_bits(self) -> u64 { f64::to_bits(self) }
+}
+
+fn byte_at(buf: &[u8], pos: usize) -> Result<u8, CodingError> {
+    buf.get(pos).copied().ok_or(CodingError::InputTooShort)
+}
+
+/// Decodes a single NibblePacked group of 8 from the start of inbuf, one bit at a time.
+/// Returns the number of bytes consumed.
+pub fn nibble_unpack8_ref(inbuf: &[u8], out: &mut [u64; 8]) -> Result<usize, CodingError> {
+    let nonzero_mask = byte_at(inbuf, 0)?;
+    *out = [0u64; 8];
+    if nonzero_mask == 0 { return Ok(1) }
+
+    let nibble_word = byte_at(inbuf, 1)?;
+    let num_bits = ((nibble_word >> 4) as u32 + 1) * 4;
+    let trailing_zeros = (nibble_word & 0x0f) as u32 * 4;
+    if num_bits + trailing_zeros > 64 {
+        return Err(CodingError::InvalidFormat(format!("Nibble word {:#x} exceeds 64 bits", nibble_word)));
+    }
+
+    let mut bit_pos = 0usize;
+    for (i, value) in out.iter_mut().enumerate() {
+        if nonzero_mask & (1 << i) != 0 {
+            let mut bits = 0u64;
+            for b in 0..num_bits as usize {
+                let byte = byte_at(inbuf, 2 + (bit_pos + b) / 8)?;
+                bits |= (((byte >> ((bit_pos + b) % 8)) & 1) as u64) << b;
+            }
+            *value = bits << trailing_zeros;
+            bit_pos += num_bits as usize;
+        }
+    }
+    Ok(2 + (bit_pos + 7) / 8)
+}
+
+// Decodes FIXED_LEN NibblePacked values, optionally in zero runs mode
+fn unpack_section_ref(inbuf: &[u8], zero_runs: bool, out: &mut [u64; FIXED_LEN]) -> Result<(), CodingError> {
+    let mut pos = 0;
+    let mut group = 0;
+    let mut octet = [0u64; 8];
+    while group < FIXED_LEN / 8 {
+        let rest = inbuf.get(pos..).ok_or(CodingError::InputTooShort)?;
+        match zero_run_groups(rest).filter(|_| zero_runs) {
+            Some(run) => {
+                if group + run > FIXED_LEN / 8 {
+                    return Err(CodingError::InvalidFormat(format!("Zero run of {} groups too long", run)));
+                }
+                out[group * 8..(group + run) * 8].iter_mut().for_each(|x| *x = 0);
+                group += run;
+                pos += 2;
+            }
+            None => {
+                pos += nibble_unpack8_ref(rest, &mut octet)?;
+                out[group * 8..group * 8 + 8].copy_from_slice(&octet);
+                group += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Decompresses an LZ4 block, one sequence at a time.  Stops with an error past max_len bytes.
+fn lz4_block_ref(block: &[u8], max_len: usize) -> Result<Vec<u8>, CodingError> {
+    let read_length = |pos: &mut usize, mut len: usize| -> Result<usize, CodingError> {
+        loop {
+            let byte = byte_at(block, *pos)?;
+            *pos += 1;
+            len += byte as usize;
+            if byte != 255 { return Ok(len) }
+        }
+    };
+    let mut out = Vec::new();
+    let mut pos = 0;
+    loop {
+        let token = byte_at(block, pos)?;
+        pos += 1;
+        let num_literals = if token >> 4 == 15 { read_length(&mut pos, 15)? } else { (token >> 4) as usize };
+        out.extend_from_slice(block.get(pos..pos + num_literals).ok_or(CodingError::InputTooShort)?);
+        pos += num_literals;
+        if pos == block.len() { break }
+        let offset = byte_at(block, pos)? as usize | (byte_at(block, pos + 1)? as usize) << 8;
+        pos += 2;
+        let match_len = 4 + if token & 15 == 15 { read_length(&mut pos, 15)? } else { (token & 15) as usize };
+        if offset == 0 || offset > out.len() { return Err(CodingError::InvalidFormat("Bad LZ4 offset".into())) }
+        if out.len() + match_len > max_len { return Err(CodingError::InvalidFormat("LZ4 block too long".into())) }
+        for _ in 0..match_len {
+            out.push(out[out.len() - offset]);
+        }
+    }
+    if out.len() > max_len { return Err(CodingError::InvalidFormat("LZ4 block too long".into())) }
+    Ok(out)
+}
+
+fn read_len(sect_bytes: &[u8]) -> Result<usize, CodingError> {
+    let len: u16 = sect_bytes.pread_with(1, LE)?;
+    Ok(len as usize)
+}
+
+// Decodes the Gorilla XOR bit stream of an XorF64Medium section, one bit at a time.  bits[0] is the first value.
+fn xor_f64_stream_ref(stream: &[u8], bits: &mut [u64; FIXED_LEN]) -> Result<(), CodingError> {
+    let mut bit_pos = 0usize;
+    let mut read = |num_bits: u32| -> Result<u64, CodingError> {
+        let mut value = 0u64;
+        for _ in 0..num_bits {
+            let byte = byte_at(stream, bit_pos / 8)?;
+            value = (value << 1) | ((byte >> (7 - bit_pos % 8)) & 1) as u64;
+            bit_pos += 1;
+        }
+        Ok(value)
+    };
+    let (mut leading, mut meaningful) = (0u32, 64u32);
+    for i in 1..FIXED_LEN {
+        let mut xor = 0;
+        if read(1)? == 1 {
+            if read(1)? == 1 {
+                leading = read(5)? as u32;
+                meaningful = read(6)? as u32 + 1;
+                if leading + meaningful > 64 {
+                    return Err(CodingError::InvalidFormat("XorF64 window over 64 bits".into()));
+                }
+            }
+            xor = read(meaningful)? << (64 - leading - meaningful);
+        }
+        bits[i] = bits[i - 1] ^ xor;
+    }
+    Ok(())
+}
+
+// Decodes the delta-of-delta bit stream of a DeltaDeltaMedium section, one bit at a time.  bits[0] is the first
+// value and bits[1] - bits[0] the first delta.
+fn delta_delta_stream_ref(stream: &[u8], bits: &mut [u64; FIXED_LEN]) -> Result<(), CodingError> {
+    let mut pos = 0;
+    let mut read = |num_bits: u32| -> Result<u64, CodingError> {
+        let mut value = 0u64;
+        for _ in 0..num_bits {
+            let byte = byte_at(stream, pos / 8)?;
+            value = (value << 1) | ((byte >> (7 - pos % 8)) & 1) as u64;
+            pos += 1;
+        }
+        Ok(value)
+    };
+    let mut delta = bits[1].wrapping_sub(bits[0]);
+    for i in 2..FIXED_LEN {
+        let mut ones = 0;
+        while ones < 5 && read(1)? == 1 { ones += 1; }
+        let zigzag = match ones {
+            0 => 0,
+            1 => read(7)?,
+            2 => read(9)?,
+            3 => read(12)?,
+            4 => read(32)?,
+            _ => read(64)?,
+        };
+        let dod = ((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64);
+        delta = delta.wrapping_add(dod as u64);
+        bits[i] = bits[i - 1].wrapping_add(delta);
+    }
+    Ok(())
+}
+
+/// Decodes one section starting at the beginning of sect_bytes into out, using only the reference decoder.
+/// `prev` is the previously decoded section, needed for RepeatPrevious sections.
+/// Returns the number of bytes in the section.
+pub fn decode_section_ref<T: RefBits>(sect_bytes: &[u8],
+                                      prev: Option<&[T; FIXED_LEN]>,
+                                      out: &mut [T; FIXED_LEN]) -> Result<usize, CodingError> {
+    let sect_type = SectionType::try_from(byte_at(sect_bytes, 0)?)?;
+    let mut bits = [0u64; FIXED_LEN];
+    let num_bytes = match (sect_type, T::IS_FLOAT) {
+        (SectionType::Null, _) => 1,
+        (SectionType::RepeatPrevious, _) => {
+            let prev = prev.ok_or_else(|| CodingError::InvalidFormat("RepeatPrevious with no previous".into()))?;
+            out.copy_from_slice(&prev[..]);
+            return Ok(1);
+        }
+        (SectionType::Constant, _) => {
+            let width = (T::BITS / 8) as usize;
+            let mut value = 0u64;
+            for i in 0..width {
+                value |= (byte_at(sect_bytes, 1 + i)? as u64) << (i * 8);
+            }
+            bits = [value; FIXED_LEN];
+            1 + width
+        }
+        (SectionType::NibblePackedMedium, false) | (SectionType::NibblePackedZRMedium, false) => {
+            let len = read_len(sect_bytes)?;
+            let zero_runs = sect_type == SectionType::NibblePackedZRMedium;
+            unpack_section_ref(&sect_bytes[3..], zero_runs, &mut bits)?;
+            len + 3
+        }
+        (SectionType::DeltaNPMedium, false) => {
+            let len = read_len(sect_bytes)?;
+            let base: u64 = sect_bytes.pread_with(4, LE)?;
+            unpack_section_ref(sect_bytes.get(12..).ok_or(CodingError::InputTooShort)?, false, &mut bits)?;
+            bits.iter_mut().for_each(|x| *x = x.wrapping_add(base));
+            len + 12
+        }
+        (SectionType::MonoDeltaNPMedium, false) => {
+            let len = read_len(sect_bytes)?;
+            let mut sum: u64 = sect_bytes.pread_with(4, LE)?;
+            unpack_section_ref(sect_bytes.get(20..).ok_or(CodingError::InputTooShort)?, false, &mut bits)?;
+            for x in bits.iter_mut() {
+                sum = sum.wrapping_add(*x);
+                *x = sum;
+            }
+            len + 20
+        }
+        (SectionType::XorNPMedium, true) => {
+            let total_len = read_len(sect_bytes)?;
+            unpack_section_ref(&sect_bytes[3..], false, &mut bits)?;
+            for i in 8..FIXED_LEN {
+                bits[i] ^= bits[i - 8];
+            }
+            total_len
+        }
+        (SectionType::XorF64Medium, true) if T::BITS == 64 => {
+            let total_len = read_len(sect_bytes)?;
+            let stream = sect_bytes.get(11..total_len).ok_or(CodingError::InputTooShort)?;
+            bits[0] = sect_bytes.pread_with(3, LE)?;
+            xor_f64_stream_ref(stream, &mut bits)?;
+            total_len
+        }
+        (SectionType::DeltaDeltaMedium, false) if T::BITS == 64 => {
+            let total_len = read_len(sect_bytes)?;
+            let stream = sect_bytes.get(19..total_len).ok_or(CodingError::InputTooShort)?;
+            bits[0] = sect_bytes.pread_with(3, LE)?;
+            let first_delta: u64 = sect_bytes.pread_with(11, LE)?;
+            bits[1] = bits[0].wrapping_add(first_delta);
+            delta_delta_stream_ref(stream, &mut bits)?;
+            total_len
+        }
+        (SectionType::SparseExceptions, false) => {
+            let encoded_len = read_len(sect_bytes)?;
+            let num_exceptions = byte_at(sect_bytes, 3)? as usize;
+            let width = (T::BITS / 8) as usize;
+            let mut default = 0u64;
+            for i in 0..width {
+                default |= (byte_at(sect_bytes, 4 + i)? as u64) << (i * 8);
+            }
+            bits = [default; FIXED_LEN];
+            let indices_start = 4 + width;
+            let mut pos = indices_start + num_exceptions;
+            let mut group = [0u64; 8];
+            for j in 0..num_exceptions {
+                if j % 8 == 0 {
+                    pos += nibble_unpack8_ref(sect_bytes.get(pos..).ok_or(CodingError::InputTooShort)?, &mut group)?;
+                }
+                bits[byte_at(sect_bytes, indices_start + j)? as usize] = group[j % 8];
+            }
+            indices_start + num_exceptions + encoded_len
+        }
+        (SectionType::Simple8bMedium, false) => {
+            let num_words = read_len(sect_bytes)?;
+            let mut num_values = 0;
+            for w in 0..num_words {
+                let word: u64 = sect_bytes.pread_with(3 + w * 8, LE)?;
+                let selector = (word >> 60) as usize;
+                // Selectors 0 and 1 are runs of zeroes, the others fill 60 bits with values of one width
+                let (count, width) = match selector {
+                    0 => (240, 0),
+                    1 => (120, 0),
+                    _ => {
+                        let width = [1, 2, 3, 4, 5, 6, 7, 8, 10, 12, 15, 20, 30, 60][selector - 2];
+                        (60 / width, width)
+                    }
+                };
+                let mask = if width == 0 { 0 } else { !0u64 >> (64 - width) };
+                for i in 0..count {
+                    let out = bits.get_mut(num_values)
+                                  .ok_or_else(|| CodingError::InvalidFormat("Too many values".into()))?;
+                    *out = (word >> (i * width)) & mask;
+                    num_values += 1;
+                }
+            }
+            if num_values != FIXED_LEN { return Err(CodingError::InvalidFormat("Too few values".into())) }
+            3 + num_words * 8
+        }
+        (SectionType::DecimalF64Medium, true) if T::BITS == 64 => {
+            let len = read_len(sect_bytes)?;
+            let scale = byte_at(sect_bytes, 3)? as i32;
+            if scale > 15 { return Err(CodingError::InvalidFormat("Decimal scale too large".into())) }
+            let mut ints = [0u64; FIXED_LEN];
+            decode_section_ref(sect_bytes.get(4..).ok_or(CodingError::InputTooShort)?, None, &mut ints)?;
+            let divisor = format!("1e{}", scale).parse::<f64>().expect("Power of ten should parse");
+            for (b, &zigzag) in bits.iter_mut().zip(ints.iter()) {
+                let scaled = ((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64);
+                *b = (scaled as f64 / divisor).to_bits();
+            }
+            len + 4
+        }
+        (SectionType::Lz4Medium, _) => {
+            let len = read_len(sect_bytes)?;
+            let width = (T::BITS / 8) as usize;
+            let raw = lz4_block_ref(sect_bytes.get(3..3 + len).ok_or(CodingError::InputTooShort)?, FIXED_LEN * width)?;
+            if raw.len() != FIXED_LEN * width { return Err(CodingError::InvalidFormat("Too few values".into())) }
+            for (b, value_bytes) in bits.iter_mut().zip(raw.chunks(width)) {
+                *b = value_bytes.iter().rev().fold(0u64, |value, &byte| value << 8 | byte as u64);
+            }
+            3 + len
+        }
+        (SectionType::PartialNull, _) => {
+            let len = read_len(sect_bytes)?;
+            let values_bytes = sect_bytes.get(35..).ok_or(CodingError::InputTooShort)?;
+            if byte_at(values_bytes, 0)? == SectionType::PartialNull.as_num() {
+                return Err(CodingError::InvalidFormat("PartialNull sections cannot be nested".into()));
+            }
+            let mut packed = [T::zero(); FIXED_LEN];
+            decode_section_ref(values_bytes, None, &mut packed)?;
+            let mut next = 0;
+            for (i, b) in bits.iter_mut().enumerate() {
+                if byte_at(sect_bytes, 3 + i / 8)? & (1 << (i % 8)) != 0 {
+                    *b = packed[next].to_bits();
+                    next += 1;
+                }
+            }
+            len + 35
+        }
+        _ => return Err(CodingError::InvalidFormat(format!("Section {:?} invalid for type", sect_type))),
+    };
+
+    for (o, &b) in out.iter_mut().zip(bits.iter()) {
+        *o = T::from_bits(b);
+    }
+    Ok(num_bytes)
+}
+
+/// Decodes all the sections in encoded_bytes (the bytes following the vector header) using the reference
+/// decoder, returning the first num_elements values.
+pub fn decode_vector_ref<T: RefBits>(encoded_bytes: &[u8], num_elements: usize) -> Result<Vec<T>, CodingError> {
+    let mut result = Vec::with_capacity(num_elements + FIXED_LEN);
+    let mut values = [T::zero(); FIXED_LEN];
+    let mut prev = [T::zero(); FIXED_LEN];
+    let mut pos = 0;
+    let mut first = true;
+    while pos < encoded_bytes.len() && result.len() < num_elements {
+        pos += decode_section_ref(&encoded_bytes[pos..], if first { None } else { Some(&prev) }, &mut values)?;
+        result.extend_from_slice(&values[..]);
+        prev = values;
+        first = false;
+    }
+    if result.len() < num_elements { return Err(CodingError::InputTooShort) }
+    result.truncate(num_elements);
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nibblepacking;
+    use crate::section::*;
+    use crate::vector::{VectorAppender, VectorReader, VectorU32Appender, VectorU64Appender, VectorF32XorAppender,
+                        VectorF64GorillaAppender, VectorU64DeltaDeltaAppender, VectorU32Simple8bAppender,
+                        VectorF64DecimalAppender};
+
+    #[test]
+    fn test_nibble_unpack8_ref_matches_packer() {
+        let inputs = [0u64, 1000, 0, 0xffff_ffff_ffff, 5, 0, 0, 1 << 63];
+        let mut buf = [0u8; 128];
+        let written = nibblepacking::nibble_pack8(&inputs, &mut buf, 0).unwrap();
+        let mut out = [0u64; 8];
+        assert_eq!(nibble_unpack8_ref(&buf[..written], &mut out).unwrap(), written);
+        assert_eq!(out, inputs);
+
+        // Truncated input is an error, not a panic
+        assert_eq!(nibble_unpack8_ref(&buf[..written - 1], &mut out), Err(CodingError::InputTooShort));
+    }
+
+    #[test]
+    fn test_reference_matches_simd_all_section_types() {
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        appender.dedup_sections(true);
+        (0..256u64).for_each(|i| appender.append(i * i).unwrap());                    // NibblePack
+        (0..256u64).for_each(|_| appender.append(777).unwrap());                      // Constant
+        (0..256u64).for_each(|i| appender.append(if i % 20 == 7 { i << 40 } else { 5 }).unwrap()); // Sparse
+        (0..256u64).for_each(|i| appender.append(1_590_000_000_000 + i).unwrap());   // Monotonic delta
+        (0..256u64).for_each(|i| appender.append(1_590_000_000_000 + i).unwrap());   // Repeat
+        appender.append_nulls(300).unwrap();
+        let bytes = appender.finish(1656).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.decode_checked().unwrap(), reader.iterate().collect::<Vec<u64>>());
+        reader.cross_check().unwrap();
+
+        let mut appender = VectorAppender::<u32, NibblePackZRMedFixedSect<u32>>::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..600u32).map(|i| if i % 97 == 0 { i } else { 0 })).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.decode_checked().unwrap(), reader.iterate().collect::<Vec<u32>>());
+        reader.cross_check().unwrap();
+
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..600u32).map(|i| i.wrapping_mul(2_654_435_761))).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.decode_checked().unwrap(), reader.iterate().collect::<Vec<u32>>());
+        reader.cross_check().unwrap();
+
+        let mut appender = VectorF32XorAppender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..600).map(|i| i as f32 / 3.0)).unwrap();
+        let reader = VectorReader::<f32>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.decode_checked().unwrap(), reader.iterate().collect::<Vec<f32>>());
+        reader.cross_check().unwrap();
+
+        let mut appender = VectorF64GorillaAppender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..600).map(|i| 20.0 + (i / 7) as f64 * 0.25)).unwrap();
+        let reader = VectorReader::<f64>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.decode_checked().unwrap(), reader.iterate().collect::<Vec<f64>>());
+        reader.cross_check().unwrap();
+
+        let mut appender = VectorU32Simple8bAppender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..600u32).map(|i| if i % 3 == 0 { i % 7 } else { i * 4099 % 61 })).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert!(reader.sect_iter().any(|s| s.unwrap().sect_type() == SectionType::Simple8bMedium));
+        assert_eq!(reader.decode_checked().unwrap(), reader.iterate().collect::<Vec<u32>>());
+        reader.cross_check().unwrap();
+
+        let mut appender = VectorF64DecimalAppender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..600).map(|i| ((i * 37 % 1000) as f64 - 300.0) / 100.0)).unwrap();
+        let reader = VectorReader::<f64>::try_new(&bytes[..]).unwrap();
+        assert!(reader.sect_iter().all(|s| s.unwrap().sect_type() == SectionType::DecimalF64Medium));
+        assert_eq!(reader.decode_checked().unwrap(), reader.iterate().collect::<Vec<f64>>());
+        reader.cross_check().unwrap();
+
+        #[cfg(feature = "lz4")]
+        {
+            let mut appender = crate::vector::VectorU64Lz4Appender::try_new(8192).unwrap();
+            let bytes = appender.encode_all((0..600u64).map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15))).unwrap();
+            let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+            assert!(reader.sect_iter().any(|s| s.unwrap().sect_type() == SectionType::Lz4Medium));
+            assert_eq!(reader.decode_checked().unwrap(), reader.iterate().collect::<Vec<u64>>());
+            reader.cross_check().unwrap();
+        }
+
+        let mut appender = VectorU64DeltaDeltaAppender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..600u64).map(|i| 1_590_000_000_000 + i * 15_000 + (i % 11) * 3)).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.decode_checked().unwrap(), reader.iterate().collect::<Vec<u64>>());
+        reader.cross_check().unwrap();
+    }
+
+    #[test]
+    fn test_reference_rejects_invalid_sections() {
+        let mut out = [0u32; FIXED_LEN];
+        assert_eq!(decode_section_ref::<u32>(&[SectionType::RepeatPrevious.as_num()], None, &mut out),
+                   Err(CodingError::InvalidFormat("RepeatPrevious with no previous".into())));
+        assert_eq!(decode_section_ref::<u32>(&[42], None, &mut out), Err(CodingError::InvalidSectionType(42)));
+        // NibblePack section whose data is cut short
+        assert_eq!(decode_section_ref::<u32>(&[1, 2, 0, 0xff], None, &mut out), Err(CodingError::InputTooShort));
+    }
+}
@@ -0,0 +1,136 @@
+/// The `scanner` module is the entry point for external query engines, such as DataFusion, which push predicates
+/// down into vectors.  A `SectionScanner` exposes the metadata of each section, read from the section headers
+/// without decoding, and decodes only the sections the engine selects with a mask:
+/// ```
+/// # use compressed_vec::scanner::SectionScanner;
+/// # use compressed_vec::vector::*;
+/// # use packed_simd::u32x8;
+///     let mut appender = VectorU64Appender::try_new(4096).unwrap();
+///     appender.append_nulls(256).unwrap();
+///     (0..256u64).for_each(|_| appender.append(5).unwrap());
+///     (0..1000u64).for_each(|i| appender.append(i * 10).unwrap());
+///     let bytes = appender.finish(1512).unwrap();
+///     let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+///
+///     // WHERE x >= 9000: skip sections whose bounds rule it out, and check the values of the others
+///     let infos = reader.section_infos().unwrap();
+///     let mut masks = infos.iter().map(|info| match info.bounds {
+///         Some((_, max)) if max < 9000 => u32x8::splat(0),
+///         _                            => u32x8::splat(u32::max_value()),
+///     });
+///     let mut matches = Vec::new();
+///     reader.visit_with_masks(&mut masks, &mut |sect_num, mask, values| {
+///         for (i, &v) in values.iter().enumerate() {
+///             if mask.extract(i / 32) & (1 << (i % 32)) != 0 && v >= 9000 { matches.push(sect_num * 256 + i); }
+///         }
+///     }).unwrap();
+///     assert_eq!(matches.len(), 100);
+/// ```
+/// The trait is object safe, so an engine can hold scanners of different element types or vector kinds behind
+/// `Box<dyn SectionScanner<T>>`.
+use num::NumCast;
+
+use crate::error::CodingError;
+use crate::filter::SectionMask;
+use crate::section::*;
+use crate::selection::first_n_mask;
+use crate::sink::{Section256Sink, Sink};
+use crate::vector::{BaseSubtypeMapping, VectorReader};
+
+
+/// The metadata of one section, from its header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SectionInfo<T> {
+    pub sect_num: usize,
+    pub sect_type: SectionType,
+    pub num_elements: usize,           // Elements of the vector in this section, FIXED_LEN except for the last
+    pub bounds: Option<(T, T)>,        // Inclusive bounds on the values, if known without decoding
+    pub may_have_nulls: bool,          // True for PartialNull sections, which hold tracked nulls
+}
+
+/// Per-section access to a vector for predicate pushdown: metadata to prune sections, and decoding of only the
+/// selected ones.
+pub trait SectionScanner<T: VectBase> {
+    /// The metadata of every section, in order.  Bounds are exact for null and constant sections; see
+    /// `LazySection::bounds()` for the other section types with bounds.  Nulls are zeroes in the bounds.
+    fn section_infos(&self) -> Result<Vec<SectionInfo<T>>, CodingError>;
+
+    /// Decodes each section whose mask has any bit ON, and calls visit with the section number, the mask limited
+    /// to the elements of the vector, and the FIXED_LEN decoded values of the section.  Sections with empty masks
+    /// are skipped without decoding, and the scan stops when the masks run out.
+    fn visit_with_masks(&self,
+                        masks: &mut dyn Iterator<Item = SectionMask>,
+                        visit: &mut dyn FnMut(usize, SectionMask, &[T])) -> Result<(), CodingError>;
+}
+
+impl<'buf, T> SectionScanner<T> for VectorReader<'buf, T>
+where T: VectBase + NumCast + BaseSubtypeMapping {
+    fn section_infos(&self) -> Result<Vec<SectionInfo<T>>, CodingError> {
+        let mut elems_left = self.num_elements();
+        let num_sects = (elems_left + FIXED_LEN - 1) / FIXED_LEN;
+        self.lazy_sections().enumerate().take(num_sects).map(|(sect_num, sect)| {
+            let sect = sect?;
+            let num_elements = elems_left.min(FIXED_LEN);
+            elems_left -= num_elements;
+            let sect_type = sect.sect_type();
+            Ok(SectionInfo {
+                sect_num,
+                sect_type,
+                num_elements,
+                bounds: sect.bounds(),
+                may_have_nulls: sect_type == SectionType::PartialNull,
+            })
+        }).collect()
+    }
+
+    fn visit_with_masks(&self,
+                        masks: &mut dyn Iterator<Item = SectionMask>,
+                        visit: &mut dyn FnMut(usize, SectionMask, &[T])) -> Result<(), CodingError> {
+        let mut sink = Section256Sink::<T>::new();
+        let mut elems_left = self.num_elements();
+        for ((sect_num, sect), mask) in self.sect_iter().enumerate().zip(masks) {
+            if elems_left == 0 { break }
+            let sect_elems = elems_left.min(FIXED_LEN);
+            elems_left -= sect_elems;
+            let mask = mask & first_n_mask(sect_elems);
+            if mask.count_ones().wrapping_sum() == 0 { continue }
+            sink.reset();
+            sect?.decode(&mut sink)?;
+            visit(sect_num, mask, &sink.values[..]);
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::VectorU32Appender;
+    use packed_simd::u32x8;
+
+    #[test]
+    fn test_section_infos_and_visit() {
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        (0..256u32).for_each(|_| appender.append(9).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..300u32).for_each(|i| appender.append(i * 3).unwrap());
+        let bytes = appender.finish(812).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+
+        let infos = reader.section_infos().unwrap();
+        assert_eq!(infos.iter().map(|i| i.num_elements).collect::<Vec<_>>(), vec![256, 256, 256, 44]);
+        assert_eq!((infos[0].sect_type, infos[0].bounds), (SectionType::Constant, Some((9, 9))));
+        assert_eq!((infos[1].bounds, infos[1].may_have_nulls), (Some((0, 0)), false));
+        assert!(!infos[2].may_have_nulls);
+
+        // Only sections with masks are decoded, and padding is masked out
+        let mut visited = Vec::new();
+        let mut masks = vec![u32x8::splat(0), u32x8::splat(1), u32x8::splat(0), u32x8::splat(!0)].into_iter();
+        let scanner: &dyn SectionScanner<u32> = &reader;
+        scanner.visit_with_masks(&mut masks, &mut |sect_num, mask, values| {
+            visited.push((sect_num, mask.count_ones().wrapping_sum(), values[0], values[43]));
+        }).unwrap();
+        assert_eq!(visited, vec![(1, 8, 0, 0), (3, 44, 768, 897)]);
+    }
+}
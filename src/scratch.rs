@@ -0,0 +1,139 @@
+/// The `scratch` module has a per-thread pool of scratch buffers for decoding, such as one section's worth of
+/// values, so that hot loops decoding many vectors do not allocate a buffer per vector.  Each thread has its
+/// own pool, so parallel decoding never contends on a lock.
+///
+/// `ScratchGuard::take()` takes a cleared buffer from the pool of the current thread, allocating one only if
+/// the pool is empty, and the guard returns the buffer to the pool when dropped:
+/// ```
+/// # use compressed_vec::scratch::ScratchGuard;
+/// # use compressed_vec::sink::Section256Sink;
+/// # use compressed_vec::vector::*;
+///     let bytes = VectorU64Appender::try_new(1024).unwrap().encode_all(0..300u64).unwrap();
+///     let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+///     for sect in reader.sect_iter() {
+///         let mut sink = ScratchGuard::<Section256Sink<u64>>::take();   // Allocated only the first time
+///         sect.unwrap().decode(&mut *sink).unwrap();
+///     }
+/// ```
+use std::cell::RefCell;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+
+use crate::section::VectBase;
+use crate::sink::{Section256Sink, Sink, VecSink};
+
+
+/// Maximum number of free buffers of each type kept per thread
+const MAX_POOLED_PER_TYPE: usize = 4;
+
+thread_local! {
+    static POOL: RefCell<HashMap<TypeId, Vec<Box<dyn Any>>>> = RefCell::new(HashMap::new());
+}
+
+/// A buffer which can be pooled as scratch space
+pub trait Scratch: 'static {
+    fn new_scratch() -> Self;
+    /// Clears the buffer before it is handed out again.  Should not free any memory.
+    fn clear_scratch(&mut self);
+}
+
+impl<T: VectBase + 'static> Scratch for Section256Sink<T> {
+    fn new_scratch() -> Self { Self::new() }
+    fn clear_scratch(&mut self) { self.reset() }
+}
+
+impl<T: VectBase + 'static> Scratch for VecSink<T> {
+    fn new_scratch() -> Self { Self::new() }
+    fn clear_scratch(&mut self) { self.vec.clear() }
+}
+
+/// A scratch buffer taken from the pool of the current thread, and returned to it on drop
+pub struct ScratchGuard<S: Scratch> {
+    buf: Option<Box<S>>,
+}
+
+impl<S: Scratch> ScratchGuard<S> {
+    pub fn take() -> Self {
+        let pooled = POOL.with(|pool| {
+            pool.borrow_mut().get_mut(&TypeId::of::<S>()).and_then(|bufs| bufs.pop())
+        });
+        let mut buf = match pooled.map(|b| b.downcast::<S>()) {
+            Some(Ok(buf)) => buf,
+            _             => Box::new(S::new_scratch()),
+        };
+        buf.clear_scratch();
+        Self { buf: Some(buf) }
+    }
+
+    /// Number of free buffers of type S in the pool of the current thread
+    pub fn num_pooled() -> usize {
+        POOL.with(|pool| pool.borrow().get(&TypeId::of::<S>()).map_or(0, |bufs| bufs.len()))
+    }
+}
+
+impl<S: Scratch> Deref for ScratchGuard<S> {
+    type Target = S;
+    fn deref(&self) -> &S {
+        self.buf.as_ref().unwrap()
+    }
+}
+
+impl<S: Scratch> DerefMut for ScratchGuard<S> {
+    fn deref_mut(&mut self) -> &mut S {
+        self.buf.as_mut().unwrap()
+    }
+}
+
+impl<S: Scratch> Drop for ScratchGuard<S> {
+    fn drop(&mut self) {
+        if let Some(buf) = self.buf.take() {
+            // The pool is gone if the thread is exiting; then the buffer is just freed
+            let _ = POOL.try_with(|pool| {
+                if let Ok(mut pool) = pool.try_borrow_mut() {
+                    let bufs = pool.entry(TypeId::of::<S>()).or_insert_with(Vec::new);
+                    if bufs.len() < MAX_POOLED_PER_TYPE { bufs.push(buf); }
+                }
+            });
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_buffers_are_reused_per_type() {
+        type U32Sink = Section256Sink<u32>;
+        assert_eq!(ScratchGuard::<U32Sink>::num_pooled(), 0);
+        let ptr = {
+            let mut sink = ScratchGuard::<U32Sink>::take();
+            sink.process(packed_simd::u32x8::splat(9));
+            &*sink as *const U32Sink
+        };
+        assert_eq!(ScratchGuard::<U32Sink>::num_pooled(), 1);
+
+        // The same buffer comes back, and nested guards get their own buffers
+        {
+            let first = ScratchGuard::<U32Sink>::take();
+            let second = ScratchGuard::<U32Sink>::take();
+            assert_eq!(&*first as *const U32Sink, ptr);
+            assert_ne!(&*second as *const U32Sink, ptr);
+            assert_eq!(ScratchGuard::<U32Sink>::num_pooled(), 0);
+        }
+        assert_eq!(ScratchGuard::<U32Sink>::num_pooled(), 2);
+
+        // Buffers are cleared, and pools are per type and per thread
+        let mut vec_sink = ScratchGuard::<VecSink<u64>>::take();
+        vec_sink.vec.push(5);
+        drop(vec_sink);
+        assert!(ScratchGuard::<VecSink<u64>>::take().vec.is_empty());
+        std::thread::spawn(|| assert_eq!(ScratchGuard::<U32Sink>::num_pooled(), 0)).join().unwrap();
+
+        let guards: Vec<_> = (0..6).map(|_| ScratchGuard::<U32Sink>::take()).collect();
+        drop(guards);
+        assert_eq!(ScratchGuard::<U32Sink>::num_pooled(), MAX_POOLED_PER_TYPE);
+    }
+}
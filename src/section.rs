@@ -8,6 +8,8 @@
 ///
 /// The code uses Scroll to ensure efficient encoding but one that works across platforms and endianness.
 use crate::error::CodingError;
+use crate::lz4;
+use crate::memory::MemoryTracker;
 use crate::nibblepacking;
 use crate::nibblepack_simd;
 use crate::sink::*;
@@ -16,11 +18,13 @@ use std::cmp::Ordering;
 use core::marker::PhantomData;
 use std::ops::{Add, BitXor};
 use std::convert::TryFrom;
+use std::sync::Arc;
 
 use enum_dispatch::enum_dispatch;
-use num::{PrimInt, Unsigned, Num, Bounded, Float};
+use num::{PrimInt, Unsigned, Num, NumCast, Bounded, Float};
+use num::traits::WrappingAdd;
 use num_enum::{TryFromPrimitive, TryFromPrimitiveError};
-use packed_simd::{u32x8, u64x8, f32x8};
+use packed_simd::{u8x8, u16x8, u32x8, u64x8, f32x8, f64x8, FromCast};
 use scroll::{ctx, Endian, Pread, Pwrite, LE};
 
 
@@ -29,12 +33,27 @@ use scroll::{ctx, Endian, Pread, Pwrite, LE};
 /// FixedSections are generic, they do not contain type information which is in the vector type.
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[non_exhaustive]
 pub enum SectionType {
     Null = 0,                 // FIXED_LEN unavailable or null elements in a row
     NibblePackedMedium = 1,   // Nibble-packed u64/u32's, total size < 64KB
+    NibblePackedZRMedium = 2, // Nibble-packed u64/u32's with zero run suppression, total size < 64KB
     DeltaNPMedium      = 3,   // Nibble-packed u64/u32's, delta encoded, total size < 64KB
     Constant           = 5,   // Constant value section
     XorNPMedium        = 6,   // XORed f64/f32, NibblePacked, total size < 64KB
+    BytePlanes16       = 7,   // 16-byte fixed binary values as 16 byte planes, see fixedbin module
+    RepeatPrevious     = 8,   // Identical to the previous section, one byte only
+    Transformed        = 9,   // Section payload sealed by a SectionTransform, eg encrypted
+    KeyValuePairs      = 10,  // Rows of sparse u64 key/value pairs, see kvpairs module
+    XorF64Medium       = 11,  // Gorilla XORed f64, leading/trailing zero bits suppressed, total size < 64KB
+    MonoDeltaNPMedium  = 12,  // Nibble-packed deltas between successive increasing u64/u32's, total size < 64KB
+    DeltaDeltaMedium   = 13,  // Gorilla delta-of-delta u64 timestamps, variable length bit fields, total size < 64KB
+    Bitmap256          = 14,  // 256 booleans as a 32-byte bitmap, see boolvec module
+    PartialNull        = 15,  // Validity bitmap plus a section of the non-null values packed together
+    SparseExceptions   = 16,  // Default u64/u32 value plus up to 25 (index, value) exceptions
+    Simple8bMedium     = 17,  // u64/u32's packed into 64-bit Simple8b words, total size < 64KB
+    DecimalF64Medium   = 18,  // f64's with few decimal digits as scaled integers in a u64 section
+    Lz4Medium          = 19,  // Raw little endian values as an LZ4 block, see lz4 module, total size < 64KB
 }
 
 impl From<TryFromPrimitiveError<SectionType>> for CodingError {
@@ -77,6 +96,22 @@ pub struct SectionHeader {
     typ: SectionType,
 }
 
+/// Number of bytes in an encoded SectionHeader
+pub const SECTION_HEADER_SIZE: usize = 5;
+
+impl SectionHeader {
+    pub fn new(num_bytes: u16, num_elements: u16, typ: SectionType) -> Self {
+        Self { num_bytes, num_elements, typ }
+    }
+
+    /// Number of bytes in the section after the header
+    pub fn num_bytes(&self) -> usize { self.num_bytes as usize }
+
+    pub fn num_elements(&self) -> usize { self.num_elements as usize }
+
+    pub fn sect_type(&self) -> SectionType { self.typ }
+}
+
 /// Result: (bytes_written, elements_written)
 type CodingResult = Result<(u16, u16), CodingError>;
 
@@ -86,7 +121,7 @@ type CodingResult = Result<(u16, u16), CodingError>;
 ///
 /// Example which adds 8 0xff elements and returns an error if there isn't enough space:
 /// ```
-/// # use compressed_vec::section::*;
+/// # use compressed_vec::raw::section::*;
 /// # use compressed_vec::error::CodingError;
 /// let mut buf = [0u8; 1024];
 /// let mut writer = SectionWriter::new(&mut buf, 256);
@@ -121,6 +156,7 @@ impl<'a> SectionWriter<'a> {
     pub fn cur_pos(&self) -> usize { self.cur_pos }
 
     fn init_new_section(&mut self, sect_type: SectionType) -> CodingResult {
+        if self.write_buf.len() < self.cur_pos + SECTION_HEADER_SIZE { return Err(CodingError::NotEnoughSpace) }
         self.cur_header.num_bytes = 0;
         self.cur_header.num_elements = 0;
         self.cur_header.typ = sect_type;
@@ -142,39 +178,67 @@ impl<'a> SectionWriter<'a> {
     /// If given slice is not large enough, then method may advance to next section
     /// which should give more room to grow.
     /// sect_type is used to fill in new section
+    ///
+    /// Invariants kept between calls: the current section has at most 65535 bytes and at most
+    /// max_elements_per_sect elements, and cur_pos is within the write buffer.  Errors:
+    /// - `Overflow` if the filler claims to have written more bytes or elements than it was given
+    /// - `SectionFull` if the filler returns `NotEnoughSpace` for a new, empty section with 64KB of room
+    /// - `NotEnoughSpace` if the write buffer runs out
     pub fn add_64kb<F>(&mut self, sect_type: SectionType, filler: F) -> CodingResult
         where F: Fn(&mut [u8], usize) -> CodingResult
     {
         // If buffer empty / no section initialized, go ahead initialize it
         if self.cur_pos == 0 { self.init_new_section(sect_type)?; }
 
-        let elements_left = self.max_elements_per_sect - self.cur_header.num_elements;
+        match self.try_fill(&filler) {
+            Err(CodingError::NotEnoughSpace) if !self.cur_sect_is_empty() => {
+                // Try to write a new section, then try writing again, once
+                self.init_new_section(sect_type)?;
+                self.try_fill(&filler)
+            }
+            res => res,
+        }
+    }
+
+    fn cur_sect_is_empty(&self) -> bool {
+        self.cur_header.num_bytes == 0 && self.cur_header.num_elements == 0
+    }
+
+    // Calls the filler once with the room left in the current section and buffer, and updates the section
+    // header.  Nothing is updated if the filler fails or breaks the invariants.
+    fn try_fill<F>(&mut self, filler: &F) -> CodingResult
+        where F: Fn(&mut [u8], usize) -> CodingResult
+    {
+        let elements_left = self.max_elements_per_sect.checked_sub(self.cur_header.num_elements)
+                                .ok_or(CodingError::Overflow)?;
         // Smaller of how much left in section vs how much left in input buffer
-        let bytes_left = std::cmp::min(65535 - self.cur_header.num_bytes as usize,
-                                       self.write_buf.len() - self.cur_pos);
+        let sect_bytes_left = (u16::MAX - self.cur_header.num_bytes) as usize;
+        let buf_bytes_left = self.write_buf.len().checked_sub(self.cur_pos).ok_or(CodingError::Overflow)?;
+        let bytes_left = sect_bytes_left.min(buf_bytes_left);
 
-        // Call filler func once.  If not enough space, try to allocate new section before giving up
         let writable_bytes = &mut self.write_buf[self.cur_pos..self.cur_pos + bytes_left];
-        let filled_res = filler(writable_bytes, elements_left as usize);
-        match filled_res {
+        match filler(writable_bytes, elements_left as usize) {
             Ok((bytes_written, elements_written)) => {
-                assert!(elements_written <= elements_left);
+                if bytes_written as usize > bytes_left || elements_written > elements_left {
+                    return Err(CodingError::Overflow);
+                }
+                // Cannot overflow given the checks above, but stay explicit
+                let num_bytes = self.cur_header.num_bytes.checked_add(bytes_written)
+                                    .ok_or(CodingError::Overflow)?;
+                let num_elements = self.cur_header.num_elements.checked_add(elements_written)
+                                       .ok_or(CodingError::Overflow)?;
                 // Update section header as well as other internal pointers
-                self.cur_header.num_bytes += bytes_written;
-                self.cur_header.num_elements += elements_written;
+                self.cur_header.num_bytes = num_bytes;
+                self.cur_header.num_elements = num_elements;
                 self.cur_pos += bytes_written as usize;
 
                 self.update_sect_header()?;
                 Ok((bytes_written, elements_written))
             },
-            Err(CodingError::NotEnoughSpace) => {
-                // Try to write a new section
-                self.init_new_section(sect_type)?;
-
-                // Now try writing again
-                self.add_64kb(sect_type, filler)
-            }
-            e @ Err(_) => return e,
+            // An empty section with 64KB of room is as big as sections get
+            Err(CodingError::NotEnoughSpace) if self.cur_sect_is_empty() && buf_bytes_left >= sect_bytes_left =>
+                Err(CodingError::SectionFull),
+            e @ Err(_) => e,
         }
     }
 }
@@ -209,13 +273,23 @@ pub trait FixedSection {
 /// A FixedSectEnum is an enum over different FixedSection implementations, for the purpose of very fast,
 /// inlineable iteration over different section types without resorting to dynamic method calls.
 #[enum_dispatch(FixedSection)]
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[non_exhaustive]
 pub enum FixedSectEnum<'buf, T: VectBase> {
     NullFixedSect,
     NibblePackMedFixedSect(NibblePackMedFixedSect<'buf, T>),
+    NibblePackZRMedFixedSect(NibblePackZRMedFixedSect<'buf, T>),
     DeltaNPMedFixedSect(DeltaNPMedFixedSect<'buf, T>),
+    MonoDeltaNPMedFixedSect(MonoDeltaNPMedFixedSect<'buf, T>),
     ConstFixedSect(ConstFixedSect<'buf, T>),
     XorNPMedFixedSect(XorNPMedFixedSect<'buf>),
+    XorF64MedFixedSect(XorF64MedFixedSect<'buf>),
+    DeltaDeltaMedFixedSect(DeltaDeltaMedFixedSect<'buf>),
+    PartialNullFixedSect(PartialNullFixedSect<'buf, T>),
+    SparseFixedSect(SparseFixedSect<'buf, T>),
+    Simple8bMedFixedSect(Simple8bMedFixedSect<'buf, T>),
+    DecimalF64MedFixedSect(DecimalF64MedFixedSect<'buf>),
+    Lz4MedFixedSect(Lz4MedFixedSect<'buf, T>),
 }
 
 impl<'buf, T: VectBase> FixedSectEnum<'buf, T> {
@@ -223,7 +297,7 @@ impl<'buf, T: VectBase> FixedSectEnum<'buf, T> {
     /// Note that you need to specify an explicit base type as FixedSectEnums are typeless.
     /// For example, to write to the generic section sink which materializes every value in a section:
     /// ```
-    /// # use compressed_vec::section::{FixedSectEnum, SectionType};
+    /// # use compressed_vec::raw::section::{FixedSectEnum, SectionType};
     /// # use std::convert::TryFrom;
     /// # let mut sect_bytes = [0u8; 256];
     /// # sect_bytes[0] = SectionType::NibblePackedMedium.as_num();
@@ -247,6 +321,16 @@ impl<'buf, T: VectBase> FixedSectEnum<'buf, T> {
             _ => false,
         }
     }
+
+    /// Returns the mask of non-null elements.  Only PartialNull sections have null elements; in all other
+    /// sections, including null sections, every element is a value.
+    #[inline]
+    pub fn validity_mask(&self) -> u32x8 {
+        match self {
+            FixedSectEnum::PartialNullFixedSect(ps) => ps.validity_mask(),
+            _ => u32x8::splat(u32::max_value()),
+        }
+    }
 }
 
 impl<'buf, T: VectBase> TryFrom<&'buf [u8]> for FixedSectEnum<'buf, T> {
@@ -260,12 +344,40 @@ impl<'buf, T: VectBase> TryFrom<&'buf [u8]> for FixedSectEnum<'buf, T> {
             SectionType::Null => Ok((NullFixedSect {}).into()),
             SectionType::NibblePackedMedium =>
                 NibblePackMedFixedSect::try_from(s).map(|sect| sect.into()),
+            SectionType::NibblePackedZRMedium =>
+                NibblePackZRMedFixedSect::try_from(s).map(|sect| sect.into()),
             SectionType::DeltaNPMedium =>
                 DeltaNPMedFixedSect::try_from(s).map(|sect| sect.into()),
+            SectionType::MonoDeltaNPMedium =>
+                MonoDeltaNPMedFixedSect::try_from(s).map(|sect| sect.into()),
             SectionType::Constant =>
                 ConstFixedSect::try_from(s).map(|sect| sect.into()),
             SectionType::XorNPMedium =>
                 XorNPMedFixedSect::try_from(s).map(|sect| sect.into()),
+            SectionType::XorF64Medium =>
+                XorF64MedFixedSect::try_from(s).map(|sect| sect.into()),
+            SectionType::DeltaDeltaMedium =>
+                DeltaDeltaMedFixedSect::try_from(s).map(|sect| sect.into()),
+            SectionType::PartialNull =>
+                PartialNullFixedSect::try_from(s).map(|sect| sect.into()),
+            SectionType::SparseExceptions =>
+                SparseFixedSect::try_from(s).map(|sect| sect.into()),
+            SectionType::Simple8bMedium =>
+                Simple8bMedFixedSect::try_from(s).map(|sect| sect.into()),
+            SectionType::DecimalF64Medium =>
+                DecimalF64MedFixedSect::try_from(s).map(|sect| sect.into()),
+            SectionType::Lz4Medium =>
+                Lz4MedFixedSect::try_from(s).map(|sect| sect.into()),
+            SectionType::BytePlanes16 =>
+                Err(CodingError::InvalidFormat("BytePlanes16 sections are only valid in FixedBin16 vectors".into())),
+            SectionType::RepeatPrevious =>
+                Err(CodingError::InvalidFormat("RepeatPrevious section must be read using FixedSectIterator".into())),
+            SectionType::Transformed =>
+                Err(CodingError::InvalidFormat("Transformed section must be opened with its SectionTransform".into())),
+            SectionType::KeyValuePairs =>
+                Err(CodingError::InvalidFormat("KeyValuePairs sections are only valid in FixedKeyValue vectors".into())),
+            SectionType::Bitmap256 =>
+                Err(CodingError::InvalidFormat("Bitmap256 sections are only valid in FixedBool vectors".into())),
         }
     }
 }
@@ -276,8 +388,8 @@ pub trait FixedSectReader<T: VectBase>: FixedSection {
     /// This is the most generic method of processing data from a section.
     /// For example, to get an iterator out:
     /// ```
-    /// # use compressed_vec::section::{FixedSectReader, NibblePackMedFixedSect};
-    /// # use compressed_vec::nibblepack_simd;
+    /// # use compressed_vec::raw::section::{FixedSectReader, NibblePackMedFixedSect};
+    /// # use compressed_vec::raw::nibblepack_simd;
     /// # let mut sect_bytes = [0u8; 256];
     /// # sect_bytes[1] = 253;
     ///     let sect = NibblePackMedFixedSect::<u32>::try_from(&sect_bytes[..]).unwrap();
@@ -319,8 +431,14 @@ impl<'buf> FSUtils<u32> for FSUtilsMarker {
         match e {
             FixedSectEnum::NullFixedSect(nfs) => FixedSectReader::<u32>::decode_to_sink(&nfs, output),
             FixedSectEnum::NibblePackMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::NibblePackZRMedFixedSect(fs) => fs.decode_to_sink(output),
             FixedSectEnum::DeltaNPMedFixedSect(fs)    => fs.decode_to_sink(output),
+            FixedSectEnum::MonoDeltaNPMedFixedSect(fs) => fs.decode_to_sink(output),
             FixedSectEnum::ConstFixedSect(cs)         => cs.decode_to_sink(output),
+            FixedSectEnum::PartialNullFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::SparseFixedSect(fs)     => fs.decode_to_sink(output),
+            FixedSectEnum::Simple8bMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::Lz4MedFixedSect(fs)     => fs.decode_to_sink(output),
             _ => Err(CodingError::InvalidFormat(format!("Section {:?} invalid for u32", e))),
         }
     }
@@ -350,8 +468,15 @@ impl<'buf> FSUtils<u64> for FSUtilsMarker {
         match e {
             FixedSectEnum::NullFixedSect(nfs) => FixedSectReader::<u64>::decode_to_sink(&nfs, output),
             FixedSectEnum::NibblePackMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::NibblePackZRMedFixedSect(fs) => fs.decode_to_sink(output),
             FixedSectEnum::DeltaNPMedFixedSect(fs)    => fs.decode_to_sink(output),
+            FixedSectEnum::MonoDeltaNPMedFixedSect(fs) => fs.decode_to_sink(output),
             FixedSectEnum::ConstFixedSect(cs)         => cs.decode_to_sink(output),
+            FixedSectEnum::DeltaDeltaMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::PartialNullFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::SparseFixedSect(fs)     => fs.decode_to_sink(output),
+            FixedSectEnum::Simple8bMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::Lz4MedFixedSect(fs)     => fs.decode_to_sink(output),
             _ => Err(CodingError::InvalidFormat(format!("Section {:?} invalid for u64", e))),
         }
     }
@@ -368,7 +493,116 @@ impl<'buf> FSUtils<u64> for FSUtilsMarker {
 
     #[inline]
     fn nibblepack_decode<'a, S: Sink<u64x8>>(buf: &'a [u8], sink: &mut S) -> Result<&'a [u8], CodingError> {
-        nibblepacking::nibble_unpack8(buf, sink)
+        nibblepack_simd::unpack8_u64_simd(buf, sink)
+    }
+}
+
+impl<'buf> FSUtils<u16> for FSUtilsMarker {
+    const BYTE_WIDTH: usize = 2;
+
+    #[inline]
+    fn decode_to_sink<Output>(e: FixedSectEnum<u16>, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<u16x8> {
+        match e {
+            FixedSectEnum::NullFixedSect(nfs) => FixedSectReader::<u16>::decode_to_sink(&nfs, output),
+            FixedSectEnum::NibblePackMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::NibblePackZRMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::DeltaNPMedFixedSect(fs)    => fs.decode_to_sink(output),
+            FixedSectEnum::MonoDeltaNPMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::ConstFixedSect(cs)         => cs.decode_to_sink(output),
+            FixedSectEnum::PartialNullFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::SparseFixedSect(fs)     => fs.decode_to_sink(output),
+            FixedSectEnum::Simple8bMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::Lz4MedFixedSect(fs)     => fs.decode_to_sink(output),
+            _ => Err(CodingError::InvalidFormat(format!("Section {:?} invalid for u16", e))),
+        }
+    }
+
+    #[inline]
+    fn read_le_offset<'a>(buf: &'a [u8], offset: usize) -> Result<u16, scroll::Error> {
+        buf.pread_with(offset, LE)
+    }
+
+    #[inline]
+    fn write_le_offset<'a>(buf: &'a mut [u8], offset: usize, value: u16) -> Result<usize, scroll::Error> {
+        buf.pwrite_with(value, offset, LE)
+    }
+
+    #[inline]
+    fn nibblepack_decode<'a, S: Sink<u16x8>>(buf: &'a [u8], sink: &mut S) -> Result<&'a [u8], CodingError> {
+        nibblepack_simd::unpack8_u32_simd(buf, &mut NarrowingSink::new(sink))
+    }
+}
+
+impl<'buf> FSUtils<u8> for FSUtilsMarker {
+    const BYTE_WIDTH: usize = 1;
+
+    #[inline]
+    fn decode_to_sink<Output>(e: FixedSectEnum<u8>, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<u8x8> {
+        match e {
+            FixedSectEnum::NullFixedSect(nfs) => FixedSectReader::<u8>::decode_to_sink(&nfs, output),
+            FixedSectEnum::NibblePackMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::NibblePackZRMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::DeltaNPMedFixedSect(fs)    => fs.decode_to_sink(output),
+            FixedSectEnum::MonoDeltaNPMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::ConstFixedSect(cs)         => cs.decode_to_sink(output),
+            FixedSectEnum::PartialNullFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::SparseFixedSect(fs)     => fs.decode_to_sink(output),
+            FixedSectEnum::Simple8bMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::Lz4MedFixedSect(fs)     => fs.decode_to_sink(output),
+            _ => Err(CodingError::InvalidFormat(format!("Section {:?} invalid for u8", e))),
+        }
+    }
+
+    #[inline]
+    fn read_le_offset<'a>(buf: &'a [u8], offset: usize) -> Result<u8, scroll::Error> {
+        buf.pread_with(offset, LE)
+    }
+
+    #[inline]
+    fn write_le_offset<'a>(buf: &'a mut [u8], offset: usize, value: u8) -> Result<usize, scroll::Error> {
+        buf.pwrite_with(value, offset, LE)
+    }
+
+    #[inline]
+    fn nibblepack_decode<'a, S: Sink<u8x8>>(buf: &'a [u8], sink: &mut S) -> Result<&'a [u8], CodingError> {
+        nibblepack_simd::unpack8_u32_simd(buf, &mut NarrowingSink::new(sink))
+    }
+}
+
+// Narrows u32x8's from the u32 NibblePack decoder to the lanes of u16 and u8 sinks
+struct NarrowingSink<'a, I: SinkInput, S: Sink<I>> {
+    inner_sink: &'a mut S,
+    _input: PhantomData<I>,
+}
+
+impl<'a, I: SinkInput, S: Sink<I>> NarrowingSink<'a, I, S> {
+    fn new(inner_sink: &'a mut S) -> Self {
+        Self { inner_sink, _input: PhantomData }
+    }
+}
+
+impl<'a, I, S> Sink<u32x8> for NarrowingSink<'a, I, S>
+where I: SinkInput + FromCast<u32x8>,
+      S: Sink<I> {
+    #[inline]
+    fn process(&mut self, data: u32x8) {
+        self.inner_sink.process(I::from_cast(data));
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.inner_sink.process_zeroes();
+    }
+
+    fn reset(&mut self) {
+        self.inner_sink.reset();
+    }
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.inner_sink.is_done()
     }
 }
 
@@ -381,7 +615,9 @@ impl<'buf> FSUtils<f32> for FSUtilsMarker {
         match e {
             FixedSectEnum::NullFixedSect(nfs)    => FixedSectReader::<f32>::decode_to_sink(&nfs, output),
             FixedSectEnum::ConstFixedSect(cs)    => cs.decode_to_sink(output),
-            FixedSectEnum::XorNPMedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::XorNPMedFixedSect(fs) => FixedSectReader::<f32>::decode_to_sink(&fs, output),
+            FixedSectEnum::PartialNullFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::Lz4MedFixedSect(fs)   => fs.decode_to_sink(output),
             _ => Err(CodingError::InvalidFormat(format!("Section {:?} invalid for f32", e))),
         }
     }
@@ -398,7 +634,41 @@ impl<'buf> FSUtils<f32> for FSUtilsMarker {
 
     #[inline]
     fn nibblepack_decode<'a, S: Sink<f32x8>>(_buf: &'a [u8], _sink: &mut S) -> Result<&'a [u8], CodingError> {
-        unimplemented!()
+        Err(CodingError::InvalidFormat("NibblePack decoding is not supported for f32".into()))
+    }
+}
+
+impl<'buf> FSUtils<f64> for FSUtilsMarker {
+    const BYTE_WIDTH: usize = 8;
+
+    #[inline]
+    fn decode_to_sink<Output>(e: FixedSectEnum<f64>, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<f64x8> {
+        match e {
+            FixedSectEnum::NullFixedSect(nfs)    => FixedSectReader::<f64>::decode_to_sink(&nfs, output),
+            FixedSectEnum::ConstFixedSect(cs)    => cs.decode_to_sink(output),
+            FixedSectEnum::XorNPMedFixedSect(fs) => FixedSectReader::<f64>::decode_to_sink(&fs, output),
+            FixedSectEnum::XorF64MedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::PartialNullFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::DecimalF64MedFixedSect(fs) => fs.decode_to_sink(output),
+            FixedSectEnum::Lz4MedFixedSect(fs)   => fs.decode_to_sink(output),
+            _ => Err(CodingError::InvalidFormat(format!("Section {:?} invalid for f64", e))),
+        }
+    }
+
+    #[inline]
+    fn read_le_offset<'a>(buf: &'a [u8], offset: usize) -> Result<f64, scroll::Error> {
+        buf.pread_with(offset, LE)
+    }
+
+    #[inline]
+    fn write_le_offset<'a>(buf: &'a mut [u8], offset: usize, value: f64) -> Result<usize, scroll::Error> {
+        buf.pwrite_with(value, offset, LE)
+    }
+
+    #[inline]
+    fn nibblepack_decode<'a, S: Sink<f64x8>>(_buf: &'a [u8], _sink: &mut S) -> Result<&'a [u8], CodingError> {
+        Err(CodingError::InvalidFormat("NibblePack decoding is not supported for f64".into()))
     }
 }
 
@@ -422,16 +692,47 @@ impl VectBase for u64 {
     type Utils = FSUtilsMarker;
 }
 
+impl VectBase for u16 {
+    type SI = u16x8;
+    type Utils = FSUtilsMarker;
+}
+
+impl VectBase for u8 {
+    type SI = u8x8;
+    type Utils = FSUtilsMarker;
+}
+
 impl VectBase for f32 {
     type SI = f32x8;
     type Utils = FSUtilsMarker;
 }
 
+impl VectBase for f64 {
+    type SI = f64x8;
+    type Utils = FSUtilsMarker;
+}
+
 
-/// A NullFixedSect are 256 "Null" or 0 elements.
+/// A NullFixedSect are 256 zero elements.  Despite the name its elements are values, not nulls, in every kernel:
+/// appenders write one for `append_nulls()` only without null tracking, where nulls are zeroes, and the
+/// AutoEncoder writes one for any section of zero values.  Tracked nulls are only ever the null elements of
+/// PartialNull sections, see `VectorAppender::track_nulls()`.
 /// For dictionary encoding they represent missing or Null values.
 /// Its binary representation consists solely of a SectionType::Null byte.
-#[derive(Debug, PartialEq)]
+///
+/// Decoding one reads no payload, it only passes 32 chunks of zeroes to the sink.  Kernels on mostly-null
+/// vectors should not do even that; each was checked to handle null sections from the section type alone:
+/// - iterating: `VectorItemIter` returns zeroes without decoding
+/// - filtering: `VectorFilter` returns the mask for zero, and `is_null()` no nulls, without decoding
+/// - aggregating: `masked_aggregate`, `sum`, `mean`, `bucket_counts`, `group_aggregate` and `hll::sketch` add
+///   the zeroes from the section type
+/// - gathering: `SelectionBuilder` and `compact::transcode_into` append zeroes
+/// - arithmetic: `binary_op` appends zeroes for two Null sections, or with one uses the zeroes above
+/// - `minmax` uses the section bounds, and `first()` and `last()` return zero
+///
+/// Kernels handing decoded values to a callback or sink, such as the scanner, pipeline and ewma, still decode
+/// null sections, since they are owed the values, but that is only the zeroes above.
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct NullFixedSect {}
 
 impl NullFixedSect {
@@ -534,7 +835,7 @@ impl<'buf, T: VectBase> NibblePackMedFixedSect<'buf, T> {
     pub fn try_from(sect_bytes: &'buf [u8]) -> Result<NibblePackMedFixedSect<T>, CodingError> {
         let encoded_bytes = sect_bytes.pread_with(1, LE)
                                 .and_then(|n| {
-                                    if (n + 3) <= sect_bytes.len() as u16 { Ok(n) }
+                                    if n as usize + 3 <= sect_bytes.len() { Ok(n) }
                                     else { Err(scroll::Error::Custom("Slice not large enough".to_string())) }
                                 })?;
         Ok(Self { sect_bytes, encoded_bytes, _type: PhantomData })
@@ -587,7 +888,90 @@ where T: PrimInt + Unsigned + VectBase + num::cast::AsPrimitive<u64> {
 }
 
 
+/// A FixedSection which is: NP=NibblePack'ed with zero run suppression (NibblePackMode::ZeroRuns), u64/u32
+/// elements, Medium sized (<64KB).  Much smaller than NibblePackMedFixedSect for sparse sections with long
+/// runs of zeroes.  Uses a separate section type code, so that readers not aware of ZeroRuns mode fail with
+/// InvalidSectionType rather than mis-decoding.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::NibblePackedZRMedium
+///  +1   2-byte LE size of NibblePack-encoded bytes to follow
+///  +3   NibblePack ZeroRuns-encoded 256 u64 elements
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct NibblePackZRMedFixedSect<'buf, T: VectBase> {
+    sect_bytes: &'buf [u8],
+    encoded_bytes: u16,
+    _type: PhantomData<T>,
+}
+
+impl<'buf, T: VectBase> NibblePackZRMedFixedSect<'buf, T> {
+    /// Tries to create a new NibblePackZRMedFixedSect from a byte slice starting from the first
+    /// section type byte of the section.  Byte slice should be as large as the length bytes indicate.
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<NibblePackZRMedFixedSect<T>, CodingError> {
+        let encoded_bytes = sect_bytes.pread_with(1, LE)
+                                .and_then(|n| {
+                                    if n as usize + 3 <= sect_bytes.len() { Ok(n) }
+                                    else { Err(scroll::Error::Custom("Slice not large enough".to_string())) }
+                                })?;
+        Ok(Self { sect_bytes, encoded_bytes, _type: PhantomData })
+    }
+}
+
+impl<'buf, T: VectBase> FixedSectReader<T> for NibblePackZRMedFixedSect<'buf, T> {
+    #[inline]
+    fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<T::SI> {
+        let mut groups_left = FIXED_LEN / 8;
+        let mut inbuf = &self.sect_bytes[3..];
+        while groups_left > 0 {
+            if let Some(run) = nibblepacking::zero_run_groups(inbuf) {
+                if run > groups_left {
+                    return Err(CodingError::InvalidFormat(format!("Zero run of {} groups exceeds {} left",
+                                                                  run, groups_left)));
+                }
+                for _ in 0..run { output.process_zeroes(); }
+                groups_left -= run;
+                inbuf = &inbuf[2..];
+            } else {
+                inbuf = T::Utils::nibblepack_decode(inbuf, output)?;
+                groups_left -= 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'buf, T: VectBase> FixedSection for NibblePackZRMedFixedSect<'buf, T> {
+    fn num_bytes(&self) -> usize { self.encoded_bytes as usize + 3 }
+    fn sect_bytes(&self) -> Option<&[u8]> { Some(self.sect_bytes) }
+    fn sect_type(&self) -> SectionType { SectionType::NibblePackedZRMedium }
+}
+
+impl<'buf, T> FixedSectionWriter<T> for NibblePackZRMedFixedSect<'buf, T>
+where T: PrimInt + Unsigned + VectBase + num::cast::AsPrimitive<u64> {
+    /// Writes out a fixed NibblePacked medium section using zero run suppression.
+    /// Like NibblePackMedFixedSect, length bytes are written last.
+    fn write(out_buf: &mut [u8],
+             offset: usize,
+             values: &[T],
+             _s: SectionWriterStats<T>) -> Result<usize, CodingError> {
+        assert_eq!(values.len(), FIXED_LEN);
+        out_buf.pwrite_with(SectionType::NibblePackedZRMedium.as_num(), offset, LE)?;
+        let off = nibblepacking::pack_u64_zero_runs(values.iter().map(|&x| x.as_()),
+                                                    out_buf,
+                                                    offset + 3)?;
+        let num_bytes = off - offset - 3;
+        if num_bytes <= 65535 {
+            out_buf.pwrite_with(num_bytes as u16, offset + 1, LE)?;
+            Ok(off)
+        } else {
+            Err(CodingError::NotEnoughSpace)
+        }
+    }
+}
+
 /// A FixedSection which is: NP=NibblePack'ed, Medium sized (<64KB), Delta encoded
+/// This is frame-of-reference (FOR) encoding: the deltas are from the section minimum, the base, so large but
+/// tightly clustered values such as IDs only need the nibbles of their range.
 /// Binary layout (all offsets are from start of section/type byte)
 ///  +0   SectionType::DeltaNPMedium
 ///  +1   2-byte LE size of NibblePack-encoded bytes to follow after this header
@@ -613,7 +997,7 @@ where T: VectBase {
     pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
         let encoded_bytes = sect_bytes.pread_with(1, LE)
                                 .and_then(|n| {
-                                    if (n + DELTA_NP_SECT_HEADER_SIZE as u16) <= sect_bytes.len() as u16 { Ok(n) }
+                                    if n as usize + DELTA_NP_SECT_HEADER_SIZE <= sect_bytes.len() { Ok(n) }
                                     else { Err(scroll::Error::Custom("Slice not large enough".to_string())) }
                                 })?;
         let base: T = T::Utils::read_le_offset(sect_bytes, 4)?;
@@ -675,108 +1059,1089 @@ where T: VectBase {
     fn sect_type(&self) -> SectionType { SectionType::DeltaNPMedium }
 }
 
-/// A Floating Point section encoded by XORing successive octets, then NibblePacking the result.
-/// Designed for fast SIMD decoding.
-/// For layout details, please refer to vector_format.md
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct XorNPMedFixedSect<'buf> {
+/// A FixedSection for monotonically increasing values such as timestamps and counters:
+/// NP=NibblePack'ed, Medium sized (<64KB), each value encoded as the delta from the previous value.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::MonoDeltaNPMedium
+///  +1   2-byte LE size of NibblePack-encoded bytes to follow after this header
+///  +3   u8: number of bits needed by largest successive delta
+///  +4   u64: first value
+///  +12  u64: last value
+///  +20  NibblePack-encoded 256 u64 successive deltas.  The first delta is always 0.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct MonoDeltaNPMedFixedSect<'buf, T>
+where T: VectBase {
     sect_bytes: &'buf [u8],
-    total_bytes: u16,     // This is a separate field as sect_bytes might extend beyond end of section
-                          // for performance reasons.  It is faster to be able to read beyond end
+    encoded_bytes: u16,
+    first: T,             // first and smallest value, deltas are summed starting from here
+    last: T,              // last and largest value
+    delta_numbits: u8,
 }
 
-impl<'buf> XorNPMedFixedSect<'buf> {
-    /// Tries to create a new XorNPMedFixedSect from a byte slice starting from the first
+const MONO_DELTA_NP_SECT_HEADER_SIZE: usize = 20;
+
+impl<'buf, T> MonoDeltaNPMedFixedSect<'buf, T>
+where T: VectBase {
+    /// Tries to create a new MonoDeltaNPMedFixedSect from a byte slice starting from the first
     /// section type byte of the section.  Byte slice should be as large as the length bytes indicate.
     pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
-        let total_bytes = sect_bytes.pread_with(1, LE)
+        let encoded_bytes = sect_bytes.pread_with(1, LE)
                                 .and_then(|n| {
-                                    if n <= sect_bytes.len() as u16 { Ok(n) }
+                                    if n as usize + MONO_DELTA_NP_SECT_HEADER_SIZE <= sect_bytes.len() { Ok(n) }
                                     else { Err(scroll::Error::Custom("Slice not large enough".to_string())) }
                                 })?;
-        Ok(Self { sect_bytes, total_bytes })
+        let first: T = T::Utils::read_le_offset(sect_bytes, 4)?;
+        let last: T = T::Utils::read_le_offset(sect_bytes, 12)?;
+        let delta_numbits: u8 = sect_bytes[3];
+        Ok(Self { sect_bytes, encoded_bytes, first, last, delta_numbits })
     }
+
+    /// Returns the number of bits needed by the largest delta between successive values
+    pub fn delta_numbits(&self) -> u8 { self.delta_numbits }
 }
 
-impl<'buf> FixedSectReader<f32> for XorNPMedFixedSect<'buf> {
+impl<'buf, T> FixedSectReader<T> for MonoDeltaNPMedFixedSect<'buf, T>
+where T: PrimInt + Unsigned + VectBase + WrappingAdd {
     #[inline]
     fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
-        where Output: Sink<f32x8> {
+        where Output: Sink<T::SI> {
         let mut values_left = FIXED_LEN;
-        let mut inbuf = &self.sect_bytes[3..];
-        let mut xor_sink = XorSink::<'_, f32, u32, _>::new(output);
+        let mut inbuf = &self.sect_bytes[MONO_DELTA_NP_SECT_HEADER_SIZE..];
+        let mut sum_sink = DeltaSumSink::new(self.first, output);
         while values_left > 0 {
-            inbuf = nibblepack_simd::unpack8_u32_simd(inbuf, &mut xor_sink)?;
+            inbuf = T::Utils::nibblepack_decode(inbuf, &mut sum_sink)?;
             values_left -= 8;
         }
         Ok(())
     }
 }
 
-impl<'buf, T: VectBase + Float> FixedSectionWriter<T> for XorNPMedFixedSect<'buf> {
-    /// Writes out floating point values whose bits are XORed and NibblePacked.
+/// Returns the largest delta between successive values, or None if the values are not monotonically increasing
+fn max_successive_delta<T: PrimInt>(values: &[T]) -> Option<T> {
+    let mut max_delta = T::zero();
+    for pair in values.windows(2) {
+        if pair[1] < pair[0] { return None }
+        let delta = pair[1] - pair[0];
+        if delta > max_delta { max_delta = delta }
+    }
+    Some(max_delta)
+}
+
+impl<'buf, T> FixedSectionWriter<T> for MonoDeltaNPMedFixedSect<'buf, T>
+where T: PrimInt + Unsigned + VectBase + num::cast::AsPrimitive<u64> {
+    /// Writes out a NibblePacked section of deltas between successive values.
+    /// If the values are not monotonically increasing, a DeltaNPMedFixedSect is written instead.
     /// Returns the final offset after last bytes written.
     fn write(out_buf: &mut [u8],
              offset: usize,
              values: &[T],
              stats: SectionWriterStats<T>) -> Result<usize, CodingError> {
         assert_eq!(values.len(), FIXED_LEN);
-        if stats.min == stats.max {
-            if stats.min == T::zero() {
-                // All 0's, write out a null section
-                NullFixedSect::write(out_buf, offset)
-            } else {
-                // Constant section
-                ConstFixedSect::write(out_buf, offset, values, stats)
-            }
+        let max_delta = match max_successive_delta(values) {
+            Some(max_delta) => max_delta,
+            None => return DeltaNPMedFixedSect::write(out_buf, offset, values, stats),
+        };
+        out_buf.pwrite_with(SectionType::MonoDeltaNPMedium.as_num(), offset, LE)?;
+        let deltas = std::iter::once(0u64)
+                         .chain(values.windows(2).map(|pair| (pair[1] - pair[0]).as_()));
+        let off = nibblepacking::pack_u64(deltas, out_buf, offset + MONO_DELTA_NP_SECT_HEADER_SIZE)?;
+        let num_bytes = off - offset - MONO_DELTA_NP_SECT_HEADER_SIZE;
+        if num_bytes <= 65535 {
+            out_buf.pwrite_with(num_bytes as u16, offset + 1, LE)?;
+            out_buf[offset + 3] = (T::Utils::BYTE_WIDTH * 8) as u8 - max_delta.leading_zeros() as u8;
+            out_buf.pwrite_with(values[0].as_(), offset + 4, LE)?;
+            out_buf.pwrite_with(values[FIXED_LEN - 1].as_(), offset + 12, LE)?;
+            Ok(off)
         } else {
-            out_buf.pwrite_with(SectionType::XorNPMedium.as_num(), offset, LE)?;
-
-            // Start with all 0's u32/u64's.  Then, XOR each new octet, replacing the last ones done.
-            let mut last_bits = u64x8::splat(0);
-            let mut off = offset + 3;
-            for octet in values.chunks(8) {
-                // Load octet of floats into a u64x8, doing bit casting (float bits as ints)
-                let octet_bits = T::SI::to_u64x8_bits(octet);
-                off = nibblepack_simd::pack8_u64_simd(octet_bits.bitxor(last_bits), out_buf, off)?;
-                last_bits = octet_bits;
-            }
-
-            let total_bytes = off - offset;
-            if total_bytes <= 65535 {
-                out_buf.pwrite_with(total_bytes as u16, offset + 1, LE)?;
-                Ok(off)
-            } else {
-                Err(CodingError::NotEnoughSpace)
-            }
+            Err(CodingError::NotEnoughSpace)
         }
     }
 }
 
-impl<'buf> FixedSection for XorNPMedFixedSect<'buf> {
-    fn num_bytes(&self) -> usize { self.total_bytes as usize }
+impl<'buf, T> FixedSection for MonoDeltaNPMedFixedSect<'buf, T>
+where T: VectBase {
+    fn num_bytes(&self) -> usize { self.encoded_bytes as usize + MONO_DELTA_NP_SECT_HEADER_SIZE }
     fn sect_bytes(&self) -> Option<&[u8]> { Some(self.sect_bytes) }
-    fn sect_type(&self) -> SectionType { SectionType::XorNPMedium }
+    fn sect_type(&self) -> SectionType { SectionType::MonoDeltaNPMedium }
 }
 
-/// A Constant section represents repeating values
+/// A FixedSection with some null elements, as opposed to NullFixedSect whose elements are all null, or rather
+/// zero.  Nulls decode as zero to a sink; the validity bitmap tells them apart from actual zeroes.
+/// The non-null values are packed together, in order, into a section of any other type valid for the vector,
+/// padded by repeating the last value, so that nulls do not get in the way of delta or XOR encoding.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::PartialNull
+///  +1   2-byte LE size of the values section to follow after this header
+///  +3   32-byte validity bitmap, LSB first: bit n of byte n/8 is set if element n is not null
+///  +35  FixedSection of the non-null values
 #[derive(Debug, PartialEq, Copy, Clone)]
-pub struct ConstFixedSect<'buf, T: VectBase> {
+pub struct PartialNullFixedSect<'buf, T: VectBase> {
     sect_bytes: &'buf [u8],
-    value: T
+    values_bytes: u16,
+    _type: PhantomData<T>,
 }
 
-impl<'buf, T: VectBase> ConstFixedSect<'buf, T> {
+pub const VALIDITY_BITMAP_BYTES: usize = FIXED_LEN / 8;
+const PARTIAL_NULL_SECT_HEADER_SIZE: usize = 3 + VALIDITY_BITMAP_BYTES;
+
+impl<'buf, T: VectBase> PartialNullFixedSect<'buf, T> {
+    /// Tries to create a new PartialNullFixedSect from a byte slice starting from the first
+    /// section type byte of the section.  Byte slice should be as large as the length bytes indicate.
     pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
-        if sect_bytes.len() >= (1 + T::Utils::BYTE_WIDTH) {
-            let value = T::Utils::read_le_offset(sect_bytes, 1)?;
-            Ok(Self { sect_bytes, value })
+        let values_bytes: u16 = sect_bytes.pread_with(1, LE)?;
+        if values_bytes == 0 || values_bytes as usize + PARTIAL_NULL_SECT_HEADER_SIZE > sect_bytes.len() {
+            return Err(CodingError::InputTooShort)
+        }
+        if sect_bytes[PARTIAL_NULL_SECT_HEADER_SIZE] == SectionType::PartialNull.as_num() {
+            return Err(CodingError::InvalidFormat("PartialNull sections cannot be nested".into()))
+        }
+        Ok(Self { sect_bytes, values_bytes, _type: PhantomData })
+    }
+
+    /// Writes out a PartialNull section, packing the values whose bit in the validity bitmap is set into a
+    /// section written by W.  Values of null elements are ignored.
+    /// Returns the final offset after last bytes written.
+    pub fn write<W: FixedSectionWriter<T>>(out_buf: &mut [u8],
+                                           offset: usize,
+                                           values: &[T],
+                                           validity: &[u8; VALIDITY_BITMAP_BYTES]) -> Result<usize, CodingError> {
+        assert_eq!(values.len(), FIXED_LEN);
+        let mut packed = [T::zero(); FIXED_LEN];
+        let mut num_valid = 0;
+        for (i, &value) in values.iter().enumerate() {
+            if validity[i / 8] & (1 << (i % 8)) != 0 {
+                packed[num_valid] = value;
+                num_valid += 1;
+            }
+        }
+        if num_valid > 0 {
+            let last = packed[num_valid - 1];
+            packed[num_valid..].iter_mut().for_each(|x| *x = last);
+        }
+        out_buf.pwrite_with(SectionType::PartialNull.as_num(), offset, LE)?;
+        let bitmap_start = offset + 3;
+        out_buf.get_mut(bitmap_start..bitmap_start + VALIDITY_BITMAP_BYTES)
+               .ok_or(CodingError::NotEnoughSpace)?
+               .copy_from_slice(&validity[..]);
+        let values_start = offset + PARTIAL_NULL_SECT_HEADER_SIZE;
+        let off = W::gen_stats_and_write(out_buf, values_start, &packed[..])?;
+        let num_bytes = off - values_start;
+        if num_bytes <= 65535 {
+            out_buf.pwrite_with(num_bytes as u16, offset + 1, LE)?;
+            Ok(off)
         } else {
-            Err(CodingError::InputTooShort)
+            Err(CodingError::NotEnoughSpace)
         }
     }
 
-    pub fn get_value(&self) -> T { self.value }
+    /// Returns the mask of non-null elements
+    pub fn validity_mask(&self) -> u32x8 {
+        bitmap_mask(&self.sect_bytes[3..PARTIAL_NULL_SECT_HEADER_SIZE])
+    }
+
+    /// Returns true if the element at index (0..FIXED_LEN) is not null
+    pub fn is_valid(&self, index: usize) -> bool {
+        self.sect_bytes[3 + index / 8] & (1 << (index % 8)) != 0
+    }
+
+    /// Returns the number of non-null elements
+    pub fn num_valid(&self) -> usize {
+        self.sect_bytes[3..PARTIAL_NULL_SECT_HEADER_SIZE].iter().map(|b| b.count_ones() as usize).sum()
+    }
+
+    /// Returns the section holding the packed non-null values
+    pub fn values_section(&self) -> Result<FixedSectEnum<'buf, T>, CodingError> {
+        FixedSectEnum::try_from(&self.sect_bytes[PARTIAL_NULL_SECT_HEADER_SIZE..])
+    }
+}
+
+impl<'buf, T: VectBase> FixedSectReader<T> for PartialNullFixedSect<'buf, T> {
+    /// Decodes the packed values, then spreads them out to the positions of the non-null elements
+    fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<T::SI> {
+        let mut packed = Section256Sink::<T>::new();
+        self.values_section()?.decode(&mut packed)?;
+        let mut octet = [T::zero(); 8];
+        let mut next = 0;
+        for &bits in &self.sect_bytes[3..PARTIAL_NULL_SECT_HEADER_SIZE] {
+            if bits == 0 {
+                output.process_zeroes();
+                continue;
+            }
+            for (i, value) in octet.iter_mut().enumerate() {
+                *value = if bits & (1 << i) != 0 { next += 1; packed.values[next - 1] } else { T::zero() };
+            }
+            output.process(T::SI::from_slice(&octet[..]));
+        }
+        Ok(())
+    }
+}
+
+impl<'buf, T: VectBase> FixedSection for PartialNullFixedSect<'buf, T> {
+    fn num_bytes(&self) -> usize { self.values_bytes as usize + PARTIAL_NULL_SECT_HEADER_SIZE }
+    fn sect_bytes(&self) -> Option<&[u8]> { Some(self.sect_bytes) }
+    fn sect_type(&self) -> SectionType { SectionType::PartialNull }
+}
+
+/// A FixedSection for sections where almost all elements have the same value, such as sparse counters:
+/// the default value plus a list of (index, value) exceptions, for up to 10% of the elements.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::SparseExceptions
+///  +1   2-byte LE size of the NibblePack-encoded exception values
+///  +3   u8: number of exceptions n
+///  +4   default value, BYTE_WIDTH bytes LE
+///  +4+BYTE_WIDTH     n u8 indices of the exceptions, strictly increasing
+///  +4+BYTE_WIDTH+n   NibblePack-encoded u64 exception values, in index order
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SparseFixedSect<'buf, T: VectBase> {
+    sect_bytes: &'buf [u8],
+    encoded_bytes: u16,
+    num_exceptions: u8,
+    default: T,
+}
+
+/// The largest number of exceptions in a SparseFixedSect
+pub const MAX_SPARSE_EXCEPTIONS: usize = FIXED_LEN / 10;
+const SPARSE_SECT_HEADER_SIZE: usize = 4;   // Not including the default value
+
+impl<'buf, T: VectBase> SparseFixedSect<'buf, T> {
+    /// Tries to create a new SparseFixedSect from a byte slice starting from the first
+    /// section type byte of the section.  Byte slice should be as large as the length bytes indicate.
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let encoded_bytes: u16 = sect_bytes.pread_with(1, LE)?;
+        let num_exceptions: u8 = sect_bytes.pread_with(3, LE)?;
+        let default = T::Utils::read_le_offset(sect_bytes, SPARSE_SECT_HEADER_SIZE)?;
+        let sect = Self { sect_bytes, encoded_bytes, num_exceptions, default };
+        if sect.num_bytes() > sect_bytes.len() { return Err(CodingError::InputTooShort) }
+        if sect.indices().windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(CodingError::InvalidFormat("Sparse section indices are not increasing".into()))
+        }
+        Ok(sect)
+    }
+
+    /// Returns the value of all the elements which are not exceptions
+    pub fn default_value(&self) -> T { self.default }
+
+    /// Returns the number of elements which differ from the default value
+    pub fn num_exceptions(&self) -> usize { self.num_exceptions as usize }
+
+    /// Returns the indices of the exceptions, in increasing order
+    pub fn indices(&self) -> &'buf [u8] {
+        let start = SPARSE_SECT_HEADER_SIZE + T::Utils::BYTE_WIDTH;
+        &self.sect_bytes[start..start + self.num_exceptions as usize]
+    }
+}
+
+/// Returns the value of a sparse section, if at most MAX_SPARSE_EXCEPTIONS values differ from it
+fn sparse_default<T: PrimInt>(values: &[T]) -> Option<T> {
+    // Boyer-Moore majority vote: the default of a sparse section must be the majority value
+    let mut candidate = values[0];
+    let mut count = 0;
+    for &value in values {
+        if count == 0 { candidate = value; }
+        if value == candidate { count += 1 } else { count -= 1 }
+    }
+    let num_exceptions = values.iter().filter(|&&value| value != candidate).count();
+    if num_exceptions <= MAX_SPARSE_EXCEPTIONS { Some(candidate) } else { None }
+}
+
+impl<'buf, T> SparseFixedSect<'buf, T>
+where T: VectBase + PrimInt + num::cast::AsPrimitive<u64> {
+    fn write_with_default(out_buf: &mut [u8], offset: usize, values: &[T], default: T) -> Result<usize, CodingError> {
+        out_buf.pwrite_with(SectionType::SparseExceptions.as_num(), offset, LE)?;
+        T::Utils::write_le_offset(out_buf, offset + SPARSE_SECT_HEADER_SIZE, default)?;
+        let indices_start = offset + SPARSE_SECT_HEADER_SIZE + T::Utils::BYTE_WIDTH;
+        let mut num_exceptions = 0;
+        for (i, _) in values.iter().enumerate().filter(|(_, &value)| value != default) {
+            out_buf.pwrite_with(i as u8, indices_start + num_exceptions, LE)?;
+            num_exceptions += 1;
+        }
+        out_buf.pwrite_with(num_exceptions as u8, offset + 3, LE)?;
+        let encoded_start = indices_start + num_exceptions;
+        let exceptions = values.iter().filter(|&&value| value != default).map(|value| value.as_());
+        let off = nibblepacking::pack_u64(exceptions, out_buf, encoded_start)?;
+        out_buf.pwrite_with((off - encoded_start) as u16, offset + 1, LE)?;
+        Ok(off)
+    }
+}
+
+impl<'buf, T> FixedSectReader<T> for SparseFixedSect<'buf, T>
+where T: VectBase + NumCast {
+    fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<T::SI> {
+        let indices = self.indices();
+        let mut exceptions = Section256Sink::<u64>::new();
+        let encoded_start = SPARSE_SECT_HEADER_SIZE + T::Utils::BYTE_WIDTH + indices.len();
+        nibblepacking::unpack(&self.sect_bytes[encoded_start..], &mut exceptions, indices.len())?;
+        let defaults = T::SI::from_slice(&[self.default; 8]);
+        let mut next = 0;
+        for start in (0..FIXED_LEN).step_by(8) {
+            if next >= indices.len() || indices[next] as usize >= start + 8 {
+                if self.default == T::zero() { output.process_zeroes() } else { output.process(defaults) }
+                continue;
+            }
+            let mut octet = [self.default; 8];
+            while next < indices.len() && (indices[next] as usize) < start + 8 {
+                octet[indices[next] as usize - start] = NumCast::from(exceptions.values[next])
+                    .ok_or_else(|| CodingError::InvalidFormat("Sparse exception too large for type".into()))?;
+                next += 1;
+            }
+            output.process(T::SI::from_slice(&octet[..]));
+        }
+        Ok(())
+    }
+}
+
+impl<'buf, T> FixedSectionWriter<T> for SparseFixedSect<'buf, T>
+where T: VectBase + PrimInt + Unsigned + num::cast::AsPrimitive<u64> {
+    /// Writes out the values which differ from the most common value as exceptions.
+    /// If more than MAX_SPARSE_EXCEPTIONS values differ, a NibblePackMedFixedSect is written instead.
+    /// Returns the final offset after last bytes written.
+    fn write(out_buf: &mut [u8],
+             offset: usize,
+             values: &[T],
+             stats: SectionWriterStats<T>) -> Result<usize, CodingError> {
+        assert_eq!(values.len(), FIXED_LEN);
+        match sparse_default(values) {
+            Some(default) => Self::write_with_default(out_buf, offset, values, default),
+            None => NibblePackMedFixedSect::write(out_buf, offset, values, stats),
+        }
+    }
+}
+
+impl<'buf, T: VectBase> FixedSection for SparseFixedSect<'buf, T> {
+    fn num_bytes(&self) -> usize {
+        SPARSE_SECT_HEADER_SIZE + T::Utils::BYTE_WIDTH + self.num_exceptions as usize + self.encoded_bytes as usize
+    }
+    fn sect_bytes(&self) -> Option<&[u8]> { Some(self.sect_bytes) }
+    fn sect_type(&self) -> SectionType { SectionType::SparseExceptions }
+}
+
+/// A FixedSection of integers packed into 64-bit Simple8b words, an alternative to NibblePacking.
+/// The top 4 bits of each word are a selector for the number of values in the word and their width, and the
+/// values are packed from the least significant bit up.  Selectors 0 and 1 are runs of 240 and 120 zeroes.
+/// Simple8b decodes one value at a time instead of with SIMD, but packs values of mixed widths more tightly
+/// than NibblePack's groups of 8.  Values must fit in 60 bits.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::Simple8bMedium
+///  +1   2-byte LE number of 64-bit words to follow
+///  +3   u64 LE Simple8b words
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Simple8bMedFixedSect<'buf, T: VectBase> {
+    sect_bytes: &'buf [u8],
+    num_words: u16,
+    _type: PhantomData<T>,
+}
+
+const SIMPLE8B_SECT_HEADER_SIZE: usize = 3;
+
+/// (number of values, bits per value) for each Simple8b selector
+const SIMPLE8B_SELECTORS: [(usize, usize); 16] = [(240, 0), (120, 0), (60, 1), (30, 2), (20, 3), (15, 4), (12, 5),
+                                                  (10, 6), (8, 7), (7, 8), (6, 10), (5, 12), (4, 15), (3, 20),
+                                                  (2, 30), (1, 60)];
+
+/// Returns the selector which packs the most values from the start of values into one word, or None if the
+/// first value needs more than 60 bits
+fn simple8b_selector(values: &[u64]) -> Option<usize> {
+    SIMPLE8B_SELECTORS.iter().position(|&(count, bits)| {
+        count <= values.len() && values[..count].iter().all(|&value| value >> bits == 0)
+    })
+}
+
+/// Returns the number of Simple8b words needed for values, or None if a value needs more than 60 bits
+fn simple8b_num_words(values: &[u64]) -> Option<usize> {
+    let mut pos = 0;
+    let mut num_words = 0;
+    while pos < values.len() {
+        pos += SIMPLE8B_SELECTORS[simple8b_selector(&values[pos..])?].0;
+        num_words += 1;
+    }
+    Some(num_words)
+}
+
+impl<'buf, T: VectBase> Simple8bMedFixedSect<'buf, T> {
+    /// Tries to create a new Simple8bMedFixedSect from a byte slice starting from the first
+    /// section type byte of the section.  Byte slice should be as large as the length bytes indicate.
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let num_words: u16 = sect_bytes.pread_with(1, LE)?;
+        let sect = Self { sect_bytes, num_words, _type: PhantomData };
+        if sect.num_bytes() > sect_bytes.len() { return Err(CodingError::InputTooShort) }
+        Ok(sect)
+    }
+
+    /// Writes the 256 values as Simple8b words.  Returns the final offset after last bytes written, or
+    /// InvalidFormat if a value needs more than 60 bits.
+    fn write_words(out_buf: &mut [u8], offset: usize, values: &[u64]) -> Result<usize, CodingError> {
+        out_buf.pwrite_with(SectionType::Simple8bMedium.as_num(), offset, LE)?;
+        let mut off = offset + SIMPLE8B_SECT_HEADER_SIZE;
+        let mut pos = 0;
+        while pos < values.len() {
+            let selector = simple8b_selector(&values[pos..])
+                               .ok_or_else(|| CodingError::InvalidFormat("Value too large for Simple8b".into()))?;
+            let (count, bits) = SIMPLE8B_SELECTORS[selector];
+            let word = values[pos..pos + count].iter().enumerate()
+                           .fold((selector as u64) << 60, |word, (i, &value)| word | value << (i * bits));
+            off += out_buf.pwrite_with(word, off, LE)?;
+            pos += count;
+        }
+        let num_words = (off - offset - SIMPLE8B_SECT_HEADER_SIZE) / 8;
+        out_buf.pwrite_with(num_words as u16, offset + 1, LE)?;
+        Ok(off)
+    }
+}
+
+impl<'buf, T> FixedSectReader<T> for Simple8bMedFixedSect<'buf, T>
+where T: VectBase + NumCast {
+    fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<T::SI> {
+        let mut octet = [T::zero(); 8];
+        let mut num_values = 0;
+        for w in 0..self.num_words as usize {
+            let word: u64 = self.sect_bytes.pread_with(SIMPLE8B_SECT_HEADER_SIZE + w * 8, LE)?;
+            let (count, bits) = SIMPLE8B_SELECTORS[(word >> 60) as usize];
+            if num_values + count > FIXED_LEN {
+                return Err(CodingError::InvalidFormat("Too many values in Simple8b section".into()))
+            }
+            let mask = (1u64 << bits) - 1;
+            for i in 0..count {
+                octet[num_values % 8] = NumCast::from(word >> (i * bits) & mask)
+                    .ok_or_else(|| CodingError::InvalidFormat("Simple8b value too large for type".into()))?;
+                num_values += 1;
+                if num_values % 8 == 0 { output.process(T::SI::from_slice(&octet[..])); }
+            }
+        }
+        if num_values == FIXED_LEN { Ok(()) }
+        else { Err(CodingError::InvalidFormat("Too few values in Simple8b section".into())) }
+    }
+}
+
+impl<'buf, T> FixedSectionWriter<T> for Simple8bMedFixedSect<'buf, T>
+where T: VectBase + PrimInt + Unsigned + num::cast::AsPrimitive<u64> {
+    /// Writes out the values as Simple8b words.  If any value needs more than 60 bits, a NibblePackMedFixedSect
+    /// is written instead.
+    /// Returns the final offset after last bytes written.
+    fn write(out_buf: &mut [u8],
+             offset: usize,
+             values: &[T],
+             stats: SectionWriterStats<T>) -> Result<usize, CodingError> {
+        assert_eq!(values.len(), FIXED_LEN);
+        if stats.max.as_() >> 60 != 0 { return NibblePackMedFixedSect::write(out_buf, offset, values, stats) }
+        let mut bits = [0u64; FIXED_LEN];
+        bits.iter_mut().zip(values).for_each(|(b, &value)| *b = value.as_());
+        Self::write_words(out_buf, offset, &bits[..])
+    }
+}
+
+impl<'buf, T: VectBase> FixedSection for Simple8bMedFixedSect<'buf, T> {
+    fn num_bytes(&self) -> usize { SIMPLE8B_SECT_HEADER_SIZE + self.num_words as usize * 8 }
+    fn sect_bytes(&self) -> Option<&[u8]> { Some(self.sect_bytes) }
+    fn sect_type(&self) -> SectionType { SectionType::Simple8bMedium }
+}
+
+/// Converts a 32-byte LSB-first bitmap to a u32x8 mask, bit n of the bitmap being bit n of the mask
+pub(crate) fn bitmap_mask(bitmap: &[u8]) -> u32x8 {
+    let mut lanes = [0u32; 8];
+    for (i, lane) in lanes.iter_mut().enumerate() {
+        *lane = bitmap.pread_with(i * 4, LE).unwrap_or(0);
+    }
+    u32x8::from_slice_unaligned(&lanes)
+}
+
+/// A Floating Point section encoded by XORing successive octets, then NibblePacking the result.
+/// Designed for fast SIMD decoding.
+/// For layout details, please refer to vector_format.md
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XorNPMedFixedSect<'buf> {
+    sect_bytes: &'buf [u8],
+    total_bytes: u16,     // This is a separate field as sect_bytes might extend beyond end of section
+                          // for performance reasons.  It is faster to be able to read beyond end
+}
+
+impl<'buf> XorNPMedFixedSect<'buf> {
+    /// Tries to create a new XorNPMedFixedSect from a byte slice starting from the first
+    /// section type byte of the section.  Byte slice should be as large as the length bytes indicate.
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let total_bytes = sect_bytes.pread_with(1, LE)
+                                .and_then(|n| {
+                                    if n as usize <= sect_bytes.len() { Ok(n) }
+                                    else { Err(scroll::Error::Custom("Slice not large enough".to_string())) }
+                                })?;
+        Ok(Self { sect_bytes, total_bytes })
+    }
+}
+
+impl<'buf> FixedSectReader<f32> for XorNPMedFixedSect<'buf> {
+    #[inline]
+    fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<f32x8> {
+        let mut values_left = FIXED_LEN;
+        let mut inbuf = &self.sect_bytes[3..];
+        let mut xor_sink = XorSink::<'_, f32, u32, _>::new(output);
+        while values_left > 0 {
+            inbuf = nibblepack_simd::unpack8_u32_simd(inbuf, &mut xor_sink)?;
+            values_left -= 8;
+        }
+        Ok(())
+    }
+}
+
+impl<'buf> FixedSectReader<f64> for XorNPMedFixedSect<'buf> {
+    #[inline]
+    fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<f64x8> {
+        let mut values_left = FIXED_LEN;
+        let mut inbuf = &self.sect_bytes[3..];
+        let mut xor_sink = XorSink::<'_, f64, u64, _>::new(output);
+        while values_left > 0 {
+            inbuf = nibblepack_simd::unpack8_u64_simd(inbuf, &mut xor_sink)?;
+            values_left -= 8;
+        }
+        Ok(())
+    }
+}
+
+impl<'buf, T: VectBase + Float> FixedSectionWriter<T> for XorNPMedFixedSect<'buf> {
+    /// Writes out floating point values whose bits are XORed and NibblePacked.
+    /// Returns the final offset after last bytes written.
+    fn write(out_buf: &mut [u8],
+             offset: usize,
+             values: &[T],
+             stats: SectionWriterStats<T>) -> Result<usize, CodingError> {
+        assert_eq!(values.len(), FIXED_LEN);
+        if stats.min == stats.max {
+            if stats.min == T::zero() {
+                // All 0's, write out a null section
+                NullFixedSect::write(out_buf, offset)
+            } else {
+                // Constant section
+                ConstFixedSect::write(out_buf, offset, values, stats)
+            }
+        } else {
+            out_buf.pwrite_with(SectionType::XorNPMedium.as_num(), offset, LE)?;
+
+            // Start with all 0's u32/u64's.  Then, XOR each new octet, replacing the last ones done.
+            let mut last_bits = u64x8::splat(0);
+            let mut off = offset + 3;
+            for octet in values.chunks(8) {
+                // Load octet of floats into a u64x8, doing bit casting (float bits as ints)
+                let octet_bits = T::SI::to_u64x8_bits(octet);
+                off = nibblepack_simd::pack8_u64_simd(octet_bits.bitxor(last_bits), out_buf, off)?;
+                last_bits = octet_bits;
+            }
+
+            let total_bytes = off - offset;
+            if total_bytes <= 65535 {
+                out_buf.pwrite_with(total_bytes as u16, offset + 1, LE)?;
+                Ok(off)
+            } else {
+                Err(CodingError::NotEnoughSpace)
+            }
+        }
+    }
+}
+
+impl<'buf> FixedSection for XorNPMedFixedSect<'buf> {
+    fn num_bytes(&self) -> usize { self.total_bytes as usize }
+    fn sect_bytes(&self) -> Option<&[u8]> { Some(self.sect_bytes) }
+    fn sect_type(&self) -> SectionType { SectionType::XorNPMedium }
+}
+
+/// A Gorilla-style f64 section: each value is XORed with the previous one, and only the meaningful bits of the
+/// XOR, between its leading and trailing zero bits, are written to a bit stream.  Smaller than XorNP sections
+/// for slowly changing gauges, but decoded one bit field at a time instead of with SIMD.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::XorF64Medium
+///  +1   2-byte LE size of this section, including these 3 header bytes
+///  +3   8-byte LE bits of the first value
+///  +11  bit stream for the other 255 values, most significant bit of each byte first, see vector_format.md
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XorF64MedFixedSect<'buf> {
+    sect_bytes: &'buf [u8],
+    total_bytes: u16,
+}
+
+const XOR_F64_HEADER_BYTES: usize = 11;
+
+// Writes bit fields, most significant bit first, overwriting whatever is in the buffer
+struct BitWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,     // Byte offset of the current byte
+    cur: u8,
+    num_bits: u32,  // Bits in cur
+}
+
+impl<'a> BitWriter<'a> {
+    fn write(&mut self, value: u64, num_bits: u32) -> Result<(), CodingError> {
+        for i in (0..num_bits).rev() {
+            self.cur = (self.cur << 1) | ((value >> i) & 1) as u8;
+            self.num_bits += 1;
+            if self.num_bits == 8 { self.flush()?; }
+        }
+        Ok(())
+    }
+
+    // Writes out the current partial byte, padded with zero bits.  Returns the offset after it.
+    fn flush(&mut self) -> Result<usize, CodingError> {
+        if self.num_bits > 0 {
+            *self.buf.get_mut(self.pos).ok_or(CodingError::NotEnoughSpace)? = self.cur << (8 - self.num_bits);
+            self.pos += 1;
+            self.cur = 0;
+            self.num_bits = 0;
+        }
+        Ok(self.pos)
+    }
+}
+
+// Reads bit fields of up to 64 bits, most significant bit first
+struct BitReader<'a> {
+    buf: &'a [u8],
+    pos: usize,     // In bits
+}
+
+impl<'a> BitReader<'a> {
+    #[inline]
+    fn read(&mut self, num_bits: u32) -> Result<u64, CodingError> {
+        if num_bits == 0 { return Ok(0) }
+        let first = self.pos / 8;
+        let bit_off = (self.pos % 8) as u32;
+        let num_bytes = ((bit_off + num_bits + 7) / 8) as usize;
+        let bytes = self.buf.get(first..first + num_bytes).ok_or(CodingError::InputTooShort)?;
+        let acc = bytes.iter().fold(0u128, |acc, &b| (acc << 8) | b as u128);
+        self.pos += num_bits as usize;
+        let shift = num_bytes as u32 * 8 - bit_off - num_bits;
+        Ok(((acc >> shift) & ((1u128 << num_bits) - 1)) as u64)
+    }
+}
+
+impl<'buf> XorF64MedFixedSect<'buf> {
+    /// Tries to create a new XorF64MedFixedSect from a byte slice starting from the first section type byte
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let total_bytes: u16 = sect_bytes.pread_with(1, LE)?;
+        if (total_bytes as usize) < XOR_F64_HEADER_BYTES || total_bytes as usize > sect_bytes.len() {
+            return Err(CodingError::InputTooShort);
+        }
+        Ok(Self { sect_bytes, total_bytes })
+    }
+}
+
+impl<'buf> FixedSectReader<f64> for XorF64MedFixedSect<'buf> {
+    fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<f64x8> {
+        let mut bits = BitReader { buf: &self.sect_bytes[XOR_F64_HEADER_BYTES..self.total_bytes as usize], pos: 0 };
+        let mut prev: u64 = self.sect_bytes.pread_with(3, LE)?;
+        let (mut leading, mut meaningful) = (0u32, 64u32);
+        let mut octet = [0f64; 8];
+        octet[0] = f64::from_bits(prev);
+        for i in 1..FIXED_LEN {
+            if bits.read(1)? == 1 {
+                if bits.read(1)? == 1 {
+                    leading = bits.read(5)? as u32;
+                    meaningful = bits.read(6)? as u32 + 1;
+                    if leading + meaningful > 64 {
+                        return Err(CodingError::InvalidFormat("XorF64 window over 64 bits".into()));
+                    }
+                }
+                prev ^= bits.read(meaningful)? << (64 - leading - meaningful);
+            }
+            octet[i % 8] = f64::from_bits(prev);
+            if i % 8 == 7 { output.process(f64x8::from_slice_unaligned(&octet)); }
+        }
+        Ok(())
+    }
+}
+
+impl<'buf> FixedSectionWriter<f64> for XorF64MedFixedSect<'buf> {
+    /// Writes out f64 values as a Gorilla XOR bit stream, or a null or constant section if all values are equal.
+    /// Returns the final offset after last bytes written.
+    fn write(out_buf: &mut [u8],
+             offset: usize,
+             values: &[f64],
+             stats: SectionWriterStats<f64>) -> Result<usize, CodingError> {
+        assert_eq!(values.len(), FIXED_LEN);
+        if stats.min == stats.max && stats.min == 0.0 {
+            return NullFixedSect::write(out_buf, offset);
+        } else if stats.min == stats.max {
+            return ConstFixedSect::write(out_buf, offset, values, stats);
+        }
+
+        out_buf.pwrite_with(SectionType::XorF64Medium.as_num(), offset, LE)?;
+        let mut prev = values[0].to_bits();
+        out_buf.pwrite_with(prev, offset + 3, LE)?;
+        let mut bits = BitWriter { buf: out_buf, pos: offset + XOR_F64_HEADER_BYTES, cur: 0, num_bits: 0 };
+        let mut window: Option<(u32, u32)> = None;   // Leading zeros and meaningful bits of the last window
+        for &value in &values[1..] {
+            let xor = value.to_bits() ^ prev;
+            prev = value.to_bits();
+            if xor == 0 {
+                bits.write(0, 1)?;
+                continue;
+            }
+            let leading = xor.leading_zeros().min(31);
+            let trailing = xor.trailing_zeros();
+            match window {
+                Some((w_leading, w_meaningful)) if leading >= w_leading &&
+                                                   trailing >= 64 - w_leading - w_meaningful => {
+                    bits.write(0b10, 2)?;
+                    bits.write(xor >> (64 - w_leading - w_meaningful), w_meaningful)?;
+                }
+                _ => {
+                    let meaningful = 64 - leading - trailing;
+                    bits.write(0b11, 2)?;
+                    bits.write(leading as u64, 5)?;
+                    bits.write((meaningful - 1) as u64, 6)?;
+                    bits.write(xor >> trailing, meaningful)?;
+                    window = Some((leading, meaningful));
+                }
+            }
+        }
+        let off = bits.flush()?;
+
+        let total_bytes = off - offset;
+        if total_bytes <= 65535 {
+            out_buf.pwrite_with(total_bytes as u16, offset + 1, LE)?;
+            Ok(off)
+        } else {
+            Err(CodingError::NotEnoughSpace)
+        }
+    }
+}
+
+impl<'buf> FixedSection for XorF64MedFixedSect<'buf> {
+    fn num_bytes(&self) -> usize { self.total_bytes as usize }
+    fn sect_bytes(&self) -> Option<&[u8]> { Some(self.sect_bytes) }
+    fn sect_type(&self) -> SectionType { SectionType::XorF64Medium }
+}
+
+/// A section for f64 values with few decimal digits, such as prices and percentages.  Each value is stored as
+/// an integer scaled by 10^scale, where the scale is the smallest one which reconstructs every value in the
+/// section exactly as integer / 10^scale.  The ZigZag encoded integers are written as a u64 section by the
+/// AutoEncoder, so that delta, sparse and constant encodings apply.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::DecimalF64Medium
+///  +1   2-byte LE size of the u64 section to follow after this header
+///  +3   u8: scale, 0 to MAX_DECIMAL_SCALE
+///  +4   u64 FixedSection of the ZigZag encoded scaled integers
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct DecimalF64MedFixedSect<'buf> {
+    sect_bytes: &'buf [u8],
+    ints_bytes: u16,
+    scale: u8,
+}
+
+const DECIMAL_SECT_HEADER_SIZE: usize = 4;
+
+/// The largest number of decimal digits of a DecimalF64MedFixedSect
+pub const MAX_DECIMAL_SCALE: usize = 15;
+
+// Exact powers of ten, to divide the scaled integers by
+pub(crate) const POWERS_OF_TEN: [f64; MAX_DECIMAL_SCALE + 1] = [1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9,
+                                                                1e10, 1e11, 1e12, 1e13, 1e14, 1e15];
+
+/// Returns the value scaled by 10^scale as an integer, if the value is exactly that integer / 10^scale
+fn decimal_scaled(value: f64, scale: usize) -> Option<i64> {
+    let scaled = (value * POWERS_OF_TEN[scale]).round();
+    if scaled.abs() > (1u64 << 53) as f64 { return None }
+    let scaled = scaled as i64;
+    if (scaled as f64 / POWERS_OF_TEN[scale]).to_bits() == value.to_bits() { Some(scaled) } else { None }
+}
+
+impl<'buf> DecimalF64MedFixedSect<'buf> {
+    /// Tries to create a new DecimalF64MedFixedSect from a byte slice starting from the first
+    /// section type byte of the section.  Byte slice should be as large as the length bytes indicate.
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let ints_bytes: u16 = sect_bytes.pread_with(1, LE)?;
+        let scale: u8 = sect_bytes.pread_with(3, LE)?;
+        if scale as usize > MAX_DECIMAL_SCALE {
+            return Err(CodingError::InvalidFormat(format!("Decimal scale {} too large", scale)))
+        }
+        let sect = Self { sect_bytes, ints_bytes, scale };
+        if sect.num_bytes() > sect_bytes.len() { return Err(CodingError::InputTooShort) }
+        Ok(sect)
+    }
+
+    /// Returns the number of decimal digits of the values in this section
+    pub fn scale(&self) -> u8 { self.scale }
+
+    /// Returns the section of the ZigZag encoded scaled integers
+    pub fn ints_section(&self) -> Result<FixedSectEnum<'buf, u64>, CodingError> {
+        FixedSectEnum::try_from(&self.sect_bytes[DECIMAL_SECT_HEADER_SIZE..])
+    }
+}
+
+impl<'buf> FixedSectReader<f64> for DecimalF64MedFixedSect<'buf> {
+    fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<f64x8> {
+        self.ints_section()?.decode(&mut DecimalSink::new(self.scale, output)?)
+    }
+}
+
+impl<'buf> FixedSectionWriter<f64> for DecimalF64MedFixedSect<'buf> {
+    /// Writes out the values as scaled integers, using the smallest scale which represents all values exactly.
+    /// If there is no such scale, for example for NaNs or values with many digits, or if all values are equal,
+    /// an XorNPMedFixedSect is written instead.
+    /// Returns the final offset after last bytes written.
+    fn write(out_buf: &mut [u8],
+             offset: usize,
+             values: &[f64],
+             stats: SectionWriterStats<f64>) -> Result<usize, CodingError> {
+        assert_eq!(values.len(), FIXED_LEN);
+        let mut ints = [0u64; FIXED_LEN];
+        let scale = (0..=MAX_DECIMAL_SCALE).find(|&scale| {
+            values.iter().zip(ints.iter_mut()).all(|(&value, int)| match decimal_scaled(value, scale) {
+                Some(scaled) => { *int = ((scaled << 1) ^ (scaled >> 63)) as u64; true }
+                None => false,
+            })
+        });
+        let scale = match scale {
+            Some(scale) if stats.min != stats.max => scale,
+            _ => return XorNPMedFixedSect::write(out_buf, offset, values, stats),
+        };
+        out_buf.pwrite_with(SectionType::DecimalF64Medium.as_num(), offset, LE)?;
+        out_buf.pwrite_with(scale as u8, offset + 3, LE)?;
+        let ints_start = offset + DECIMAL_SECT_HEADER_SIZE;
+        let off = AutoEncoder::gen_stats_and_write(out_buf, ints_start, &ints[..])?;
+        let num_bytes = off - ints_start;
+        if num_bytes <= 65535 {
+            out_buf.pwrite_with(num_bytes as u16, offset + 1, LE)?;
+            Ok(off)
+        } else {
+            Err(CodingError::NotEnoughSpace)
+        }
+    }
+}
+
+impl<'buf> FixedSection for DecimalF64MedFixedSect<'buf> {
+    fn num_bytes(&self) -> usize { self.ints_bytes as usize + DECIMAL_SECT_HEADER_SIZE }
+    fn sect_bytes(&self) -> Option<&[u8]> { Some(self.sect_bytes) }
+    fn sect_type(&self) -> SectionType { SectionType::DecimalF64Medium }
+}
+
+/// A section of the raw little endian bytes of the values, compressed as a single LZ4 block.  This is a fallback
+/// for high-entropy values such as hashes and random IDs, which NibblePacking expands, and for values with
+/// repeating byte patterns that NibblePacking cannot exploit.  Decoded by decompressing the whole block, so it is
+/// much slower to decode than NibblePacked sections.  Writing requires the `lz4` cargo feature.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::Lz4Medium
+///  +1   2-byte LE size of the LZ4 block to follow after this header
+///  +3   LZ4 block of the FIXED_LEN values, BYTE_WIDTH bytes each
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Lz4MedFixedSect<'buf, T: VectBase> {
+    sect_bytes: &'buf [u8],
+    block_bytes: u16,
+    _type: PhantomData<T>,
+}
+
+const LZ4_SECT_HEADER_SIZE: usize = 3;
+
+// Enough for the raw bytes of FIXED_LEN u64's
+const LZ4_RAW_BYTES: usize = FIXED_LEN * 8;
+
+impl<'buf, T: VectBase> Lz4MedFixedSect<'buf, T> {
+    /// Tries to create a new Lz4MedFixedSect from a byte slice starting from the first
+    /// section type byte of the section.  Byte slice should be as large as the length bytes indicate.
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let block_bytes: u16 = sect_bytes.pread_with(1, LE)?;
+        let sect = Self { sect_bytes, block_bytes, _type: PhantomData };
+        if sect.num_bytes() > sect_bytes.len() { return Err(CodingError::InputTooShort) }
+        Ok(sect)
+    }
+
+    /// Compresses the raw little endian bytes of the values into block.  Returns the number of bytes of block used.
+    #[cfg(feature = "lz4")]
+    fn compress_values(values: &[T], block: &mut [u8]) -> Result<usize, CodingError> {
+        let width = T::Utils::BYTE_WIDTH;
+        let mut raw = [0u8; LZ4_RAW_BYTES];
+        for (i, &value) in values.iter().enumerate() {
+            T::Utils::write_le_offset(&mut raw, i * width, value)?;
+        }
+        lz4::compress(&raw[..values.len() * width], block)
+    }
+
+    /// Writes the section header for an LZ4 block of block_len bytes, already written after the header.
+    /// Returns the final offset after last bytes written.
+    #[cfg(feature = "lz4")]
+    fn write_header(out_buf: &mut [u8], offset: usize, block_len: usize) -> Result<usize, CodingError> {
+        out_buf.pwrite_with(SectionType::Lz4Medium.as_num(), offset, LE)?;
+        out_buf.pwrite_with(block_len as u16, offset + 1, LE)?;
+        Ok(offset + LZ4_SECT_HEADER_SIZE + block_len)
+    }
+}
+
+impl<'buf, T: VectBase> FixedSectReader<T> for Lz4MedFixedSect<'buf, T> {
+    fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<T::SI> {
+        let width = T::Utils::BYTE_WIDTH;
+        let mut raw = [0u8; LZ4_RAW_BYTES];
+        let block = &self.sect_bytes[LZ4_SECT_HEADER_SIZE..self.num_bytes()];
+        if lz4::decompress(block, &mut raw[..FIXED_LEN * width])? != FIXED_LEN * width {
+            return Err(CodingError::InvalidFormat("LZ4 block has too few values".into()))
+        }
+        let mut octet = [T::zero(); 8];
+        for group in raw[..FIXED_LEN * width].chunks(8 * width) {
+            for (i, value) in octet.iter_mut().enumerate() {
+                *value = T::Utils::read_le_offset(group, i * width)?;
+            }
+            output.process(T::SI::from_slice(&octet[..]));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "lz4")]
+impl<'buf, T: VectBase> FixedSectionWriter<T> for Lz4MedFixedSect<'buf, T> {
+    /// Writes out the values as an LZ4 block, whatever their size.  See Lz4AutoEncoder for only using LZ4 when it
+    /// is smaller.
+    /// Returns the final offset after last bytes written.
+    fn write(out_buf: &mut [u8],
+             offset: usize,
+             values: &[T],
+             _stats: SectionWriterStats<T>) -> Result<usize, CodingError> {
+        assert_eq!(values.len(), FIXED_LEN);
+        let block = out_buf.get_mut(offset + LZ4_SECT_HEADER_SIZE..).ok_or(CodingError::NotEnoughSpace)?;
+        let block_len = Self::compress_values(values, block)?;
+        Self::write_header(out_buf, offset, block_len)
+    }
+}
+
+impl<'buf, T: VectBase> FixedSection for Lz4MedFixedSect<'buf, T> {
+    fn num_bytes(&self) -> usize { LZ4_SECT_HEADER_SIZE + self.block_bytes as usize }
+    fn sect_bytes(&self) -> Option<&[u8]> { Some(self.sect_bytes) }
+    fn sect_type(&self) -> SectionType { SectionType::Lz4Medium }
+}
+
+/// A Gorilla-style delta-of-delta section for u64 timestamps.  Each value's delta from the previous value is
+/// compared to the previous delta, and the difference is ZigZag encoded into a variable length bit field.
+/// Regularly spaced timestamps need only 1 bit per value.  Decoded one bit field at a time instead of with SIMD.
+/// Binary layout (all offsets are from start of section/type byte)
+///  +0   SectionType::DeltaDeltaMedium
+///  +1   2-byte LE size of this section, including these 3 header bytes
+///  +3   8-byte LE first value
+///  +11  8-byte LE delta of the second value from the first, wrapping
+///  +19  bit stream for the other 254 values, most significant bit of each byte first, see vector_format.md
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeltaDeltaMedFixedSect<'buf> {
+    sect_bytes: &'buf [u8],
+    total_bytes: u16,
+}
+
+const DELTA_DELTA_HEADER_BYTES: usize = 19;
+
+// (prefix, prefix bits, value bits) for each delta-of-delta bucket, from smallest to largest
+const DELTA_DELTA_BUCKETS: [(u64, u32, u32); 5] = [
+    (0b10, 2, 7),
+    (0b110, 3, 9),
+    (0b1110, 4, 12),
+    (0b11110, 5, 32),
+    (0b11111, 5, 64),
+];
+
+impl<'buf> DeltaDeltaMedFixedSect<'buf> {
+    /// Tries to create a new DeltaDeltaMedFixedSect from a byte slice starting from the first section type byte
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let total_bytes: u16 = sect_bytes.pread_with(1, LE)?;
+        if (total_bytes as usize) < DELTA_DELTA_HEADER_BYTES || total_bytes as usize > sect_bytes.len() {
+            return Err(CodingError::InputTooShort);
+        }
+        Ok(Self { sect_bytes, total_bytes })
+    }
+}
+
+impl<'buf> FixedSectReader<u64> for DeltaDeltaMedFixedSect<'buf> {
+    fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
+        where Output: Sink<u64x8> {
+        let mut bits = BitReader { buf: &self.sect_bytes[DELTA_DELTA_HEADER_BYTES..self.total_bytes as usize],
+                                   pos: 0 };
+        let mut value: u64 = self.sect_bytes.pread_with(3, LE)?;
+        let mut delta: u64 = self.sect_bytes.pread_with(11, LE)?;
+        let mut octet = [0u64; 8];
+        octet[0] = value;
+        for i in 1..FIXED_LEN {
+            if i > 1 {
+                let mut prefix_bits = 0;
+                while prefix_bits < 5 && bits.read(1)? == 1 { prefix_bits += 1; }
+                if prefix_bits > 0 {
+                    let zigzag = bits.read(DELTA_DELTA_BUCKETS[prefix_bits - 1].2)?;
+                    delta = delta.wrapping_add((zigzag >> 1) ^ (zigzag & 1).wrapping_neg());
+                }
+            }
+            value = value.wrapping_add(delta);
+            octet[i % 8] = value;
+            if i % 8 == 7 { output.process(u64x8::from_slice_unaligned(&octet)); }
+        }
+        Ok(())
+    }
+}
+
+impl<'buf> FixedSectionWriter<u64> for DeltaDeltaMedFixedSect<'buf> {
+    /// Writes out u64 values as a delta-of-delta bit stream, or a null or constant section if all values are equal.
+    /// Returns the final offset after last bytes written.
+    fn write(out_buf: &mut [u8],
+             offset: usize,
+             values: &[u64],
+             stats: SectionWriterStats<u64>) -> Result<usize, CodingError> {
+        assert_eq!(values.len(), FIXED_LEN);
+        if stats.min == stats.max && stats.min == 0 {
+            return NullFixedSect::write(out_buf, offset);
+        } else if stats.min == stats.max {
+            return ConstFixedSect::write(out_buf, offset, values, stats);
+        }
+
+        out_buf.pwrite_with(SectionType::DeltaDeltaMedium.as_num(), offset, LE)?;
+        let mut prev_delta = values[1].wrapping_sub(values[0]);
+        out_buf.pwrite_with(values[0], offset + 3, LE)?;
+        out_buf.pwrite_with(prev_delta, offset + 11, LE)?;
+        let mut bits = BitWriter { buf: out_buf, pos: offset + DELTA_DELTA_HEADER_BYTES, cur: 0, num_bits: 0 };
+        for pair in values[1..].windows(2) {
+            let delta = pair[1].wrapping_sub(pair[0]);
+            let dod = delta.wrapping_sub(prev_delta) as i64;
+            prev_delta = delta;
+            let zigzag = ((dod << 1) ^ (dod >> 63)) as u64;
+            if zigzag == 0 {
+                bits.write(0, 1)?;
+                continue;
+            }
+            let &(prefix, prefix_bits, value_bits) = DELTA_DELTA_BUCKETS.iter()
+                .find(|&&(_, _, value_bits)| value_bits == 64 || zigzag < 1u64 << value_bits)
+                .unwrap();
+            bits.write(prefix, prefix_bits)?;
+            bits.write(zigzag, value_bits)?;
+        }
+        let off = bits.flush()?;
+
+        let total_bytes = off - offset;
+        if total_bytes <= 65535 {
+            out_buf.pwrite_with(total_bytes as u16, offset + 1, LE)?;
+            Ok(off)
+        } else {
+            Err(CodingError::NotEnoughSpace)
+        }
+    }
+}
+
+impl<'buf> FixedSection for DeltaDeltaMedFixedSect<'buf> {
+    fn num_bytes(&self) -> usize { self.total_bytes as usize }
+    fn sect_bytes(&self) -> Option<&[u8]> { Some(self.sect_bytes) }
+    fn sect_type(&self) -> SectionType { SectionType::DeltaDeltaMedium }
+}
+
+/// A Constant section represents repeating values
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ConstFixedSect<'buf, T: VectBase> {
+    sect_bytes: &'buf [u8],
+    value: T
+}
+
+impl<'buf, T: VectBase> ConstFixedSect<'buf, T> {
+    pub fn try_from(sect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        if sect_bytes.len() >= (1 + T::Utils::BYTE_WIDTH) {
+            let value = T::Utils::read_le_offset(sect_bytes, 1)?;
+            Ok(Self { sect_bytes, value })
+        } else {
+            Err(CodingError::InputTooShort)
+        }
+    }
+
+    pub fn get_value(&self) -> T { self.value }
 }
 
 impl<'buf, T: VectBase> FixedSectReader<T> for ConstFixedSect<'buf, T> {
@@ -813,8 +2178,12 @@ impl<'buf, T: VectBase> FixedSection for ConstFixedSect<'buf, T> {
 /// The AutoEncoder automatically picks the optimal type of section to use based on
 /// the SectionWriterStats.
 /// 1. If min==max, use a Constant or Null section
-/// 2. If min-max range uses less nibbles than otherwise for max, then Delta is a win.
-/// 3. Otherwise use standard NibblePackMedFixedSect
+/// 2. If at most 10% of values differ from the most common value, use SparseFixedSect.  Sparse counters and
+///    mostly constant values with a few spikes end up here.
+/// 3. If values are monotonically increasing and successive deltas use less nibbles than either the min-max
+///    range or max, use MonoDeltaNPMedFixedSect.  Timestamps and counters usually end up here.
+/// 4. If min-max range uses less nibbles than otherwise for max, then Delta is a win.
+/// 5. Otherwise use standard NibblePackMedFixedSect
 pub struct AutoEncoder {}
 
 impl<'buf, T> FixedSectionWriter<T> for AutoEncoder
@@ -831,9 +2200,20 @@ where T: VectBase + PrimInt + Unsigned + num::cast::AsPrimitive<u64> {
                 // Constant section
                 ConstFixedSect::write(out_buf, offset, values, stats)
             }
+        } else if let Some(default) = sparse_default(values) {
+            SparseFixedSect::write_with_default(out_buf, offset, values, default)
         } else {
             let regular_nibbles = (stats.num_bits_max() + 3) / 4;
             let range_nibbles = (stats.num_bits_range() + 3) / 4;
+            // Cheap check for monotonic input before scanning all the deltas
+            if values[0] == stats.min && values[values.len() - 1] == stats.max {
+                if let Some(max_delta) = max_successive_delta(values) {
+                    let delta_bits = (T::Utils::BYTE_WIDTH * 8) as u8 - max_delta.leading_zeros() as u8;
+                    if (delta_bits + 3) / 4 < range_nibbles.min(regular_nibbles) {
+                        return MonoDeltaNPMedFixedSect::write(out_buf, offset, values, stats);
+                    }
+                }
+            }
             // If doing delta results in less nibbles, it will probably save space
             if range_nibbles < regular_nibbles {
                 DeltaNPMedFixedSect::write(out_buf, offset, values, stats)
@@ -845,36 +2225,275 @@ where T: VectBase + PrimInt + Unsigned + num::cast::AsPrimitive<u64> {
 }
 
 
+/// Like the AutoEncoder, but writes a Simple8b section instead whenever it is smaller than the section the
+/// AutoEncoder picked.  Simple8b sections decode one value at a time, so they are slower to decode than
+/// NibblePacked sections; compare the writers on your data with the "u32 vector decode by shape" benchmark.
+pub struct Simple8bAutoEncoder {}
+
+impl<T> FixedSectionWriter<T> for Simple8bAutoEncoder
+where T: VectBase + PrimInt + Unsigned + num::cast::AsPrimitive<u64> {
+    fn write(out_buf: &mut [u8],
+             offset: usize,
+             values: &[T],
+             stats: SectionWriterStats<T>) -> Result<usize, CodingError> {
+        let auto_end = AutoEncoder::write(out_buf, offset, values, stats)?;
+        let mut bits = [0u64; FIXED_LEN];
+        bits.iter_mut().zip(values).for_each(|(b, &value)| *b = value.as_());
+        match simple8b_num_words(&bits[..]) {
+            Some(num_words) if SIMPLE8B_SECT_HEADER_SIZE + num_words * 8 < auto_end - offset =>
+                Simple8bMedFixedSect::<T>::write_words(out_buf, offset, &bits[..]),
+            _ => Ok(auto_end),
+        }
+    }
+}
+
+
+/// Like the AutoEncoder, but writes an Lz4Medium section instead whenever it is smaller than the section the
+/// AutoEncoder picked, which is mostly for high-entropy values that NibblePacking expands.  LZ4 sections must be
+/// decompressed before filtering, so they are much slower to decode.
+#[cfg(feature = "lz4")]
+pub struct Lz4AutoEncoder {}
+
+#[cfg(feature = "lz4")]
+impl<T> FixedSectionWriter<T> for Lz4AutoEncoder
+where T: VectBase + PrimInt + Unsigned + num::cast::AsPrimitive<u64> {
+    fn write(out_buf: &mut [u8],
+             offset: usize,
+             values: &[T],
+             stats: SectionWriterStats<T>) -> Result<usize, CodingError> {
+        let auto_end = AutoEncoder::write(out_buf, offset, values, stats)?;
+        let mut block = [0u8; lz4::max_compressed_len(LZ4_RAW_BYTES)];
+        let block_len = Lz4MedFixedSect::<T>::compress_values(values, &mut block)?;
+        if LZ4_SECT_HEADER_SIZE + block_len < auto_end - offset {
+            let block_start = offset + LZ4_SECT_HEADER_SIZE;
+            out_buf[block_start..block_start + block_len].copy_from_slice(&block[..block_len]);
+            Lz4MedFixedSect::<T>::write_header(out_buf, offset, block_len)
+        } else {
+            Ok(auto_end)
+        }
+    }
+}
+
+
 /// Iterates over a series of encoded FixedSections, basically the data of any Vector encoded as Fixed256
+#[derive(Clone)]
 pub struct FixedSectIterator<'buf, T: VectBase> {
     encoded_bytes: &'buf [u8],
+    last_sect: Option<FixedSectEnum<'buf, T>>,   // For resolving RepeatPrevious sections
     _typ: PhantomData<T>,
 }
 
 impl<'buf, T: VectBase> FixedSectIterator<'buf, T> {
     pub fn new(encoded_bytes: &'buf [u8]) -> Self {
-        FixedSectIterator { encoded_bytes, _typ: PhantomData }
+        FixedSectIterator { encoded_bytes, last_sect: None, _typ: PhantomData }
     }
 }
 
 /// FixedSectIterator iterates over Result of FixedSectEnum.  Any decoding errors, such as trying to decode
 /// a u32 section with u64 or the wrong type, for example, would result in Err(CodingError).
+/// RepeatPrevious sections are resolved transparently by yielding the previous section again.
 /// Iterates until there are no more bytes left in self.encoded_bytes.
 impl<'buf, T: VectBase> Iterator for FixedSectIterator<'buf, T> {
     type Item = Result<FixedSectEnum<'buf, T>, CodingError>;
     fn next(&mut self) -> Option<Self::Item> {
         if self.encoded_bytes.is_empty() {
             None
+        } else if self.encoded_bytes[0] == SectionType::RepeatPrevious.as_num() {
+            match self.last_sect {
+                Some(sect) => {
+                    self.encoded_bytes = &self.encoded_bytes[1..];
+                    Some(Ok(sect))
+                }
+                None => Some(Err(CodingError::InvalidFormat("RepeatPrevious with no previous section".into()))),
+            }
         } else {
             let res = FixedSectEnum::try_from(self.encoded_bytes);
             if let Ok(fsreader) = &res {
                 self.encoded_bytes = &self.encoded_bytes[fsreader.num_bytes()..];
+                self.last_sect = Some(*fsreader);
             }
             Some(res)
         }
     }
 }
 
+/// Size of a CPU cache line, the granularity of prefetching
+const CACHE_LINE_BYTES: usize = 64;
+
+/// Starts loading the payload of a section into cache by touching each of its cache lines, so that the loads
+/// overlap with other work, such as decoding the previous section.  Null sections have no payload.
+#[inline]
+pub fn prefetch_section<T: VectBase>(sect: &FixedSectEnum<T>) {
+    if let Some(bytes) = sect.sect_bytes() {
+        let bytes = &bytes[..sect.num_bytes().min(bytes.len())];
+        for offset in (CACHE_LINE_BYTES..bytes.len()).step_by(CACHE_LINE_BYTES) {
+            // Volatile so that the otherwise unused load is not optimized away
+            unsafe { std::ptr::read_volatile(&bytes[offset]); }
+        }
+    }
+}
+
+/// Iterates over sections like FixedSectIterator, but one section ahead, for software pipelining: the header
+/// of section N+1 is parsed and its payload prefetched before section N is returned, so that the header parsing
+/// and memory loads of the next section overlap with the decoding of the current one instead of serializing.
+pub struct LookaheadSectIterator<'buf, T: VectBase> {
+    sect_iter: FixedSectIterator<'buf, T>,
+    next_sect: Option<Result<FixedSectEnum<'buf, T>, CodingError>>,
+}
+
+impl<'buf, T: VectBase> LookaheadSectIterator<'buf, T> {
+    pub fn new(encoded_bytes: &'buf [u8]) -> Self {
+        let mut sect_iter = FixedSectIterator::new(encoded_bytes);
+        let next_sect = sect_iter.next();
+        Self { sect_iter, next_sect }
+    }
+}
+
+impl<'buf, T: VectBase> Iterator for LookaheadSectIterator<'buf, T> {
+    type Item = Result<FixedSectEnum<'buf, T>, CodingError>;
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let sect = self.next_sect.take()?;
+        // The iterator cannot advance past an error, so stop after returning it
+        if sect.is_ok() {
+            self.next_sect = self.sect_iter.next();
+            if let Some(Ok(next)) = &self.next_sect { prefetch_section(next) }
+        }
+        Some(sect)
+    }
+}
+
+/// A LazySection wraps a FixedSectEnum, giving cheap access to metadata which can be read from the section
+/// header alone -- the section type, whether it is null, and bounds on the values where available.
+/// The payload is only decoded on the first access to the values, after which the values are cached.
+/// This lets a query planner skip sections based on metadata and only pay the decoding cost when needed.
+/// ```
+/// # use compressed_vec::raw::section::*;
+/// # let mut buf = [0u8; 16];
+/// # ConstFixedSect::gen_stats_and_write(&mut buf, 0, &[42u32; 256]).unwrap();
+///     let mut lazy = LazySection::<u32>::try_from(&buf[..]).unwrap();
+///     assert_eq!(lazy.bounds(), Some((42, 42)));     // No decoding needed
+///     assert_eq!(lazy.values().unwrap()[255], 42);   // Decodes and caches values
+/// ```
+pub struct LazySection<'buf, T: VectBase> {
+    sect: FixedSectEnum<'buf, T>,
+    decoded: Option<Box<Section256Sink<T>>>,
+    tracker: Option<Arc<dyn MemoryTracker>>,
+}
+
+impl<'buf, T: VectBase> LazySection<'buf, T> {
+    pub fn new(sect: FixedSectEnum<'buf, T>) -> Self {
+        Self { sect, decoded: None, tracker: None }
+    }
+
+    /// Creates a LazySection whose cache of decoded values is reported to the MemoryTracker
+    pub fn with_tracker(sect: FixedSectEnum<'buf, T>, tracker: Arc<dyn MemoryTracker>) -> Self {
+        Self { sect, decoded: None, tracker: Some(tracker) }
+    }
+
+    /// Parses the section at the beginning of the slice, without decoding it
+    pub fn try_from(s: &'buf [u8]) -> Result<Self, CodingError> {
+        FixedSectEnum::try_from(s).map(Self::new)
+    }
+
+    #[inline]
+    pub fn sect_type(&self) -> SectionType { self.sect.sect_type() }
+
+    #[inline]
+    pub fn is_null(&self) -> bool { self.sect.is_null() }
+
+    #[inline]
+    pub fn num_bytes(&self) -> usize { self.sect.num_bytes() }
+
+    /// Returns the underlying section
+    pub fn section(&self) -> FixedSectEnum<'buf, T> { self.sect }
+
+    /// True if the values have already been decoded and cached
+    pub fn is_decoded(&self) -> bool { self.decoded.is_some() }
+
+    /// Returns all FIXED_LEN values of the section, decoding them on the first call only.
+    pub fn values(&mut self) -> Result<&[T; FIXED_LEN], CodingError> {
+        if self.decoded.is_none() {
+            if let Some(tracker) = &self.tracker { tracker.allocate(std::mem::size_of::<Section256Sink<T>>())?; }
+            let mut sink = Box::new(Section256Sink::<T>::new());
+            if let Err(e) = self.sect.decode(sink.as_mut()) {
+                if let Some(tracker) = &self.tracker { tracker.release(std::mem::size_of::<Section256Sink<T>>()); }
+                return Err(e);
+            }
+            self.decoded = Some(sink);
+        }
+        Ok(&self.decoded.as_ref().unwrap().values)
+    }
+
+    /// Returns the value at position index within the section, decoding the section if needed.
+    pub fn get(&mut self, index: usize) -> Result<T, CodingError> {
+        if index >= FIXED_LEN { return Err(CodingError::BadOffset(index)) }
+        self.values().map(|values| values[index])
+    }
+
+    /// Decodes the section to a sink without caching.
+    pub fn decode<S: Sink<T::SI>>(&self, sink: &mut S) -> Result<(), CodingError> {
+        self.sect.decode(sink)
+    }
+}
+
+impl<'buf, T: VectBase> Drop for LazySection<'buf, T> {
+    fn drop(&mut self) {
+        if let (Some(tracker), Some(_)) = (&self.tracker, &self.decoded) {
+            tracker.release(std::mem::size_of::<Section256Sink<T>>());
+        }
+    }
+}
+
+impl<'buf, T: VectBase + NumCast> LazySection<'buf, T> {
+    /// Returns inclusive (lower, upper) bounds on the values in this section, using only header information.
+    /// Bounds are exact for null, constant and monotonic delta sections.  For other delta sections the lower
+    /// bound is exact, but the upper bound is rounded up to the next power of two of the delta range.
+    /// Returns None if the bounds can only be found by decoding.
+    pub fn bounds(&self) -> Option<(T, T)> {
+        match self.sect {
+            FixedSectEnum::NullFixedSect(_) => Some((T::zero(), T::zero())),
+            FixedSectEnum::ConstFixedSect(cs) => Some((cs.value, cs.value)),
+            FixedSectEnum::MonoDeltaNPMedFixedSect(ms) => Some((ms.first, ms.last)),
+            FixedSectEnum::DeltaNPMedFixedSect(ds) => {
+                let max_delta = 1u64.checked_shl(ds.delta_numbits as u32).map_or(u64::max_value(), |r| r - 1);
+                let max_delta: T = NumCast::from(max_delta)?;
+                let upper = if T::max_value() - ds.base < max_delta { T::max_value() }
+                            else                                    { ds.base + max_delta };
+                Some((ds.base, upper))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns inclusive (lower, upper) bounds on the values in this section for previews, such as sparklines
+    /// over huge vectors, where exact values don't matter.  Much cheaper than decoding for most sections:
+    /// - Null, constant and delta sections use bounds()
+    /// - NibblePacked sections only read the header of each group of 8: the lower bound is 0 and the upper
+    ///   bound is the largest value with all bits set up to its highest nibble, eg 4095 for 765
+    /// - Other sections, such as XOR floats, are decoded for their exact min and max
+    pub fn preview(&self) -> Result<(T, T), CodingError> {
+        if let Some(bounds) = self.bounds() { return Ok(bounds) }
+        let (sect_bytes, mode) = match self.sect {
+            FixedSectEnum::NibblePackMedFixedSect(s) => (s.sect_bytes, nibblepacking::NibblePackMode::Standard),
+            FixedSectEnum::NibblePackZRMedFixedSect(s) => (s.sect_bytes, nibblepacking::NibblePackMode::ZeroRuns),
+            _ => {
+                let mut sink = Section256Sink::<T>::new();
+                let values = match &self.decoded {
+                    Some(decoded) => &decoded.values,
+                    None => { self.sect.decode(&mut sink)?; &sink.values }
+                };
+                return Ok(values.iter().fold((values[0], values[0]), |(lower, upper), &v| {
+                    (if v < lower { v } else { lower }, if v > upper { v } else { upper })
+                }));
+            }
+        };
+        let bits = nibblepacking::max_bits_with_mode(&sect_bytes[3..], FIXED_LEN, mode)?;
+        let upper = 1u64.checked_shl(bits).map_or(u64::max_value(), |r| r - 1);
+        Ok((T::zero(), NumCast::from(upper).unwrap_or_else(T::max_value)))
+    }
+}
+
 // This is partly for perf disassembly and partly for convenience
 pub fn unpack_u32_section(buf: &[u8]) -> [u32; 256] {
     let mut sink = U32_256Sink::new();
@@ -921,6 +2540,38 @@ mod tests {
         assert_eq!(writer.cur_pos(), 13);
     }
 
+    #[test]
+    fn test_sectwriter_overflow_and_section_full() {
+        let mut buf = vec![0u8; 200_000];
+        let mut writer = SectionWriter::new(&mut buf[..], 10);
+
+        // Fillers claiming more bytes or elements than given are errors, and leave the writer unchanged
+        assert_eq!(writer.add_64kb(SectionType::Null, |_, _| Ok((0, 11))), Err(CodingError::Overflow));
+        assert_eq!(writer.cur_pos(), SECTION_HEADER_SIZE);
+
+        // Fill a section right up to 65535 bytes, then roll over
+        let fill_64kb = |b: &mut [u8], _| if b.len() < 65535 { Err(CodingError::NotEnoughSpace) }
+                                          else { Ok((65535, 1)) };
+        assert_eq!(writer.add_64kb(SectionType::Null, fill_64kb), Ok((65535, 1)));
+        assert_eq!(writer.add_64kb(SectionType::Null, fill_64kb), Ok((65535, 1)));
+        assert_eq!(writer.cur_pos(), 2 * (SECTION_HEADER_SIZE + 65535));
+
+        // An item which never fits in a section is SectionFull, not an endless rollover
+        let res = writer.add_64kb(SectionType::Null, |_, _| Err(CodingError::NotEnoughSpace));
+        assert_eq!(res, Err(CodingError::SectionFull));
+
+        let mut buf = [0u8; 64];
+        let mut writer = SectionWriter::new(&mut buf, 10);
+        assert_eq!(writer.add_64kb(SectionType::Null, |b, _| Ok((b.len() as u16 + 1, 1))),
+                   Err(CodingError::Overflow));
+
+        // No elements allowed per section
+        let mut writer = SectionWriter::new(&mut buf, 0);
+        let res = writer.add_64kb(SectionType::Null, |_, left| if left == 0 { Err(CodingError::NotEnoughSpace) }
+                                                                else { Ok((0, 1)) });
+        assert_eq!(res, Err(CodingError::NotEnoughSpace));
+    }
+
     #[test]
     fn test_npu64med_write_error_no_room() {
         // Allocate a buffer that's not large enough - first, no room for header
@@ -971,6 +2622,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fixedsectiterator_repeat_previous() {
+        let mut buf = [0u8; 1024];
+        buf[0] = SectionType::RepeatPrevious.as_num();
+        let res = FixedSectIterator::<u64>::new(&buf[..1]).next().unwrap();
+        assert!(res.is_err());
+
+        let data: Vec<u64> = (0..256).collect();
+        let mut off = NibblePackMedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        buf[off] = SectionType::RepeatPrevious.as_num();
+        buf[off + 1] = SectionType::RepeatPrevious.as_num();
+        off += 2;
+
+        let sections = FixedSectIterator::<u64>::new(&buf[0..off]).map(|x| x.unwrap())
+                                                                    .collect::<Vec<FixedSectEnum<u64>>>();
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[2], sections[0]);
+        let mut sink = U64_256Sink::new();
+        sections[2].decode(&mut sink).unwrap();
+        assert_eq!(sink.values[..], data[..]);
+    }
+
+    #[test]
+    fn test_lookahead_iterator_matches_fixedsectiterator() {
+        let mut buf = [0u8; 4096];
+        let mut off = NibblePackMedFixedSect::gen_stats_and_write(&mut buf, 0, &[7u64; 256]).unwrap();
+        off = NullFixedSect::write(&mut buf, off).unwrap();
+        let data: Vec<u64> = (0..256).map(|i| i * 1000).collect();
+        off = NibblePackMedFixedSect::gen_stats_and_write(&mut buf, off, &data[..]).unwrap();
+        buf[off] = SectionType::RepeatPrevious.as_num();
+        off += 1;
+
+        let expected: Vec<_> = FixedSectIterator::<u64>::new(&buf[..off]).collect();
+        let sections: Vec<_> = LookaheadSectIterator::<u64>::new(&buf[..off]).collect();
+        assert_eq!(sections.len(), 4);
+        assert_eq!(sections, expected);
+
+        // A bad section ends the iteration after the error
+        buf[off] = 0xff;
+        let sections: Vec<_> = LookaheadSectIterator::<u64>::new(&buf[..off + 1]).collect();
+        assert_eq!(sections.len(), 5);
+        assert_eq!(sections[4], Err(CodingError::InvalidSectionType(0xff)));
+        assert_eq!(LookaheadSectIterator::<u64>::new(&buf[..0]).next(), None);
+    }
+
     #[test]
     fn test_fixedsect_u32_write_and_decode() {
         let mut buf = [0u8; 1024];
@@ -984,6 +2680,33 @@ mod tests {
         assert_eq!(values.iter().map(|&x| x).collect::<Vec<u32>>(), data);
     }
 
+    #[test]
+    fn test_zero_runs_write_and_decode() {
+        let mut buf = [0u8; 1024];
+        let mut data = [0u64; 256];
+        data[3] = 1000;
+        data[200] = 5;
+
+        let std_bytes = NibblePackMedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        let zr_bytes = NibblePackZRMedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        assert!(zr_bytes < std_bytes / 2);
+
+        let sect = FixedSectEnum::<u64>::try_from(&buf[..]).unwrap();
+        assert_eq!(sect.sect_type(), SectionType::NibblePackedZRMedium);
+        assert_eq!(sect.num_bytes(), zr_bytes);
+        let mut sink = U64_256Sink::new();
+        sect.decode(&mut sink).unwrap();
+        assert_eq!(sink.values[..], data[..]);
+
+        // u32, all zeroes
+        let data = [0u32; 256];
+        let off = NibblePackZRMedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        assert_eq!(off, 5);
+        let mut sink = U32_256Sink::new();
+        FixedSectEnum::<u32>::try_from(&buf[..]).unwrap().decode(&mut sink).unwrap();
+        assert_eq!(sink.values[..], data[..]);
+    }
+
     #[test]
     fn test_delta_write_and_decode() {
         // u64
@@ -1050,8 +2773,8 @@ mod tests {
             _ => panic!("Got the wrong sect: {:?}", sect),
         }
 
-        // Test 3: Normal items range between 1 and n
-        let data: Vec<u32> = (0..256).collect();
+        // Test 3: Normal items range between 1 and n.  Decreasing, as increasing values would be monotonic delta
+        let data: Vec<u32> = (0..256).rev().collect();
         let _off = AutoEncoder::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
         let sect = FixedSectEnum::<u32>::try_from(&buf[..]).unwrap();
         match sect {
@@ -1060,7 +2783,7 @@ mod tests {
         }
 
         // Test 4: Elevated, should be delta (max-min << max)
-        let data: Vec<u32> = (10_000..10_256).collect();
+        let data: Vec<u32> = (10_000..10_256).rev().collect();
         let _off = AutoEncoder::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
         let sect = FixedSectEnum::<u32>::try_from(&buf[..]).unwrap();
         match sect {
@@ -1080,8 +2803,185 @@ mod tests {
         let mut sink = Section256Sink::<f32>::new();
         let section = XorNPMedFixedSect::try_from(&buf).unwrap();
         dbg!(section.num_bytes());
-        section.decode_to_sink(&mut sink).unwrap();
+        FixedSectReader::<f32>::decode_to_sink(&section, &mut sink).unwrap();
+        assert_eq!(sink.values[..], data[..]);
+    }
+
+    #[test]
+    fn test_xor_f64_write_and_decode() {
+        let mut buf = [0u8; 4096];
+        let mut sink = Section256Sink::<f64>::new();
+
+        // Slowly changing gauge with repeats, plus special values
+        let mut data: Vec<f64> = (0..256).map(|i| 100.0 + (i / 4) as f64 * 0.5).collect();
+        data[7] = -0.0;
+        data[100] = f64::MAX;
+        data[101] = f64::MIN_POSITIVE;
+        data[200] = f64::INFINITY;
+        let off = XorF64MedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        let sect = FixedSectEnum::<f64>::try_from(&buf[..]).unwrap();
+        assert_eq!((sect.sect_type(), sect.num_bytes()), (SectionType::XorF64Medium, off));
+        sect.decode(&mut sink).unwrap();
+        assert_eq!(sink.values.iter().map(|v| v.to_bits()).collect::<Vec<_>>(),
+                   data.iter().map(|v| v.to_bits()).collect::<Vec<_>>());
+
+        // Much smaller than XorNP for gauges
+        let gauge: Vec<f64> = (0..256).map(|i| 100.0 + (i / 4) as f64 * 0.5).collect();
+        let gorilla_bytes = XorF64MedFixedSect::gen_stats_and_write(&mut buf, 0, &gauge[..]).unwrap();
+        let xornp_bytes = XorNPMedFixedSect::gen_stats_and_write(&mut buf, 0, &gauge[..]).unwrap();
+        assert!(gorilla_bytes < xornp_bytes / 2);
+
+        // Truncated bit streams are errors, and the section is invalid for other types
+        XorF64MedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        buf[1..3].copy_from_slice(&(off as u16 - 10).to_le_bytes());
+        let sect = FixedSectEnum::<f64>::try_from(&buf[..]).unwrap();
+        sink.reset();
+        assert_eq!(sect.decode(&mut sink), Err(CodingError::InputTooShort));
+        assert!(FixedSectEnum::<u64>::try_from(&buf[..]).unwrap().decode(&mut Section256Sink::<u64>::new()).is_err());
+    }
+
+    #[test]
+    fn test_mono_delta_write_and_decode() {
+        let mut buf = [0u8; 4096];
+        let mut sink = Section256Sink::<u64>::new();
+
+        // Millisecond timestamps with a little jitter and some repeats
+        let data: Vec<u64> = (0..256u64).map(|i| 1_600_000_000_000 + i * 10_000 + (i % 3) * 7).collect();
+        let off = AutoEncoder::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        let sect = FixedSectEnum::<u64>::try_from(&buf[..]).unwrap();
+        assert_eq!((sect.sect_type(), sect.num_bytes()), (SectionType::MonoDeltaNPMedium, off));
+        sect.decode(&mut sink).unwrap();
+        assert_eq!(sink.values[..], data[..]);
+        assert_eq!(LazySection::new(sect).bounds(), Some((data[0], data[255])));
+
+        // Much smaller than delta from the minimum
+        let delta_bytes = DeltaNPMedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        assert!(off < delta_bytes);
+
+        // u32 counters, including zero deltas
+        let counters: Vec<u32> = (0..256u32).map(|i| 70_000 + i / 2).collect();
+        let mut sink32 = Section256Sink::<u32>::new();
+        AutoEncoder::gen_stats_and_write(&mut buf, 0, &counters[..]).unwrap();
+        let sect = FixedSectEnum::<u32>::try_from(&buf[..]).unwrap();
+        assert_eq!(sect.sect_type(), SectionType::MonoDeltaNPMedium);
+        sect.decode(&mut sink32).unwrap();
+        assert_eq!(sink32.values[..], counters[..]);
+
+        // Non-monotonic input falls back to delta from the minimum
+        let mut jumbled = data.clone();
+        jumbled.swap(10, 11);
+        MonoDeltaNPMedFixedSect::gen_stats_and_write(&mut buf, 0, &jumbled[..]).unwrap();
+        assert_eq!(SectionType::try_from(buf[0]).unwrap(), SectionType::DeltaNPMedium);
+        assert_eq!(AutoEncoder::gen_stats_and_write(&mut buf, 0, &jumbled[..]).unwrap(), delta_bytes);
+    }
+
+    #[test]
+    fn test_delta_delta_write_and_decode() {
+        let mut buf = [0u8; 4096];
+        let mut sink = Section256Sink::<u64>::new();
+
+        // Regularly sampled timestamps take 1 bit per value
+        let regular: Vec<u64> = (0..256u64).map(|i| 1_600_000_000_000 + i * 10_000).collect();
+        let off = DeltaDeltaMedFixedSect::gen_stats_and_write(&mut buf, 0, &regular[..]).unwrap();
+        assert_eq!(off, 19 + 254 / 8 + 1);
+        let sect = FixedSectEnum::<u64>::try_from(&buf[..]).unwrap();
+        assert_eq!((sect.sect_type(), sect.num_bytes()), (SectionType::DeltaDeltaMedium, off));
+        sect.decode(&mut sink).unwrap();
+        assert_eq!(sink.values[..], regular[..]);
+
+        // Jitter, missed samples, a step backwards and huge jumps use every bucket
+        let mut data = regular.clone();
+        data[10] += 3;
+        data[20] -= 200;
+        data[30] += 2000;
+        data[40] += 1_000_000;
+        data[50] = 5;
+        data[51] = u64::max_value();
+        let off = DeltaDeltaMedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        let sect = FixedSectEnum::<u64>::try_from(&buf[..]).unwrap();
+        assert_eq!(sect.num_bytes(), off);
+        sink.reset();
+        sect.decode(&mut sink).unwrap();
+        assert_eq!(sink.values[..], data[..]);
+
+        // Truncated bit streams are errors, and the section is invalid for other types
+        buf[1..3].copy_from_slice(&(off as u16 - 10).to_le_bytes());
+        let sect = FixedSectEnum::<u64>::try_from(&buf[..]).unwrap();
+        sink.reset();
+        assert_eq!(sect.decode(&mut sink), Err(CodingError::InputTooShort));
+        assert!(FixedSectEnum::<u32>::try_from(&buf[..]).unwrap().decode(&mut Section256Sink::<u32>::new()).is_err());
+    }
+
+    #[test]
+    fn test_simple8b_write_and_decode() {
+        let mut buf = [0u8; 4096];
+        let mut sink = Section256Sink::<u64>::new();
+
+        // A run of zeroes, small values and a few wide ones
+        let data: Vec<u64> = (0..256u64).map(|i| match i {
+            0..=119   => 0,
+            120..=199 => i % 3,
+            _         => i << 40,
+        }).collect();
+        let off = Simple8bMedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        // 1 word for the zeroes, 3 for 80 2-bit values (30 + 30 + 20) and 56 for the 49-bit values
+        assert_eq!(off, 3 + (1 + 3 + 56) * 8);
+        let sect = FixedSectEnum::<u64>::try_from(&buf[..]).unwrap();
+        assert_eq!((sect.sect_type(), sect.num_bytes()), (SectionType::Simple8bMedium, off));
+        sect.decode(&mut sink).unwrap();
         assert_eq!(sink.values[..], data[..]);
+
+        // Values over 60 bits fall back to NibblePack
+        let mut wide = data.clone();
+        wide[255] = 1 << 61;
+        Simple8bMedFixedSect::gen_stats_and_write(&mut buf, 0, &wide[..]).unwrap();
+        assert_eq!(buf[0], SectionType::NibblePackedMedium.as_num());
+
+        // The auto encoder keeps the smaller of the AutoEncoder's section and Simple8b
+        let mixed: Vec<u32> = (0..256u32).map(|i| if i % 2 == 0 { 1 } else { i % 4 }).collect();
+        let auto_off = AutoEncoder::gen_stats_and_write(&mut buf, 0, &mixed[..]).unwrap();
+        let off = Simple8bAutoEncoder::gen_stats_and_write(&mut buf, 0, &mixed[..]).unwrap();
+        assert_eq!(buf[0], SectionType::Simple8bMedium.as_num());
+        assert!(off < auto_off);
+        let constant = [7u32; 256];
+        assert_eq!(Simple8bAutoEncoder::gen_stats_and_write(&mut buf, 0, &constant[..]).unwrap(), 5);
+
+        // Sections with too few values are errors
+        let off = Simple8bMedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        buf[1..3].copy_from_slice(&((off as u16 - 3) / 8 - 1).to_le_bytes());
+        let sect = FixedSectEnum::<u64>::try_from(&buf[..]).unwrap();
+        sink.reset();
+        assert!(sect.decode(&mut sink).is_err());
+    }
+
+    #[test]
+    fn test_lazy_section_metadata_and_decode() {
+        let mut buf = [0u8; 1024];
+        let data: Vec<u32> = (10_000..10_256).collect();
+        let off = DeltaNPMedFixedSect::gen_stats_and_write(&mut buf, 0, &data[..]).unwrap();
+        let off2 = NullFixedSect::write(&mut buf, off).unwrap();
+        NibblePackMedFixedSect::gen_stats_and_write(&mut buf, off2, &data[..]).unwrap();
+
+        let mut sections = FixedSectIterator::<u32>::new(&buf[..]).map(|s| LazySection::new(s.unwrap()));
+
+        // Delta section: bounds known from header, max rounded up to power of 2
+        let mut lazy = sections.next().unwrap();
+        assert_eq!(lazy.sect_type(), SectionType::DeltaNPMedium);
+        assert_eq!(lazy.bounds(), Some((10_000, 10_255)));
+        assert!(!lazy.is_decoded());
+        assert_eq!(lazy.get(5).unwrap(), 10_005);
+        assert!(lazy.is_decoded());
+        assert_eq!(lazy.values().unwrap()[..], data[..]);
+        assert_eq!(lazy.get(256), Err(CodingError::BadOffset(256)));
+
+        let lazy = sections.next().unwrap();
+        assert!(lazy.is_null());
+        assert_eq!(lazy.bounds(), Some((0, 0)));
+
+        // Regular NibblePacked sections need decoding to find bounds
+        let mut lazy = sections.next().unwrap();
+        assert_eq!(lazy.bounds(), None);
+        assert_eq!(lazy.values().unwrap()[..], data[..]);
     }
 
     #[test]
@@ -1124,5 +3024,61 @@ mod tests {
         sect.decode(&mut sink).unwrap();
         assert_eq!(sink.values[..], data[..]);
     }
+
+    #[test]
+    fn test_float_nibblepack_decode_is_an_error() {
+        let buf = [0u8; 64];
+        let res = <FSUtilsMarker as FSUtils<f32>>::nibblepack_decode(&buf[..], &mut Section256Sink::<f32>::new());
+        assert!(matches!(res, Err(CodingError::InvalidFormat(_))));
+        let res = <FSUtilsMarker as FSUtils<f64>>::nibblepack_decode(&buf[..], &mut Section256Sink::<f64>::new());
+        assert!(matches!(res, Err(CodingError::InvalidFormat(_))));
+    }
 }
 
+#[cfg(test)]
+mod props {
+    extern crate proptest;
+
+    use self::proptest::prelude::*;
+    use super::*;
+    use crate::stream::SectionHeaderIter;
+
+    // Fill sizes clustered around the 64KB section boundary, plus small ones
+    fn arb_fill() -> impl Strategy<Value = (usize, u16)> {
+        (prop_oneof![0usize..64, 16_000usize..16_400, 32_700usize..32_800, 65_500usize..=65_535],
+         0u16..20)
+    }
+
+    proptest! {
+        #[test]
+        fn prop_sectwriter_rollover(fills in proptest::collection::vec(arb_fill(), 1..24),
+                                    max_elements in 0u16..40,
+                                    buf_len in 0usize..400_000) {
+            let mut buf = vec![0u8; buf_len];
+            let mut writer = SectionWriter::new(&mut buf[..], max_elements);
+            let mut written = (0usize, 0usize);
+            for &(num_bytes, num_elements) in &fills {
+                let res = writer.add_64kb(SectionType::Null, |b, left| {
+                    if num_bytes > b.len() || num_elements as usize > left { Err(CodingError::NotEnoughSpace) }
+                    else { Ok((num_bytes as u16, num_elements)) }
+                });
+                match res {
+                    Ok((b, e)) => {
+                        prop_assert_eq!((b as usize, e), (num_bytes, num_elements));
+                        written = (written.0 + num_bytes, written.1 + num_elements as usize);
+                    }
+                    Err(CodingError::NotEnoughSpace) | Err(CodingError::SectionFull) => {}
+                    Err(e) => prop_assert!(false, "Unexpected error {:?}", e),
+                }
+                prop_assert!(writer.cur_pos() <= buf_len);
+            }
+
+            // Every section is within limits, and the sections add up to what was written
+            let stream_len = writer.cur_pos();
+            let headers: Vec<_> = SectionHeaderIter::new(&buf[..stream_len]).collect::<Result<_, _>>().unwrap();
+            prop_assert!(headers.iter().all(|(h, _)| h.num_elements() <= max_elements as usize));
+            prop_assert_eq!(headers.iter().map(|(h, _)| h.num_bytes()).sum::<usize>(), written.0);
+            prop_assert_eq!(headers.iter().map(|(h, _)| h.num_elements()).sum::<usize>(), written.1);
+        }
+    }
+}
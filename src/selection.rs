@@ -0,0 +1,308 @@
+/// The `selection` module contains `SelectionBuilder`, which builds a new encoded vector from the values of
+/// source sections selected by 256-bit filter masks, such as those from `VectorFilter`.  Selected values are
+/// appended directly from the decoding sink to a `VectorAppender`, fusing filter, compaction and re-encoding
+/// without decoding whole sections or vectors into intermediate arrays.  Sections with no matches are skipped
+/// without being decoded.  Selected null elements of PartialNull sections are appended as nulls, and the
+/// appender is then set to track nulls until the new vector is finished, so they stay null in it.  The
+/// elements of Null sections, and other zeroes, are appended as zero values.
+/// ```
+/// # use compressed_vec::filter::EqualsSink;
+/// # use compressed_vec::selection::SelectionBuilder;
+/// # use compressed_vec::vector::{VectorU32Appender, VectorReader, VectorU64Appender};
+///     let mut appender = VectorU32Appender::try_new(4096).unwrap();
+///     let keys = appender.encode_all((0..1000u32).map(|i| i % 10)).unwrap();
+///     let mut appender = VectorU64Appender::try_new(4096).unwrap();
+///     let values = appender.encode_all((0..1000u64).map(|i| i * 100)).unwrap();
+///
+///     // Select values where key == 3
+///     let key_reader = VectorReader::<u32>::try_new(&keys[..]).unwrap();
+///     let masks = key_reader.filter_iter(EqualsSink::<u32>::new(&3));
+///     let mut builder = SelectionBuilder::<u64, _>::new(VectorU64Appender::try_new(1024).unwrap());
+///     builder.select_vector(&values[..], masks).unwrap();
+///     let selected = builder.finish().unwrap();
+///
+///     let reader = VectorReader::<u64>::try_new(&selected[..]).unwrap();
+///     assert_eq!(reader.num_elements(), 100);
+///     assert_eq!(reader.iterate().take(3).collect::<Vec<_>>(), vec![300, 1300, 2300]);
+/// ```
+use std::convert::TryFrom;
+
+use packed_simd::u32x8;
+
+use crate::error::CodingError;
+use crate::section::*;
+use crate::sink::{Sink, SinkInput};
+use crate::vector::{BaseSubtypeMapping, VectorAppender, VectorReader};
+
+
+/// Sink which appends the values whose mask bits are set to an appender, 8 at a time
+struct SelectSink<'a, T, W>
+where T: VectBase + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    mask: [u32; 8],
+    valid: [u32; 8],        // Non-null elements
+    octet: usize,
+    appender: &'a mut VectorAppender<T, W>,
+    result: Result<(), CodingError>,
+}
+
+impl<'a, T, W> SelectSink<'a, T, W>
+where T: VectBase + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    fn new(mask: u32x8, valid: u32x8, appender: &'a mut VectorAppender<T, W>) -> Self {
+        let mut words = [0u32; 8];
+        mask.write_to_slice_unaligned(&mut words);
+        let mut valid_words = [0u32; 8];
+        valid.write_to_slice_unaligned(&mut valid_words);
+        Self { mask: words, valid: valid_words, octet: 0, appender, result: Ok(()) }
+    }
+
+    // The mask bits and validity bits for the next 8 values
+    #[inline]
+    fn next_bits(&mut self) -> (u8, u8) {
+        let bits = if self.octet < 32 {
+            let shift = (self.octet % 4) * 8;
+            ((self.mask[self.octet / 4] >> shift) as u8, (self.valid[self.octet / 4] >> shift) as u8)
+        } else {
+            (0, 0)
+        };
+        self.octet += 1;
+        bits
+    }
+}
+
+impl<'a, T, W> Sink<T::SI> for SelectSink<'a, T, W>
+where T: VectBase + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    #[inline]
+    fn process(&mut self, data: T::SI) {
+        let (bits, valid) = self.next_bits();
+        if bits == 0 || self.result.is_err() { return }
+        let mut values = [T::zero(); 8];
+        data.write_to_slice(&mut values);
+        for (i, &value) in values.iter().enumerate() {
+            if bits & (1 << i) != 0 {
+                let res = if valid & (1 << i) != 0 { self.appender.append(value) }
+                          else                     { self.appender.append_nulls(1) };
+                if let Err(e) = res {
+                    self.result = Err(e);
+                    return;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        let (bits, valid) = self.next_bits();
+        if bits == 0 || self.result.is_err() { return }
+        if bits & !valid == 0 {
+            self.result = self.appender.append_zeroes(bits.count_ones() as usize);
+            return;
+        }
+        for i in 0..8 {
+            if bits & (1 << i) != 0 {
+                let res = if valid & (1 << i) != 0 { self.appender.append_zeroes(1) }
+                          else                     { self.appender.append_nulls(1) };
+                if let Err(e) = res {
+                    self.result = Err(e);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.octet = 0;
+    }
+}
+
+/// Builds a new vector out of the values selected from source sections by masks.
+/// Bit n of the mask (bit n % 32 of u32 lane n / 32) selects element n of the section.
+pub struct SelectionBuilder<T, W>
+where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    appender: VectorAppender<T, W>,
+    tracked: bool,          // Whether the appender tracked nulls before any were selected
+}
+
+impl<T, W> SelectionBuilder<T, W>
+where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    /// Creates a SelectionBuilder which writes selected values using the given appender, so the encoding
+    /// of the new vector can be chosen.
+    pub fn new(appender: VectorAppender<T, W>) -> Self {
+        let tracked = appender.tracks_nulls();
+        Self { appender, tracked }
+    }
+
+    /// Number of values selected so far
+    pub fn num_elements(&self) -> usize {
+        self.appender.num_elements()
+    }
+
+    /// Appends the values of a decoded section selected by the mask.
+    pub fn select(&mut self, sect: FixedSectEnum<T>, mask: u32x8) -> Result<(), CodingError> {
+        let num_selected = mask.count_ones().wrapping_sum() as usize;
+        if num_selected == 0 { return Ok(()) }
+        if sect.is_null() { return self.appender.append_zeroes(num_selected) }
+
+        let valid = sect.validity_mask();
+        if mask & !valid != u32x8::splat(0) { self.appender.track_nulls(true); }
+        let mut sink = SelectSink::new(mask, valid, &mut self.appender);
+        sect.decode(&mut sink)?;
+        sink.result
+    }
+
+    /// Appends the values of the section at the start of sect_bytes selected by the mask.
+    pub fn select_section(&mut self, sect_bytes: &[u8], mask: u32x8) -> Result<(), CodingError> {
+        self.select(FixedSectEnum::<T>::try_from(sect_bytes)?, mask)
+    }
+
+    /// Appends the values of a whole vector selected by masks, one mask per section, for example from a
+    /// `VectorFilter` or `MultiVectorFilter`.  Mask bits past the number of elements in the vector are ignored.
+    /// Sections without a mask are not selected.
+    pub fn select_vector<M>(&mut self, vect_bytes: &[u8], masks: M) -> Result<(), CodingError>
+    where M: Iterator<Item = u32x8> {
+        let reader = VectorReader::<T>::try_new(vect_bytes)?;
+        let mut elems_left = reader.num_elements();
+        for (sect, mask) in reader.sect_iter().zip(masks) {
+            let mask = if elems_left < FIXED_LEN { mask & first_n_mask(elems_left) } else { mask };
+            self.select(sect?, mask)?;
+            elems_left = elems_left.saturating_sub(FIXED_LEN);
+        }
+        Ok(())
+    }
+
+    /// Finishes the new vector with all the values selected so far, returning its bytes.
+    /// The builder is reset and may be used again, with the appender's original null tracking.
+    pub fn finish(&mut self) -> Result<Vec<u8>, CodingError> {
+        let num_elements = self.appender.num_elements();
+        let result = self.appender.finish(num_elements);
+        self.appender.track_nulls(self.tracked);
+        result
+    }
+}
+
+/// A mask with the first n bits (elements) set
+pub(crate) fn first_n_mask(n: usize) -> u32x8 {
+    let mut words = [0u32; 8];
+    for (i, word) in words.iter_mut().enumerate() {
+        let bits = n.saturating_sub(i * 32).min(32);
+        *word = if bits == 32 { u32::MAX } else { (1u32 << bits) - 1 };
+    }
+    u32x8::from(words)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{FilterFnSink, MultiVectorFilter, EqualsSink};
+    use crate::vector::{VectorF32XorAppender, VectorU32Appender, VectorU64Appender};
+
+    #[test]
+    fn test_select_sections_and_nulls() {
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..256u64).for_each(|i| appender.append(i + 1).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..100u64).for_each(|i| appender.append(i * 7).unwrap());
+        let source = appender.finish(612).unwrap();
+
+        // Every other element of section 0, 10 elements of the null section, and all of the last section,
+        // including bits past the end of the vector which must be ignored
+        let masks = vec![u32x8::splat(0x5555_5555), u32x8::new(0x3ff, 0, 0, 0, 0, 0, 0, 0), u32x8::splat(u32::MAX)];
+        let mut builder = SelectionBuilder::new(VectorU64Appender::try_new(1024).unwrap());
+        builder.select_vector(&source[..], masks.into_iter()).unwrap();
+        assert_eq!(builder.num_elements(), 128 + 10 + 100);
+        let selected = builder.finish().unwrap();
+
+        let expected: Vec<u64> = (0..256u64).step_by(2).map(|i| i + 1)
+                                            .chain(std::iter::repeat(0).take(10))
+                                            .chain((0..100u64).map(|i| i * 7))
+                                            .collect();
+        let reader = VectorReader::<u64>::try_new(&selected[..]).unwrap();
+        assert_eq!(reader.num_elements(), expected.len());
+        assert_eq!(reader.iterate().collect::<Vec<_>>(), expected);
+
+        // Empty mask selects nothing; builder was reset by finish()
+        let reader = VectorReader::<u64>::try_new(&source[..]).unwrap();
+        let first_sect = reader.sect_iter().next().unwrap().unwrap();
+        builder.select(first_sect, u32x8::splat(0)).unwrap();
+        assert_eq!(builder.num_elements(), 0);
+    }
+
+    #[test]
+    fn test_select_keeps_null_elements() {
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        appender.track_nulls(true);
+        let source = appender.encode_all_options((0..300u32).map(|i| if i % 4 == 0 { None } else { Some(i) }))
+                             .unwrap();
+
+        // Every other element, so half of the selected elements are null
+        let masks = vec![u32x8::splat(0x5555_5555), u32x8::splat(0x5555_5555)];
+        let mut builder = SelectionBuilder::new(VectorU32Appender::try_new(1024).unwrap());
+        builder.select_vector(&source[..], masks.into_iter()).unwrap();
+        let selected = builder.finish().unwrap();
+
+        let expected: Vec<Option<u32>> = (0..300u32).step_by(2).map(|i| if i % 4 == 0 { None } else { Some(i) })
+                                                    .collect();
+        let reader = VectorReader::<u32>::try_new(&selected[..]).unwrap();
+        assert_eq!(reader.iterate_options().take(150).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_select_zeroes_after_null_elements() {
+        // A PartialNull section, then zeroes within a section and a Null section of zeroes
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        appender.track_nulls(true);
+        appender.append_options((0..256u32).map(|i| if i % 4 == 0 { None } else { Some(i) })).unwrap();
+        (0..256u32).for_each(|i| appender.append(if i < 128 || i % 3 == 0 { 0 } else { i }).unwrap());
+        (0..256u32).for_each(|_| appender.append(0).unwrap());
+        let source = appender.finish(768).unwrap();
+        let source_reader = VectorReader::<u32>::try_new(&source[..]).unwrap();
+        assert_eq!(source_reader.num_null_sections().unwrap(), 1);
+
+        let mut builder = SelectionBuilder::new(VectorU32Appender::try_new(1024).unwrap());
+        builder.select_vector(&source[..], std::iter::repeat(u32x8::splat(u32::MAX))).unwrap();
+        let selected = builder.finish().unwrap();
+        let reader = VectorReader::<u32>::try_new(&selected[..]).unwrap();
+        assert_eq!(reader.iterate_options().take(768).collect::<Vec<_>>(),
+                   source_reader.iterate_options().take(768).collect::<Vec<_>>());
+        assert_eq!(reader.null_count(), 64);
+
+        // Null tracking was only turned on for the vector with nulls
+        assert!(!builder.appender.tracks_nulls());
+    }
+
+    #[test]
+    fn test_select_with_multi_vector_filter() {
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        let keys1 = appender.encode_all((0..700u32).map(|i| i % 4)).unwrap();
+        let keys2 = appender.encode_all((0..700u32).map(|i| i % 3)).unwrap();
+        let mut appender = VectorF32XorAppender::try_new(4096).unwrap();
+        let values = appender.encode_all((0..700).map(|i| i as f32 * 0.5)).unwrap();
+
+        let r1 = VectorReader::<u32>::try_new(&keys1[..]).unwrap();
+        let r2 = VectorReader::<u32>::try_new(&keys2[..]).unwrap();
+        let filter = MultiVectorFilter::new(vec![r1.filter_iter(EqualsSink::<u32>::new(&1)),
+                                                 r2.filter_iter(EqualsSink::<u32>::new(&1))]);
+        let mut builder = SelectionBuilder::new(VectorF32XorAppender::try_new(1024).unwrap());
+        builder.select_vector(&values[..], filter).unwrap();
+        let selected = builder.finish().unwrap();
+
+        let expected: Vec<f32> = (0..700).filter(|i| i % 4 == 1 && i % 3 == 1).map(|i| i as f32 * 0.5).collect();
+        let reader = VectorReader::<f32>::try_new(&selected[..]).unwrap();
+        assert_eq!(reader.iterate().collect::<Vec<_>>(), expected);
+
+        // Select from section bytes directly
+        let odd = FilterFnSink::<u32, _>::new(|octet: u32x8| (octet & u32x8::splat(1)).eq_mask(u32x8::splat(1)));
+        let mask = r1.filter_iter(odd).next().unwrap();
+        let mut builder = SelectionBuilder::new(VectorU32Appender::try_new(1024).unwrap());
+        builder.select_section(&keys1[crate::vector::NUM_HEADER_BYTES_TOTAL..], mask).unwrap();
+        let selected = builder.finish().unwrap();
+        let reader = VectorReader::<u32>::try_new(&selected[..]).unwrap();
+        assert_eq!(reader.num_elements(), 128);
+        assert!(reader.iterate().all(|k| k == 1 || k == 3));
+    }
+}
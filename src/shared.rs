@@ -0,0 +1,175 @@
+/// The `shared` module supports reading vectors out of memory shared between processes, such as a shared memory
+/// segment or a memory-mapped file, so that many query worker processes can serve one copy of the data.
+///
+/// Lifetime and ownership: this crate never maps, unmaps or writes shared memory.  The caller maps the segment
+/// and creates the `&'buf [u8]` slice over it, and must guarantee that for all of `'buf` the mapping stays valid
+/// and its bytes are not modified, by this or any other process.  Readers only borrow the bytes: they never
+/// copy the sections, and never keep pointers beyond `'buf`.  Unmapping the segment while a `SharedVector` is
+/// alive, or rewriting it in place, is undefined behavior, just as for any other slice.
+///
+/// Bytes written by another process are validated once when attaching, rather than trusted: `attach()` checks
+/// the header, footer and every section, and decodes every section, so later reads and filters do not fail
+/// or panic on malformed data.  Attaching is O(vector size); `pin_directory()` then copies the section offsets
+/// into local memory, so that workers can jump to any section without walking the sections before it.
+/// ```
+/// # use compressed_vec::shared::SharedVector;
+/// # use compressed_vec::vector::VectorU64Appender;
+/// # use compressed_vec::sink::Section256Sink;
+///     // Stands in for bytes in a shared memory segment
+///     let segment = VectorU64Appender::try_new(4096).unwrap().encode_all(0..1000u64).unwrap();
+///
+///     let mut shared = SharedVector::<u64>::attach(&segment[..]).unwrap();
+///     shared.pin_directory().unwrap();
+///     let mut sink = Section256Sink::<u64>::new();
+///     shared.section(3).unwrap().decode(&mut sink).unwrap();
+///     assert_eq!(sink.values[0], 768);
+///     assert_eq!(shared.reader().iterate().sum::<u64>(), 499_500);
+/// ```
+use std::convert::TryFrom;
+
+use crate::error::CodingError;
+use crate::section::*;
+use crate::sink::{Section256Sink, Sink};
+use crate::vector::{BaseSubtypeMapping, VectorReader};
+
+
+/// The offsets of the sections of a vector, copied to local memory for constant time access to any section.
+/// RepeatPrevious sections are resolved to the offset of the section they repeat.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionDirectory {
+    offsets: Vec<u32>,
+}
+
+impl SectionDirectory {
+    /// Builds the directory by walking all of the sections
+    pub fn build<T: VectBase>(sect_bytes: &[u8]) -> Result<Self, CodingError> {
+        let mut offsets = Vec::new();
+        let mut pos = 0;
+        while pos < sect_bytes.len() {
+            if sect_bytes[pos] == SectionType::RepeatPrevious.as_num() {
+                let prev = *offsets.last().ok_or_else(|| {
+                    CodingError::InvalidFormat("RepeatPrevious with no previous section".into())
+                })?;
+                offsets.push(prev);
+                pos += 1;
+            } else {
+                offsets.push(pos as u32);
+                pos += FixedSectEnum::<T>::try_from(&sect_bytes[pos..])?.num_bytes();
+            }
+        }
+        Ok(Self { offsets })
+    }
+
+    pub fn num_sections(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Offset of section n from the start of the first section
+    pub fn offset(&self, n: usize) -> Option<usize> {
+        self.offsets.get(n).map(|&off| off as usize)
+    }
+
+    /// Bytes of local memory used by the directory
+    pub fn local_bytes(&self) -> usize {
+        self.offsets.capacity() * std::mem::size_of::<u32>()
+    }
+}
+
+/// A vector in shared memory, validated on attach.  See the module docs for lifetime requirements.
+pub struct SharedVector<'buf, T: VectBase> {
+    reader: VectorReader<'buf, T>,
+    directory: Option<SectionDirectory>,
+}
+
+impl<'buf, T> SharedVector<'buf, T>
+where T: VectBase + BaseSubtypeMapping {
+    /// Attaches to the vector at the start of the shared bytes, validating it.  Fails if the header, footer or
+    /// any section is malformed, if the number of sections does not match the number of elements, or if any
+    /// section fails to decode.
+    pub fn attach(shared_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let reader = VectorReader::<T>::try_new(shared_bytes)?;
+        let mut sink = Section256Sink::<T>::new();
+        let mut num_sections = 0;
+        for sect in reader.sect_iter() {
+            sink.reset();
+            sect?.decode(&mut sink)?;
+            num_sections += 1;
+        }
+        let expected_sections = (reader.num_elements() + FIXED_LEN - 1) / FIXED_LEN;
+        if num_sections != expected_sections {
+            return Err(CodingError::InvalidFormat(format!("{} sections for {} elements, expected {}",
+                                                          num_sections, reader.num_elements(), expected_sections)));
+        }
+        Ok(Self { reader, directory: None })
+    }
+
+    /// The reader for the vector, for iterating, filtering etc.
+    pub fn reader(&self) -> &VectorReader<'buf, T> {
+        &self.reader
+    }
+
+    /// Copies the section directory into local memory, if not done already, and returns it.
+    pub fn pin_directory(&mut self) -> Result<&SectionDirectory, CodingError> {
+        if self.directory.is_none() {
+            self.directory = Some(SectionDirectory::build::<T>(self.reader.sect_bytes())?);
+        }
+        Ok(self.directory.as_ref().unwrap())
+    }
+
+    /// The pinned section directory, if any
+    pub fn directory(&self) -> Option<&SectionDirectory> {
+        self.directory.as_ref()
+    }
+
+    /// Returns section n.  Constant time once the directory is pinned, otherwise walks the sections before n.
+    pub fn section(&self, n: usize) -> Result<FixedSectEnum<'buf, T>, CodingError> {
+        let sect_bytes = self.reader.sect_bytes();
+        match &self.directory {
+            Some(dir) => {
+                let offset = dir.offset(n).ok_or(CodingError::BadOffset(n))?;
+                FixedSectEnum::try_from(&sect_bytes[offset..])
+            }
+            None => self.reader.sect_iter().nth(n).unwrap_or(Err(CodingError::BadOffset(n))),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{VectorU32Appender, VectorReader};
+
+    #[test]
+    fn test_attach_validates_and_directory() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.dedup_sections(true);
+        (0..512u32).for_each(|i| appender.append(i % 256 * 3).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..100u32).for_each(|i| appender.append(i).unwrap());
+        let bytes = appender.finish(868).unwrap();
+
+        let mut shared = SharedVector::<u32>::attach(&bytes[..]).unwrap();
+        assert!(shared.directory().is_none());
+        let dir = shared.pin_directory().unwrap().clone();
+        assert_eq!(dir.num_sections(), 4);
+        assert_eq!(dir.offset(0), dir.offset(1));    // RepeatPrevious resolved
+        for n in 0..4 {
+            assert_eq!(shared.section(n).unwrap(), shared.reader().sect_iter().nth(n).unwrap().unwrap());
+        }
+        assert_eq!(shared.section(4), Err(CodingError::BadOffset(4)));
+
+        // Number of elements beyond the sections
+        let mut bad = bytes.clone();
+        bad[10] = 1;
+        assert!(matches!(SharedVector::<u32>::attach(&bad[..]), Err(CodingError::InvalidFormat(_))));
+
+        // Truncated section payload
+        let mut bad = bytes.clone();
+        let num_bytes = (bad.len() - 4 - 3) as u32;
+        bad.truncate(bad.len() - 3);
+        bad[..4].copy_from_slice(&num_bytes.to_le_bytes());
+        assert!(VectorReader::<u32>::try_new(&bad[..]).is_ok());
+        assert!(SharedVector::<u32>::attach(&bad[..]).is_err());
+    }
+}
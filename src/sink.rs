@@ -6,12 +6,16 @@
 ///     regular unpack8_u32_simd -> u32 to f32 XOR sink -> MultiplySink -> VecSink
 /// TODO: examples
 use core::marker::PhantomData;
-use std::ops::{Add, BitXor};
+use std::fmt::Display;
+use std::io::Write;
+use std::ops::{Add, BitAnd, BitXor, Shr};
 
-use crate::section::VectBase;
+use crate::error::CodingError;
+use crate::section::{FSUtils, VectBase, POWERS_OF_TEN};
 
 use num::{Zero, Unsigned, Float};
-use packed_simd::{u32x8, u64x8, f32x8, FromCast, FromBits, IntoBits};
+use num::traits::WrappingAdd;
+use packed_simd::{u8x8, u16x8, u32x8, u64x8, i64x8, f32x8, f64x8, FromCast, FromBits, IntoBits};
 
 /// An input to a sink.  Sinks take a type which represents 8 values of an int, such as [u64; 8].
 /// Item type represents the underlying type of each individual item in the 8 item SinkInput.
@@ -26,10 +30,19 @@ pub trait SinkInput: Copy + core::fmt::Debug {
     /// Creates one of these types from a base Item type by splatting (replicating it 8x)
     fn splat(item: Self::Item) -> Self;
 
+    /// Loads 8 items from a slice
+    fn from_slice(slice: &[Self::Item]) -> Self;
+
     /// Methods for implementing filtering/masking.
     /// Compares my 8 values to other 8 values, returning a bitmask for equality
     fn eq_mask(self, other: Self) -> u8;
 
+    /// Compares my 8 values to other 8 values, returning a bitmask for mine being less than other's
+    fn lt_mask(self, other: Self) -> u8;
+
+    /// Compares my 8 values to other 8 values, returning a bitmask for mine being less than or equal to other's
+    fn le_mask(self, other: Self) -> u8;
+
     /// Loads the bits from a slice into a u64x8. Mostly used for converting FP bits to int bits for XORing.
     fn to_u64x8_bits(slice: &[Self::Item]) -> u64x8;
 }
@@ -45,6 +58,13 @@ impl SinkInput for [u64; 8] {
     #[inline]
     fn splat(item: u64) -> Self { [item; 8] }
 
+    #[inline]
+    fn from_slice(slice: &[u64]) -> Self {
+        let mut items = [0u64; 8];
+        items.copy_from_slice(&slice[..8]);
+        items
+    }
+
     #[inline]
     fn eq_mask(self, other: Self) -> u8 {
         let mut mask = 0u8;
@@ -56,6 +76,28 @@ impl SinkInput for [u64; 8] {
         mask
     }
 
+    #[inline]
+    fn lt_mask(self, other: Self) -> u8 {
+        let mut mask = 0u8;
+        for i in 0..8 {
+            if self[i] < other[i] {
+                mask |= 1 << i;
+            }
+        }
+        mask
+    }
+
+    #[inline]
+    fn le_mask(self, other: Self) -> u8 {
+        let mut mask = 0u8;
+        for i in 0..8 {
+            if self[i] <= other[i] {
+                mask |= 1 << i;
+            }
+        }
+        mask
+    }
+
     #[inline]
     fn to_u64x8_bits(_slice: &[u64]) -> u64x8 { todo!("blah") }
 }
@@ -72,11 +114,24 @@ impl SinkInput for u64x8 {
     #[inline]
     fn splat(item: u64) -> Self { u64x8::splat(item) }
 
+    #[inline]
+    fn from_slice(slice: &[u64]) -> Self { u64x8::from_slice_unaligned(slice) }
+
     #[inline]
     fn eq_mask(self, other: Self) -> u8 {
         self.eq(other).bitmask()
     }
 
+    #[inline]
+    fn lt_mask(self, other: Self) -> u8 {
+        self.lt(other).bitmask()
+    }
+
+    #[inline]
+    fn le_mask(self, other: Self) -> u8 {
+        self.le(other).bitmask()
+    }
+
     #[inline]
     fn to_u64x8_bits(slice: &[u64]) -> u64x8 { u64x8::from_slice_unaligned(slice) }
 }
@@ -95,17 +150,102 @@ impl SinkInput for u32x8 {
     #[inline]
     fn splat(item: u32) -> Self { u32x8::splat(item) }
 
+    #[inline]
+    fn from_slice(slice: &[u32]) -> Self { u32x8::from_slice_unaligned(slice) }
+
     #[inline]
     fn eq_mask(self, other: Self) -> u8 {
         self.eq(other).bitmask()
     }
 
+    #[inline]
+    fn lt_mask(self, other: Self) -> u8 {
+        self.lt(other).bitmask()
+    }
+
+    #[inline]
+    fn le_mask(self, other: Self) -> u8 {
+        self.le(other).bitmask()
+    }
+
     #[inline]
     fn to_u64x8_bits(slice: &[u32]) -> u64x8 {
         u64x8::from_cast(u32x8::from_slice_unaligned(slice))
     }
 }
 
+impl SinkInput for u16x8 {
+    type Item = u16;
+    const ZERO: u16x8 = u16x8::splat(0);
+
+    #[inline]
+    fn write_to_slice(&self, slice: &mut [Self::Item]) {
+        self.write_to_slice_unaligned(slice);
+    }
+
+    #[inline]
+    fn splat(item: u16) -> Self { u16x8::splat(item) }
+
+    #[inline]
+    fn from_slice(slice: &[u16]) -> Self { u16x8::from_slice_unaligned(slice) }
+
+    #[inline]
+    fn eq_mask(self, other: Self) -> u8 {
+        self.eq(other).bitmask()
+    }
+
+    #[inline]
+    fn lt_mask(self, other: Self) -> u8 {
+        self.lt(other).bitmask()
+    }
+
+    #[inline]
+    fn le_mask(self, other: Self) -> u8 {
+        self.le(other).bitmask()
+    }
+
+    #[inline]
+    fn to_u64x8_bits(slice: &[u16]) -> u64x8 {
+        u64x8::from_cast(u16x8::from_slice_unaligned(slice))
+    }
+}
+
+impl SinkInput for u8x8 {
+    type Item = u8;
+    const ZERO: u8x8 = u8x8::splat(0);
+
+    #[inline]
+    fn write_to_slice(&self, slice: &mut [Self::Item]) {
+        self.write_to_slice_unaligned(slice);
+    }
+
+    #[inline]
+    fn splat(item: u8) -> Self { u8x8::splat(item) }
+
+    #[inline]
+    fn from_slice(slice: &[u8]) -> Self { u8x8::from_slice_unaligned(slice) }
+
+    #[inline]
+    fn eq_mask(self, other: Self) -> u8 {
+        self.eq(other).bitmask()
+    }
+
+    #[inline]
+    fn lt_mask(self, other: Self) -> u8 {
+        self.lt(other).bitmask()
+    }
+
+    #[inline]
+    fn le_mask(self, other: Self) -> u8 {
+        self.le(other).bitmask()
+    }
+
+    #[inline]
+    fn to_u64x8_bits(slice: &[u8]) -> u64x8 {
+        u64x8::from_cast(u8x8::from_slice_unaligned(slice))
+    }
+}
+
 impl SinkInput for f32x8 {
     type Item = f32;
     const ZERO: f32x8 = f32x8::splat(0.0);
@@ -118,11 +258,24 @@ impl SinkInput for f32x8 {
     #[inline]
     fn splat(item: f32) -> Self { f32x8::splat(item) }
 
+    #[inline]
+    fn from_slice(slice: &[f32]) -> Self { f32x8::from_slice_unaligned(slice) }
+
     #[inline]
     fn eq_mask(self, other: Self) -> u8 {
         self.eq(other).bitmask()
     }
 
+    #[inline]
+    fn lt_mask(self, other: Self) -> u8 {
+        self.lt(other).bitmask()
+    }
+
+    #[inline]
+    fn le_mask(self, other: Self) -> u8 {
+        self.le(other).bitmask()
+    }
+
     #[inline]
     fn to_u64x8_bits(slice: &[f32]) -> u64x8 {
         let f_bits: u32x8 = f32x8::from_slice_unaligned(slice).into_bits();
@@ -130,6 +283,42 @@ impl SinkInput for f32x8 {
     }
 }
 
+impl SinkInput for f64x8 {
+    type Item = f64;
+    const ZERO: f64x8 = f64x8::splat(0.0);
+
+    #[inline]
+    fn write_to_slice(&self, slice: &mut [f64]) {
+        self.write_to_slice_unaligned(slice);
+    }
+
+    #[inline]
+    fn splat(item: f64) -> Self { f64x8::splat(item) }
+
+    #[inline]
+    fn from_slice(slice: &[f64]) -> Self { f64x8::from_slice_unaligned(slice) }
+
+    #[inline]
+    fn eq_mask(self, other: Self) -> u8 {
+        self.eq(other).bitmask()
+    }
+
+    #[inline]
+    fn lt_mask(self, other: Self) -> u8 {
+        self.lt(other).bitmask()
+    }
+
+    #[inline]
+    fn le_mask(self, other: Self) -> u8 {
+        self.le(other).bitmask()
+    }
+
+    #[inline]
+    fn to_u64x8_bits(slice: &[f64]) -> u64x8 {
+        f64x8::from_slice_unaligned(slice).into_bits()
+    }
+}
+
 /// A sink processes data during unpacking.  The type, Input, is supposed to represent 8 integers of fixed width,
 /// since NibblePack works on 8 ints at a time.
 pub trait Sink<Input: SinkInput> {
@@ -142,6 +331,12 @@ pub trait Sink<Input: SinkInput> {
     /// Resets state in the sink; exact meaning depends on the sink itself.  Many sinks operate on more than
     /// 8 items; for example 256 items or entire sections.
     fn reset(&mut self);
+
+    /// Returns true once the sink needs no more items, eg after the rows of a LIMIT or a first match.
+    /// Vector decoding checks this between sections and stops early, so a sink may still get the rest of the
+    /// current section.  Sinks which forward to another sink should also forward this.
+    #[inline]
+    fn is_done(&self) -> bool { false }
 }
 
 
@@ -234,6 +429,12 @@ where T: VectBase {
 pub type U32_256Sink = Section256Sink<u32>;
 pub type U64_256Sink = Section256Sink<u64>;
 
+/// A sink of u64 octets, such as the output of `nibblepack_simd::unpack8_u64_simd()`.
+/// Implemented for every `Sink<u64x8>`, so it is just a shorter bound for u64 decoding paths.
+pub trait SinkU64: Sink<u64x8> {}
+
+impl<S: Sink<u64x8>> SinkU64 for S {}
+
 
 /// A sink for FP/XOR decoding.  Keeps a running "last bits" octet and XORs each new octet with the last one.
 /// Forwards resulting XORed/restored output to another sink.
@@ -281,6 +482,11 @@ where F: VectBase + Float,      // Output floating point type
     }
 
     fn reset(&mut self) {}
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.inner_sink.is_done()
+    }
 }
 
 /// A Sink for adding a constant value to all output elements.  Note that all SIMD types we use also support Add :)
@@ -317,4 +523,407 @@ where T: VectBase,
     }
 
     fn reset(&mut self) {}
-}
\ No newline at end of file
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.inner_sink.is_done()
+    }
+}
+
+/// A Sink which turns deltas into running sums starting from a base value, and passes the sums to another sink.
+/// Used for decoding sections of monotonically increasing values stored as deltas from the previous value.
+/// Sums wrap around on overflow, like the reference decoder, so corrupt deltas cannot panic.
+#[derive(Debug)]
+pub struct DeltaSumSink<'a, T, S>
+where T: VectBase + WrappingAdd,
+      S: Sink<T::SI> {
+    base: T,
+    sum: T,
+    inner_sink: &'a mut S,
+}
+
+impl<'a, T, S> DeltaSumSink<'a, T, S>
+where T: VectBase + WrappingAdd,
+      S: Sink<T::SI> {
+    pub fn new(base: T, inner_sink: &'a mut S) -> Self {
+        Self { base, sum: base, inner_sink }
+    }
+}
+
+impl<'a, T, S> Sink<T::SI> for DeltaSumSink<'a, T, S>
+where T: VectBase + WrappingAdd,
+      S: Sink<T::SI> {
+    #[inline]
+    fn process(&mut self, deltas: T::SI) {
+        let mut sums = [T::zero(); 8];
+        deltas.write_to_slice(&mut sums);
+        for sum in sums.iter_mut() {
+            self.sum = self.sum.wrapping_add(sum);
+            *sum = self.sum;
+        }
+        self.inner_sink.process(T::SI::from_slice(&sums));
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.inner_sink.process(T::SI::splat(self.sum));
+    }
+
+    fn reset(&mut self) {
+        self.sum = self.base;
+    }
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.inner_sink.is_done()
+    }
+}
+
+/// A Sink which extracts a bit field from each element, computing `(x & mask) >> shift`, and passes the result
+/// to another sink.  Fusing this into decoding avoids a second pass, for example to get just the high bits of
+/// composite keys:
+/// ```
+/// # use compressed_vec::sink::{MaskShiftSink, VecSink};
+/// # use compressed_vec::vector::{VectorU64Appender, VectorReader};
+///     let mut appender = VectorU64Appender::try_new(1024).unwrap();
+///     let keys = appender.encode_all(vec![(7u64 << 32) | 1, (9 << 32) | 2]).unwrap();
+///     let mut sink = VecSink::<u64>::new();
+///     let mut high_bits = MaskShiftSink::<u64, _>::new(0xffff_ffff_0000_0000, 32, &mut sink);
+///     VectorReader::<u64>::try_new(&keys[..]).unwrap().decode_to_sink(&mut high_bits).unwrap();
+///     assert_eq!(sink.vec[..2], [7, 9]);
+/// ```
+#[derive(Debug)]
+pub struct MaskShiftSink<'a, T, S>
+where T: VectBase + Unsigned,
+      S: Sink<T::SI> {
+    mask: T::SI,
+    shift: u32,
+    inner_sink: &'a mut S,
+}
+
+impl<'a, T, S> MaskShiftSink<'a, T, S>
+where T: VectBase + Unsigned,
+      S: Sink<T::SI> {
+    pub fn new(mask: T, shift: u32, inner_sink: &'a mut S) -> Self {
+        Self { mask: T::SI::splat(mask), shift, inner_sink }
+    }
+}
+
+impl<'a, T, S> Sink<T::SI> for MaskShiftSink<'a, T, S>
+where T: VectBase + Unsigned,
+      S: Sink<T::SI>,
+      T::SI: BitAnd<T::SI, Output = T::SI> + Shr<u32, Output = T::SI> {
+    #[inline]
+    fn process(&mut self, unpacked: T::SI) {
+        self.inner_sink.process((unpacked & self.mask) >> self.shift);
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        // (0 & mask) >> shift == 0
+        self.inner_sink.process_zeroes();
+    }
+
+    fn reset(&mut self) {}
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.inner_sink.is_done()
+    }
+}
+
+/// A Sink for decoding scaled decimals: turns ZigZag encoded integers, which are f64 values times 10^scale,
+/// back into the f64 values and passes them to another sink.  Used for decoding DecimalF64MedFixedSect straight
+/// from its integer section:
+/// ```
+/// # use compressed_vec::sink::{DecimalSink, VecSink};
+/// # use compressed_vec::vector::{VectorU64Appender, VectorReader};
+///     // ZigZag encoded -1234 and 5
+///     let ints = VectorU64Appender::try_new(1024).unwrap().encode_all(vec![2467, 10]).unwrap();
+///     let mut sink = VecSink::<f64>::new();
+///     let mut decimals = DecimalSink::new(2, &mut sink).unwrap();
+///     VectorReader::<u64>::try_new(&ints[..]).unwrap().decode_to_sink(&mut decimals).unwrap();
+///     assert_eq!(sink.vec[..2], [-12.34, 0.05]);
+/// ```
+#[derive(Debug)]
+pub struct DecimalSink<'a, S: Sink<f64x8>> {
+    divisor: f64x8,
+    inner_sink: &'a mut S,
+}
+
+impl<'a, S: Sink<f64x8>> DecimalSink<'a, S> {
+    /// Creates a sink for values with scale decimal digits, at most MAX_DECIMAL_SCALE
+    pub fn new(scale: u8, inner_sink: &'a mut S) -> Result<Self, CodingError> {
+        let divisor = *POWERS_OF_TEN.get(scale as usize)
+            .ok_or_else(|| CodingError::InvalidFormat(format!("Decimal scale {} too large", scale)))?;
+        Ok(Self { divisor: f64x8::splat(divisor), inner_sink })
+    }
+}
+
+impl<'a, S: Sink<f64x8>> Sink<u64x8> for DecimalSink<'a, S> {
+    #[inline]
+    fn process(&mut self, zigzags: u64x8) {
+        let magnitudes: i64x8 = (zigzags >> 1).into_bits();
+        let signs: i64x8 = (zigzags & 1).into_bits();
+        let ints = magnitudes ^ (i64x8::splat(0) - signs);
+        self.inner_sink.process(f64x8::from_cast(ints) / self.divisor);
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        // 0 / divisor == 0.0
+        self.inner_sink.process_zeroes();
+    }
+
+    fn reset(&mut self) {}
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.inner_sink.is_done()
+    }
+}
+
+/// A Sink which applies a function to each decoded octet, eg a unit conversion or scaling, and passes the result
+/// to another sink.  The function works on whole octets, so it can use SIMD operations:
+/// ```
+/// # use compressed_vec::sink::{MapSink, VecSink};
+/// # use compressed_vec::vector::{VectorF64XorAppender, VectorReader};
+///     let celsius = VectorF64XorAppender::try_new(1024).unwrap().encode_all(vec![-40.0, 0.0, 100.0]).unwrap();
+///     let mut sink = VecSink::<f64>::new();
+///     let mut to_fahrenheit = MapSink::<f64, _, _>::new(|c| c * 1.8 + 32.0, &mut sink);
+///     VectorReader::<f64>::try_new(&celsius[..]).unwrap().decode_to_sink(&mut to_fahrenheit).unwrap();
+///     assert_eq!(sink.vec[..3], [-40.0, 32.0, 212.0]);
+/// ```
+#[derive(Debug)]
+pub struct MapSink<'a, T, F, S>
+where T: VectBase,
+      F: FnMut(T::SI) -> T::SI,
+      S: Sink<T::SI> {
+    func: F,
+    inner_sink: &'a mut S,
+    _t: PhantomData<T>,
+}
+
+impl<'a, T, F, S> MapSink<'a, T, F, S>
+where T: VectBase,
+      F: FnMut(T::SI) -> T::SI,
+      S: Sink<T::SI> {
+    pub fn new(func: F, inner_sink: &'a mut S) -> Self {
+        Self { func, inner_sink, _t: PhantomData }
+    }
+}
+
+impl<'a, T, F, S> Sink<T::SI> for MapSink<'a, T, F, S>
+where T: VectBase,
+      F: FnMut(T::SI) -> T::SI,
+      S: Sink<T::SI> {
+    #[inline]
+    fn process(&mut self, unpacked: T::SI) {
+        self.inner_sink.process((self.func)(unpacked));
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        // The function need not map zeroes to zeroes
+        self.inner_sink.process((self.func)(T::SI::ZERO));
+    }
+
+    fn reset(&mut self) {}
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.inner_sink.is_done()
+    }
+}
+
+/// A Sink which passes each decoded octet to two other sinks, so that both see the values from one decoding pass.
+/// Tees can be nested to feed more sinks:
+/// ```
+/// # use compressed_vec::hll::{HllSink, HyperLogLog};
+/// # use compressed_vec::sink::{TeeSink, VecSink};
+/// # use compressed_vec::vector::{VectorU64Appender, VectorReader};
+///     let ids = VectorU64Appender::try_new(1024).unwrap().encode_all(vec![5, 9, 5, 5, 7]).unwrap();
+///     let mut values = VecSink::<u64>::new();
+///     let mut hll = HyperLogLog::new(12).unwrap();
+///     let mut distinct = HllSink::<u64>::new(&mut hll);
+///     let mut both = TeeSink::<u64, _, _>::new(&mut values, &mut distinct);
+///     VectorReader::<u64>::try_new(&ids[..]).unwrap().decode_to_sink(&mut both).unwrap();
+///     assert_eq!(values.vec[..5], [5, 9, 5, 5, 7]);
+///     assert_eq!(hll.estimate(), 4);   // The padding zeroes of the section are values too
+/// ```
+#[derive(Debug)]
+pub struct TeeSink<'a, T, S1, S2>
+where T: VectBase,
+      S1: Sink<T::SI>,
+      S2: Sink<T::SI> {
+    first: &'a mut S1,
+    second: &'a mut S2,
+    _t: PhantomData<T>,
+}
+
+impl<'a, T, S1, S2> TeeSink<'a, T, S1, S2>
+where T: VectBase,
+      S1: Sink<T::SI>,
+      S2: Sink<T::SI> {
+    pub fn new(first: &'a mut S1, second: &'a mut S2) -> Self {
+        Self { first, second, _t: PhantomData }
+    }
+}
+
+impl<'a, T, S1, S2> Sink<T::SI> for TeeSink<'a, T, S1, S2>
+where T: VectBase,
+      S1: Sink<T::SI>,
+      S2: Sink<T::SI> {
+    #[inline]
+    fn process(&mut self, unpacked: T::SI) {
+        self.first.process(unpacked);
+        self.second.process(unpacked);
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.first.process_zeroes();
+        self.second.process_zeroes();
+    }
+
+    // A tee has no state of its own, so resetting it resets both sinks
+    fn reset(&mut self) {
+        self.first.reset();
+        self.second.reset();
+    }
+
+    // Done only when neither sink needs more values
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.first.is_done() && self.second.is_done()
+    }
+}
+
+/// How a WriteSink writes each value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriteFormat {
+    LittleEndian,     // The little endian bytes of the value, eg 8 bytes for a u64
+    Csv,              // The value as text on its own line, ie a one-column CSV
+}
+
+/// A Sink which streams decoded values to an io::Write, for exporting vectors of any size without a Vec of
+/// all the values.  Only the first num_values values are written, so the padding of the last section is dropped.
+/// Sinks cannot fail, so the first error is kept and nothing more is written; `finish()` returns it.
+/// Each octet is one small write, so wrap files and sockets in a BufWriter.
+#[derive(Debug)]
+pub struct WriteSink<T: VectBase + Display, W: Write> {
+    writer: W,
+    format: WriteFormat,
+    values_left: usize,
+    error: Option<CodingError>,
+    _t: PhantomData<T>,
+}
+
+impl<T: VectBase + Display, W: Write> WriteSink<T, W> {
+    pub fn new(writer: W, format: WriteFormat, num_values: usize) -> Self {
+        Self { writer, format, values_left: num_values, error: None, _t: PhantomData }
+    }
+
+    /// Flushes and returns the writer, or the first error writing to it
+    pub fn finish(mut self) -> Result<W, CodingError> {
+        if let Some(err) = self.error.take() { return Err(err) }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_values(&mut self, values: &[T]) -> Result<(), CodingError> {
+        match self.format {
+            WriteFormat::LittleEndian => {
+                let width = T::Utils::BYTE_WIDTH;
+                let mut buf = [0u8; 64];
+                for (i, &v) in values.iter().enumerate() {
+                    T::Utils::write_le_offset(&mut buf, i * width, v)?;
+                }
+                self.writer.write_all(&buf[..values.len() * width])?;
+            }
+            WriteFormat::Csv => for v in values {
+                writeln!(self.writer, "{}", v)?;
+            },
+        }
+        Ok(())
+    }
+}
+
+impl<T: VectBase + Display, W: Write> Sink<T::SI> for WriteSink<T, W> {
+    #[inline]
+    fn process(&mut self, data: T::SI) {
+        if self.values_left == 0 || self.error.is_some() { return }
+        let mut values = [T::zero(); 8];
+        data.write_to_slice(&mut values);
+        let n = self.values_left.min(8);
+        self.values_left -= n;
+        if let Err(err) = self.write_values(&values[..n]) { self.error = Some(err); }
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        self.process(T::SI::ZERO);
+    }
+
+    fn reset(&mut self) {}
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.values_left == 0 || self.error.is_some()
+    }
+}
+
+/// A Sink which passes only the first octets holding limit values to another sink, eg for LIMIT queries, and is
+/// then done, so that decoding stops after the current section.  The inner sink gets limit values rounded up to
+/// a whole octet:
+/// ```
+/// # use compressed_vec::sink::{LimitSink, VecSink};
+/// # use compressed_vec::vector::{VectorU64Appender, VectorReader};
+///     let bytes = VectorU64Appender::try_new(1024).unwrap().encode_all(0..100_000u64).unwrap();
+///     let mut sink = VecSink::<u64>::new();
+///     let mut first_300 = LimitSink::<u64, _>::new(300, &mut sink);
+///     VectorReader::<u64>::try_new(&bytes[..]).unwrap().decode_to_sink(&mut first_300).unwrap();
+///     assert_eq!(sink.vec.len(), 304);
+///     assert_eq!(sink.vec[299], 299);
+/// ```
+#[derive(Debug)]
+pub struct LimitSink<'a, T, S>
+where T: VectBase,
+      S: Sink<T::SI> {
+    values_left: usize,
+    inner_sink: &'a mut S,
+    _t: PhantomData<T>,
+}
+
+impl<'a, T, S> LimitSink<'a, T, S>
+where T: VectBase,
+      S: Sink<T::SI> {
+    pub fn new(limit: usize, inner_sink: &'a mut S) -> Self {
+        Self { values_left: limit, inner_sink, _t: PhantomData }
+    }
+}
+
+impl<'a, T, S> Sink<T::SI> for LimitSink<'a, T, S>
+where T: VectBase,
+      S: Sink<T::SI> {
+    #[inline]
+    fn process(&mut self, unpacked: T::SI) {
+        if self.values_left == 0 { return }
+        self.values_left = self.values_left.saturating_sub(8);
+        self.inner_sink.process(unpacked);
+    }
+
+    #[inline]
+    fn process_zeroes(&mut self) {
+        if self.values_left == 0 { return }
+        self.values_left = self.values_left.saturating_sub(8);
+        self.inner_sink.process_zeroes();
+    }
+
+    fn reset(&mut self) {}
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.values_left == 0 || self.inner_sink.is_done()
+    }
+}
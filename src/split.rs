@@ -0,0 +1,192 @@
+/// The `split` module shards one large vector into smaller standalone vectors on section boundaries, for
+/// example to spread one huge column across nodes.  No section is decoded or re-encoded: `split_points()`
+/// proposes byte offsets giving roughly equal shards, and `split_at_points()` copies the sections between them
+/// into new vectors with their own headers.
+/// ```
+/// # use compressed_vec::split::*;
+/// # use compressed_vec::vector::{VectorReader, VectorU64Appender};
+///     let vector = VectorU64Appender::try_new(4096).unwrap()
+///                      .encode_all((0..5000u64).map(|i| i * i)).unwrap();
+///     let points = split_points::<u64>(&vector[..], vector.len() / 4).unwrap();
+///     let shards = split_at_points::<u64>(&vector[..], &points[..]).unwrap();
+///     assert!(shards.len() >= 4);
+///
+///     let values: Vec<u64> = shards.iter().flat_map(|shard| {
+///         VectorReader::<u64>::try_new(&shard[..]).unwrap().iterate().collect::<Vec<_>>()
+///     }).collect();
+///     assert_eq!(values, (0..5000u64).map(|i| i * i).collect::<Vec<_>>());
+/// ```
+/// Every shard but the last has a multiple of 256 elements.  A shard starting with a RepeatPrevious section
+/// gets a copy of the section it repeats.  Shards keep the footer entries of the original vector, except that
+/// per-section Bloom filters are cut down to the sections of each shard.  The vector Bloom filter still covers
+/// the values of the whole vector: still correct, but with more false positives.
+use std::convert::TryFrom;
+
+use scroll::{Pread, Pwrite, LE};
+
+use crate::bloom::SectionBloomFilters;
+use crate::error::CodingError;
+use crate::footer::{FooterTag, FooterWriter};
+use crate::section::*;
+use crate::vector::{BaseSubtypeMapping, FixedSectStats, VectorReader, BINARYVECT_HEADER_SIZE,
+                    NUM_HEADER_BYTES_TOTAL};
+
+
+// Offsets of each section from the start of the vector, and the offset just past the last section.
+// RepeatPrevious sections are resolved to the offset of the section they repeat.
+pub(crate) fn section_offsets<T>(reader: &VectorReader<T>) -> Result<(Vec<(usize, usize)>, usize), CodingError>
+where T: VectBase + BaseSubtypeMapping {
+    let sect_bytes = reader.sect_bytes();
+    let mut offsets = Vec::new();
+    let mut pos = 0;
+    while pos < sect_bytes.len() {
+        if sect_bytes[pos] == SectionType::RepeatPrevious.as_num() {
+            let &(_, prev) = offsets.last().ok_or_else(|| {
+                CodingError::InvalidFormat("RepeatPrevious with no previous section".into())
+            })?;
+            offsets.push((pos + NUM_HEADER_BYTES_TOTAL, prev));
+            pos += 1;
+        } else {
+            offsets.push((pos + NUM_HEADER_BYTES_TOTAL, pos + NUM_HEADER_BYTES_TOTAL));
+            pos += FixedSectEnum::<T>::try_from(&sect_bytes[pos..])?.num_bytes();
+        }
+    }
+    Ok((offsets, pos + NUM_HEADER_BYTES_TOTAL))
+}
+
+/// Proposes split points for shards of about target_bytes each: byte offsets from the start of the vector,
+/// each at the start of a section.  The start and end of the vector are not included.
+pub fn split_points<T>(vect_bytes: &[u8], target_bytes: usize) -> Result<Vec<usize>, CodingError>
+where T: VectBase + BaseSubtypeMapping {
+    let reader = VectorReader::<T>::try_new(vect_bytes)?;
+    let (offsets, _) = section_offsets(&reader)?;
+    let mut points = Vec::new();
+    let mut shard_start = NUM_HEADER_BYTES_TOTAL;
+    for &(offset, _) in offsets.iter().skip(1) {
+        if offset - shard_start >= target_bytes.max(1) {
+            points.push(offset);
+            shard_start = offset;
+        }
+    }
+    Ok(points)
+}
+
+/// Splits the vector at the given increasing split points, which must be section starts as returned by
+/// split_points(), into standalone vectors.  Returns BadOffset for a split point which is not a section start.
+pub fn split_at_points<T>(vect_bytes: &[u8], points: &[usize]) -> Result<Vec<Vec<u8>>, CodingError>
+where T: VectBase + BaseSubtypeMapping {
+    let reader = VectorReader::<T>::try_new(vect_bytes)?;
+    let (offsets, sects_end) = section_offsets(&reader)?;
+    let vect_end = vect_bytes.pread_with::<u32>(0, LE)? as usize + 4;
+    if offsets.is_empty() && points.is_empty() { return Ok(vec![vect_bytes[..vect_end].to_vec()]) }
+
+    // Indices of the first section of each shard
+    let mut shard_starts = vec![0];
+    for &point in points {
+        let index = offsets.iter().position(|&(offset, _)| offset == point)
+                           .filter(|&i| i > *shard_starts.last().unwrap())
+                           .ok_or(CodingError::BadOffset(point))?;
+        shard_starts.push(index);
+    }
+    shard_starts.push(offsets.len());
+
+    let mut elements_left = reader.num_elements();
+    shard_starts.windows(2).map(|w| {
+        let (first, end) = (w[0], w[1]);
+        let mut shard = vect_bytes[..NUM_HEADER_BYTES_TOTAL].to_vec();
+        // The first section cannot be a RepeatPrevious, so copy the section it repeats
+        let (first_offset, first_resolved) = offsets[first];
+        let first_len = FixedSectEnum::<T>::try_from(&vect_bytes[first_resolved..])?.num_bytes();
+        shard.extend_from_slice(&vect_bytes[first_resolved..first_resolved + first_len]);
+        let rest_start = if first_offset == first_resolved { first_offset + first_len } else { first_offset + 1 };
+        let rest_end = offsets.get(end).map_or(sects_end, |&(offset, _)| offset);
+        shard.extend_from_slice(&vect_bytes[rest_start..rest_end]);
+        if let Some(footer) = reader.footer() {
+            let mut writer = FooterWriter::new();
+            for (tag, entry) in footer.iter() {
+                if tag == FooterTag::SectionBloomFilters as u8 {
+                    writer.add_raw(tag, SectionBloomFilters::entry_for_sections(entry, first..end)?);
+                } else {
+                    writer.add_raw(tag, entry);
+                }
+            }
+            shard.extend_from_slice(&writer.finish());
+        }
+
+        let num_null_sections = (first..end).filter(|&i| vect_bytes[offsets[i].1] == SectionType::Null.as_num())
+                                            .count();
+        let num_elements = elements_left.min((end - first) * FIXED_LEN);
+        elements_left -= num_elements;
+        let stats = FixedSectStats { num_elements: num_elements as u32, num_null_sections: num_null_sections as u16 };
+        shard.pwrite_with(stats, BINARYVECT_HEADER_SIZE, LE)?;
+        let num_bytes = (shard.len() - 4) as u32;
+        shard.pwrite_with(num_bytes, 0, LE)?;
+        Ok(shard)
+    }).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{match_positions, EqualsSink};
+    use crate::vector::VectorU32Appender;
+
+    #[test]
+    fn test_split_with_repeats_nulls_and_footer() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.dedup_sections(true);
+        appender.enable_bloom_filter(10);
+        (0..768u32).for_each(|i| appender.append(i % 256 * 5).unwrap());    // Section, then 2 RepeatPrevious
+        appender.append_nulls(512).unwrap();
+        (0..100u32).for_each(|i| appender.append(i).unwrap());
+        let vector = appender.finish(1380).unwrap();
+        let reader = VectorReader::<u32>::try_new(&vector[..]).unwrap();
+        let (offsets, _) = section_offsets(&reader).unwrap();
+        assert_eq!(offsets.len(), 6);
+
+        // Split between the two RepeatPrevious sections, and between the null sections
+        let points = vec![offsets[2].0, offsets[4].0];
+        let shards = split_at_points::<u32>(&vector[..], &points[..]).unwrap();
+        assert_eq!(shards.len(), 3);
+        let readers: Vec<_> = shards.iter().map(|s| VectorReader::<u32>::try_new(&s[..]).unwrap()).collect();
+        assert_eq!(readers.iter().map(|r| r.num_elements()).collect::<Vec<_>>(), vec![512, 512, 356]);
+        assert_eq!(readers.iter().map(|r| r.get_stats().num_null_sections).collect::<Vec<_>>(), vec![0, 1, 1]);
+        let values: Vec<u32> = readers.iter().flat_map(|r| r.iterate().collect::<Vec<_>>()).collect();
+        assert_eq!(values, reader.iterate().collect::<Vec<_>>());
+        assert!(readers.iter().all(|r| r.might_contain(255 * 5)));
+        readers.iter().for_each(|r| r.cross_check().unwrap());
+
+        // No split points gives the same vector back, and split points must be section starts in order
+        assert_eq!(split_at_points::<u32>(&vector[..], &[]).unwrap(), vec![vector.clone()]);
+        let empty = VectorU32Appender::try_new(1024).unwrap().finish(0).unwrap();
+        assert_eq!(split_at_points::<u32>(&empty[..], &[]).unwrap(), vec![empty.clone()]);
+        assert_eq!(split_at_points::<u32>(&vector[..], &[offsets[0].0 + 1]),
+                   Err(CodingError::BadOffset(offsets[0].0 + 1)));
+        assert_eq!(split_at_points::<u32>(&vector[..], &[offsets[4].0, offsets[2].0]),
+                   Err(CodingError::BadOffset(offsets[2].0)));
+
+        // Every section is its own shard with a tiny target
+        let points = split_points::<u32>(&vector[..], 1).unwrap();
+        assert_eq!(points, offsets[1..].iter().map(|&(offset, _)| offset).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_split_cuts_section_bloom_filters() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.enable_section_bloom_filters(16);
+        let vector = appender.encode_all((0..1100u32).map(|i| i * 3)).unwrap();
+        let reader = VectorReader::<u32>::try_new(&vector[..]).unwrap();
+        let (offsets, _) = section_offsets(&reader).unwrap();
+
+        let shards = split_at_points::<u32>(&vector[..], &[offsets[2].0]).unwrap();
+        let second = VectorReader::<u32>::try_new(&shards[1][..]).unwrap();
+        let blooms = second.section_bloom_filters().unwrap().unwrap();
+        assert_eq!(blooms.clone().count(), 3);
+
+        // 700 * 3 is in the first section of the second shard, the third section of the vector
+        let mut filter = second.filter_iter(EqualsSink::<u32>::new(&2100));
+        filter.set_section_bloom_filters(blooms);
+        assert_eq!(match_positions(filter), vec![700 - 512]);
+    }
+}
@@ -0,0 +1,425 @@
+/// The `strdict` module stores vectors of strings with few distinct values, such as label or tag columns, by
+/// dictionary encoding them.  Each distinct string gets a u32 code, and the codes are stored as the elements of
+/// a u32 vector, so they get all the regular section encodings and filters.  The strings themselves are stored
+/// once per vector in a dictionary blob in the vector footer.
+/// ```
+/// # use compressed_vec::strdict::*;
+///     let mut appender = StrDictAppender::try_new(1024).unwrap();
+///     appender.append("us-east").unwrap();
+///     appender.append_nulls(1).unwrap();
+///     appender.append("eu-west").unwrap();
+///     appender.append("us-east").unwrap();
+///     let bytes = appender.finish(4).unwrap();
+///
+///     let reader = StrDictReader::try_new(&bytes[..]).unwrap();
+///     assert_eq!(reader.dictionary().len(), 2);
+///     assert_eq!(reader.to_vec().unwrap(), vec![Some("us-east"), None, Some("eu-west"), Some("us-east")]);
+/// ```
+/// Code 0 is reserved for nulls, so that null sections and the padding of the last section decode as `None`.
+/// The dictionary string with index i has code i + 1, in order of first appearance.  To filter on a string,
+/// look up its code with `code_of()`, and filter the vector of codes from `codes()`.
+///
+/// String kernels such as `lengths()` and `prefix()` compute their result once per dictionary string, and
+/// broadcast it to the elements through the codes, so the strings are never decoded per element.
+///
+/// Vectors written by different appenders number the same strings differently.  Each vector stores a
+/// fingerprint of its dictionary which does not depend on the order of the strings, and `align_codes()` maps the
+/// codes of one vector to those of another whose dictionary holds the same strings, eg to concatenate them.
+///
+/// The dictionary blob has this layout:
+///
+/// | offset | description |
+/// | ------ | ----------- |
+/// | +0     | u32: number of strings n |
+/// | +4     | n + 1 u32's: offset of each string into the string bytes, then the length of the string bytes |
+/// | +8+4n  | string bytes, UTF-8, back to back |
+use crate::error::CodingError;
+
+use std::collections::HashMap;
+
+use scroll::{Pread, LE};
+
+use crate::bloom::mix;
+use crate::footer::{FooterTag, FooterWriter};
+use crate::section::FIXED_LEN;
+use crate::sink::{Section256Sink, Sink};
+use crate::vector::{BinaryVector, VectorItemIter, VectorReader, VectorSubType, VectorU32Appender};
+
+
+// Sum of the hashes of the strings, so that the order of the strings does not matter
+fn fingerprint<'a, I: Iterator<Item = &'a str>>(strings: I) -> u64 {
+    strings.map(|s| {
+        mix(s.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3)))
+    }).fold(0, u64::wrapping_add)
+}
+
+/// The dictionary of a string vector, borrowed from the vector footer
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StrDictionary<'buf> {
+    offsets: &'buf [u8],
+    strings: &'buf str,
+}
+
+impl<'buf> StrDictionary<'buf> {
+    /// Parses and validates a dictionary blob
+    pub fn try_from(blob: &'buf [u8]) -> Result<Self, CodingError> {
+        let num_strings: u32 = blob.pread_with(0, LE)?;
+        let strings_start = (num_strings as usize + 1).checked_mul(4).and_then(|n| n.checked_add(4))
+                                                      .ok_or(CodingError::InputTooShort)?;
+        let offsets = blob.get(4..strings_start).ok_or(CodingError::InputTooShort)?;
+        let strings = std::str::from_utf8(&blob[strings_start..])
+                          .map_err(|e| CodingError::InvalidFormat(format!("Dictionary is not UTF-8: {}", e)))?;
+        let dict = Self { offsets, strings };
+        // Every string must start and end on a char boundary within the string bytes, in order
+        let mut prev = 0;
+        for i in 0..=num_strings as usize {
+            let off = dict.offset(i);
+            if off < prev || !strings.is_char_boundary(off) || (i == num_strings as usize && off != strings.len()) {
+                return Err(CodingError::InvalidFormat(format!("Invalid dictionary offset {} at {}", off, i)));
+            }
+            prev = off;
+        }
+        Ok(dict)
+    }
+
+    // Offsets were checked against the length of offsets in try_from
+    fn offset(&self, i: usize) -> usize {
+        self.offsets.pread_with::<u32>(i * 4, LE).unwrap_or(0) as usize
+    }
+
+    /// The number of distinct strings
+    pub fn len(&self) -> usize {
+        self.offsets.len() / 4 - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the string for a code, None for the null code 0.  Returns BadOffset for codes not in the dictionary.
+    pub fn get(&self, code: u32) -> Result<Option<&'buf str>, CodingError> {
+        match code as usize {
+            0 => Ok(None),
+            c if c <= self.len() => Ok(Some(&self.strings[self.offset(c - 1)..self.offset(c)])),
+            c => Err(CodingError::BadOffset(c)),
+        }
+    }
+
+    /// Returns the code of a string, if it is in the dictionary
+    pub fn code_of(&self, s: &str) -> Option<u32> {
+        self.iter().position(|d| d == s).map(|i| i as u32 + 1)
+    }
+
+    /// Computes the fingerprint of the strings, which is the same for dictionaries with the same strings in any
+    /// order.  See `StrDictReader::fingerprint()` for the one stored in the vector.
+    pub fn fingerprint(&self) -> u64 {
+        fingerprint(self.iter())
+    }
+
+    /// Iterates over the strings in code order, starting with code 1
+    pub fn iter(&self) -> impl Iterator<Item = &'buf str> {
+        let dict = *self;
+        (1..=self.len()).map(move |c| &dict.strings[dict.offset(c - 1)..dict.offset(c)])
+    }
+}
+
+/// An appender for dictionary encoded string vectors.  `finish()` clones out the vector and resets the appender.
+pub struct StrDictAppender {
+    codes: VectorU32Appender,
+    dict: HashMap<String, u32>,
+    strings: Vec<String>,
+}
+
+impl StrDictAppender {
+    pub fn try_new(initial_capacity: usize) -> Result<Self, CodingError> {
+        Ok(Self { codes: VectorU32Appender::try_new(initial_capacity)?, dict: HashMap::new(), strings: Vec::new() })
+    }
+
+    /// Appends all strings from a collection and finishes the vector, returning the encoded bytes.
+    pub fn encode_all<'a, C>(&mut self, collection: C) -> Result<Vec<u8>, CodingError>
+    where C: IntoIterator<Item = &'a str> {
+        let mut count = 0;
+        for s in collection.into_iter() {
+            count += 1;
+            self.append(s)?;
+        }
+        self.finish(count)
+    }
+
+    pub fn num_elements(&self) -> usize {
+        self.codes.num_elements()
+    }
+
+    /// The number of distinct strings appended so far
+    pub fn num_strings(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Appends a string, adding it to the dictionary if it is new
+    pub fn append(&mut self, s: &str) -> Result<(), CodingError> {
+        let code = self.code_for(s);
+        self.codes.append(code)
+    }
+
+    // Returns the code of a string, adding it to the dictionary if it is new
+    fn code_for(&mut self, s: &str) -> u32 {
+        match self.dict.get(s) {
+            Some(&code) => code,
+            None => {
+                let code = self.strings.len() as u32 + 1;
+                self.dict.insert(s.to_string(), code);
+                self.strings.push(s.to_string());
+                code
+            }
+        }
+    }
+
+    /// Appends a number of nulls
+    pub fn append_nulls(&mut self, num_nulls: usize) -> Result<(), CodingError> {
+        self.codes.append_nulls(num_nulls)
+    }
+
+    /// Finishes the vector with total_num_rows elements, padding with nulls, and resets the appender.
+    pub fn finish(&mut self, total_num_rows: usize) -> Result<Vec<u8>, CodingError> {
+        let mut footer = FooterWriter::new();
+        footer.add(FooterTag::StrDictionary, &self.dictionary_blob()[..]);
+        let fingerprint = fingerprint(self.strings.iter().map(|s| s.as_str()));
+        footer.add(FooterTag::StrDictFingerprint, &fingerprint.to_le_bytes());
+        let mut vect_bytes = self.codes.finish_with_footer(total_num_rows, footer)?;
+        vect_bytes[offset_of!(BinaryVector, minor_type)] = VectorSubType::FixedStrDict as u8;
+        self.dict.clear();
+        self.strings.clear();
+        Ok(vect_bytes)
+    }
+
+    fn dictionary_blob(&self) -> Vec<u8> {
+        let num_bytes: usize = self.strings.iter().map(|s| s.len()).sum();
+        let mut blob = Vec::with_capacity(8 + 4 * self.strings.len() + num_bytes);
+        blob.extend_from_slice(&(self.strings.len() as u32).to_le_bytes());
+        let mut offset = 0u32;
+        for s in &self.strings {
+            blob.extend_from_slice(&offset.to_le_bytes());
+            offset += s.len() as u32;
+        }
+        blob.extend_from_slice(&offset.to_le_bytes());
+        self.strings.iter().for_each(|s| blob.extend_from_slice(s.as_bytes()));
+        blob
+    }
+
+    pub fn reset(&mut self) -> Result<(), CodingError> {
+        self.dict.clear();
+        self.strings.clear();
+        self.codes.reset()
+    }
+}
+
+/// A reader for dictionary encoded string vectors
+pub struct StrDictReader<'buf> {
+    codes: VectorReader<'buf, u32>,
+    dictionary: StrDictionary<'buf>,
+    fingerprint: Option<u64>,     // Stored fingerprint, if any
+}
+
+impl<'buf> StrDictReader<'buf> {
+    pub fn try_new(vect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        let codes = VectorReader::try_new_with_subtype(vect_bytes, VectorSubType::FixedStrDict)?;
+        let blob = codes.footer().and_then(|f| f.get(FooterTag::StrDictionary))
+                        .ok_or_else(|| CodingError::InvalidFormat("String vector has no dictionary".into()))?;
+        let dictionary = StrDictionary::try_from(blob)?;
+        let fingerprint = match codes.footer().and_then(|f| f.get(FooterTag::StrDictFingerprint)) {
+            Some(entry) => Some(entry.pread_with::<u64>(0, LE)?),
+            None        => None,
+        };
+        Ok(Self { codes, dictionary, fingerprint })
+    }
+
+    pub fn num_elements(&self) -> usize {
+        self.codes.num_elements()
+    }
+
+    pub fn dictionary(&self) -> StrDictionary<'buf> {
+        self.dictionary
+    }
+
+    /// The vector of u32 codes, for filtering and other processing with the regular u32 machinery
+    pub fn codes(&self) -> &VectorReader<'buf, u32> {
+        &self.codes
+    }
+
+    /// Returns the code of a string, if it is in the dictionary
+    pub fn code_of(&self, s: &str) -> Option<u32> {
+        self.dictionary.code_of(s)
+    }
+
+    /// The fingerprint of the dictionary stored in the vector, or computed from the dictionary for vectors written
+    /// without one
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint.unwrap_or_else(|| self.dictionary.fingerprint())
+    }
+
+    /// Iterates over the strings of the vector, None for nulls.  Codes not in the dictionary are errors.
+    pub fn iterate(&self) -> impl Iterator<Item = Result<Option<&'buf str>, CodingError>> {
+        let dictionary = self.dictionary;
+        let codes: VectorItemIter<'buf, u32> = self.codes.iterate();
+        codes.map(move |code| dictionary.get(code))
+    }
+
+    /// Decodes all the strings of the vector
+    pub fn to_vec(&self) -> Result<Vec<Option<&'buf str>>, CodingError> {
+        self.iterate().collect()
+    }
+
+    /// Computes the length in chars of every string as a u32 vector, written and finished with the given
+    /// appender.  Nulls are appended with `append_nulls()`, so they are zeroes unless the appender tracks nulls.
+    pub fn lengths(&self, appender: &mut VectorU32Appender) -> Result<Vec<u8>, CodingError> {
+        let lengths: Vec<u32> = self.dictionary.iter().map(|s| s.chars().count() as u32).collect();
+        self.broadcast(&lengths[..], appender)?;
+        appender.finish(self.num_elements())
+    }
+
+    /// Computes the first n chars of every string as a new string vector, written and finished with the given
+    /// appender, which is reset first.  Strings with the same prefix share one dictionary string.
+    pub fn prefix(&self, n: usize, appender: &mut StrDictAppender) -> Result<Vec<u8>, CodingError> {
+        appender.reset()?;
+        let codes: Vec<u32> = self.dictionary.iter().map(|s| {
+            let end = s.char_indices().nth(n).map_or(s.len(), |(i, _)| i);
+            appender.code_for(&s[..end])
+        }).collect();
+        self.broadcast(&codes[..], &mut appender.codes)?;
+        appender.finish(self.num_elements())
+    }
+
+    // Appends values[code - 1] for the code of every element, and a null for the null code.  Null sections are
+    // appended without decoding.
+    fn broadcast(&self, values: &[u32], out: &mut VectorU32Appender) -> Result<(), CodingError> {
+        let mut sink = Section256Sink::<u32>::new();
+        let mut elems_left = self.num_elements();
+        for sect in self.codes.sect_iter() {
+            if elems_left == 0 { break }
+            let sect = sect?;
+            let sect_elems = elems_left.min(FIXED_LEN);
+            elems_left -= sect_elems;
+            if sect.is_null() {
+                out.append_nulls(sect_elems)?;
+                continue;
+            }
+            sink.reset();
+            sect.decode(&mut sink)?;
+            for &code in &sink.values[..sect_elems] {
+                match code as usize {
+                    0 => out.append_nulls(1)?,
+                    c => out.append(*values.get(c - 1).ok_or(CodingError::BadOffset(c))?)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Maps the codes of vector b to the codes of vector a, if their dictionaries hold the same strings in any order:
+/// the string with code c in b has code `remap[c]` in a, and the null code 0 maps to 0.  Returns None if the
+/// dictionaries differ, which differing fingerprints tell without comparing any strings.
+pub fn align_codes(a: &StrDictReader, b: &StrDictReader) -> Option<Vec<u32>> {
+    if a.dictionary.len() != b.dictionary.len() || a.fingerprint() != b.fingerprint() { return None }
+    let codes_a: HashMap<&str, u32> = a.dictionary.iter().zip(1..).collect();
+    std::iter::once(Some(0)).chain(b.dictionary.iter().map(|s| codes_a.get(s).cloned())).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{count_hits, EqualsSink};
+
+    #[test]
+    fn test_strdict_roundtrip_and_filter() {
+        let labels = ["GET", "POST", "", "PUT", "ünïcødé"];
+        let mut appender = StrDictAppender::try_new(1024).unwrap();
+        for i in 0..600 {
+            if i % 50 == 7 { appender.append_nulls(1).unwrap() } else { appender.append(labels[i % 5]).unwrap() }
+        }
+        assert_eq!(appender.num_strings(), 5);
+        let bytes = appender.finish(610).unwrap();
+        assert_eq!(appender.num_elements(), 0);
+
+        let reader = StrDictReader::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.num_elements(), 610);
+        let expected: Vec<Option<&str>> = (0..610).map(|i| {
+            if i >= 600 || i % 50 == 7 { None } else { Some(labels[i % 5]) }
+        }).collect();
+        assert_eq!(reader.to_vec().unwrap(), expected);
+
+        // Filter on the codes of a string
+        let code = reader.code_of("POST").unwrap();
+        let hits = count_hits(reader.codes().filter_iter(EqualsSink::<u32>::new(&code)));
+        assert_eq!(hits, expected.iter().filter(|&&s| s == Some("POST")).count());
+        assert_eq!(reader.code_of("DELETE"), None);
+
+        // Other vector types are rejected, and corrupt dictionaries are errors
+        let u32_bytes = VectorU32Appender::try_new(1024).unwrap().encode_all(vec![1, 2]).unwrap();
+        assert!(matches!(StrDictReader::try_new(&u32_bytes[..]), Err(CodingError::WrongElementType { .. })));
+        assert!(matches!(VectorReader::<u32>::try_new(&bytes[..]), Err(CodingError::WrongElementType { .. })));
+        assert!(StrDictionary::try_from(&[1, 0, 0, 0, 0, 0, 0, 0, 9, 0, 0, 0, b'a'][..]).is_err());
+        let dict = StrDictionary::try_from(&[1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, b'a'][..]).unwrap();
+        assert_eq!((dict.get(0), dict.get(1), dict.get(2)), (Ok(None), Ok(Some("a")), Err(CodingError::BadOffset(2))));
+    }
+
+    #[test]
+    fn test_lengths_and_prefix() {
+        let labels = ["us-east-1", "us-east-2", "eu-west-1", "ünïcødé", ""];
+        // With a null section in the middle
+        let mut appender = StrDictAppender::try_new(1024).unwrap();
+        for i in 0..612 {
+            if i == 512 { appender.append_nulls(256).unwrap(); }
+            if i % 40 == 3 { appender.append_nulls(1).unwrap() } else { appender.append(labels[i % 5]).unwrap() }
+        }
+        let bytes = appender.finish(868).unwrap();
+        let reader = StrDictReader::try_new(&bytes[..]).unwrap();
+        let strings = reader.to_vec().unwrap();
+
+        // Nulls are zeroes, or nulls when tracked
+        let mut u32_appender = VectorU32Appender::try_new(1024).unwrap();
+        let lengths = reader.lengths(&mut u32_appender).unwrap();
+        let lengths = VectorReader::<u32>::try_new(&lengths[..]).unwrap();
+        let expected: Vec<u32> = strings.iter().map(|s| s.map_or(0, |s| s.chars().count() as u32)).collect();
+        assert_eq!(lengths.iterate().collect::<Vec<_>>(), expected);
+        assert_eq!(lengths.num_null_sections().unwrap(), 1);
+        u32_appender.track_nulls(true);
+        let lengths = reader.lengths(&mut u32_appender).unwrap();
+        let lengths = VectorReader::<u32>::try_new(&lengths[..]).unwrap();
+        assert_eq!(lengths.null_count(), strings.iter().filter(|s| s.is_none()).count());
+
+        // Prefixes shared by several strings get one dictionary string
+        let prefixes = reader.prefix(5, &mut appender).unwrap();
+        let prefixes = StrDictReader::try_new(&prefixes[..]).unwrap();
+        assert_eq!(prefixes.dictionary().iter().collect::<Vec<_>>(), vec!["us-ea", "eu-we", "ünïcø", ""]);
+        let expected: Vec<Option<&str>> = strings.iter().map(|s| s.map(|s| {
+            &s[..s.char_indices().nth(5).map_or(s.len(), |(i, _)| i)]
+        })).collect();
+        assert_eq!(prefixes.to_vec().unwrap(), expected);
+        let empty = reader.prefix(0, &mut appender).unwrap();
+        assert_eq!(StrDictReader::try_new(&empty[..]).unwrap().dictionary().iter().collect::<Vec<_>>(), vec![""]);
+    }
+
+    #[test]
+    fn test_fingerprint_and_align_codes() {
+        let mut appender = StrDictAppender::try_new(1024).unwrap();
+        let a_bytes = appender.encode_all(vec!["x", "y", "z", "x"]).unwrap();
+        let b_bytes = appender.encode_all(vec!["z", "x", "y", "y", "z"]).unwrap();
+        let c_bytes = appender.encode_all(vec!["x", "y", "w"]).unwrap();
+        let a = StrDictReader::try_new(&a_bytes[..]).unwrap();
+        let b = StrDictReader::try_new(&b_bytes[..]).unwrap();
+        let c = StrDictReader::try_new(&c_bytes[..]).unwrap();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_eq!(a.fingerprint(), a.dictionary().fingerprint());
+        assert_ne!(a.fingerprint(), c.fingerprint());
+
+        // Remapped codes of b are the codes of the same strings in a
+        let remap = align_codes(&a, &b).unwrap();
+        assert_eq!(remap, vec![0, 3, 1, 2]);
+        for code in b.codes().iterate() {
+            assert_eq!(a.dictionary().get(remap[code as usize]), b.dictionary().get(code));
+        }
+        assert_eq!(align_codes(&a, &c), None);
+    }
+}
@@ -0,0 +1,193 @@
+/// The `stream` module converts between variable-sized section streams written with `SectionWriter`, and the
+/// fixed 256-element section vectors used by `VectorReader` and the SIMD filtering and query paths.  Data
+/// ingested with the flexible writer can thus be promoted to fixed-section vectors for serving, and vice versa.
+///
+/// `SectionWriter` does not define the contents of a section, so conversion relies on the following
+/// conventions for u64 values, based on the section type in each `SectionHeader`:
+/// - `Null`: num_elements zeroes, no bytes
+/// - `NibblePackedMedium`: NibblePacked groups of 8 values, as written by `nibble_pack8`.  Only the last
+///   group of a section may have fewer than 8 valid values; it is padded with zeroes.
+/// - `NibblePackedZRMedium`: same, but in ZeroRuns mode
+/// - `Constant`: one u64 LE value, repeated num_elements times
+///
+/// ```
+/// # use compressed_vec::raw::section::SectionWriter;
+/// # use compressed_vec::stream::*;
+/// # use compressed_vec::vector::VectorU64Appender;
+///     let mut appender = VectorU64Appender::try_new(4096).unwrap();
+///     let vector = appender.encode_all((0..1000u64).map(|i| i * i)).unwrap();
+///
+///     let mut buf = vec![0u8; 16384];
+///     let mut writer = SectionWriter::new(&mut buf[..], 512);
+///     assert_eq!(vector_to_stream(&vector[..], &mut writer).unwrap(), 1000);
+///     let stream_len = writer.cur_pos();
+///
+///     let promoted = stream_to_vector(&buf[..stream_len], &mut appender).unwrap();
+///     assert_eq!(promoted, vector);
+/// ```
+use crate::error::CodingError;
+
+use scroll::{Pread, LE};
+
+use crate::nibblepacking::{nibble_pack8, unpack_with_mode, NibblePackMode};
+use crate::scratch::ScratchGuard;
+use crate::section::*;
+use crate::sink::{Section256Sink, Sink, VecSink};
+use crate::vector::{VectorAppender, VectorReader};
+
+/// Iterates over the (SectionHeader, section bytes) of a stream written by SectionWriter
+pub struct SectionHeaderIter<'buf> {
+    encoded_bytes: &'buf [u8],
+}
+
+impl<'buf> SectionHeaderIter<'buf> {
+    pub fn new(encoded_bytes: &'buf [u8]) -> Self {
+        Self { encoded_bytes }
+    }
+}
+
+impl<'buf> Iterator for SectionHeaderIter<'buf> {
+    type Item = Result<(SectionHeader, &'buf [u8]), CodingError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.encoded_bytes.is_empty() { return None }
+        let res = self.encoded_bytes.pread_with::<SectionHeader>(0, LE)
+            .map_err(CodingError::from)
+            .and_then(|header| {
+                let end = SECTION_HEADER_SIZE + header.num_bytes();
+                let bytes = self.encoded_bytes.get(SECTION_HEADER_SIZE..end).ok_or(CodingError::InputTooShort)?;
+                Ok((header, bytes))
+            });
+        match &res {
+            Ok((header, _)) => self.encoded_bytes = &self.encoded_bytes[SECTION_HEADER_SIZE + header.num_bytes()..],
+            Err(_)          => self.encoded_bytes = &[],
+        }
+        Some(res)
+    }
+}
+
+/// Appends all the values of a SectionWriter stream to the appender, and finishes the vector, returning its
+/// bytes.  The appender decides on the best encoding for each fixed section.
+pub fn stream_to_vector<W>(stream_bytes: &[u8],
+                           appender: &mut VectorAppender<u64, W>) -> Result<Vec<u8>, CodingError>
+where W: FixedSectionWriter<u64> {
+    let mut sink = ScratchGuard::<VecSink<u64>>::take();
+    for sect in SectionHeaderIter::new(stream_bytes) {
+        let (header, bytes) = sect?;
+        let num_elements = header.num_elements();
+        let mode = match header.sect_type() {
+            SectionType::Null => {
+                appender.append_zeroes(num_elements)?;
+                continue;
+            }
+            SectionType::Constant => {
+                let value: u64 = bytes.pread_with(0, LE)?;
+                for _ in 0..num_elements { appender.append(value)?; }
+                continue;
+            }
+            SectionType::NibblePackedMedium   => NibblePackMode::Standard,
+            SectionType::NibblePackedZRMedium => NibblePackMode::ZeroRuns,
+            other => return Err(CodingError::InvalidSectionType(other.as_num())),
+        };
+        sink.vec.clear();
+        unpack_with_mode(bytes, &mut *sink, num_elements, mode)?;
+        for &value in &sink.vec[..num_elements] {
+            appender.append(value)?;
+        }
+    }
+    let num_elements = appender.num_elements();
+    appender.finish(num_elements)
+}
+
+/// Writes all the values of a u64 fixed-section vector to a SectionWriter as NibblePackedMedium sections,
+/// returning the number of values written.  The writer decides how many values go into each section.
+pub fn vector_to_stream(vect_bytes: &[u8], writer: &mut SectionWriter) -> Result<usize, CodingError> {
+    let reader = VectorReader::<u64>::try_new(vect_bytes)?;
+    let num_elements = reader.num_elements();
+    let mut sink = ScratchGuard::<Section256Sink<u64>>::take();
+    let mut written = 0;
+    for sect in reader.sect_iter() {
+        sink.reset();
+        sect?.decode(&mut *sink)?;
+        let sect_elements = (num_elements - written).min(FIXED_LEN);
+        let mut values = &sink.values[..sect_elements];
+        while !values.is_empty() {
+            let (_, num_written) = writer.add_64kb(SectionType::NibblePackedMedium, |buf, elements_left| {
+                // Returning NotEnoughSpace makes the writer start a new section
+                let num = values.len().min(8).min(elements_left);
+                if num == 0 { return Err(CodingError::NotEnoughSpace) }
+                let mut group = [0u64; 8];
+                group[..num].copy_from_slice(&values[..num]);
+                let num_bytes = nibble_pack8(&group, buf, 0)?;
+                Ok((num_bytes as u16, num as u16))
+            })?;
+            values = &values[num_written as usize..];
+        }
+        written += sect_elements;
+        if written >= num_elements { break; }
+    }
+    Ok(written)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scroll::Pwrite;
+    use crate::nibblepacking::pack_u64;
+    use crate::vector::VectorU64Appender;
+
+    #[test]
+    fn test_promote_stream_with_all_section_types() {
+        let mut buf = [0u8; 4096];
+        let mut off = 0;
+        // Null section of 100 elements
+        off += buf.pwrite_with(SectionHeader::new(0, 100, SectionType::Null), off, LE).unwrap();
+        // Constant section of 50 elements
+        off += buf.pwrite_with(SectionHeader::new(8, 50, SectionType::Constant), off, LE).unwrap();
+        off += buf.pwrite_with(77u64, off, LE).unwrap();
+        // NibblePacked section of 21 elements
+        let data: Vec<u64> = (0..21).map(|i| i * 1000).collect();
+        let end = pack_u64(data.iter().cloned(), &mut buf, off + SECTION_HEADER_SIZE).unwrap();
+        let num_bytes = (end - off - SECTION_HEADER_SIZE) as u16;
+        buf.pwrite_with(SectionHeader::new(num_bytes, 21, SectionType::NibblePackedMedium), off, LE).unwrap();
+        off = end;
+
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        let vector = stream_to_vector(&buf[..off], &mut appender).unwrap();
+        let values: Vec<u64> = VectorReader::<u64>::try_new(&vector[..]).unwrap().iterate().collect();
+        let expected: Vec<u64> = std::iter::repeat(0).take(100)
+                                     .chain(std::iter::repeat(77).take(50))
+                                     .chain(data.into_iter())
+                                     .collect();
+        assert_eq!(values, expected);
+
+        // Truncated and unsupported sections are errors
+        assert_eq!(stream_to_vector(&buf[..off - 1], &mut appender).unwrap_err(), CodingError::InputTooShort);
+        appender.reset().unwrap();
+        buf.pwrite_with(SectionHeader::new(0, 10, SectionType::XorNPMedium), 0, LE).unwrap();
+        assert_eq!(stream_to_vector(&buf[..off], &mut appender), Err(CodingError::InvalidSectionType(6)));
+    }
+
+    #[test]
+    fn test_vector_to_stream_small_sections() {
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..300u64).for_each(|i| appender.append(i).unwrap());
+        appender.append_nulls(300).unwrap();
+        appender.append(5).unwrap();
+        let vector = appender.finish(601).unwrap();
+
+        // 100 elements per section is not a multiple of 8, so every section ends with a partial group
+        let mut buf = vec![0u8; 8192];
+        let mut writer = SectionWriter::new(&mut buf[..], 100);
+        assert_eq!(vector_to_stream(&vector[..], &mut writer).unwrap(), 601);
+        let stream_len = writer.cur_pos();
+
+        let headers: Vec<SectionHeader> = SectionHeaderIter::new(&buf[..stream_len]).map(|s| s.unwrap().0).collect();
+        assert_eq!(headers.len(), 7);
+        assert!(headers[..6].iter().all(|h| h.num_elements() == 100));
+        assert_eq!(headers[6].num_elements(), 1);
+
+        let promoted = stream_to_vector(&buf[..stream_len], &mut appender).unwrap();
+        assert_eq!(promoted, vector);
+    }
+}
@@ -0,0 +1,223 @@
+/// The `transform` module allows section payloads to pass through a user-provided `SectionTransform`, for
+/// example for encryption at rest using AES-GCM keyed per vector.  The surrounding vector format is unchanged:
+/// the 16-byte header stays readable, and every section is replaced by a Transformed section wrapping the
+/// sealed bytes of the original section.  The id of the transform is recorded in the header, so that readers
+/// can tell which transform (key, algorithm) is needed, and so that `VectorReader` refuses to read sealed
+/// vectors directly.
+///
+/// Sealing is done on a finished vector, and opening produces a regular vector which can then be read,
+/// filtered etc. as usual:
+/// ```
+/// # use compressed_vec::vector::{VectorU32Appender, VectorReader};
+/// # use compressed_vec::transform::*;
+/// # use compressed_vec::error::CodingError;
+/// struct Rot13 {}
+///
+/// impl SectionTransform for Rot13 {
+///     fn id(&self) -> u8 { 13 }
+///     fn seal(&self, _sect_no: u32, plain: &[u8]) -> Result<Vec<u8>, CodingError> {
+///         Ok(plain.iter().map(|b| b.wrapping_add(13)).collect())
+///     }
+///     fn open(&self, _sect_no: u32, sealed: &[u8]) -> Result<Vec<u8>, CodingError> {
+///         Ok(sealed.iter().map(|b| b.wrapping_sub(13)).collect())
+///     }
+/// }
+///
+///     let mut appender = VectorU32Appender::try_new(1024).unwrap();
+///     let plain = appender.encode_all(0..300u32).unwrap();
+///     let sealed = seal_vector::<u32>(&plain[..], &Rot13 {}).unwrap();
+///     assert!(VectorReader::<u32>::try_new(&sealed[..]).is_err());
+///
+///     let opened = open_vector::<u32>(&sealed[..], &Rot13 {}).unwrap();
+///     assert_eq!(opened, plain);
+/// ```
+///
+/// NOTE: the header, including the number of elements and null sections, is not sealed.  Neither is the footer,
+/// so Bloom filters of sealed vectors reveal which values might be present.
+use std::convert::TryFrom;
+
+use scroll::{Pread, Pwrite, LE};
+
+use crate::error::CodingError;
+use crate::footer::Footer;
+use crate::section::*;
+use crate::vector::{BaseSubtypeMapping, VectorReader, NUM_HEADER_BYTES_TOTAL, TRANSFORM_ID_OFFSET};
+
+
+/// A reversible transform of section bytes, such as authenticated encryption.
+/// The section number (starting from 0) is passed in so that it may be used to derive a nonce.
+pub trait SectionTransform {
+    /// A nonzero id which identifies this transform, stored in the vector header
+    fn id(&self) -> u8;
+
+    /// Transforms the bytes of a section, returning the sealed bytes.  At most 65535 sealed bytes.
+    fn seal(&self, sect_no: u32, plain: &[u8]) -> Result<Vec<u8>, CodingError>;
+
+    /// Reverses seal().  Should return Err if the sealed bytes cannot be authenticated.
+    fn open(&self, sect_no: u32, sealed: &[u8]) -> Result<Vec<u8>, CodingError>;
+}
+
+/// Number of bytes in the section at the start of sect_bytes
+fn raw_section_len<T: VectBase>(sect_bytes: &[u8]) -> Result<usize, CodingError> {
+    if sect_bytes[0] == SectionType::RepeatPrevious.as_num() {
+        Ok(1)
+    } else {
+        Ok(FixedSectEnum::<T>::try_from(sect_bytes)?.num_bytes())
+    }
+}
+
+fn finish_vector(mut out: Vec<u8>, transform_id: u8) -> Result<Vec<u8>, CodingError> {
+    out[TRANSFORM_ID_OFFSET] = transform_id;
+    let num_bytes = (out.len() - 4) as u32;
+    out.pwrite_with(num_bytes, 0, LE)?;
+    Ok(out)
+}
+
+/// Seals every section of a finished vector using the transform, returning the new vector bytes.
+pub fn seal_vector<T>(vect_bytes: &[u8], transform: &dyn SectionTransform) -> Result<Vec<u8>, CodingError>
+where T: VectBase + BaseSubtypeMapping {
+    if transform.id() == 0 { return Err(CodingError::InvalidFormat("Transform id must be nonzero".into())) }
+    let reader = VectorReader::<T>::try_new(vect_bytes)?;
+    let mut encoded = reader.sect_bytes();
+    let footer_start = NUM_HEADER_BYTES_TOTAL + encoded.len();
+    let mut out = vect_bytes[..NUM_HEADER_BYTES_TOTAL].to_vec();
+    let mut sect_no = 0;
+    while !encoded.is_empty() {
+        let sect_len = raw_section_len::<T>(encoded)?;
+        let sealed = transform.seal(sect_no, &encoded[..sect_len])?;
+        if sealed.len() > 65535 { return Err(CodingError::NotEnoughSpace) }
+        out.push(SectionType::Transformed.as_num());
+        out.extend_from_slice(&(sealed.len() as u16).to_le_bytes());
+        out.extend_from_slice(&sealed[..]);
+        encoded = &encoded[sect_len..];
+        sect_no += 1;
+    }
+    out.extend_from_slice(&vect_bytes[footer_start..reader.total_bytes()]);
+    finish_vector(out, transform.id())
+}
+
+/// Opens every section of a vector sealed with seal_vector(), returning the bytes of the original vector.
+/// The transform id must match the one recorded in the vector.
+pub fn open_vector<T>(vect_bytes: &[u8], transform: &dyn SectionTransform) -> Result<Vec<u8>, CodingError>
+where T: VectBase + BaseSubtypeMapping {
+    let bytes_from_header: u32 = vect_bytes.pread_with(0, LE)?;
+    let transform_id: u8 = vect_bytes.pread_with(TRANSFORM_ID_OFFSET, LE)?;
+    let total_bytes = bytes_from_header as usize + 4;
+    if vect_bytes.len() < total_bytes || total_bytes < NUM_HEADER_BYTES_TOTAL {
+        return Err(CodingError::InputTooShort);
+    }
+    if transform_id != transform.id() {
+        return Err(CodingError::InvalidFormat(format!("Vector transform id {} does not match transform id {}",
+                                                      transform_id, transform.id())));
+    }
+
+    let (mut encoded, _) = Footer::split(&vect_bytes[..total_bytes])?;
+    let footer_start = NUM_HEADER_BYTES_TOTAL + encoded.len();
+    let mut out = vect_bytes[..NUM_HEADER_BYTES_TOTAL].to_vec();
+    let mut sect_no = 0;
+    while !encoded.is_empty() {
+        if encoded[0] != SectionType::Transformed.as_num() {
+            return Err(CodingError::InvalidSectionType(encoded[0]));
+        }
+        let sealed_len: u16 = encoded.pread_with(1, LE)?;
+        let sealed = encoded.get(3..3 + sealed_len as usize).ok_or(CodingError::InputTooShort)?;
+        out.extend_from_slice(&transform.open(sect_no, sealed)?[..]);
+        encoded = &encoded[3 + sealed_len as usize..];
+        sect_no += 1;
+    }
+    out.extend_from_slice(&vect_bytes[footer_start..total_bytes]);
+    let out = finish_vector(out, 0)?;
+    VectorReader::<T>::try_new(&out[..])?;
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{VectorF32XorAppender, VectorU64Appender};
+
+    // Toy keyed stream cipher with a 1-byte checksum standing in for an authentication tag.  NOT secure!
+    struct ToyCipher {
+        key: u64,
+    }
+
+    impl ToyCipher {
+        fn keystream(&self, sect_no: u32, len: usize) -> impl Iterator<Item = u8> {
+            let mut state = self.key ^ ((sect_no as u64 + 1) << 32);
+            (0..len).map(move |_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+        }
+    }
+
+    impl SectionTransform for ToyCipher {
+        fn id(&self) -> u8 { 1 }
+
+        fn seal(&self, sect_no: u32, plain: &[u8]) -> Result<Vec<u8>, CodingError> {
+            let checksum = plain.iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+            let mut sealed: Vec<u8> = plain.iter().zip(self.keystream(sect_no, plain.len()))
+                                           .map(|(p, k)| p ^ k).collect();
+            sealed.push(checksum ^ self.key as u8);
+            Ok(sealed)
+        }
+
+        fn open(&self, sect_no: u32, sealed: &[u8]) -> Result<Vec<u8>, CodingError> {
+            let (body, tag) = sealed.split_at(sealed.len() - 1);
+            let plain: Vec<u8> = body.iter().zip(self.keystream(sect_no, body.len()))
+                                     .map(|(s, k)| s ^ k).collect();
+            let checksum = plain.iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+            if checksum ^ self.key as u8 != tag[0] {
+                return Err(CodingError::InvalidFormat("Section failed authentication".into()));
+            }
+            Ok(plain)
+        }
+    }
+
+    #[test]
+    fn test_seal_and_open_roundtrip() {
+        let mut appender = VectorU64Appender::try_new(2048).unwrap();
+        (0..300u64).for_each(|i| appender.append(i * 1000).unwrap());
+        appender.append_nulls(500).unwrap();
+        (0..100u64).for_each(|_| appender.append(42).unwrap());
+        let plain = appender.finish(900).unwrap();
+
+        let cipher = ToyCipher { key: 0x1234_5678_9abc_def0 };
+        let sealed = seal_vector::<u64>(&plain[..], &cipher).unwrap();
+        assert_eq!(sealed[TRANSFORM_ID_OFFSET], 1);
+        assert_eq!(sealed.len(), plain.len() + 4 * 4);   // 4 sections, each with 3 header bytes and a tag
+        match VectorReader::<u64>::try_new(&sealed[..]) {
+            Err(CodingError::InvalidFormat(_)) => {},
+            _ => panic!("Should not be able to read sealed vector"),
+        }
+
+        let opened = open_vector::<u64>(&sealed[..], &cipher).unwrap();
+        assert_eq!(opened, plain);
+        let reader = VectorReader::<u64>::try_new(&opened[..]).unwrap();
+        assert_eq!(reader.iterate().nth(299), Some(299_000));
+
+        // The footer is carried over as is
+        appender.enable_bloom_filter(10);
+        let plain = appender.encode_all((0..300u64).map(|i| i * 1000)).unwrap();
+        let sealed = seal_vector::<u64>(&plain[..], &cipher).unwrap();
+        assert_eq!(&sealed[sealed.len() - 20..], &plain[plain.len() - 20..]);
+        let opened = open_vector::<u64>(&sealed[..], &cipher).unwrap();
+        assert_eq!(opened, plain);
+    }
+
+    #[test]
+    fn test_open_with_wrong_key_fails() {
+        let mut appender = VectorF32XorAppender::try_new(2048).unwrap();
+        let plain = appender.encode_all((0..300).map(|i| i as f32 * 0.5)).unwrap();
+        let sealed = seal_vector::<f32>(&plain[..], &ToyCipher { key: 1 }).unwrap();
+
+        let res = open_vector::<f32>(&sealed[..], &ToyCipher { key: 2 });
+        assert_eq!(res, Err(CodingError::InvalidFormat("Section failed authentication".into())));
+
+        // Opening an unsealed vector fails because the transform ids don't match
+        assert!(open_vector::<f32>(&plain[..], &ToyCipher { key: 1 }).is_err());
+    }
+}
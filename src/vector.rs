@@ -24,16 +24,32 @@
 /// Calling `finish()` clones the vector bytes to the smallest representation possible, after which the
 /// appender is reset for creation of another new vector.  The finished vector is then immutable and the
 /// caller can read it.
-use std::collections::HashMap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::convert::TryFrom;
+use std::io::Write;
+use std::iter::{FromIterator, FusedIterator};
 use std::marker::PhantomData;
 use std::mem;
+use std::ops::Range;
+use std::sync::Arc;
 
+use num::NumCast;
+use packed_simd::u32x8;
 use scroll::{ctx, Endian, Pread, Pwrite, LE};
 
+use crate::bloom::{BloomBuilder, BloomFilter, SectionBloomBuilder, SectionBloomFilters};
 use crate::error::CodingError;
-use crate::filter::{SectFilterSink, VectorFilter};
+use crate::filter::{EqualsSink, NullFilter, SectFilterSink, VectorFilter};
+use crate::footer::{Footer, FooterTag, FooterWriter, FLAGS_OFFSET, FLAG_FOOTER};
+use crate::hll;
+use crate::memory::MemoryTracker;
+use crate::minmax::{self, MinMaxInput};
+use crate::reference::{self, RefBits};
 use crate::section::*;
+use crate::selection::first_n_mask;
 use crate::sink::*;
+use crate::split;
 
 /// BinaryVector: a compressed vector storing data of the same type
 ///   enabling high speed operations on compressed data without
@@ -53,7 +69,7 @@ use crate::sink::*;
 pub struct BinaryVector {
     num_bytes: u32,         // Number of bytes in vector following this length
     major_type: VectorType, // These should probably be enums no?
-    minor_type: VectorSubType,
+    pub(crate) minor_type: VectorSubType,
     _padding: u16,
 }
 
@@ -94,12 +110,43 @@ pub enum VectorSubType {
     FixedU64  = 0x10,  // FixedSection256 with u64 elements
     FixedU32  = 0x11,  // FixedSection256 with u32 elements
     FixedF32  = 0x12,  // FixedSection256 with f32 elements
+    FixedBin16 = 0x13, // FixedSection256 with 16-byte fixed binary elements, see fixedbin module
+    FixedF64  = 0x14,  // FixedSection256 with f64 elements
+    FixedKeyValue = 0x15,  // FixedSection256 with rows of sparse u64 key/value pairs, see kvpairs module
+    FixedF16  = 0x16,  // FixedSection256 with IEEE half floats stored as u32 bit patterns, see half module
+    FixedBF16 = 0x17,  // FixedSection256 with bfloat16 values stored as u32 bit patterns, see half module
+    FixedStrDict = 0x18,  // FixedSection256 with u32 codes into a string dictionary footer, see strdict module
+    FixedBool = 0x19,  // FixedSection256 with booleans as bitmaps, see boolvec module
+    FixedU16  = 0x1a,  // FixedSection256 with u16 elements
+    FixedU8   = 0x1b,  // FixedSection256 with u8 elements
 }
 
 impl VectorSubType {
     pub fn as_num(&self) -> u8 { *self as u8 }
 }
 
+impl TryFrom<u8> for VectorSubType {
+    type Error = CodingError;
+    fn try_from(n: u8) -> Result<VectorSubType, CodingError> {
+        use VectorSubType::*;
+        [Primitive, STRING, UTF8, FIXEDMAXUTF8, DATETIME, PrimitiveNoMask, REPEATED, INT, IntNoMask,
+         FixedU64, FixedU32, FixedF32, FixedBin16, FixedF64, FixedKeyValue, FixedF16, FixedBF16,
+         FixedStrDict, FixedBool, FixedU16, FixedU8].iter()
+            .find(|subtype| subtype.as_num() == n)
+            .cloned()
+            .ok_or(CodingError::WrongVectorType(n))
+    }
+}
+
+/// The error for a reader expecting elements of subtype expected finding subtype found in a vector header:
+/// WrongElementType if found is a known subtype, otherwise WrongVectorType.
+pub(crate) fn wrong_subtype_error(found: u8, expected: VectorSubType) -> CodingError {
+    match VectorSubType::try_from(found) {
+        Ok(found) => CodingError::WrongElementType { expected, found },
+        Err(e)    => e,
+    }
+}
+
 impl ctx::TryIntoCtx<Endian> for &VectorSubType {
     type Error = scroll::Error;
     fn try_into_ctx(self, buf: &mut [u8], ctx: Endian) -> Result<usize, Self::Error> {
@@ -107,8 +154,30 @@ impl ctx::TryIntoCtx<Endian> for &VectorSubType {
     }
 }
 
-const NUM_HEADER_BYTES_TOTAL: usize = 16;
-const BINARYVECT_HEADER_SIZE: usize = std::mem::size_of::<BinaryVector>();
+pub(crate) const NUM_HEADER_BYTES_TOTAL: usize = 16;
+/// Offset of the header byte recording the id of the SectionTransform applied to sections, 0 if none
+pub(crate) const TRANSFORM_ID_OFFSET: usize = 6;
+pub(crate) const BINARYVECT_HEADER_SIZE: usize = std::mem::size_of::<BinaryVector>();
+/// Offset of the header byte holding FORMAT_MAGIC, which is followed by the format version byte
+pub(crate) const FORMAT_MAGIC_OFFSET: usize = 14;
+/// Header byte at offset 14 of every vector written by this crate
+pub const FORMAT_MAGIC: u8 = 0xc7;
+/// Version of the vector format written by this crate, at header offset 15.  Readers refuse newer versions.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Returns the format version of a vector from its header.  Vectors written before headers had a magic byte
+/// and version have zeroes there instead, and are version 0.  Returns InvalidFormat if the magic byte is wrong,
+/// and UnsupportedVersion for versions newer than FORMAT_VERSION.
+pub fn format_version(vect_bytes: &[u8]) -> Result<u8, CodingError> {
+    let magic: u8 = vect_bytes.pread_with(FORMAT_MAGIC_OFFSET, LE)?;
+    let version: u8 = vect_bytes.pread_with(FORMAT_MAGIC_OFFSET + 1, LE)?;
+    match (magic, version) {
+        (0, 0) => Ok(0),
+        (FORMAT_MAGIC, v) if v <= FORMAT_VERSION => Ok(v),
+        (FORMAT_MAGIC, v) => Err(CodingError::UnsupportedVersion(v)),
+        _ => Err(CodingError::InvalidFormat(format!("Bad vector magic byte {:#x}", magic))),
+    }
+}
 
 impl BinaryVector {
     pub fn new(major_type: VectorType, minor_type: VectorSubType) -> Self {
@@ -124,9 +193,12 @@ impl BinaryVector {
         self.num_bytes = NUM_HEADER_BYTES_TOTAL as u32 - 4;
     }
 
-    /// Writes the entire BinaryVector header into the beginning of the given buffer
+    /// Writes the entire BinaryVector header into the beginning of the given buffer, plus the magic byte and
+    /// format version at the end of the 16 header bytes
     pub fn write_header(&self, buf: &mut [u8]) -> Result<(), CodingError> {
         buf.pwrite_with(self, 0, LE)?;
+        buf.pwrite_with(FORMAT_MAGIC, FORMAT_MAGIC_OFFSET, LE)?;
+        buf.pwrite_with(FORMAT_VERSION, FORMAT_MAGIC_OFFSET + 1, LE)?;
         Ok(())
     }
 
@@ -156,14 +228,26 @@ impl BaseSubtypeMapping for u32 {
     fn vect_subtype() -> VectorSubType { VectorSubType::FixedU32 }
 }
 
+impl BaseSubtypeMapping for u16 {
+    fn vect_subtype() -> VectorSubType { VectorSubType::FixedU16 }
+}
+
+impl BaseSubtypeMapping for u8 {
+    fn vect_subtype() -> VectorSubType { VectorSubType::FixedU8 }
+}
+
 impl BaseSubtypeMapping for f32 {
     fn vect_subtype() -> VectorSubType { VectorSubType::FixedF32 }
 }
 
+impl BaseSubtypeMapping for f64 {
+    fn vect_subtype() -> VectorSubType { VectorSubType::FixedF64 }
+}
+
 #[derive(Debug, Copy, Clone, Pread, Pwrite)]
 pub struct FixedSectStats {
     pub num_elements: u32,
-    num_null_sections: u16,
+    pub(crate) num_null_sections: u16,
 }
 
 impl FixedSectStats {
@@ -187,6 +271,21 @@ impl FixedSectStats {
 
 const GROW_BYTES: usize = 4096;
 
+/// Limits on the size of a vector, so that a runaway input fails early instead of producing a vector too big
+/// for readers and caches.  See `VectorAppender::set_limits()`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct AppenderLimits {
+    pub max_bytes: Option<usize>,      // Total bytes of the vector, including the header and footer
+    pub max_sections: Option<usize>,
+}
+
+impl AppenderLimits {
+    /// No limits
+    pub fn new() -> Self {
+        Self { max_bytes: None, max_sections: None }
+    }
+}
+
 /// A builder for a BinaryVector holding encoded/compressed integral/floating values
 /// as 256-element FixedSections.   Buffers elements to be written and writes
 /// them in 256-element sections at a time.  This builder owns its own write buffer memory, expanding it
@@ -203,6 +302,10 @@ const GROW_BYTES: usize = 4096;
 ///      let mut appender = VectorF32XorAppender::try_new(2048).unwrap();
 ///      let bytes = appender.encode_all(my_vec).unwrap();
 /// ```
+///
+/// Encoding is deterministic: the same values appended with the same section writer and `dedup_sections()`
+/// setting always produce identical bytes, across runs and platforms.  See "Deterministic Encoding" in
+/// vector_format.md for details.
 pub struct VectorAppender<T, W>
 where T: VectBase + Clone + PartialOrd,
       W: FixedSectionWriter<T> {
@@ -211,6 +314,16 @@ where T: VectBase + Clone + PartialOrd,
     header: BinaryVector,
     write_buf: Vec<T>,
     stats: FixedSectStats,
+    dedup_sections: bool,
+    last_sect: Option<(usize, usize)>,  // Offset and length of last non-null section written, for dedup
+    tracker: Option<Arc<dyn MemoryTracker>>,
+    tracked_bytes: usize,           // Scratch bytes reported to the tracker
+    bloom: Option<BloomBuilder<T>>,
+    sect_blooms: Option<SectionBloomBuilder<T>>,
+    limits: AppenderLimits,
+    track_nulls: bool,
+    validity: [u8; VALIDITY_BITMAP_BYTES],  // Validity bits of write_buf, when tracking nulls
+    flushed: usize,                 // Offset up to which sections were written out by flush_sealed()
     sect_writer: PhantomData<W>     // Uses no space, this tells rustc we need W
 }
 
@@ -226,6 +339,16 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
             header: BinaryVector::new(VectorType::FixedSection256, T::vect_subtype()),
             write_buf: Vec::with_capacity(FIXED_LEN),
             stats: FixedSectStats::new(),
+            dedup_sections: false,
+            last_sect: None,
+            tracker: None,
+            tracked_bytes: 0,
+            bloom: None,
+            sect_blooms: None,
+            limits: AppenderLimits::new(),
+            track_nulls: false,
+            validity: [0; VALIDITY_BITMAP_BYTES],
+            flushed: NUM_HEADER_BYTES_TOTAL,
             sect_writer: PhantomData
         };
         new_self.write_header()?;
@@ -253,13 +376,67 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
     pub fn reset(&mut self) -> Result<(), CodingError> {
         self.header.reset();
         self.offset = NUM_HEADER_BYTES_TOTAL;
+        self.last_sect = None;
+        self.flushed = NUM_HEADER_BYTES_TOTAL;
         self.write_buf.clear();
+        self.validity = [0; VALIDITY_BITMAP_BYTES];
         self.vect_buf.resize(self.vect_buf.capacity(), 0);  // Make sure entire vec is usable
         self.stats.reset();
         self.stats.update_num_elems(&mut self.vect_buf, 0)?;
+        if let Some(bloom) = &mut self.bloom { bloom.clear(); }
+        if let Some(sect_blooms) = &mut self.sect_blooms { sect_blooms.clear(); }
         self.write_header()
     }
 
+    /// Enables or disables section deduplication.  When enabled, a section which is byte-for-byte identical to
+    /// the previous section is replaced by a one-byte RepeatPrevious section.  The FixedSectIterator resolves
+    /// these transparently, but readers from before RepeatPrevious sections existed cannot read such vectors,
+    /// so this is disabled by default.
+    pub fn dedup_sections(&mut self, enabled: bool) {
+        self.dedup_sections = enabled;
+    }
+
+    /// Enables or disables tracking of nulls.  When enabled, nulls are no longer zeroes: sections with any nulls
+    /// are written as PartialNull sections, whose validity bitmap is read with `VectorReader::validity_masks()`
+    /// or `iterate_options()`, and nulls are not added to the Bloom filter.  Readers from before PartialNull
+    /// sections existed cannot read such vectors, so this is disabled by default.  Only change this between
+    /// vectors.
+    pub fn track_nulls(&mut self, enabled: bool) {
+        self.track_nulls = enabled;
+    }
+
+    /// Returns true if nulls are tracked, see `track_nulls()`
+    pub fn tracks_nulls(&self) -> bool {
+        self.track_nulls
+    }
+
+    /// Sets limits on the size of the vectors written by this appender.  Appending or finishing fails with
+    /// `TooManySections` or `VectorTooLarge` as soon as a vector goes over a limit, after which the appender
+    /// must be reset.
+    pub fn set_limits(&mut self, limits: AppenderLimits) {
+        self.limits = limits;
+    }
+
+    /// Sets a MemoryTracker which is told about the scratch buffers of this appender, including the ones already
+    /// allocated.  Growing the buffers fails with the tracker's error if the tracker refuses the allocation.
+    pub fn set_memory_tracker(&mut self, tracker: Arc<dyn MemoryTracker>) -> Result<(), CodingError> {
+        let scratch_bytes = self.scratch_bytes();
+        tracker.allocate(scratch_bytes)?;
+        self.release_tracked();
+        self.tracker = Some(tracker);
+        self.tracked_bytes = scratch_bytes;
+        Ok(())
+    }
+
+    fn scratch_bytes(&self) -> usize {
+        self.vect_buf.capacity() + self.write_buf.capacity() * mem::size_of::<T>()
+    }
+
+    fn release_tracked(&mut self) {
+        if let Some(tracker) = &self.tracker { tracker.release(self.tracked_bytes); }
+        self.tracked_bytes = 0;
+    }
+
     /// Writes out the header for the vector.  Done automatically during try_new() / reset().
     fn write_header(&mut self) -> Result<(), CodingError> {
         self.header.write_header(self.vect_buf.as_mut_slice())
@@ -268,13 +445,53 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
     /// Encodes all the values in write_buf.  Adjust the number of elements and other vector state.
     fn encode_section(&mut self) -> Result<(), CodingError> {
         assert!(self.write_buf.len() == FIXED_LEN);
-        self.offset = self.retry_grow(|s| W::gen_stats_and_write(s.vect_buf.as_mut_slice(),
+        let sect_start = self.offset;
+        self.offset = if self.track_nulls && self.validity != [0xff; VALIDITY_BITMAP_BYTES] {
+            self.retry_grow(|s| PartialNullFixedSect::write::<W>(s.vect_buf.as_mut_slice(),
                                                                  s.offset,
-                                                                 &s.write_buf[..]))?;
+                                                                 &s.write_buf[..],
+                                                                 &s.validity))?
+        } else {
+            self.retry_grow(|s| W::gen_stats_and_write(s.vect_buf.as_mut_slice(),
+                                                       s.offset,
+                                                       &s.write_buf[..]))?
+        };
+        if self.dedup_sections { self.dedup_last_section(sect_start); }
+        if let Some(sect_blooms) = &mut self.sect_blooms { sect_blooms.add_section(&self.write_buf[..]); }
         self.write_buf.clear();
+        self.validity = [0; VALIDITY_BITMAP_BYTES];
+        self.section_written()
+    }
+
+    /// Updates the number of elements and bytes in the header after a section is written, and checks the limits
+    fn section_written(&mut self) -> Result<(), CodingError> {
         self.stats.update_num_elems(&mut self.vect_buf, self.stats.num_elements + FIXED_LEN as u32)?;
         self.header.update_num_bytes(self.vect_buf.as_mut_slice(),
-                                     (self.offset - NUM_HEADER_BYTES_TOTAL) as u32)
+                                     (self.offset - NUM_HEADER_BYTES_TOTAL) as u32)?;
+        self.check_limits()
+    }
+
+    fn check_limits(&self) -> Result<(), CodingError> {
+        let num_sections = self.stats.num_elements as usize / FIXED_LEN;
+        match (self.limits.max_sections, self.limits.max_bytes) {
+            (Some(max), _) if num_sections > max  => Err(CodingError::TooManySections(num_sections)),
+            (_, Some(max)) if self.offset > max   => Err(CodingError::VectorTooLarge(self.offset)),
+            _ => Ok(()),
+        }
+    }
+
+    /// If the section just written starting at sect_start is identical to the previous one, replaces it with
+    /// a RepeatPrevious section.  Null sections are not worth deduplicating as they are already one byte.
+    fn dedup_last_section(&mut self, sect_start: usize) {
+        let sect_len = self.offset - sect_start;
+        match self.last_sect {
+            Some((last_start, last_len)) if last_len == sect_len && sect_len > 1 &&
+                self.vect_buf[last_start..last_start + last_len] == self.vect_buf[sect_start..self.offset] => {
+                self.vect_buf[sect_start] = SectionType::RepeatPrevious.as_num();
+                self.offset = sect_start + 1;
+            }
+            _ => self.last_sect = Some((sect_start, sect_len)),
+        }
     }
 
     /// Retries a func which might return Result<..., CodingError> by growing the vect_buf.
@@ -284,9 +501,7 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
         func(self).or_else(|err| {
             match err {
                 CodingError::NotEnoughSpace | CodingError::BadOffset(_) => {
-                    // Expand vect_buf
-                    self.vect_buf.reserve(GROW_BYTES);
-                    self.vect_buf.resize(self.vect_buf.capacity(), 0);
+                    self.grow()?;
                     func(self)
                 }
                 _ => Err(err),
@@ -294,9 +509,29 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
         })
     }
 
+    /// Expands vect_buf, reporting the added capacity to the memory tracker.  If the tracker refuses,
+    /// the added capacity is given back before returning the error.
+    fn grow(&mut self) -> Result<(), CodingError> {
+        let old_capacity = self.vect_buf.capacity();
+        self.vect_buf.reserve(GROW_BYTES);
+        if let Some(tracker) = &self.tracker {
+            let grow_bytes = self.vect_buf.capacity() - old_capacity;
+            if let Err(err) = tracker.allocate(grow_bytes) {
+                self.vect_buf.shrink_to_fit();
+                return Err(err);
+            }
+            self.tracked_bytes += grow_bytes;
+        }
+        self.vect_buf.resize(self.vect_buf.capacity(), 0);
+        Ok(())
+    }
+
     /// Appends a single value to this vector.  When a section fills up, will encode all values in write buffer
     /// into the vector.
     pub fn append(&mut self, value: T) -> Result<(), CodingError> {
+        if let Some(bloom) = &mut self.bloom { bloom.insert(value); }
+        let pos = self.write_buf.len();
+        self.validity[pos / 8] |= 1 << (pos % 8);
         self.write_buf.push(value);
         if self.write_buf.len() >= FIXED_LEN {
             self.encode_section()
@@ -306,12 +541,57 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
     }
 
     /// Appends a number of nulls at once to the vector.  Super useful and fast for sparse data.
-    /// Nulls are equivalent to zero value for type T.
+    /// Nulls are equivalent to zero value for type T, unless nulls are tracked with `track_nulls()`.
     pub fn append_nulls(&mut self, num_nulls: usize) -> Result<(), CodingError> {
+        if num_nulls > 0 && !self.track_nulls {
+            if let Some(bloom) = &mut self.bloom { bloom.insert(T::zero()); }
+        }
+        self.fill_nulls(num_nulls)
+    }
+
+    /// Appends a number of zero values, which unlike nulls are values even when nulls are tracked.  Without
+    /// null tracking this is as fast as `append_nulls()`.
+    pub(crate) fn append_zeroes(&mut self, num_zeroes: usize) -> Result<(), CodingError> {
+        if self.track_nulls {
+            (0..num_zeroes).try_for_each(|_| self.append(T::zero()))
+        } else {
+            self.append_nulls(num_zeroes)
+        }
+    }
+
+    /// Appends values which may be null, eg a nullable column.  Each run of Nones is appended with a single
+    /// `append_nulls()`, so runs covering whole sections become Null sections.  With `track_nulls()` enabled
+    /// nulls are written as PartialNull sections instead, and read back as None from `iterate_options()`.
+    pub fn append_options<I>(&mut self, values: I) -> Result<(), CodingError>
+    where I: IntoIterator<Item = Option<T>> {
+        let mut num_nulls = 0;
+        for value in values {
+            match value {
+                Some(v) => {
+                    self.append_nulls(num_nulls)?;
+                    num_nulls = 0;
+                    self.append(v)?;
+                }
+                None => num_nulls += 1,
+            }
+        }
+        self.append_nulls(num_nulls)
+    }
+
+    /// Like encode_all(), for values which may be null.  See append_options().
+    pub fn encode_all_options<C>(&mut self, collection: C) -> Result<Vec<u8>, CodingError>
+    where C: IntoIterator<Item = Option<T>> {
+        let mut count = 0;
+        self.append_options(collection.into_iter().inspect(|_| count += 1))?;
+        self.finish(count)
+    }
+
+    // Appends nulls without adding them to the Bloom filter, eg for padding
+    fn fill_nulls(&mut self, num_nulls: usize) -> Result<(), CodingError> {
         let mut left = num_nulls;
         while left > 0 {
-            // If current write_buf is not empty, fill it up with zeroes and flush (maybe)
-            if self.write_buf.len() > 0 {
+            // If current write_buf is not empty, or nulls are tracked, fill it up with zeroes and flush (maybe)
+            if self.write_buf.len() > 0 || self.track_nulls {
                 let num_to_fill = left.min(FIXED_LEN - self.write_buf.len());
                 self.write_buf.resize(self.write_buf.len() + num_to_fill as usize, T::zero());
                 left -= num_to_fill;
@@ -319,10 +599,10 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
             // If empty, and we have at least FIXED_LEN nulls to go, insert a null section.
             } else if left >= FIXED_LEN {
                 self.offset = self.retry_grow(|s| NullFixedSect::write(s.vect_buf.as_mut_slice(), s.offset))?;
+                self.last_sect = None;
                 self.stats.num_null_sections += 1;
-                self.stats.update_num_elems(&mut self.vect_buf, self.stats.num_elements + FIXED_LEN as u32)?;
-                self.header.update_num_bytes(self.vect_buf.as_mut_slice(),
-                                             (self.offset - NUM_HEADER_BYTES_TOTAL) as u32)?;
+                if let Some(sect_blooms) = &mut self.sect_blooms { sect_blooms.add_unfiltered(); }
+                self.section_written()?;
                 left -= FIXED_LEN;
             // If empty, and less than fixed_len nulls, insert nulls into write_buf
             } else {
@@ -333,6 +613,56 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
         Ok(())
     }
 
+    /// Appends the elements in the given range of another vector of the same type, eg for compacting vectors
+    /// or splitting them into chunks.  Whole sections within the range are copied byte for byte without
+    /// decoding, as long as this appender is at a section boundary; partial sections at either end of the
+    /// range are decoded and appended value by value.  With Bloom filters enabled every section is decoded,
+    /// as the filters need the values.
+    pub fn append_from(&mut self, reader: &VectorReader<T>, range: Range<usize>) -> Result<(), CodingError> {
+        if range.start > range.end || range.end > reader.num_elements() {
+            return Err(CodingError::BadOffset(range.end));
+        }
+        let mut sink = Section256Sink::<T>::new();
+        for (n, sect) in reader.sect_iter().enumerate().skip(range.start / FIXED_LEN) {
+            let sect_start = n * FIXED_LEN;
+            if sect_start >= range.end { break; }
+            let sect = sect?;
+            let start = range.start.max(sect_start) - sect_start;
+            let end = range.end.min(sect_start + FIXED_LEN) - sect_start;
+            if start == 0 && end == FIXED_LEN && self.write_buf.is_empty() && self.bloom.is_none() &&
+               self.sect_blooms.is_none() {
+                self.copy_section(&sect)?;
+            } else {
+                sink.reset();
+                sect.decode(&mut sink)?;
+                let valid = sect.validity_mask();
+                for i in start..end {
+                    if self.track_nulls && valid.extract(i / 32) & (1 << (i % 32)) == 0 {
+                        self.append_nulls(1)?;
+                    } else {
+                        self.append(sink.values[i])?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Copies an encoded section as is.  The write buffer must be empty.
+    fn copy_section(&mut self, sect: &FixedSectEnum<T>) -> Result<(), CodingError> {
+        let sect_bytes = match sect.sect_bytes() {
+            Some(bytes) => &bytes[..sect.num_bytes()],
+            None        => return self.append_zeroes(FIXED_LEN),
+        };
+        let sect_start = self.offset;
+        let end = sect_start + sect_bytes.len();
+        while self.vect_buf.len() < end { self.grow()?; }
+        self.vect_buf[sect_start..end].copy_from_slice(sect_bytes);
+        self.offset = end;
+        if self.dedup_sections { self.dedup_last_section(sect_start); }
+        self.section_written()
+    }
+
     /// Call this method to wrap up a vector and any unfinished sections, and clone out resulting vector.
     /// We have no more values, and need to fill up the appender with nulls/0's until it is the right length.
     /// This is because most query engines expect all vectors to be of the same number of elements.
@@ -341,26 +671,48 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
     /// an entire section is written.
     /// NOTE: TooFewRows is returned if total_num_rows is below the total number of elements written so far.
     pub fn finish(&mut self, total_num_rows: usize) -> Result<Vec<u8>, CodingError> {
+        self.finish_with_footer(total_num_rows, FooterWriter::new())
+    }
+
+    /// Like finish(), but also writes the given footer entries, eg the dictionary of a string vector
+    pub(crate) fn finish_with_footer(&mut self,
+                                     total_num_rows: usize,
+                                     mut footer: FooterWriter) -> Result<Vec<u8>, CodingError> {
         let total_so_far = self.stats.num_elements as usize + self.write_buf.len();
         if total_so_far > total_num_rows { return Err(CodingError::InvalidNumRows(total_num_rows, total_so_far)); }
         if total_num_rows > u32::max_value() as usize {
             return Err(CodingError::InvalidNumRows(total_num_rows, u32::max_value() as usize));
         }
 
+        // Nulls up to total_num_rows are values, the rest is padding
+        if total_num_rows > total_so_far && !self.track_nulls {
+            if let Some(bloom) = &mut self.bloom { bloom.insert(T::zero()); }
+        }
+
         // Round out the section if needed
         if self.write_buf.len() > 0 {
             let number_to_fill = FIXED_LEN - self.write_buf.len();
-            self.append_nulls(number_to_fill)?;
+            self.fill_nulls(number_to_fill)?;
         }
 
         while self.stats.num_elements < total_num_rows as u32 {
-            self.append_nulls(256)?;
+            self.fill_nulls(256)?;
         }
 
         // Re-write the number of elements to reflect total_num_rows
         self.stats.update_num_elems(self.vect_buf.as_mut_slice(), total_num_rows as u32)?;
         self.vect_buf.as_mut_slice().pwrite_with(&self.stats, BINARYVECT_HEADER_SIZE, LE)?;
 
+        if let Some(bloom) = &mut self.bloom {
+            footer.add(FooterTag::BloomFilter, &bloom.finish()[..]);
+        }
+        if let Some(sect_blooms) = &mut self.sect_blooms {
+            footer.add(FooterTag::SectionBloomFilters, &sect_blooms.finish()[..]);
+        }
+        if !footer.is_empty() {
+            self.write_footer(footer)?;
+        }
+
         self.vect_buf.resize(self.offset, 0);
         let mut returned_vec = Vec::with_capacity(self.offset);
         returned_vec.append(&mut self.vect_buf);
@@ -368,6 +720,18 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
         Ok(returned_vec)
     }
 
+    /// Writes the footer after the last section, and sets the footer flag in the header
+    fn write_footer(&mut self, footer: FooterWriter) -> Result<(), CodingError> {
+        let footer_bytes = footer.finish();
+        let end = self.offset + footer_bytes.len();
+        while self.vect_buf.len() < end { self.grow()?; }
+        self.vect_buf[self.offset..end].copy_from_slice(&footer_bytes[..]);
+        self.offset = end;
+        self.vect_buf[FLAGS_OFFSET] |= FLAG_FOOTER;
+        self.header.update_num_bytes(self.vect_buf.as_mut_slice(), (self.offset - NUM_HEADER_BYTES_TOTAL) as u32)?;
+        self.check_limits()
+    }
+
     /// Obtains a reader for reading from the bytes of this appender.
     /// NOTE: reader will only read what has been written so far, and due to Rust borrowing rules, one should
     /// not attempt to read and append at the same time; the returned reader is not safe across threads.
@@ -377,15 +741,311 @@ where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
     }
 }
 
+/// The state of a VectorAppender after `flush_sealed()`, from which `VectorAppender::resume()` rebuilds the
+/// appender given the flushed bytes, eg after a crash.  Store it with `to_bytes()` next to the flushed bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Pread, Pwrite)]
+pub struct ResumeToken {
+    num_elements: u32,        // Elements in the flushed sections
+    flushed_bytes: u32,       // Bytes of flushed sections
+    num_null_sections: u16,
+    last_sect_offset: u32,    // Offset of the last non-null section from the first section, for dedup
+    last_sect_len: u32,       // 0 if there is no such section
+}
+
+impl ResumeToken {
+    pub const NUM_BYTES: usize = 18;
+
+    /// Number of elements in the flushed sections.  Elements after these must be appended again after resuming.
+    pub fn num_elements(&self) -> usize { self.num_elements as usize }
+
+    /// Number of bytes written out by all flush_sealed() calls so far
+    pub fn flushed_bytes(&self) -> usize { self.flushed_bytes as usize }
+
+    pub fn to_bytes(&self) -> [u8; ResumeToken::NUM_BYTES] {
+        let mut bytes = [0u8; ResumeToken::NUM_BYTES];
+        bytes.pwrite_with(*self, 0, LE).expect("ResumeToken::NUM_BYTES is too small");
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CodingError> {
+        Ok(bytes.pread_with(0, LE)?)
+    }
+}
+
+impl<T, W> VectorAppender<T, W>
+where T: VectBase + Clone + PartialOrd + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    /// Writes the sections sealed since the last flush to out, without the header, and returns the token from
+    /// which `resume()` can rebuild this appender.  Values not yet in a sealed section are not written, so an
+    /// ingestion WAL should replay from `token.num_elements()`.  Bloom filters are only kept in memory, so
+    /// flushing fails when one is enabled.
+    pub fn flush_sealed<O: Write>(&mut self, out: &mut O) -> Result<ResumeToken, CodingError> {
+        if self.bloom.is_some() || self.sect_blooms.is_some() {
+            return Err(CodingError::InvalidFormat("Cannot flush sections of an appender with a Bloom filter".into()));
+        }
+        out.write_all(&self.vect_buf[self.flushed..self.offset])?;
+        self.flushed = self.offset;
+        let (last_sect_offset, last_sect_len) = match self.last_sect {
+            Some((offset, len)) => ((offset - NUM_HEADER_BYTES_TOTAL) as u32, len as u32),
+            None                => (0, 0),
+        };
+        Ok(ResumeToken {
+            num_elements: self.stats.num_elements,
+            flushed_bytes: (self.offset - NUM_HEADER_BYTES_TOTAL) as u32,
+            num_null_sections: self.stats.num_null_sections,
+            last_sect_offset,
+            last_sect_len,
+        })
+    }
+
+    /// Rebuilds an appender from the bytes written by flush_sealed() calls, up to and including the call which
+    /// returned the token.  Extra bytes after those are ignored.  Settings such as `dedup_sections()` must be set
+    /// again.
+    pub fn resume(initial_capacity: usize, token: &ResumeToken, flushed: &[u8]) -> Result<Self, CodingError> {
+        let num_bytes = token.flushed_bytes();
+        if flushed.len() < num_bytes { return Err(CodingError::InputTooShort) }
+        let flushed = &flushed[..num_bytes];
+        let num_sections = FixedSectIterator::<T>::new(flushed).try_fold(0, |n, sect| sect.map(|_| n + 1))?;
+        if num_sections * FIXED_LEN != token.num_elements() {
+            return Err(CodingError::InvalidFormat(format!("{} flushed sections for {} elements in token",
+                                                          num_sections, token.num_elements)));
+        }
+
+        let mut appender = Self::try_new(initial_capacity.max(NUM_HEADER_BYTES_TOTAL + num_bytes))?;
+        appender.vect_buf[NUM_HEADER_BYTES_TOTAL..NUM_HEADER_BYTES_TOTAL + num_bytes].copy_from_slice(flushed);
+        appender.offset = NUM_HEADER_BYTES_TOTAL + num_bytes;
+        appender.flushed = appender.offset;
+        appender.stats.num_null_sections = token.num_null_sections;
+        appender.stats.update_num_elems(&mut appender.vect_buf, token.num_elements)?;
+        appender.header.update_num_bytes(appender.vect_buf.as_mut_slice(), num_bytes as u32)?;
+        if token.last_sect_len > 0 {
+            appender.last_sect = Some((token.last_sect_offset as usize + NUM_HEADER_BYTES_TOTAL,
+                                       token.last_sect_len as usize));
+        }
+        Ok(appender)
+    }
+
+    /// Reopens a finished vector for appending more values, eg to grow a vector across flushes.  The values of
+    /// a partial last section are decoded back into the write buffer, so appending continues right after the
+    /// last element.  Nulls are tracked again if the vector has PartialNull sections; other settings such as
+    /// `dedup_sections()` must be set again.  Vectors with a footer cannot be reopened, since Bloom filters and
+    /// dictionaries are not rebuilt.
+    pub fn from_encoded(vect_bytes: &[u8]) -> Result<Self, CodingError> {
+        let reader = VectorReader::<T>::try_new(vect_bytes)?;
+        if reader.footer().is_some() {
+            return Err(CodingError::InvalidFormat("Cannot append to a vector with a footer".into()));
+        }
+        let mut appender = Self::try_new(vect_bytes.len() + GROW_BYTES)?;
+        appender.track_nulls = reader.sect_iter().any(|sect| {
+            matches!(sect, Ok(FixedSectEnum::PartialNullFixedSect(_)))
+        });
+        appender.load_sections(&reader, reader.num_elements())?;
+        Ok(appender)
+    }
+
+    /// Drops the elements after the first len, eg to roll back a partially failed ingest.  Sections after the
+    /// cut are dropped, and a section the cut lands in is encoded again with only the values before the cut.
+    /// Returns InvalidNumRows if len is over the number of elements, and InvalidFormat if the cut lands in
+    /// sections already written out by `flush_sealed()`.  A Bloom filter keeps the dropped values, which only
+    /// adds false positives.
+    pub fn truncate(&mut self, len: usize) -> Result<(), CodingError> {
+        let sealed = self.stats.num_elements as usize;
+        if len > self.num_elements() { return Err(CodingError::InvalidNumRows(len, self.num_elements())) }
+        if len >= sealed {
+            self.write_buf.truncate(len - sealed);
+            for pos in len - sealed..FIXED_LEN { self.validity[pos / 8] &= !(1 << (pos % 8)); }
+            return Ok(())
+        }
+        let vect_bytes = self.vect_buf[..self.offset].to_vec();
+        let reader = VectorReader::<T>::try_new(&vect_bytes[..])?;
+        self.load_sections(&reader, len)
+    }
+
+    // Replaces the sections of this appender with the first num_elements elements of the reader's vector.
+    // Whole sections are copied as they are, and the values of a partial last section are appended again.
+    fn load_sections(&mut self, reader: &VectorReader<T>, num_elements: usize) -> Result<(), CodingError> {
+        let vect_bytes = reader.vect_bytes();
+        let (offsets, sects_end) = split::section_offsets(reader)?;
+        if offsets.len() * FIXED_LEN < num_elements {
+            return Err(CodingError::InvalidFormat(format!("{} sections for {} elements",
+                                                          offsets.len(), num_elements)));
+        }
+        let num_full_sects = num_elements / FIXED_LEN;
+        let full_end = offsets.get(num_full_sects).map_or(sects_end, |&(offset, _)| offset);
+        if full_end < self.flushed {
+            return Err(CodingError::InvalidFormat("Cannot drop sections which were already flushed".into()));
+        }
+
+        while self.vect_buf.len() < full_end { self.grow()?; }
+        self.vect_buf[NUM_HEADER_BYTES_TOTAL..full_end]
+            .copy_from_slice(&vect_bytes[NUM_HEADER_BYTES_TOTAL..full_end]);
+        self.offset = full_end;
+        self.write_buf.clear();
+        self.validity = [0; VALIDITY_BITMAP_BYTES];
+        self.last_sect = None;
+        self.stats.num_null_sections = 0;
+        for &(_, sect) in &offsets[..num_full_sects] {
+            if vect_bytes[sect] == SectionType::Null.as_num() {
+                self.stats.num_null_sections += 1;
+            } else {
+                let sect_len = FixedSectEnum::<T>::try_from(&vect_bytes[sect..])?.num_bytes();
+                self.last_sect = Some((sect, sect_len));
+            }
+        }
+        if let Some(sect_blooms) = &mut self.sect_blooms { sect_blooms.truncate(num_full_sects); }
+        self.stats.update_num_elems(&mut self.vect_buf, (num_full_sects * FIXED_LEN) as u32)?;
+        self.header.update_num_bytes(self.vect_buf.as_mut_slice(), (full_end - NUM_HEADER_BYTES_TOTAL) as u32)?;
+
+        let partial_values = reader.iterate_options().skip(num_full_sects * FIXED_LEN)
+                                   .take(num_elements % FIXED_LEN);
+        for value in partial_values {
+            match value {
+                Some(v) => self.append(v)?,
+                None    => self.fill_nulls(1)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T, W> Drop for VectorAppender<T, W>
+where T: VectBase + Clone + PartialOrd,
+      W: FixedSectionWriter<T> {
+    fn drop(&mut self) {
+        if let Some(tracker) = &self.tracker { tracker.release(self.tracked_bytes); }
+    }
+}
+
+impl<T, W> VectorAppender<T, W>
+where T: RefBits + Clone + PartialOrd + BaseSubtypeMapping,
+      W: FixedSectionWriter<T> {
+    /// Builds a Bloom filter over the values of each vector, stored in the vector footer and checked with
+    /// `VectorReader::might_contain()`.  The filter has about bits_per_value bits per distinct value; 10 bits
+    /// gives about 1% false positives.  The hashes of the distinct values are kept in memory until finish().
+    pub fn enable_bloom_filter(&mut self, bits_per_value: u32) {
+        self.bloom = Some(BloomBuilder::new(<T as RefBits>::to_bits, bits_per_value));
+    }
+
+    /// Builds a small Bloom filter over the values of each section, stored in the vector footer, so that
+    /// equality filters can skip the sections which definitely don't hold the value; see
+    /// `VectorFilter::set_section_bloom_filters()`.  bits_per_value is capped at 64.  Null sections have no
+    /// filter, and are never skipped.
+    pub fn enable_section_bloom_filters(&mut self, bits_per_value: u32) {
+        self.sect_blooms = Some(SectionBloomBuilder::new(<T as RefBits>::to_bits, bits_per_value));
+    }
+}
+
 /// Regular U64 appender with AutoEncoder
 pub type VectorU64Appender = VectorAppender<u64, AutoEncoder>;
 
 /// Regular U32 appender with AutoEncoder
 pub type VectorU32Appender = VectorAppender<u32, AutoEncoder>;
 
+/// U16 appender with AutoEncoder, for small range values such as ports
+pub type VectorU16Appender = VectorAppender<u16, AutoEncoder>;
+
+/// U8 appender with AutoEncoder, for small range values such as enum ids
+pub type VectorU8Appender = VectorAppender<u8, AutoEncoder>;
+
 /// Regular F32 appender with XOR-based optimizing encoder
 pub type VectorF32XorAppender = VectorAppender<f32, XorNPMedFixedSect<'static>>;
 
+/// Regular F64 appender with XOR-based optimizing encoder
+pub type VectorF64XorAppender = VectorAppender<f64, XorNPMedFixedSect<'static>>;
+
+/// F64 appender with Gorilla-style XOR bit stream sections, smaller than VectorF64XorAppender for slowly changing
+/// values such as gauges, but slower to decode
+pub type VectorF64GorillaAppender = VectorAppender<f64, XorF64MedFixedSect<'static>>;
+
+/// F64 appender which stores values with few decimal digits, such as prices, as scaled integers
+pub type VectorF64DecimalAppender = VectorAppender<f64, DecimalF64MedFixedSect<'static>>;
+
+/// U64 appender with Gorilla-style delta-of-delta sections, for regularly spaced timestamps
+pub type VectorU64DeltaDeltaAppender = VectorAppender<u64, DeltaDeltaMedFixedSect<'static>>;
+
+/// U64 appender which picks Simple8b sections over the AutoEncoder's choice when they are smaller
+pub type VectorU64Simple8bAppender = VectorAppender<u64, Simple8bAutoEncoder>;
+
+/// U32 appender which picks Simple8b sections over the AutoEncoder's choice when they are smaller
+pub type VectorU32Simple8bAppender = VectorAppender<u32, Simple8bAutoEncoder>;
+
+/// U64 appender which picks LZ4 compressed sections over the AutoEncoder's choice when they are smaller
+#[cfg(feature = "lz4")]
+pub type VectorU64Lz4Appender = VectorAppender<u64, Lz4AutoEncoder>;
+
+/// U32 appender which picks LZ4 compressed sections over the AutoEncoder's choice when they are smaller
+#[cfg(feature = "lz4")]
+pub type VectorU32Lz4Appender = VectorAppender<u32, Lz4AutoEncoder>;
+
+/// An owned, finished vector of T elements.  It can be collected from an iterator or converted from a `Vec<T>`,
+/// which encodes the values with the regular appender for T, eg `VectorU32Appender`:
+/// ```
+/// # use compressed_vec::vector::CompressedVec;
+///     let vect: CompressedVec<u32> = (0..1000u32).map(|i| i * 2).collect();
+///     assert_eq!(vect.len(), 1000);
+///     assert_eq!(vect.reader().iterate().nth(999), Some(1998));
+///     assert_eq!(CompressedVec::from(vec![1.5f64, 2.5]).reader().iterate().sum::<f64>(), 4.0);
+/// ```
+/// Use an appender directly to choose the section writer, or to handle errors such as memory limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedVec<T: VectBase> {
+    bytes: Vec<u8>,
+    _elem: PhantomData<T>,
+}
+
+impl<T> CompressedVec<T>
+where T: VectBase + BaseSubtypeMapping {
+    /// Wraps the bytes of a finished vector of T elements, checking its header
+    pub fn try_from_bytes(bytes: Vec<u8>) -> Result<Self, CodingError> {
+        VectorReader::<T>::try_new(&bytes[..])?;
+        Ok(Self { bytes, _elem: PhantomData })
+    }
+
+    pub fn reader(&self) -> VectorReader<'_, T> {
+        // Should not fail since the header was checked when the vector was created
+        VectorReader::try_new(&self.bytes[..]).unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.reader().num_elements()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The encoded bytes of the vector
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..]
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+macro_rules! impl_collect {
+    ($($elem:ty => $appender:ty),*) => { $(
+        impl FromIterator<$elem> for CompressedVec<$elem> {
+            /// Panics if encoding fails, which an appender without limits or a memory tracker never does
+            fn from_iter<I: IntoIterator<Item = $elem>>(iter: I) -> Self {
+                let bytes = <$appender>::try_new(GROW_BYTES).and_then(|mut appender| appender.encode_all(iter))
+                                                            .expect("Encoding a vector without limits failed");
+                Self { bytes, _elem: PhantomData }
+            }
+        }
+
+        impl From<Vec<$elem>> for CompressedVec<$elem> {
+            fn from(values: Vec<$elem>) -> Self {
+                values.into_iter().collect()
+            }
+        }
+    )* };
+}
+
+impl_collect!(u64 => VectorU64Appender, u32 => VectorU32Appender, u16 => VectorU16Appender,
+              u8 => VectorU8Appender, f32 => VectorF32XorAppender, f64 => VectorF64XorAppender);
+
 
 /// A reader for reading sections and elements from a `VectorAppender` written vector.
 /// Use the same base type - eg VectorU32Appender -> VectorReader::<u32>
@@ -393,6 +1053,9 @@ pub type VectorF32XorAppender = VectorAppender<f32, XorNPMedFixedSect<'static>>;
 // TODO: have a reader trait of some kind?
 pub struct VectorReader<'buf, T: VectBase> {
     vect_bytes: &'buf [u8],
+    sect_bytes: &'buf [u8],
+    footer: Option<Footer<'buf>>,
+    bloom: Option<BloomFilter<'buf>>,
     _reader: PhantomData<T>,
 }
 
@@ -401,14 +1064,33 @@ where T: VectBase + BaseSubtypeMapping {
     /// Creates a new reader out of the bytes for the vector.
     // TODO: verify that the vector is a fixed sect int.
     pub fn try_new(vect_bytes: &'buf [u8]) -> Result<Self, CodingError> {
+        Self::try_new_with_subtype(vect_bytes, T::vect_subtype())
+    }
+
+    /// Creates a reader for a vector of the given subtype whose sections hold T elements, eg half floats
+    /// stored as u32 bit patterns.
+    pub(crate) fn try_new_with_subtype(vect_bytes: &'buf [u8],
+                                       expected: VectorSubType) -> Result<Self, CodingError> {
         let bytes_from_header: u32 = vect_bytes.pread_with(0, LE)?;
         let subtype: u8 = vect_bytes.pread_with(offset_of!(BinaryVector, minor_type), LE)?;
-        if vect_bytes.len() < (bytes_from_header + 4) as usize {
+        let transform_id: u8 = vect_bytes.pread_with(TRANSFORM_ID_OFFSET, LE)?;
+        format_version(vect_bytes)?;
+        let total_bytes = (bytes_from_header as usize).checked_add(4).ok_or(CodingError::InputTooShort)?;
+        // The header must at least cover itself, or reading the stats and sections would panic
+        if vect_bytes.len() < total_bytes || total_bytes < NUM_HEADER_BYTES_TOTAL {
             Err(CodingError::InputTooShort)
-        } else if subtype != T::vect_subtype() as u8 {
-            Err(CodingError::WrongVectorType(subtype))
+        } else if subtype != expected as u8 {
+            Err(wrong_subtype_error(subtype, expected))
+        } else if transform_id != 0 {
+            Err(CodingError::InvalidFormat(format!("Sections are transformed with id {}, use transform::open_vector",
+                                                   transform_id)))
         } else {
-            Ok(Self { vect_bytes, _reader: PhantomData })
+            let (sect_bytes, footer) = Footer::split(&vect_bytes[..total_bytes])?;
+            let bloom = match footer.and_then(|f| f.get(FooterTag::BloomFilter)) {
+                Some(entry) => Some(BloomFilter::try_from_entry(entry)?),
+                None        => None,
+            };
+            Ok(Self { vect_bytes, sect_bytes, footer, bloom, _reader: PhantomData })
         }
     }
 
@@ -432,19 +1114,196 @@ where T: VectBase + BaseSubtypeMapping {
         Ok(count)
     }
 
+    /// Returns the number of nulls, that is the nulls of PartialNull sections (see `VectorAppender::track_nulls()`),
+    /// as matched by is_null().  Elements of Null sections are zeroes, not nulls.  Only section headers and
+    /// validity bitmaps are read, values are never decoded.
+    pub fn null_count(&self) -> usize {
+        self.count_masked(self.is_null())
+    }
+
+    /// Returns the number of elements which are not nulls, see null_count()
+    pub fn count_non_null(&self) -> usize {
+        self.num_elements() - self.null_count()
+    }
+
+    /// Returns the number of elements which are not zero, tracked nulls counting as zero.  Null sections are
+    /// counted from their type alone and constant sections from their value; other sections are compared with
+    /// zero in SIMD while decoding, without writing out the values.
+    pub fn count_nonzero(&self) -> usize {
+        self.num_elements() - self.count_masked(self.filter_iter(EqualsSink::<T>::new(&T::zero())))
+    }
+
+    /// Returns the first element which is not a tracked null, or None if there is none.  Only the section holding
+    /// the element is decoded, unless it is a Null or constant section, whose value is known from the header.
+    pub fn first(&self) -> Result<Option<T>, CodingError> {
+        let mut elems_left = self.num_elements();
+        for sect in self.sect_iter() {
+            if elems_left == 0 { break }
+            let sect = sect?;
+            let sect_elems = elems_left.min(FIXED_LEN);
+            elems_left -= sect_elems;
+            if let Some(value) = Self::sect_end_value(sect, sect_elems, false)? { return Ok(Some(value)) }
+        }
+        Ok(None)
+    }
+
+    /// Returns the last element which is not a tracked null, or None if there is none.  Sections can only be
+    /// found by walking their headers from the start, but only the section holding the element is decoded, as
+    /// for first().
+    pub fn last(&self) -> Result<Option<T>, CodingError> {
+        let mut sects = Vec::new();
+        let mut elems_left = self.num_elements();
+        for sect in self.sect_iter() {
+            if elems_left == 0 { break }
+            let sect = sect?;
+            let sect_elems = elems_left.min(FIXED_LEN);
+            elems_left -= sect_elems;
+            sects.push((sect, sect_elems));
+        }
+        for &(sect, sect_elems) in sects.iter().rev() {
+            if let Some(value) = Self::sect_end_value(sect, sect_elems, true)? { return Ok(Some(value)) }
+        }
+        Ok(None)
+    }
+
+    // The first or last non-null element among the first sect_elems elements of a section, if any
+    fn sect_end_value(sect: FixedSectEnum<T>, sect_elems: usize, last: bool) -> Result<Option<T>, CodingError> {
+        let valid = sect.validity_mask() & first_n_mask(sect_elems);
+        let word_pos = |w: usize| {
+            let word = valid.extract(w);
+            match word {
+                0 => None,
+                _ if last => Some(w * 32 + 31 - word.leading_zeros() as usize),
+                _ => Some(w * 32 + word.trailing_zeros() as usize),
+            }
+        };
+        let pos = if last { (0..8).rev().find_map(word_pos) } else { (0..8).find_map(word_pos) };
+        match (pos, sect) {
+            (None, _) => Ok(None),
+            (Some(_), FixedSectEnum::NullFixedSect(_)) => Ok(Some(T::zero())),
+            (Some(_), FixedSectEnum::ConstFixedSect(cs)) => Ok(Some(cs.get_value())),
+            (Some(pos), _) => {
+                let mut sink = Section256Sink::<T>::new();
+                sect.decode(&mut sink)?;
+                Ok(Some(sink.values[pos]))
+            }
+        }
+    }
+
+    // Counts the ON bits of the masks, one per section, up to the number of elements
+    fn count_masked<I: Iterator<Item = u32x8>>(&self, masks: I) -> usize {
+        let mut elems_left = self.num_elements();
+        masks.map(|mask| {
+            let sect_elems = elems_left.min(FIXED_LEN);
+            elems_left -= sect_elems;
+            (mask & first_n_mask(sect_elems)).count_ones().wrapping_sum() as usize
+        }).sum()
+    }
+
+    /// Returns the k largest elements as (index, value), largest first and lower indexes first among equal
+    /// values.  Nulls are zeroes and NaNs are never returned.  Candidates are kept in a heap of k elements; once
+    /// it is full, null and constant sections whose value is not over the smallest candidate are skipped
+    /// without decoding, and so are sections whose maximum in the zone map, if given, is not over it.
+    pub fn top_k(&self, k: usize, zone_map: Option<&[(T, T)]>) -> Result<Vec<(usize, T)>, CodingError> {
+        if k == 0 { return Ok(Vec::new()) }
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        let mut sink = Section256Sink::<T>::new();
+        let mut elems_left = self.num_elements();
+        for (sect_num, sect) in self.sect_iter().enumerate() {
+            if elems_left == 0 { break }
+            let sect = sect?;
+            let sect_elems = elems_left.min(FIXED_LEN);
+            elems_left -= sect_elems;
+            let max = match (sect, zone_map.and_then(|bounds| bounds.get(sect_num))) {
+                (FixedSectEnum::NullFixedSect(_), _)    => Some(T::zero()),
+                (FixedSectEnum::ConstFixedSect(cs), _)  => Some(cs.get_value()),
+                (_, bounds)                             => bounds.map(|&(_, max)| max),
+            };
+            let threshold = heap.peek().filter(|_| heap.len() == k).map(|Reverse(TopKEntry(value, _))| *value);
+            if let (Some(max), Some(threshold)) = (max, threshold) {
+                if max <= threshold { continue }
+            }
+
+            sink.reset();
+            sect.decode(&mut sink)?;
+            for (i, &value) in sink.values[..sect_elems].iter().enumerate() {
+                if value.partial_cmp(&value).is_none() { continue }     // NaN
+                heap.push(Reverse(TopKEntry(value, sect_num * FIXED_LEN + i)));
+                if heap.len() > k { heap.pop(); }
+            }
+        }
+        Ok(heap.into_sorted_vec().into_iter().map(|Reverse(TopKEntry(value, index))| (index, value)).collect())
+    }
+
     /// Returns a FixedSectStats extracted from the vector header.
     pub fn get_stats(&self) -> FixedSectStats {
         self.vect_bytes.pread_with(BINARYVECT_HEADER_SIZE, LE).unwrap()
     }
 
+    /// Returns the footer of this vector, if it has one
+    pub fn footer(&self) -> Option<Footer<'buf>> {
+        self.footer
+    }
+
+    /// Returns the Bloom filter over the values of this vector, if it was built with one
+    pub fn bloom_filter(&self) -> Option<BloomFilter<'buf>> {
+        self.bloom
+    }
+
+    /// Returns the Bloom filters of each section of this vector, if it was built with them
+    pub fn section_bloom_filters(&self) -> Result<Option<SectionBloomFilters<'buf>>, CodingError> {
+        match self.footer.and_then(|f| f.get(FooterTag::SectionBloomFilters)) {
+            Some(entry) => Ok(Some(SectionBloomFilters::try_from_entry(entry)?)),
+            None        => Ok(None),
+        }
+    }
+
+    /// The bytes of the whole vector
+    pub(crate) fn vect_bytes(&self) -> &'buf [u8] {
+        self.vect_bytes
+    }
+
+    /// The bytes of all the sections, between the header and the footer
+    pub(crate) fn sect_bytes(&self) -> &'buf [u8] {
+        self.sect_bytes
+    }
+
     /// Returns an iterator over each section in this vector
     pub fn sect_iter(&self) -> FixedSectIterator<'buf, T> {
-        FixedSectIterator::new(&self.vect_bytes[NUM_HEADER_BYTES_TOTAL..])
+        FixedSectIterator::new(self.sect_bytes)
+    }
+
+    /// Returns an iterator over each section which parses and prefetches the next section ahead of time.
+    /// See `LookaheadSectIterator`.
+    pub fn lookahead_sect_iter(&self) -> LookaheadSectIterator<'buf, T> {
+        LookaheadSectIterator::new(self.sect_bytes)
+    }
+
+    /// Returns an iterator over each section wrapped in a LazySection, which decodes only when values are
+    /// accessed.  Useful for deciding which sections to decode based on their metadata.
+    pub fn lazy_sections(&self) -> impl Iterator<Item = Result<LazySection<'buf, T>, CodingError>> {
+        self.sect_iter().map(|sect_res| sect_res.map(LazySection::new))
+    }
+
+    /// Like lazy_sections(), but the decoded values cached by each LazySection are reported to the tracker.
+    pub fn lazy_sections_tracked(&self, tracker: Arc<dyn MemoryTracker>)
+        -> impl Iterator<Item = Result<LazySection<'buf, T>, CodingError>> {
+        self.sect_iter().map(move |sect_res| sect_res.map(|sect| LazySection::with_tracker(sect, tracker.clone())))
     }
 
     /// Returns a VectorFilter that iterates over 256-bit masks filtered from vector elements
     pub fn filter_iter<F: SectFilterSink<T>>(&self, f: F) -> VectorFilter<'buf, F, T> {
-        VectorFilter::new(&self.vect_bytes[NUM_HEADER_BYTES_TOTAL..], f)
+        VectorFilter::new(self.sect_bytes, f)
+    }
+
+    /// Returns a filter matching the null elements of this vector, see `NullFilter`
+    pub fn is_null(&self) -> NullFilter<'buf, T> {
+        NullFilter::new(self.sect_bytes, true)
+    }
+
+    /// Returns a filter matching the non-null elements of this vector, see `NullFilter`
+    pub fn is_not_null(&self) -> NullFilter<'buf, T> {
+        NullFilter::new(self.sect_bytes, false)
     }
 
     /// Returns an iterator over all items in this vector.
@@ -452,19 +1311,168 @@ where T: VectBase + BaseSubtypeMapping {
         VectorItemIter::new(self.sect_iter(), self.num_elements())
     }
 
+    /// Returns an iterator over the items in range only.  Sections before the range are skipped using their
+    /// sizes without decoding, and sections after it are never read.  The range is clipped to the number of
+    /// elements.
+    pub fn range(&self, range: Range<usize>) -> VectorItemIter<'buf, T> {
+        let end = range.end.min(self.num_elements());
+        let mut iter = VectorItemIter::new(self.sect_iter(), end);
+        iter.i = range.start.min(end);
+        iter
+    }
+
+    /// Returns an iterator of u32x8 masks, one per section, for non-null elements.  Only vectors appended with
+    /// `track_nulls()` have nulls; combine with filter masks to skip them.
+    /// Panics on decoding error.
+    pub fn validity_masks(&self) -> impl Iterator<Item = u32x8> + 'buf where T: 'buf {
+        self.sect_iter().map(|sect| sect.expect("This should not fail!").validity_mask())
+    }
+
+    /// Returns an iterator over all items in this vector, with None for nulls
+    pub fn iterate_options(&self) -> impl Iterator<Item = Option<T>> + 'buf where T: 'buf {
+        let valid = self.validity_masks()
+                        .flat_map(|mask| (0..FIXED_LEN).map(move |i| mask.extract(i / 32) & (1 << (i % 32)) != 0));
+        self.iterate().zip(valid).map(|(value, valid)| if valid { Some(value) } else { None })
+    }
+
+    /// Returns a DecodeCursor for decoding this vector incrementally, a budget of elements at a time.
+    pub fn cursor(&self) -> DecodeCursor<'buf, T> {
+        DecodeCursor { sect_iter: self.sect_iter(), num_elems: self.num_elements(), decoded: 0 }
+    }
+
+    /// Returns the element at index, decoding only the section containing it.  Sections before it are skipped
+    /// using their sizes, without decoding.  Nulls are zeroes.  Use point_reader() for many lookups.
+    pub fn get(&self, index: usize) -> Result<T, CodingError> {
+        self.point_reader().get(index)
+    }
+
+    /// Returns a copy of this vector with only its first len elements, see `VectorAppender::truncate()`.  A section
+    /// the cut lands in is encoded again with the section writer W.  Vectors with a footer cannot be truncated.
+    pub fn truncate<W: FixedSectionWriter<T>>(&self, len: usize) -> Result<Vec<u8>, CodingError> {
+        let mut appender = VectorAppender::<T, W>::from_encoded(self.vect_bytes)?;
+        appender.truncate(len)?;
+        appender.finish(len)
+    }
+
+    /// Returns a PointReader for random access lookups, which caches the last section it decoded.
+    pub fn point_reader(&self) -> PointReader<'buf, T> {
+        PointReader { sects_start: self.sect_iter(), sect_iter: self.sect_iter(), next_sect: 0, cached: None,
+                      num_elems: self.num_elements() }
+    }
+
     /// Decodes/processes this vector's elements through a Sink.  This is the most general purpose vector
     /// decoding/processing API.
+    /// Sections are read with lookahead_sect_iter(), so that the next section is parsed and prefetched while
+    /// the current one decodes.  Decoding stops early once the sink `is_done()`.
     pub fn decode_to_sink<Output>(&self, output: &mut Output) -> Result<(), CodingError>
     where Output: Sink<T::SI> {
-        for sect in self.sect_iter() {
+        for sect in self.lookahead_sect_iter() {
+            if output.is_done() { break }
             sect?.decode(output)?;
         }
         Ok(())
     }
+
+    /// Streams the elements to a writer through a `WriteSink`, as little endian bytes or as one-column CSV text,
+    /// and returns the writer.  Nulls are zeroes.
+    /// ```
+    /// # use compressed_vec::sink::WriteFormat;
+    /// # use compressed_vec::vector::{VectorU32Appender, VectorReader};
+    ///     let bytes = VectorU32Appender::try_new(1024).unwrap().encode_all(vec![3, 1, 4]).unwrap();
+    ///     let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+    ///     let csv = reader.write_values(Vec::new(), WriteFormat::Csv).unwrap();
+    ///     assert_eq!(String::from_utf8(csv).unwrap(), "3\n1\n4\n");
+    /// ```
+    pub fn write_values<W: Write>(&self, writer: W, format: WriteFormat) -> Result<W, CodingError>
+    where T: std::fmt::Display {
+        let mut sink = WriteSink::<T, W>::new(writer, format, self.num_elements());
+        self.decode_to_sink(&mut sink)?;
+        sink.finish()
+    }
 }
 
+impl<'buf, T> VectorReader<'buf, T>
+where T: VectBase + NumCast + BaseSubtypeMapping + 'buf {
+    /// Returns approximate inclusive (lower, upper) bounds on the values of each section, at a fraction of the
+    /// cost of decoding, for previews such as sparklines.  See `LazySection::preview()`.
+    pub fn preview(&self) -> impl Iterator<Item = Result<(T, T), CodingError>> + 'buf {
+        self.sect_iter().map(|sect_res| sect_res.and_then(|sect| LazySection::new(sect).preview()))
+    }
+}
 
-/// Detailed stats, for debugging or perf analysis, on a Vector.  Includes the section types.
+impl<'buf, T> VectorReader<'buf, T>
+where T: VectBase + NumCast + BaseSubtypeMapping,
+      T::SI: MinMaxInput {
+    /// Returns the exact (min, max) of the elements, or None if there are none.  Nulls are zeroes.  If a zone
+    /// map with the bounds of every section is given, eg as stored by an index from
+    /// `minmax::per_section_minmax()`, the result comes from it without decoding; otherwise every section is
+    /// decoded through a `MinMaxSink`, except null and constant sections.
+    pub fn minmax(&self, zone_map: Option<&[(T, T)]>) -> Result<Option<(T, T)>, CodingError> {
+        let num_sects = (self.num_elements() + FIXED_LEN - 1) / FIXED_LEN;
+        let computed;
+        let bounds = match zone_map {
+            Some(bounds) if bounds.len() >= num_sects => &bounds[..num_sects],
+            _ => {
+                computed = minmax::per_section_minmax::<T>(self.vect_bytes)?;
+                &computed[..]
+            }
+        };
+        Ok(bounds.iter().fold(None, |acc, &(min, max)| match acc {
+            None => Some((min, max)),
+            Some((amin, amax)) => Some((if min < amin { min } else { amin }, if max > amax { max } else { amax })),
+        }))
+    }
+}
+
+impl<'buf, T> VectorReader<'buf, T>
+where T: RefBits + BaseSubtypeMapping {
+    /// Decodes all elements using the pure safe, scalar reference decoder in the `reference` module instead
+    /// of the SIMD decoders.  Slow, but useful as a second opinion.
+    pub fn decode_checked(&self) -> Result<Vec<T>, CodingError> {
+        reference::decode_vector_ref(self.sect_bytes, self.num_elements())
+    }
+
+    /// Returns false if the value is definitely not in this vector, using its Bloom filter.  Always true for
+    /// vectors without a Bloom filter.
+    pub fn might_contain(&self, value: T) -> bool {
+        self.bloom.map_or(true, |bloom| bloom.might_contain_bits(value.to_bits()))
+    }
+
+    /// Estimates the number of distinct non-null values with a HyperLogLog sketch, see the `hll` module.  The
+    /// standard error is about 1.6%.
+    pub fn approx_distinct(&self) -> Result<u64, CodingError> {
+        Ok(hll::sketch(self, hll::DEFAULT_PRECISION)?.estimate())
+    }
+
+    /// Debug and test builds only: decodes every section with both the SIMD decoders and the reference decoder,
+    /// and panics with the section number and element of the first divergence.  Decoding errors from either
+    /// decoder are returned as Err.
+    #[cfg(any(debug_assertions, test))]
+    pub fn cross_check(&self) -> Result<(), CodingError> {
+        let encoded_bytes = self.sect_bytes;
+        let mut sink = Section256Sink::<T>::new();
+        let mut expected = [T::zero(); FIXED_LEN];
+        let mut prev = [T::zero(); FIXED_LEN];
+        let mut pos = 0;
+        for (n, sect) in self.sect_iter().enumerate() {
+            let sect = sect?;
+            sink.reset();
+            sect.decode(&mut sink)?;
+            pos += reference::decode_section_ref(&encoded_bytes[pos..],
+                                                 if n > 0 { Some(&prev) } else { None },
+                                                 &mut expected)?;
+            if let Some(i) = (0..FIXED_LEN).find(|&i| sink.values[i].to_bits() != expected[i].to_bits()) {
+                panic!("Section {} ({:?}) diverges at element {}: SIMD decoded {:?}, reference {:?}",
+                       n, sect.sect_type(), i, sink.values[i], expected[i]);
+            }
+            prev = expected;
+        }
+        Ok(())
+    }
+}
+
+
+/// Detailed stats, for debugging or perf analysis, on a Vector.  Includes the section types.
 #[derive(Debug)]
 pub struct VectorStats {
     num_bytes: usize,
@@ -510,48 +1518,315 @@ impl VectorStats {
 // NOTE: part of reason to do this is to better control lifetimes which is hard otherwise
 pub struct VectorItemIter<'buf, T: VectBase> {
     sect_iter: FixedSectIterator<'buf, T>,
-    sink: Section256Sink<T>,
-    num_elems: usize,
+    sects_start: FixedSectIterator<'buf, T>,  // All sections, for iterating from the back
+    front: LoadedSect<T>,
+    back: Option<Box<BackSects<'buf, T>>>,    // Only created by next_back()
     i: usize,
+    end: usize,          // One past the last element not yet returned from the back
+}
+
+// A decoded section.  Null sections are not decoded.
+struct LoadedSect<T: VectBase> {
+    index: Option<usize>,
+    null_sect: bool,
+    sink: Section256Sink<T>,
+}
+
+impl<T: VectBase> LoadedSect<T> {
+    fn new() -> Self {
+        Self { index: None, null_sect: false, sink: Section256Sink::<T>::new() }
+    }
+
+    // Loads section number index; a missing section reads as nulls
+    fn load(&mut self, index: usize, sect: Option<FixedSectEnum<T>>) {
+        self.index = Some(index);
+        match sect {
+            Some(sect) if !sect.is_null() => {
+                self.null_sect = false;
+                self.sink.reset();
+                sect.decode(&mut self.sink).expect("Unexpected end of section");
+            }
+            _ => self.null_sect = true,
+        }
+    }
+
+    #[inline]
+    fn get(&self, pos: usize) -> T {
+        if self.null_sect { T::zero() } else { self.sink.values[pos % FIXED_LEN] }
+    }
+}
+
+// The sections and the decoded back section, for iterating from the back
+struct BackSects<'buf, T: VectBase> {
+    sects: Vec<FixedSectEnum<'buf, T>>,
+    loaded: LoadedSect<T>,
 }
 
 impl<'buf, T: VectBase> VectorItemIter<'buf, T> {
     pub fn new(sect_iter: FixedSectIterator<'buf, T>, num_elems: usize) -> Self {
-        let mut s = Self {
+        Self {
+            sects_start: sect_iter.clone(),
             sect_iter,
-            sink: Section256Sink::<T>::new(),
-            num_elems,
+            front: LoadedSect::new(),
+            back: None,
             i: 0,
-        };
-        if num_elems > 0 {
-            s.next_section();
+            end: num_elems,
         }
-        s
     }
 
-    fn next_section(&mut self) {
-        self.sink.reset();
-        if let Some(Ok(next_sect)) = self.sect_iter.next() {
-            next_sect.decode(&mut self.sink).expect("Unexpected end of section");
+    // The element at pos, at or after the front section.  Sections in between are skipped without decoding.
+    fn front_value(&mut self, pos: usize) -> T {
+        let index = pos / FIXED_LEN;
+        if self.front.index != Some(index) {
+            let skip = self.front.index.map_or(index, |loaded| index - loaded - 1);
+            let sect = self.sect_iter.nth(skip).and_then(|res| res.ok());
+            self.front.load(index, sect);
         }
+        self.front.get(pos)
+    }
+
+    // The element at pos, loading its section from the section list, which is built on first use
+    fn back_value(&mut self, pos: usize) -> T {
+        let sects_start = &self.sects_start;
+        let back = self.back.get_or_insert_with(|| {
+            let mut sects = Vec::new();
+            for sect in sects_start.clone() {
+                match sect {
+                    Ok(sect) => sects.push(sect),
+                    Err(_)   => break,
+                }
+            }
+            Box::new(BackSects { sects, loaded: LoadedSect::new() })
+        });
+        let index = pos / FIXED_LEN;
+        if back.loaded.index != Some(index) {
+            let sect = back.sects.get(index).cloned();
+            back.loaded.load(index, sect);
+        }
+        back.loaded.get(pos)
     }
 }
 
+/// Iterates over the elements of a vector.  `nth()` and `skip()` skip whole sections without decoding them,
+/// and iterating from the back with `rev()` or `next_back()` decodes only the sections it reaches.
 impl<'buf, T: VectBase> Iterator for VectorItemIter<'buf, T> {
     type Item = T;
     fn next(&mut self) -> Option<T> {
-        if self.i < self.num_elems {
-            let thing = self.sink.values[self.i % FIXED_LEN];
+        if self.i < self.end {
+            let thing = self.front_value(self.i);
             self.i += 1;
-            // If at boundary, get next_section
-            if self.i % FIXED_LEN == 0 && self.i < self.num_elems {
-                self.next_section();
-            }
             Some(thing)
         } else {
             None
         }
     }
+
+    fn nth(&mut self, n: usize) -> Option<T> {
+        self.i = self.i.saturating_add(n).min(self.end);
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.end - self.i;
+        (left, Some(left))
+    }
+}
+
+impl<'buf, T: VectBase> DoubleEndedIterator for VectorItemIter<'buf, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.i < self.end {
+            self.end -= 1;
+            Some(self.back_value(self.end))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'buf, T: VectBase> ExactSizeIterator for VectorItemIter<'buf, T> {}
+
+impl<'buf, T: VectBase> FusedIterator for VectorItemIter<'buf, T> {}
+
+/// Progress of a DecodeCursor after a call to decode_with_budget()
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DecodeProgress {
+    /// Some elements remain to be decoded.  Contains the number of elements decoded so far.
+    Partial(usize),
+    /// All elements of the vector have been decoded
+    Done,
+}
+
+/// A cursor which decodes a vector to a sink incrementally, so that latency sensitive servers can spread the
+/// decoding of large vectors over several event loop ticks.  Each call to decode_with_budget() resumes at the
+/// section where the previous call stopped.
+/// ```
+/// # use compressed_vec::vector::{DecodeProgress, VectorU32Appender, VectorReader};
+/// # use compressed_vec::sink::VecSink;
+///     let mut appender = VectorU32Appender::try_new(4096).unwrap();
+///     let bytes = appender.encode_all(0..1000u32).unwrap();
+///     let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+///
+///     let mut cursor = reader.cursor();
+///     let mut sink = VecSink::<u32>::new();
+///     assert_eq!(cursor.decode_with_budget(600, &mut sink).unwrap(), DecodeProgress::Partial(512));
+///     // ... handle other requests, then continue
+///     assert_eq!(cursor.decode_with_budget(600, &mut sink).unwrap(), DecodeProgress::Done);
+///     assert_eq!(sink.vec[999], 999);
+/// ```
+pub struct DecodeCursor<'buf, T: VectBase> {
+    sect_iter: FixedSectIterator<'buf, T>,
+    num_elems: usize,
+    decoded: usize,
+}
+
+impl<'buf, T: VectBase> DecodeCursor<'buf, T> {
+    /// Decodes up to budget_elems more elements to the sink.  Decoding is done a whole section (FIXED_LEN
+    /// elements) at a time, so at least one section is decoded per call, even when budget_elems is smaller.
+    /// Like VectorReader::decode_to_sink(), the last section is decoded in full, past the number of elements.
+    pub fn decode_with_budget<Output>(&mut self, budget_elems: usize, output: &mut Output)
+        -> Result<DecodeProgress, CodingError>
+    where Output: Sink<T::SI> {
+        let mut budget_left = budget_elems.max(FIXED_LEN);
+        while budget_left >= FIXED_LEN && self.decoded < self.num_elems {
+            match self.sect_iter.next() {
+                Some(sect) => sect?.decode(output)?,
+                None       => return Err(CodingError::InputTooShort),
+            }
+            self.decoded = (self.decoded + FIXED_LEN).min(self.num_elems);
+            budget_left -= FIXED_LEN;
+        }
+        Ok(self.progress())
+    }
+
+    /// Returns the progress so far without decoding anything
+    pub fn progress(&self) -> DecodeProgress {
+        if self.decoded >= self.num_elems { DecodeProgress::Done } else { DecodeProgress::Partial(self.decoded) }
+    }
+}
+
+/// Random access to the elements of a vector.  A lookup decodes only the section containing the element, and
+/// caches it, so lookups of nearby elements do not decode again.  Lookups after the cached section skip forward
+/// using section sizes; lookups before it rescan the section headers from the start of the vector.
+/// ```
+/// # use compressed_vec::vector::{VectorU64Appender, VectorReader};
+///     let mut appender = VectorU64Appender::try_new(4096).unwrap();
+///     let bytes = appender.encode_all((0..1000u64).map(|i| i * 10)).unwrap();
+///     let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+///
+///     let mut points = reader.point_reader();
+///     assert_eq!(points.get(700).unwrap(), 7000);
+///     assert_eq!(points.get(701).unwrap(), 7010);   // Same section, no decoding
+///     assert_eq!(reader.get(3).unwrap(), 30);
+/// ```
+pub struct PointReader<'buf, T: VectBase> {
+    sects_start: FixedSectIterator<'buf, T>,
+    sect_iter: FixedSectIterator<'buf, T>,
+    next_sect: usize,         // Number of the section sect_iter returns next
+    cached: Option<(usize, LazySection<'buf, T>)>,
+    num_elems: usize,
+}
+
+impl<'buf, T: VectBase> PointReader<'buf, T> {
+    /// Returns the element at index.  Indexes past the number of elements are BadOffset errors.
+    pub fn get(&mut self, index: usize) -> Result<T, CodingError> {
+        if index >= self.num_elems { return Err(CodingError::BadOffset(index)) }
+        let sect_num = index / FIXED_LEN;
+        if let Some((cached_num, sect)) = &mut self.cached {
+            if *cached_num == sect_num { return sect.get(index % FIXED_LEN) }
+        }
+        if sect_num < self.next_sect {
+            self.sect_iter = self.sects_start.clone();
+            self.next_sect = 0;
+        }
+        let sect = self.sect_iter.nth(sect_num - self.next_sect).ok_or(CodingError::InputTooShort)??;
+        self.next_sect = sect_num + 1;
+        let mut sect = LazySection::new(sect);
+        let value = sect.get(index % FIXED_LEN);
+        self.cached = Some((sect_num, sect));
+        value
+    }
+}
+
+// A top_k() candidate: larger values are greater, and among equal values, lower indexes
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct TopKEntry<T>(T, usize);
+
+impl<T: PartialOrd> Eq for TopKEntry<T> {}
+
+impl<T: PartialOrd> PartialOrd for TopKEntry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: PartialOrd> Ord for TopKEntry<T> {
+    // NaNs are never added, so values always compare
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal).then_with(|| other.1.cmp(&self.1))
+    }
+}
+
+/// Concatenates finished vectors of the same type into one vector, for example to merge segments, by splicing
+/// their sections after a copy of the first header.  No section is decoded or re-encoded.
+/// ```
+/// # use compressed_vec::vector::*;
+///     let first = VectorU32Appender::try_new(1024).unwrap().encode_all(0..512u32).unwrap();
+///     let second = VectorU32Appender::try_new(1024).unwrap().encode_all(1000..1100u32).unwrap();
+///     let merged = concat(&[&first[..], &second[..]]).unwrap();
+///     let reader = VectorReader::<u32>::try_new(&merged[..]).unwrap();
+///     assert_eq!(reader.num_elements(), 612);
+///     assert_eq!(reader.iterate().skip(510).take(3).collect::<Vec<u32>>(), vec![510, 511, 1000]);
+/// ```
+/// All vectors must have the same type, subtype and flags, and no footer, since footers such as Bloom filters
+/// only describe their own vector.  Sealed vectors from the `transform` module cannot be concatenated either,
+/// as each section is sealed with its section number, which would change.  Every vector but the last must have
+/// a multiple of 256 elements, as the nulls padding its last section would otherwise end up in the middle of the
+/// result.  Violations are InvalidFormat errors.
+pub fn concat(vectors: &[&[u8]]) -> Result<Vec<u8>, CodingError> {
+    let first = vectors.first().ok_or_else(|| CodingError::InvalidFormat("No vectors to concatenate".into()))?;
+    if first.len() < NUM_HEADER_BYTES_TOTAL { return Err(CodingError::InputTooShort) }
+    let type_bytes = &first[4..FLAGS_OFFSET + 1];
+    let transform_id: u8 = first.pread_with(TRANSFORM_ID_OFFSET, LE)?;
+    if transform_id != 0 {
+        return Err(CodingError::InvalidFormat(format!("Sections are transformed with id {}, cannot be moved",
+                                                      transform_id)));
+    }
+    let mut out = first[..NUM_HEADER_BYTES_TOTAL].to_vec();
+    let mut num_elements = 0u64;
+    let mut num_null_sections = 0u64;
+    for (i, vect_bytes) in vectors.iter().enumerate() {
+        format_version(vect_bytes)?;
+        if vect_bytes.get(4..FLAGS_OFFSET + 1) != Some(type_bytes) {
+            return Err(CodingError::InvalidFormat(format!("Vector {} differs in type, transform or flags", i)));
+        }
+        if vect_bytes[FLAGS_OFFSET] & FLAG_FOOTER != 0 {
+            return Err(CodingError::InvalidFormat(format!("Vector {} has a footer", i)));
+        }
+        let num_bytes = vect_bytes.pread_with::<u32>(0, LE)? as usize + 4;
+        if num_bytes < NUM_HEADER_BYTES_TOTAL { return Err(CodingError::InputTooShort) }
+        let vect_bytes = vect_bytes.get(..num_bytes).ok_or(CodingError::InputTooShort)?;
+        let stats: FixedSectStats = vect_bytes.pread_with(BINARYVECT_HEADER_SIZE, LE)?;
+        let partial_sect_elems = stats.num_elements as usize % FIXED_LEN;
+        if i + 1 < vectors.len() && partial_sect_elems > 0 {
+            return Err(CodingError::InvalidFormat(
+                format!("Vector {} has {} elements, not a multiple of {}", i, stats.num_elements, FIXED_LEN)));
+        }
+        // A vector never starts with a RepeatPrevious section, so the sections can be copied as they are
+        out.extend_from_slice(&vect_bytes[NUM_HEADER_BYTES_TOTAL..]);
+        num_elements += stats.num_elements as u64;
+        num_null_sections += stats.num_null_sections as u64;
+    }
+
+    let stats = FixedSectStats {
+        num_elements: u32::try_from(num_elements).map_err(|_| CodingError::Overflow)?,
+        num_null_sections: u16::try_from(num_null_sections).map_err(|_| CodingError::Overflow)?,
+    };
+    out.pwrite_with(stats, BINARYVECT_HEADER_SIZE, LE)?;
+    let num_bytes = u32::try_from(out.len() - 4).map_err(|_| CodingError::Overflow)?;
+    out.pwrite_with(num_bytes, 0, LE)?;
+    out[FORMAT_MAGIC_OFFSET] = FORMAT_MAGIC;
+    out[FORMAT_MAGIC_OFFSET + 1] = FORMAT_VERSION;
+    Ok(out)
 }
 
 #[cfg(test)]
@@ -708,6 +1983,619 @@ mod test {
         assert_eq!(sink.vec[..total_elems as usize], it_data[..]);
     }
 
+    #[test]
+    fn test_append_u16_u8_decode_and_filter() {
+        // Ports, with a stretch of nulls and a partial last section
+        let ports: Vec<u16> = (0..700usize)
+            .map(|i| if (300..556).contains(&i) { 0 } else { [80, 443, 8080, 65535][i % 4] })
+            .collect();
+        let mut appender = VectorU16Appender::try_new(1024).unwrap();
+        ports[..300].iter().for_each(|&p| appender.append(p).unwrap());
+        appender.append_nulls(256).unwrap();
+        ports[556..].iter().for_each(|&p| appender.append(p).unwrap());
+        let bytes = appender.finish(700).unwrap();
+        let reader = VectorReader::<u16>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.iterate().collect::<Vec<u16>>(), ports);
+        let mut sink = VecSink::<u16>::new();
+        reader.decode_to_sink(&mut sink).unwrap();
+        assert_eq!(sink.vec[..700], ports[..]);
+        assert_eq!(count_hits(reader.filter_iter(EqualsSink::<u16>::new(&443))), 111);
+        reader.cross_check().unwrap();
+
+        // Enum ids: constant, monotonic and NibblePacked sections
+        let ids: Vec<u8> = (0..768u32).map(|i| match i / 256 { 0 => 7, 1 => (i / 2) as u8, _ => (i * 37 % 251) as u8 })
+                                      .collect();
+        let bytes = VectorU8Appender::try_new(1024).unwrap().encode_all(ids.clone()).unwrap();
+        let reader = VectorReader::<u8>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.iterate().collect::<Vec<u8>>(), ids);
+        reader.cross_check().unwrap();
+        // Smaller than the same values in a u32 vector, which stores constants and delta bases as 4 bytes
+        let u32_bytes = VectorU32Appender::try_new(1024).unwrap()
+                                          .encode_all(ids.iter().map(|&id| id as u32)).unwrap();
+        assert!(bytes.len() < u32_bytes.len());
+        assert!(matches!(VectorReader::<u16>::try_new(&bytes[..]), Err(CodingError::WrongElementType { .. })));
+    }
+
+    #[test]
+    fn test_track_nulls_partial_null_sections() {
+        // Timestamps with every 10th missing, then a section of only nulls, then a partial section
+        let expected: Vec<Option<u64>> = (0..900u64)
+            .map(|i| if i % 10 == 3 || (256..512).contains(&i) { None } else { Some(1_590_000_000_000 + i * 1000) })
+            .collect();
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.track_nulls(true);
+        for value in &expected {
+            match value {
+                Some(v) => appender.append(*v).unwrap(),
+                None    => appender.append_nulls(1).unwrap(),
+            }
+        }
+        let bytes = appender.finish(900).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.iterate_options().collect::<Vec<_>>(), expected);
+        assert_eq!(reader.iterate().collect::<Vec<u64>>(),
+                   expected.iter().map(|v| v.unwrap_or(0)).collect::<Vec<u64>>());
+        reader.cross_check().unwrap();
+
+        // The packed values are still monotonic, nulls don't get in the way of delta encoding
+        let sects: Vec<_> = reader.sect_iter().map(|s| s.unwrap()).collect();
+        match sects[0] {
+            FixedSectEnum::PartialNullFixedSect(ps) => {
+                assert_eq!(ps.num_valid(), 230);
+                assert!(!ps.is_valid(3) && ps.is_valid(4));
+                assert_eq!(ps.values_section().unwrap().sect_type(), SectionType::MonoDeltaNPMedium);
+            }
+            _ => panic!("Expected a PartialNull section, got {:?}", sects[0]),
+        }
+        assert_eq!(sects[1].validity_mask(), u32x8::splat(0));
+        assert_eq!(sects[1].num_bytes(), 36);
+        // Padding after the last element is null too
+        assert_eq!(count_hits(reader.validity_masks()), expected.iter().filter(|v| v.is_some()).count());
+
+        // Without tracking, nulls are zeroes and the same values have no PartialNull sections
+        let zeroes = VectorU64Appender::try_new(1024).unwrap()
+                                           .encode_all(expected.iter().map(|v| v.unwrap_or(0))).unwrap();
+        let reader = VectorReader::<u64>::try_new(&zeroes[..]).unwrap();
+        assert!(reader.sect_iter().all(|s| s.unwrap().sect_type() != SectionType::PartialNull));
+        assert!(reader.iterate_options().all(|v| v.is_some()));
+    }
+
+    #[test]
+    fn test_append_options() {
+        // Scattered nulls, then a run of nulls covering two whole sections
+        let values: Vec<Option<u32>> = (0..1000u32)
+            .map(|i| if i % 7 == 0 || (256..768).contains(&i) { None } else { Some(i * 2) })
+            .collect();
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        let bytes = appender.encode_all_options(values.clone()).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.num_elements(), 1000);
+        assert_eq!(reader.get_stats().num_null_sections, 2);
+        assert_eq!(reader.iterate().collect::<Vec<u32>>(), values.iter().map(|v| v.unwrap_or(0)).collect::<Vec<_>>());
+
+        // Tracking nulls reads them back as None
+        appender.track_nulls(true);
+        let bytes = appender.encode_all_options(values.clone()).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.iterate_options().take(1000).collect::<Vec<_>>(), values);
+    }
+
+    #[test]
+    fn test_from_encoded_continues_appending() {
+        // Reopening and appending writes the same bytes as appending everything at once, including dedup
+        let values: Vec<u32> = (0..1100u32).map(|i| if (256..768).contains(&i) { i % 256 } else { i * 3 }).collect();
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.dedup_sections(true);
+        let whole = appender.encode_all(values.clone()).unwrap();
+        let first = appender.encode_all(values[..600].to_vec()).unwrap();
+
+        let mut reopened = VectorU32Appender::from_encoded(&first[..]).unwrap();
+        reopened.dedup_sections(true);
+        assert_eq!(reopened.num_elements(), 600);
+        values[600..].iter().for_each(|&v| reopened.append(v).unwrap());
+        assert_eq!(reopened.finish(1100).unwrap(), whole);
+
+        // Tracked nulls in the partial last section stay nulls
+        let options: Vec<Option<u32>> = (0..300u32).map(|i| if i % 5 == 0 { None } else { Some(i) }).collect();
+        appender.track_nulls(true);
+        let bytes = appender.encode_all_options(options[..290].to_vec()).unwrap();
+        let mut reopened = VectorU32Appender::from_encoded(&bytes[..]).unwrap();
+        reopened.append_options(options[290..].to_vec()).unwrap();
+        let bytes = reopened.finish(300).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.iterate_options().take(300).collect::<Vec<_>>(), options);
+
+        let mut with_bloom = VectorU32Appender::try_new(1024).unwrap();
+        with_bloom.enable_bloom_filter(10);
+        let bytes = with_bloom.encode_all(0..10u32).unwrap();
+        assert!(matches!(VectorU32Appender::from_encoded(&bytes[..]), Err(CodingError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_truncate() {
+        let values: Vec<u64> = (0..1000u64).map(|i| i * i).collect();
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        let expected = appender.encode_all(values[..600].to_vec()).unwrap();
+
+        // Cut in the middle of a sealed section, then within the write buffer
+        values.iter().for_each(|&v| appender.append(v).unwrap());
+        appender.truncate(700).unwrap();
+        assert_eq!(appender.num_elements(), 700);
+        appender.truncate(600).unwrap();
+        assert_eq!(appender.truncate(601), Err(CodingError::InvalidNumRows(601, 600)));
+        assert_eq!(appender.finish(600).unwrap(), expected);
+
+        // Tracked nulls cut from the write buffer don't come back as nulls
+        appender.track_nulls(true);
+        appender.append(1).unwrap();
+        appender.append_nulls(5).unwrap();
+        appender.truncate(1).unwrap();
+        appender.append(2).unwrap();
+        let bytes = appender.finish(2).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.iterate_options().take(2).collect::<Vec<_>>(), vec![Some(1), Some(2)]);
+
+        let whole = VectorU64Appender::try_new(1024).unwrap().encode_all(values.clone()).unwrap();
+        let reader = VectorReader::<u64>::try_new(&whole[..]).unwrap();
+        assert_eq!(reader.truncate::<AutoEncoder>(600).unwrap(), expected);
+        assert_eq!(reader.truncate::<AutoEncoder>(1000).unwrap(), whole);
+    }
+
+    #[test]
+    fn test_decimal_f64_sections() {
+        // Prices with cents, percentages with a varying number of digits, then values with no decimal scale
+        let values: Vec<f64> = (0..768).map(|i| match i / 256 {
+            0 => (1999 + (i % 50) * 25) as f64 / 100.0,
+            1 => [0.5, 12.125, 99.9, 3.0][i % 4],
+            _ => (i as f64).sqrt(),
+        }).collect();
+        let bytes = VectorF64DecimalAppender::try_new(1024).unwrap().encode_all(values.clone()).unwrap();
+        let reader = VectorReader::<f64>::try_new(&bytes[..]).unwrap();
+        let decoded: Vec<f64> = reader.iterate().collect();
+        assert!(decoded.iter().zip(values.iter()).all(|(a, b)| a.to_bits() == b.to_bits()));
+        reader.cross_check().unwrap();
+
+        let sects: Vec<_> = reader.sect_iter().map(|s| s.unwrap()).collect();
+        let scales: Vec<_> = sects[..2].iter().map(|s| match s {
+            FixedSectEnum::DecimalF64MedFixedSect(ds) => ds.scale(),
+            _ => panic!("Expected a decimal section, got {:?}", s),
+        }).collect();
+        assert_eq!(scales, vec![2, 3]);
+        assert_eq!(sects[2].sect_type(), SectionType::XorNPMedium);
+
+        // At least a third smaller than XOR encoding the prices
+        let prices = values[..256].to_vec();
+        let xor_bytes = VectorF64XorAppender::try_new(1024).unwrap().encode_all(prices.clone()).unwrap();
+        let decimal_bytes = VectorF64DecimalAppender::try_new(1024).unwrap().encode_all(prices).unwrap();
+        assert!(decimal_bytes.len() * 3 < xor_bytes.len() * 2);
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_lz4_sections() {
+        // Random hashes which NibblePacking expands, then a repeating pattern of large values, then a counter
+        let values: Vec<u64> = (0..768u64).map(|i| match i / 256 {
+            0 => i.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (i << 41),
+            1 => [0xdead_beef_0000_1111, 0x0123_4567_89ab_cdef, 0xffff_0000_ffff_0000][i as usize % 3],
+            _ => 1000 + i * 3,
+        }).collect();
+        let bytes = VectorU64Lz4Appender::try_new(8192).unwrap().encode_all(values.clone()).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        let types: Vec<_> = reader.sect_iter().map(|s| s.unwrap().sect_type()).collect();
+        assert_eq!(types[..2], [SectionType::Lz4Medium, SectionType::Lz4Medium]);
+        assert_ne!(types[2], SectionType::Lz4Medium);
+        assert_eq!(reader.iterate().collect::<Vec<u64>>(), values);
+        reader.cross_check().unwrap();
+        assert_eq!(count_hits(reader.filter_iter(EqualsSink::<u64>::new(&0x0123_4567_89ab_cdef))), 86);
+
+        let plain_bytes = VectorU64Appender::try_new(8192).unwrap().encode_all(values.clone()).unwrap();
+        assert!(bytes.len() < plain_bytes.len());
+
+        // Any type can be written as LZ4 sections
+        let floats: Vec<f32> = (0..300).map(|i| (i % 7) as f32 * 1.5).collect();
+        let mut appender = VectorAppender::<f32, Lz4MedFixedSect<f32>>::try_new(4096).unwrap();
+        let bytes = appender.encode_all(floats.clone()).unwrap();
+        let reader = VectorReader::<f32>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.iterate().collect::<Vec<f32>>(), floats);
+    }
+
+    #[test]
+    fn test_header_magic_and_version() {
+        let bytes = VectorU32Appender::try_new(1024).unwrap().encode_all(0..300u32).unwrap();
+        assert_eq!(bytes[FORMAT_MAGIC_OFFSET..NUM_HEADER_BYTES_TOTAL], [FORMAT_MAGIC, FORMAT_VERSION]);
+        assert_eq!(format_version(&bytes[..]), Ok(FORMAT_VERSION));
+
+        // Vectors written before the magic byte and version are version 0
+        let mut old = bytes.clone();
+        old[FORMAT_MAGIC_OFFSET..NUM_HEADER_BYTES_TOTAL].copy_from_slice(&[0, 0]);
+        assert_eq!(format_version(&old[..]), Ok(0));
+        assert_eq!(VectorReader::<u32>::try_new(&old[..]).unwrap().iterate().count(), 300);
+
+        let mut newer = bytes.clone();
+        newer[FORMAT_MAGIC_OFFSET + 1] = FORMAT_VERSION + 1;
+        assert_eq!(VectorReader::<u32>::try_new(&newer[..]).err(),
+                   Some(CodingError::UnsupportedVersion(FORMAT_VERSION + 1)));
+        let mut garbage = bytes.clone();
+        garbage[FORMAT_MAGIC_OFFSET] = 0x42;
+        assert!(matches!(VectorReader::<u32>::try_new(&garbage[..]), Err(CodingError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_header_lengths_not_covering_header() {
+        let bytes = VectorU32Appender::try_new(1024).unwrap().encode_all(0..300u32).unwrap();
+        for &flags in &[0, FLAG_FOOTER] {
+            for &num_bytes in &[0u32, 4, 11, u32::max_value() - 3, u32::max_value()] {
+                let mut corrupt = bytes.clone();
+                corrupt[..4].copy_from_slice(&num_bytes.to_le_bytes());
+                corrupt[FLAGS_OFFSET] = flags;
+                assert_eq!(VectorReader::<u32>::try_new(&corrupt[..]).err(), Some(CodingError::InputTooShort));
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_iterates_part_of_vector() {
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..1000u64).map(|i| i * 7)).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        let values: Vec<u64> = reader.iterate().collect();
+
+        assert_eq!(reader.range(300..520).collect::<Vec<u64>>(), values[300..520].to_vec());
+        assert_eq!(reader.range(300..520).len(), 220);
+        assert_eq!(reader.range(990..2000).rev().collect::<Vec<u64>>(),
+                   values[990..].iter().rev().cloned().collect::<Vec<u64>>());
+        assert_eq!(reader.range(600..500).next(), None);
+
+        // Sections after the range are never read, so they may even be garbage
+        let mut garbled = bytes.clone();
+        let second_sect = NUM_HEADER_BYTES_TOTAL + reader.sect_iter().next().unwrap().unwrap().num_bytes();
+        garbled[second_sect..].iter_mut().for_each(|b| *b = 0xff);
+        let reader = VectorReader::<u64>::try_new(&garbled[..]).unwrap();
+        assert_eq!(reader.range(10..256).sum::<u64>(), values[10..256].iter().sum::<u64>());
+    }
+
+    #[test]
+    fn test_concat_splices_sections() {
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        appender.dedup_sections(true);
+        (0..512u32).for_each(|i| appender.append(i % 256).unwrap());     // Section, then a RepeatPrevious
+        let first = appender.finish(512).unwrap();
+        appender.append_nulls(256).unwrap();
+        let nulls = appender.finish(256).unwrap();
+        let last = appender.encode_all((0..300u32).map(|i| i * 9)).unwrap();
+
+        let merged = concat(&[&first[..], &nulls[..], &last[..]]).unwrap();
+        let reader = VectorReader::<u32>::try_new(&merged[..]).unwrap();
+        assert_eq!(reader.num_elements(), 1068);
+        assert_eq!(reader.get_stats().num_null_sections, 1);
+        assert_eq!(reader.num_null_sections().unwrap(), 1);
+        let expected: Vec<u32> = [&first, &nulls, &last].iter().flat_map(|v| {
+            VectorReader::<u32>::try_new(&v[..]).unwrap().iterate().collect::<Vec<_>>()
+        }).collect();
+        assert_eq!(reader.iterate().collect::<Vec<u32>>(), expected);
+        reader.cross_check().unwrap();
+
+        // Only the last vector may end in a partial section, and types must match
+        assert!(matches!(concat(&[&last[..], &first[..]]), Err(CodingError::InvalidFormat(_))));
+        let u64s = VectorU64Appender::try_new(1024).unwrap().encode_all(0..256u64).unwrap();
+        assert!(matches!(concat(&[&first[..], &u64s[..]]), Err(CodingError::InvalidFormat(_))));
+        assert!(matches!(concat(&[]), Err(CodingError::InvalidFormat(_))));
+        assert_eq!(concat(&[&first[..10]]), Err(CodingError::InputTooShort));
+
+        // Sealed sections depend on their section number, so cannot be moved
+        let mut sealed = first.clone();
+        sealed[TRANSFORM_ID_OFFSET] = 1;
+        assert!(matches!(concat(&[&sealed[..], &sealed[..]]), Err(CodingError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_collect_compressed_vec() {
+        // Two full sections plus a partial one, and the same bytes as the appender would write
+        let vect: CompressedVec<u16> = (0..600u16).map(|i| i % 100).collect();
+        assert_eq!(vect.len(), 600);
+        assert_eq!(vect.reader().sect_iter().count(), 3);
+        assert_eq!(vect.reader().iterate().collect::<Vec<u16>>(), (0..600u16).map(|i| i % 100).collect::<Vec<_>>());
+        let bytes = VectorU16Appender::try_new(1024).unwrap().encode_all((0..600u16).map(|i| i % 100)).unwrap();
+        assert_eq!(vect.as_bytes(), &bytes[..]);
+
+        let empty = CompressedVec::<u64>::from(Vec::new());
+        assert!(empty.is_empty());
+        assert_eq!(CompressedVec::<f32>::from(vec![0.5, -1.0]).reader().iterate().collect::<Vec<f32>>(),
+                   vec![0.5, -1.0]);
+        assert_eq!(CompressedVec::<u16>::try_from_bytes(bytes.clone()).unwrap(), vect);
+        assert!(matches!(CompressedVec::<u32>::try_from_bytes(bytes), Err(CodingError::WrongElementType { .. })));
+    }
+
+    #[test]
+    fn test_vector_minmax() {
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..256u64).for_each(|i| appender.append(500 + i).unwrap());
+        (0..100u64).for_each(|i| appender.append(1000 - i).unwrap());
+        let bytes = appender.finish(356).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.minmax(None).unwrap(), Some((500, 1000)));
+
+        // A zone map covering every section is used without decoding, a short one is ignored
+        let zone_map = [(1, 2), (3, 4)];
+        assert_eq!(reader.minmax(Some(&zone_map[..])).unwrap(), Some((1, 4)));
+        assert_eq!(reader.minmax(Some(&zone_map[..1])).unwrap(), Some((500, 1000)));
+
+        let empty = appender.finish(0).unwrap();
+        assert_eq!(VectorReader::<u64>::try_new(&empty[..]).unwrap().minmax(None).unwrap(), None);
+
+        // Null sections written for zero values, even with null tracking, are zeroes
+        appender.track_nulls(true);
+        let zeroes = appender.encode_all(vec![0u64; 256]).unwrap();
+        assert_eq!(VectorReader::<u64>::try_new(&zeroes[..]).unwrap().minmax(None).unwrap(), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_null_and_nonzero_counts() {
+        // Null section and constant section, then tracked nulls and zeroes
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        appender.append_nulls(256).unwrap();
+        (0..256u32).for_each(|_| appender.append(4).unwrap());
+        let untracked = appender.finish(600).unwrap();
+        let reader = VectorReader::<u32>::try_new(&untracked[..]).unwrap();
+        assert_eq!(reader.null_count(), 0);            // Untracked nulls, like the rows added by finish(), are zeroes
+        assert_eq!(reader.count_non_null(), 600);
+        assert_eq!(reader.count_nonzero(), 256);
+
+        // Null sections written for zero values are zeroes too
+        let zeroes = appender.encode_all(vec![0u32; 256]).unwrap();
+        let reader = VectorReader::<u32>::try_new(&zeroes[..]).unwrap();
+        assert_eq!((reader.null_count(), reader.count_non_null(), reader.count_nonzero()), (0, 256, 0));
+
+        appender.track_nulls(true);
+        appender.append_options((0..300u32).map(|i| if i % 3 == 0 { None } else { Some(i % 2) })).unwrap();
+        let tracked = appender.finish(300).unwrap();
+        let reader = VectorReader::<u32>::try_new(&tracked[..]).unwrap();
+        assert_eq!(reader.null_count(), 100);
+        assert_eq!(reader.count_nonzero(), reader.iterate().filter(|&v| v != 0).count());
+        assert_eq!(reader.count_nonzero(), 100);
+    }
+
+    #[test]
+    fn test_first_and_last() {
+        // Null sections are zeroes, including rows added by finish()
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        appender.append_nulls(256).unwrap();
+        (0..256u64).for_each(|_| appender.append(4).unwrap());
+        let bytes = appender.finish(600).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!((reader.first().unwrap(), reader.last().unwrap()), (Some(0), Some(0)));
+
+        let bytes = appender.encode_all((0..512u64).map(|i| if i < 256 { 0 } else { 5 })).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!((reader.first().unwrap(), reader.last().unwrap()), (Some(0), Some(5)));
+        let bytes = appender.encode_all(vec![0u64; 256]).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!((reader.first().unwrap(), reader.last().unwrap()), (Some(0), Some(0)));
+
+        let bytes = appender.encode_all(10..600u64).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!((reader.first().unwrap(), reader.last().unwrap()), (Some(10), Some(599)));
+
+        // Tracked nulls are skipped, even when a whole section is null
+        appender.track_nulls(true);
+        appender.append_options((0..300u64).map(|i| if i % 3 == 0 { None } else { Some(i) })).unwrap();
+        appender.append_nulls(300).unwrap();
+        let bytes = appender.finish(600).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!((reader.first().unwrap(), reader.last().unwrap()), (Some(1), Some(299)));
+
+        let bytes = appender.finish(0).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!((reader.first().unwrap(), reader.last().unwrap()), (None, None));
+    }
+
+    #[test]
+    fn test_write_values() {
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..300u64).for_each(|i| appender.append(i << 40).unwrap());
+        appender.append_nulls(3).unwrap();
+        let bytes = appender.finish(303).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+
+        // Padding is not written, and nulls are zeroes
+        let le_bytes = reader.write_values(Vec::new(), WriteFormat::LittleEndian).unwrap();
+        assert_eq!(le_bytes.len(), 303 * 8);
+        let expected: Vec<u8> = reader.iterate().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+        assert_eq!(le_bytes, expected);
+
+        let bytes = VectorF64XorAppender::try_new(1024).unwrap().encode_all(vec![0.5, -2.0, 1e300]).unwrap();
+        let reader = VectorReader::<f64>::try_new(&bytes[..]).unwrap();
+        let csv = reader.write_values(Vec::new(), WriteFormat::Csv).unwrap();
+        let parsed: Vec<f64> = String::from_utf8(csv).unwrap().lines().map(|l| l.parse().unwrap()).collect();
+        assert_eq!(parsed, vec![0.5, -2.0, 1e300]);
+
+        // The first write error is returned
+        let mut buf = [0u8; 20];
+        let res = reader.write_values(&mut buf[..], WriteFormat::LittleEndian);
+        assert!(matches!(res, Err(CodingError::IoErr(_))));
+    }
+
+    // Counts octets, and is done after a number of them
+    struct DoneAfterSink { octets: usize, done_after: usize }
+
+    impl Sink<u32x8> for DoneAfterSink {
+        fn process(&mut self, _data: u32x8) { self.octets += 1; }
+        fn process_zeroes(&mut self) { self.octets += 1; }
+        fn reset(&mut self) {}
+        fn is_done(&self) -> bool { self.octets >= self.done_after }
+    }
+
+    #[test]
+    fn test_decode_stops_when_sink_done() {
+        let bytes = VectorU32Appender::try_new(4096).unwrap().encode_all(0..10_000u32).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+
+        // The rest of the section is still decoded, but no more sections
+        let mut sink = DoneAfterSink { octets: 0, done_after: 40 };
+        reader.decode_to_sink(&mut sink).unwrap();
+        assert_eq!(sink.octets, 64);
+
+        // Adapters forward it
+        let mut sink = DoneAfterSink { octets: 0, done_after: 1 };
+        reader.decode_to_sink(&mut AddConstSink::<u32, _>::new(5, &mut sink)).unwrap();
+        assert_eq!(sink.octets, 32);
+
+        let mut limited = VecSink::<u32>::new();
+        reader.decode_to_sink(&mut LimitSink::<u32, _>::new(600, &mut limited)).unwrap();
+        assert_eq!(limited.vec, (0..600u32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_top_k() {
+        let values: Vec<u32> = (0..1000u32).map(|i| (i * 7919) % 1000).collect();
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        appender.append_nulls(256).unwrap();
+        values.iter().for_each(|&v| appender.append(v).unwrap());
+        (0..256u32).for_each(|_| appender.append(999).unwrap());
+        let bytes = appender.finish(1512).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+
+        let mut expected: Vec<(usize, u32)> = reader.iterate().enumerate().collect();
+        expected.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        assert_eq!(reader.top_k(5, None).unwrap(), expected[..5].to_vec());
+        assert_eq!(reader.top_k(300, None).unwrap(), expected[..300].to_vec());
+        assert_eq!(reader.top_k(0, None).unwrap(), vec![]);
+
+        // Null sections written for zero values, even with null tracking, are zeroes
+        appender.track_nulls(true);
+        let zeroes = appender.encode_all(vec![0u32; 256]).unwrap();
+        let zeroes = VectorReader::<u32>::try_new(&zeroes[..]).unwrap();
+        assert_eq!(zeroes.top_k(2, None).unwrap(), vec![(0, 0), (1, 0)]);
+        appender.track_nulls(false);
+
+        // Once the heap is full, sections ruled out by the zone map are not decoded, so the 999s at the end of
+        // section 4 are missed when the zone map says otherwise
+        let zone_map = [(0, 0), (0, 1), (0, 1), (0, 1), (0, 1), (999, 999)];
+        assert_eq!(reader.top_k(3, Some(&zone_map[..])).unwrap(), vec![(1280, 999), (1281, 999), (1282, 999)]);
+        let zone_map = [(0, 0), (0, 1), (0, 1), (0, 1), (0, 1), (0, 1)];
+        assert_eq!(reader.top_k(3, Some(&zone_map[..])).unwrap().len(), 3);
+
+        let floats = VectorF64XorAppender::try_new(1024).unwrap()
+                         .encode_all(vec![1.5, std::f64::NAN, -2.0, 7.25]).unwrap();
+        let floats = VectorReader::<f64>::try_new(&floats[..]).unwrap();
+        assert_eq!(floats.top_k(2, None).unwrap(), vec![(3, 7.25), (0, 1.5)]);
+    }
+
+    #[test]
+    fn test_point_reader_get() {
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        (0..300u32).for_each(|i| appender.append(i * 3).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..100u32).for_each(|i| appender.append(i + 7).unwrap());
+        let bytes = appender.finish(656).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        let values: Vec<u32> = reader.iterate().collect();
+
+        // Forwards, backwards and random lookups all match iteration
+        let mut points = reader.point_reader();
+        for &i in [0, 255, 256, 299, 300, 555, 556, 655, 10, 400, 260, 640].iter() {
+            assert_eq!(points.get(i).unwrap(), values[i]);
+        }
+        assert_eq!(reader.get(655).unwrap(), 106);
+        assert_eq!(points.get(656), Err(CodingError::BadOffset(656)));
+    }
+
+    #[test]
+    fn test_sparse_sections() {
+        // Error counter which is mostly 0, a gauge which is mostly 1000 with spikes both ways, and 29 exceptions
+        let values: Vec<u32> = (0..768u32).map(|i| match i / 256 {
+            0 => if i % 11 == 5 { i * 3 } else { 0 },
+            1 => if i % 16 == 0 { i / 16 % 2 * 2000 } else { 1000 },
+            _ => if i % 9 == 0 { i } else { 42 },
+        }).collect();
+        let bytes = VectorU32Appender::try_new(1024).unwrap().encode_all(values.clone()).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert_eq!(reader.iterate().collect::<Vec<u32>>(), values);
+        reader.cross_check().unwrap();
+        assert_eq!(count_hits(reader.filter_iter(EqualsSink::<u32>::new(&1000))), 256 - 16);
+
+        let sects: Vec<_> = reader.sect_iter().map(|s| s.unwrap()).collect();
+        match sects[1] {
+            FixedSectEnum::SparseFixedSect(ss) => {
+                assert_eq!(ss.default_value(), 1000);
+                assert_eq!(ss.num_exceptions(), 16);
+                assert_eq!(ss.indices()[..3], [0, 16, 32]);
+            }
+            _ => panic!("Expected a sparse section, got {:?}", sects[1]),
+        }
+        assert_eq!(sects[0].sect_type(), SectionType::SparseExceptions);
+        assert_eq!(sects[2].sect_type(), SectionType::NibblePackedMedium);
+
+        // Smaller than NibblePacking the first section, which has a non-zero value in every group of 8
+        let np_bytes = VectorAppender::<u32, NibblePackMedFixedSect<u32>>::try_new(1024).unwrap()
+                                                                           .encode_all(values[..256].to_vec()).unwrap();
+        let sparse_bytes = VectorU32Appender::try_new(1024).unwrap().encode_all(values[..256].to_vec()).unwrap();
+        assert!(sparse_bytes.len() < np_bytes.len());
+    }
+
+    #[test]
+    fn test_decode_mask_shift() {
+        // Composite keys: 16-bit tenant in high bits, 20-bit id in the middle, 8 low bits of flags
+        let keys: Vec<u64> = (0..600).map(|i| ((i % 5) << 48) | ((i * 3) << 8) | (i % 7)).collect();
+        let mut appender = VectorU64Appender::try_new(2048).unwrap();
+        let finished_vec = appender.encode_all(keys.clone()).unwrap();
+        let reader = VectorReader::<u64>::try_new(&finished_vec[..]).unwrap();
+
+        let mut sink = VecSink::<u64>::new();
+        let mut tenants = MaskShiftSink::<u64, _>::new(0xffff << 48, 48, &mut sink);
+        reader.decode_to_sink(&mut tenants).unwrap();
+        let expected: Vec<u64> = (0..600).map(|i| i % 5).collect();
+        assert_eq!(sink.vec[..600], expected[..]);
+
+        let mut sink = VecSink::<u64>::new();
+        let mut ids = MaskShiftSink::<u64, _>::new(0xf_ffff << 8, 8, &mut sink);
+        reader.decode_to_sink(&mut ids).unwrap();
+        let expected: Vec<u64> = (0..600).map(|i| i * 3).collect();
+        assert_eq!(sink.vec[..600], expected[..]);
+
+        // u32 with null sections
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.append_nulls(300).unwrap();
+        (0..100u32).for_each(|i| appender.append(0xabcd_0000 | i).unwrap());
+        let finished_vec = appender.finish(400).unwrap();
+        let reader = VectorReader::<u32>::try_new(&finished_vec[..]).unwrap();
+        let mut sink = VecSink::<u32>::new();
+        let mut high = MaskShiftSink::<u32, _>::new(0xffff_0000, 16, &mut sink);
+        reader.decode_to_sink(&mut high).unwrap();
+        let expected: Vec<u32> = (0..400).map(|i| if i < 300 { 0 } else { 0xabcd }).collect();
+        assert_eq!(sink.vec[..400], expected[..]);
+    }
+
+    #[test]
+    fn test_dedup_repeated_sections() {
+        // 4 identical sections, a null section, then 2 more identical sections
+        let pattern: Vec<u32> = (0..256).map(|i| (i * 7) % 100).collect();
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        let plain_vec = appender.encode_all(pattern.iter().cycle().take(1024).cloned()).unwrap();
+
+        appender.dedup_sections(true);
+        (0..4).for_each(|_| pattern.iter().for_each(|&x| appender.append(x).unwrap()));
+        appender.append_nulls(256).unwrap();
+        (0..2).for_each(|_| pattern.iter().for_each(|&x| appender.append(x).unwrap()));
+        let finished_vec = appender.finish(256 * 7).unwrap();
+
+        let reader = VectorReader::<u32>::try_new(&finished_vec[..]).unwrap();
+        assert_eq!(reader.sect_iter().count(), 7);
+        // Only the first section and the one after the null section are stored in full.  The rest are
+        // 4 one-byte repeat markers plus the null section.
+        let sect_bytes = (plain_vec.len() - 16) / 4;
+        assert_eq!(finished_vec.len(), 16 + sect_bytes * 2 + 5);
+        assert_eq!(reader.num_null_sections().unwrap(), 1);
+
+        let expected: Vec<u32> = (0..7).flat_map(|s| pattern.iter().map(move |&x| if s == 4 { 0 } else { x }))
+                                       .collect();
+        assert_eq!(reader.iterate().collect::<Vec<u32>>(), expected);
+
+        let filter_iter = reader.filter_iter(EqualsSink::<u32>::new(&42));
+        let per_section = pattern.iter().filter(|&&x| x == 42).count();
+        assert_eq!(count_hits(filter_iter), per_section * 6);
+    }
+
     #[test]
     fn test_append_u32_large_vector() {
         // 9999 nulls, then an item, 10 times = 100k items total
@@ -724,6 +2612,19 @@ mod test {
         assert_eq!(reader.num_elements(), vector_size as usize);
     }
 
+    #[test]
+    fn test_vector_over_64kb_decodes_with_lookahead() {
+        // Section lengths used to be checked against the remaining bytes truncated to u16
+        let values: Vec<u64> = (0..65536u64).map(|i| (i * 7919) % 100_003).collect();
+        let bytes = VectorU64Appender::try_new(65536).unwrap().encode_all(values.clone()).unwrap();
+        assert!(bytes.len() > 128 * 1024);
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        let mut sink = VecSink::<u64>::new();
+        reader.decode_to_sink(&mut sink).unwrap();
+        assert_eq!(sink.vec, values);
+        assert_eq!(reader.lookahead_sect_iter().count(), 256);
+    }
+
     #[test]
     fn test_read_wrong_type_error() {
         let vector_size = 400;
@@ -734,7 +2635,10 @@ mod test {
         let finished_vec = appender.finish(vector_size as usize).unwrap();
 
         let res = VectorReader::<u64>::try_new(&finished_vec[..]);
-        assert_eq!(res.err().unwrap(), CodingError::WrongVectorType(VectorSubType::FixedU32 as u8))
+        assert_eq!(res.err().unwrap(), CodingError::WrongElementType { expected: VectorSubType::FixedU64,
+                                                                       found: VectorSubType::FixedU32 });
+        assert_eq!(VectorSubType::try_from(0x11), Ok(VectorSubType::FixedU32));
+        assert_eq!(VectorSubType::try_from(0x42), Err(CodingError::WrongVectorType(0x42)));
     }
 
     #[test]
@@ -752,5 +2656,348 @@ mod test {
         reader.decode_to_sink(&mut sink).unwrap();
         assert_eq!(sink.vec[..vector_size], data[..]);
     }
+
+    #[test]
+    fn test_decode_with_budget() {
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..300u64).for_each(|i| appender.append(i * 3).unwrap());
+        appender.append_nulls(512).unwrap();
+        (0..200u64).for_each(|i| appender.append(i).unwrap());
+        let bytes = appender.finish(1012).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+
+        // Small budgets still decode one section at a time
+        let mut cursor = reader.cursor();
+        let mut sink = VecSink::<u64>::new();
+        assert_eq!(cursor.progress(), DecodeProgress::Partial(0));
+        assert_eq!(cursor.decode_with_budget(10, &mut sink).unwrap(), DecodeProgress::Partial(256));
+        assert_eq!(cursor.decode_with_budget(513, &mut sink).unwrap(), DecodeProgress::Partial(768));
+        assert_eq!(cursor.decode_with_budget(1000, &mut sink).unwrap(), DecodeProgress::Done);
+        assert_eq!(cursor.decode_with_budget(1000, &mut sink).unwrap(), DecodeProgress::Done);
+
+        let mut expected = VecSink::<u64>::new();
+        reader.decode_to_sink(&mut expected).unwrap();
+        assert_eq!(sink.vec, expected.vec);
+
+        // Empty vector is done immediately
+        let empty = appender.finish(0).unwrap();
+        let reader = VectorReader::<u64>::try_new(&empty[..]).unwrap();
+        assert_eq!(reader.cursor().progress(), DecodeProgress::Done);
+    }
+
+    #[test]
+    fn test_encoding_is_deterministic() {
+        let values: Vec<u64> = (0..3000u64).map(|i| if i % 700 < 300 { 0 } else { i * i % 10007 }).collect();
+        let expected = VectorU64Appender::try_new(65536).unwrap().encode_all(values.clone()).unwrap();
+
+        // Initial capacity and earlier use of the appender do not change the bytes
+        let mut appender = VectorU64Appender::try_new(16).unwrap();
+        appender.encode_all((0..5000u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15))).unwrap();
+        assert_eq!(appender.encode_all(values.clone()).unwrap(), expected);
+        assert_eq!(appender.encode_all(values).unwrap(), expected);
+
+        // Float bits, including NaN payloads, are encoded exactly
+        let floats = vec![1.5f32, f32::from_bits(0x7fc0_1234), -0.0, f32::NAN, 2.25];
+        let bytes = VectorF32XorAppender::try_new(64).unwrap().encode_all(floats.clone()).unwrap();
+        let reader = VectorReader::<f32>::try_new(&bytes[..]).unwrap();
+        let bits: Vec<u32> = reader.iterate().map(f32::to_bits).collect();
+        assert_eq!(bits, floats.iter().map(|f| f.to_bits()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_preview_bounds() {
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        (0..256u64).rev().for_each(|i| appender.append(i * 3).unwrap());        // NibblePacked
+        appender.append_nulls(256).unwrap();                                     // Null
+        (0..256u64).rev().for_each(|i| appender.append(1_000_000 + i).unwrap()); // Delta
+        (0..100u64).for_each(|_| appender.append(42).unwrap());                 // Padded with zeroes
+        let bytes = appender.finish(868).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        let previews: Vec<(u64, u64)> = reader.preview().map(|p| p.unwrap()).collect();
+        assert_eq!(previews, vec![(0, 4095), (0, 0), (1_000_000, 1_000_255), (0, 255)]);
+
+        // XOR float sections are decoded for exact bounds
+        let mut appender = VectorF32XorAppender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..300).map(|i| i as f32 * 0.5 - 10.0)).unwrap();
+        let reader = VectorReader::<f32>::try_new(&bytes[..]).unwrap();
+        let previews: Vec<(f32, f32)> = reader.preview().map(|p| p.unwrap()).collect();
+        assert_eq!(previews, vec![(-10.0, 117.5), (0.0, 139.5)]);
+    }
+
+    #[test]
+    fn test_bloom_filter_footer() {
+        let mut appender = VectorU32Appender::try_new(1024).unwrap();
+        appender.enable_bloom_filter(12);
+        (0..300u32).for_each(|i| appender.append(i * 3 + 1).unwrap());
+        let bytes = appender.finish(300).unwrap();
+
+        // Padding nulls are not values
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert!(reader.footer().is_some());
+        assert!((0..300u32).all(|i| reader.might_contain(i * 3 + 1)));
+        assert!(!reader.might_contain(0));
+        assert_eq!(reader.iterate().count(), 300);
+        assert_eq!(reader.iterate().last(), Some(898));
+        assert_eq!(reader.sect_iter().count(), 2);
+        reader.cross_check().unwrap();
+
+        // Nulls appended and rows added by finish() are zero values
+        appender.append(5).unwrap();
+        appender.append_nulls(10).unwrap();
+        let bytes = appender.finish(11).unwrap();
+        let reader = VectorReader::<u32>::try_new(&bytes[..]).unwrap();
+        assert!(reader.might_contain(0) && reader.might_contain(5));
+        let bytes = appender.encode_all(vec![5]).unwrap();
+        assert!(!VectorReader::<u32>::try_new(&bytes[..]).unwrap().might_contain(0));
+        let bytes = { appender.append(5).unwrap(); appender.finish(2).unwrap() };
+        assert!(VectorReader::<u32>::try_new(&bytes[..]).unwrap().might_contain(0));
+
+        // Footers bigger than a doubling of the vector buffer, and vectors without a Bloom filter
+        let mut appender = VectorU64Appender::try_new(64).unwrap();
+        appender.enable_bloom_filter(64);
+        let bytes = appender.encode_all(0..5000u64).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert!(reader.bloom_filter().unwrap().num_bits() >= 5000 * 64);
+        assert!(reader.might_contain(4999) && !reader.might_contain(5000));
+        let bytes = VectorU64Appender::try_new(64).unwrap().encode_all(0..5000u64).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert!(reader.footer().is_none() && reader.might_contain(5000));
+    }
+
+    #[test]
+    fn test_section_bloom_filters_prune_equality() {
+        use crate::filter::OneOfSink;
+        use smallvec::smallvec;
+
+        // The null section has no filter
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.enable_section_bloom_filters(10);
+        (0..256u64).for_each(|i| appender.append(i * 7).unwrap());
+        appender.append_nulls(256).unwrap();
+        (512..1000u64).for_each(|i| appender.append(i * 7).unwrap());
+        let bytes = appender.finish(1000).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        let blooms = reader.section_bloom_filters().unwrap().unwrap();
+        assert_eq!(blooms.clone().map(|b| b.is_some()).collect::<Vec<_>>(), vec![true, false, true, true]);
+        assert!(reader.bloom_filter().is_none());
+
+        let mut filter = reader.filter_iter(EqualsSink::<u64>::new(&(600 * 7)));
+        filter.set_section_bloom_filters(blooms.clone());
+        filter.enable_stats();
+        assert_eq!(count_hits(&mut filter), 1);
+        let stats = filter.stats().unwrap().total();
+        assert_eq!((stats.pruned, stats.mask_only, stats.decoded), (2, 1, 1));
+
+        let mut filter = reader.filter_iter(OneOfSink::<u64>::new(&smallvec![35, 999 * 7]));
+        filter.set_section_bloom_filters(blooms);
+        filter.enable_stats();
+        assert_eq!(count_hits(&mut filter), 2);
+        assert_eq!(filter.stats().unwrap().total().pruned, 1);
+
+        // Truncating drops the filters of the dropped sections, and -0.0 matches 0.0
+        let mut appender = VectorF64XorAppender::try_new(1024).unwrap();
+        appender.enable_section_bloom_filters(10);
+        (0..600).for_each(|i| appender.append(if i == 260 { -0.0 } else { i as f64 + 0.5 }).unwrap());
+        appender.truncate(300).unwrap();
+        let bytes = appender.finish(300).unwrap();
+        let reader = VectorReader::<f64>::try_new(&bytes[..]).unwrap();
+        let blooms = reader.section_bloom_filters().unwrap().unwrap();
+        assert_eq!(blooms.clone().count(), 2);
+        let mut filter = reader.filter_iter(EqualsSink::<f64>::new(&0.0));
+        filter.set_section_bloom_filters(blooms);
+        filter.enable_stats();
+        assert_eq!(count_hits(&mut filter), 1 + 212);
+        assert_eq!(filter.stats().unwrap().total().pruned, 1);
+
+        // And -0.0 matches 0.0
+        let mut appender = VectorF64XorAppender::try_new(1024).unwrap();
+        appender.enable_section_bloom_filters(10);
+        (0..256).for_each(|i| appender.append(if i == 10 { 0.0 } else { i as f64 + 0.5 }).unwrap());
+        let bytes = appender.finish(256).unwrap();
+        let reader = VectorReader::<f64>::try_new(&bytes[..]).unwrap();
+        let mut filter = reader.filter_iter(EqualsSink::<f64>::new(&-0.0));
+        filter.set_section_bloom_filters(reader.section_bloom_filters().unwrap().unwrap());
+        filter.enable_stats();
+        assert_eq!(count_hits(&mut filter), 1);
+        assert_eq!(filter.stats().unwrap().total().pruned, 0);
+    }
+
+    #[test]
+    fn test_append_from_copies_whole_sections() {
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        (0..256u64).for_each(|i| appender.append(i * 1000).unwrap());
+        appender.append_nulls(256).unwrap();
+        (0..256u64).for_each(|_| appender.append(7).unwrap());
+        (0..200u64).for_each(|i| appender.append(i).unwrap());
+        let source = appender.finish(968).unwrap();
+        let reader = VectorReader::<u64>::try_new(&source[..]).unwrap();
+        let values: Vec<u64> = reader.iterate().collect();
+
+        // The whole vector copies to identical bytes
+        appender.append_from(&reader, 0..968).unwrap();
+        assert_eq!(appender.finish(968).unwrap(), source);
+
+        // Unaligned ranges decode the boundary sections
+        appender.append(1).unwrap();
+        appender.append_from(&reader, 100..900).unwrap();
+        appender.append_from(&reader, 256..768).unwrap();
+        let bytes = appender.finish(1313).unwrap();
+        let copied: Vec<u64> = VectorReader::<u64>::try_new(&bytes[..]).unwrap().iterate().collect();
+        let expected: Vec<u64> = std::iter::once(1).chain(values[100..900].iter().cloned())
+                                                   .chain(values[256..768].iter().cloned()).collect();
+        assert_eq!(copied, expected);
+
+        // Copied sections are deduplicated, and still go into the Bloom filter
+        appender.dedup_sections(true);
+        appender.enable_bloom_filter(10);
+        appender.append_from(&reader, 512..768).unwrap();
+        appender.append_from(&reader, 512..768).unwrap();
+        let bytes = appender.finish(512).unwrap();
+        let copied = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!(copied.sect_bytes().last(), Some(&SectionType::RepeatPrevious.as_num()));
+        assert!(copied.might_contain(7) && copied.iterate().all(|v| v == 7));
+
+        assert_eq!(appender.append_from(&reader, 900..969), Err(CodingError::BadOffset(969)));
+    }
+
+    #[test]
+    fn test_append_from_copies_null_sections_as_zeroes() {
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.append_nulls(256).unwrap();
+        (0..256u64).for_each(|i| appender.append(i).unwrap());
+        let source = appender.finish(512).unwrap();
+        let reader = VectorReader::<u64>::try_new(&source[..]).unwrap();
+        assert_eq!(reader.sect_bytes()[0], SectionType::Null.as_num());
+
+        // A Null section is 256 zero values, not 256 tracked nulls
+        appender.track_nulls(true);
+        appender.append_from(&reader, 0..512).unwrap();
+        let bytes = appender.finish(512).unwrap();
+        let copied = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        assert_eq!(copied.null_count(), 0);
+        assert!(copied.iterate_options().take(256).all(|v| v == Some(0)));
+        assert_eq!(copied.iterate().collect::<Vec<_>>(), reader.iterate().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_item_iter_adapters() {
+        // Regular, null, repeated and partial sections
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.dedup_sections(true);
+        (0..300u64).for_each(|i| appender.append(i + 1).unwrap());
+        appender.append_nulls(300).unwrap();
+        (0..512u64).for_each(|i| appender.append(i % 256).unwrap());
+        (0..100u64).for_each(|i| appender.append(i * 5).unwrap());
+        let bytes = appender.finish(1212).unwrap();
+        let reader = VectorReader::<u64>::try_new(&bytes[..]).unwrap();
+        let values: Vec<u64> = reader.iterate().collect();
+
+        assert_eq!(reader.iterate().len(), 1212);
+        assert_eq!(reader.iterate().rev().collect::<Vec<_>>(), values.iter().rev().cloned().collect::<Vec<_>>());
+        assert_eq!(reader.iterate().nth(1000), Some(values[1000]));
+        assert_eq!(reader.iterate().skip(5).step_by(97).collect::<Vec<_>>(),
+                   values.iter().skip(5).step_by(97).cloned().collect::<Vec<_>>());
+
+        // Both ends at once, meeting in the middle of a section
+        let mut iter = reader.iterate();
+        assert_eq!((iter.next(), iter.next_back(), iter.nth(299)), (Some(1), Some(495), Some(0)));
+        assert_eq!(iter.len(), 1212 - 302);
+        let middle: Vec<u64> = iter.by_ref().collect();
+        assert_eq!(middle[..], values[301..1211]);
+        assert_eq!((iter.next(), iter.next_back(), iter.len()), (None, None, 0));
+    }
+
+    #[test]
+    fn test_flush_sealed_and_resume() {
+        let values: Vec<u64> = (0..2000u64).map(|i| if (600..900).contains(&i) { 0 } else { i % 300 }).collect();
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.dedup_sections(true);
+        let mut wal = Vec::new();
+        values[..700].iter().for_each(|&v| appender.append(v).unwrap());
+        let token = appender.flush_sealed(&mut wal).unwrap();
+        assert_eq!((token.num_elements(), token.flushed_bytes(), wal.len()), (512, wal.len(), wal.len()));
+        values[700..1300].iter().for_each(|&v| appender.append(v).unwrap());
+        let token = appender.flush_sealed(&mut wal).unwrap();
+        assert_eq!(token.num_elements(), 1280);
+        assert_eq!(token.flushed_bytes(), wal.len());
+        // Values appended after the last flush are lost in a crash, and replayed from the WAL
+        values[1300..1500].iter().for_each(|&v| appender.append(v).unwrap());
+        let mut fresh = VectorU64Appender::try_new(1024).unwrap();
+        fresh.dedup_sections(true);
+        let expected = fresh.encode_all(values.iter().cloned()).unwrap();
+
+        let token = ResumeToken::from_bytes(&token.to_bytes()[..]).unwrap();
+        let mut resumed = VectorU64Appender::resume(1024, &token, &wal[..]).unwrap();
+        resumed.dedup_sections(true);
+        values[token.num_elements()..].iter().for_each(|&v| resumed.append(v).unwrap());
+        assert_eq!(resumed.finish(2000).unwrap(), expected);
+
+        assert_eq!(VectorU64Appender::resume(1024, &token, &wal[..10]).err(), Some(CodingError::InputTooShort));
+        let mut bloom_appender = VectorU64Appender::try_new(1024).unwrap();
+        bloom_appender.enable_bloom_filter(10);
+        assert!(matches!(bloom_appender.flush_sealed(&mut wal), Err(CodingError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_appender_limits() {
+        let mut appender = VectorU64Appender::try_new(1024).unwrap();
+        appender.set_limits(AppenderLimits { max_bytes: None, max_sections: Some(2) });
+        assert!(appender.encode_all(0..512u64).is_ok());
+        assert_eq!(appender.encode_all(0..513u64), Err(CodingError::TooManySections(3)));
+        appender.reset().unwrap();
+        // Null sections count too
+        assert_eq!(appender.append_nulls(1000), Err(CodingError::TooManySections(3)));
+        appender.reset().unwrap();
+
+        // Random values take about 8 bytes each, so 2 sections are over 4000 bytes
+        appender.set_limits(AppenderLimits { max_bytes: Some(4000), max_sections: None });
+        let random = (0..512u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        assert!(matches!(appender.encode_all(random), Err(CodingError::VectorTooLarge(n)) if n > 4000));
+        appender.reset().unwrap();
+
+        // The footer counts towards max_bytes
+        let small = appender.encode_all(0..256u64).unwrap();
+        appender.set_limits(AppenderLimits { max_bytes: Some(small.len() + 10), max_sections: None });
+        appender.enable_bloom_filter(10);
+        assert!(matches!(appender.encode_all(0..256u64), Err(CodingError::VectorTooLarge(_))));
+    }
 }
 
+/// Golden hashes of encoded vectors, which must be identical on every platform.  Enable with the
+/// `golden-tests` feature; a change in these hashes is a change in the encoding of existing data.
+#[cfg(all(test, feature = "golden-tests"))]
+mod golden {
+    use super::*;
+
+    // FNV-1a, so the hashes do not depend on the std hasher
+    fn fnv1a(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, &b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3))
+    }
+
+    #[test]
+    fn test_golden_u64() {
+        let mut appender = VectorU64Appender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..2000u64).map(|i| if i % 600 < 256 { 7 } else { i * 31 % 997 })).unwrap();
+        assert_eq!(fnv1a(&bytes[..]), 0x67c8_b9f2_7c2a_4f9a);
+        appender.dedup_sections(true);
+        let bytes = appender.encode_all((0..2000u64).map(|i| 1_000_000 + i % 256)).unwrap();
+        assert_eq!(fnv1a(&bytes[..]), 0x01f2_d6a4_1f75_b26b);
+    }
+
+    #[test]
+    fn test_golden_u32() {
+        let mut appender = VectorU32Appender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..1500u32).map(|i| if i < 512 { 0 } else { i.wrapping_mul(2654435761) >> 8 })).unwrap();
+        assert_eq!(fnv1a(&bytes[..]), 0x4e94_9fae_8f96_916d);
+    }
+
+    #[test]
+    fn test_golden_floats() {
+        let mut appender = VectorF32XorAppender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..1000).map(|i| (i % 100) as f32 * 0.37 - 12.5)).unwrap();
+        assert_eq!(fnv1a(&bytes[..]), 0xd1d2_a4a2_d059_ea15);
+        let mut appender = VectorF64XorAppender::try_new(4096).unwrap();
+        let bytes = appender.encode_all((0..1000).map(|i| 20.0 + (i % 17) as f64 * 0.125)).unwrap();
+        assert_eq!(fnv1a(&bytes[..]), 0xb1c9_d3e3_d432_d1d7);
+    }
+}